@@ -1,3 +1,11 @@
+mainmenu-entry-continue = Продължи
 mainmenu-entry-play = Влез в играта!
 mainmenu-entry-settings = Настройки
 mainmenu-entry-exit = Изход
+settings-title = Управление
+settings-rebind-hint = Натиснете върху клавиш и след това новия клавиш или бутон на геймпада. Delete го премахва, Escape отказва.
+settings-entry-reset = Възстанови по подразбиране
+settings-entry-back = Назад
+settings-rumble = Вибрация
+settings-rumble-on = Вкл.
+settings-rumble-off = Изкл.
@@ -0,0 +1,5 @@
+passive-choice-title = Всички места за пасивни умения са заети
+passive-choice-new = Ново:
+passive-choice-replace = Замени
+passive-choice-discard = Изхвърли
+passive-choice-leave = Остави го засега
@@ -0,0 +1,13 @@
+shop-title = Стоките на Скитника
+shop-swap-title = От кое пасивно умение да се откажеш?
+shop-xp = ОО
+shop-swap = Размени за
+shop-reroll = Покажи ми нещо друго
+shop-leave = Тръгни си
+shop-cancel = Отказ
+shop-sold = Продадено
+shop-upgrade-whetstone = Точило (+10% атака)
+shop-upgrade-plating = Броня (+10% защита)
+shop-upgrade-boots = Леки ботуши (+5% скорост)
+shop-upgrade-hourglass = Пясъчен часовник (+10% намаляване на презареждането)
+shop-upgrade-heart = Засищаща яхния (+20 макс. здраве)
@@ -0,0 +1,4 @@
+dialogue-speaker-vagrant = The Vagrant
+dialogue-speaker-snaffles = Mr Snaffles
+dialogue-merchant-trade = Let's trade.
+dialogue-merchant-bye = I should go.
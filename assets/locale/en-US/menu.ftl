@@ -1,3 +1,11 @@
+mainmenu-entry-continue = Continue
 mainmenu-entry-play = Play Game!
 mainmenu-entry-settings = Settings
 mainmenu-entry-exit = Exit Game
+settings-title = Controls
+settings-rebind-hint = Click a binding and press the new key or gamepad button. Delete removes it, Escape cancels.
+settings-entry-reset = Reset to Defaults
+settings-entry-back = Back
+settings-rumble = Rumble
+settings-rumble-on = On
+settings-rumble-off = Off
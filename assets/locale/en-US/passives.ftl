@@ -0,0 +1,5 @@
+passive-choice-title = Your passive slots are full
+passive-choice-new = New:
+passive-choice-replace = Replace
+passive-choice-discard = Discard
+passive-choice-leave = Leave it for now
@@ -0,0 +1,13 @@
+shop-title = The Vagrant's Wares
+shop-swap-title = Give up which passive?
+shop-xp = XP
+shop-swap = Swap for
+shop-reroll = Show me something else
+shop-leave = Leave
+shop-cancel = Cancel
+shop-sold = Sold
+shop-upgrade-whetstone = Whetstone (+10% attack)
+shop-upgrade-plating = Plating (+10% defense)
+shop-upgrade-boots = Light Boots (+5% speed)
+shop-upgrade-hourglass = Hourglass (+10% cooldown reduction)
+shop-upgrade-heart = Hearty Stew (+20 max health)
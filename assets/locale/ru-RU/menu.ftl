@@ -1,3 +1,11 @@
+mainmenu-entry-continue = Продолжить
 mainmenu-entry-play = Играть!
 mainmenu-entry-settings = Настройки
 mainmenu-entry-exit = Выход
+settings-title = Управление
+settings-rebind-hint = Нажмите на привязку и затем новую клавишу или кнопку геймпада. Delete удаляет её, Escape отменяет.
+settings-entry-reset = Сбросить по умолчанию
+settings-entry-back = Назад
+settings-rumble = Вибрация
+settings-rumble-on = Вкл
+settings-rumble-off = Выкл
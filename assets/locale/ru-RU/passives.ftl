@@ -0,0 +1,5 @@
+passive-choice-title = Все ячейки пассивных умений заняты
+passive-choice-new = Новое:
+passive-choice-replace = Заменить
+passive-choice-discard = Выбросить
+passive-choice-leave = Оставить пока
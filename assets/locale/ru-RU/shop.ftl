@@ -0,0 +1,13 @@
+shop-title = Товары Бродяги
+shop-swap-title = От какого пассивного умения отказаться?
+shop-xp = ОП
+shop-swap = Обменять на
+shop-reroll = Покажи что-нибудь другое
+shop-leave = Уйти
+shop-cancel = Отмена
+shop-sold = Продано
+shop-upgrade-whetstone = Точильный камень (+10% атаки)
+shop-upgrade-plating = Пластины (+10% защиты)
+shop-upgrade-boots = Лёгкие сапоги (+5% скорости)
+shop-upgrade-hourglass = Песочные часы (+10% сокращения перезарядки)
+shop-upgrade-heart = Сытная похлёбка (+20 к макс. здоровью)
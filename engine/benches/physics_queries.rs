@@ -0,0 +1,260 @@
+//! Per-tick cost of the physics queries, on the walls of `level.01`
+//!
+//! Every tick, the enemies and projectiles move around, the query
+//! pipelines are updated, and each of them makes the kinds of queries the
+//! game makes: enemies check the ground below them and cast along their
+//! velocity, projectiles ray cast along theirs and check what they overlap.
+//!
+//! Run with `cargo bench --bench physics_queries`. Needs the real level
+//! file, fetched with git LFS.
+
+use bevy::prelude::*;
+use bevy::transform::systems::sync_simple_transforms;
+use bevy_ecs_ldtk::ldtk::LdtkJson;
+use criterion::{
+    black_box, criterion_group, criterion_main, BenchmarkId, Criterion,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rapier2d::prelude::{Group, InteractionGroups};
+use theseeker_engine::physics::{
+    update_query_pipeline, Collider, PhysicsWorld, StaticCollider, ENEMY,
+    ENEMY_ATTACK, GROUND, PLAYER,
+};
+
+const LEVEL_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/assets/levels/level.01.ldtk"
+);
+
+/// The int grid values of wall tiles (see `game.rs`)
+const WALL_VALUES: [i32; 4] = [17, 18, 19, 20];
+
+/// Simulate this many ticks before measuring, so that all colliders are
+/// in the pipelines and the static one has settled
+const WARMUP_TICKS: usize = 4;
+
+#[derive(Resource)]
+struct Bounds(Rect);
+
+#[derive(Component)]
+struct Velocity(Vec2);
+
+#[derive(Component)]
+struct Projectile;
+
+/// Wall rectangles (center and size) of the level, one per row of tiles
+///
+/// The game merges them further across rows, so this is a bit more than
+/// the game has to deal with.
+fn level_walls() -> Vec<(Vec2, Vec2)> {
+    let json = std::fs::read_to_string(LEVEL_PATH)
+        .expect("the level file should exist");
+    let project: LdtkJson = serde_json::from_str(&json)
+        .expect("the level file should be valid (is it fetched from git LFS?)");
+    let mut walls = vec![];
+    for level in &project.levels {
+        for layer in level.layer_instances.iter().flatten() {
+            let grid_size = layer.grid_size as f32;
+            let is_wall = |x: i32, y: i32| {
+                x < layer.c_wid
+                    && layer
+                        .int_grid_csv
+                        .get((y * layer.c_wid + x) as usize)
+                        .is_some_and(|value| WALL_VALUES.contains(value))
+            };
+            for y in 0..layer.c_hei {
+                let mut start = None;
+                for x in 0..=layer.c_wid {
+                    match (start, is_wall(x, y)) {
+                        (Some(left), false) => {
+                            let center = Vec2::new(
+                                level.world_x as f32
+                                    + (left + x) as f32 * grid_size / 2.0,
+                                -(level.world_y as f32
+                                    + (y as f32 + 0.5) * grid_size),
+                            );
+                            let size = Vec2::new(
+                                (x - left) as f32 * grid_size,
+                                grid_size,
+                            );
+                            walls.push((center, size));
+                            start = None;
+                        },
+                        (None, true) => start = Some(x),
+                        _ => {},
+                    }
+                }
+            }
+        }
+    }
+    walls
+}
+
+fn setup(
+    walls: &[(Vec2, Vec2)],
+    enemies: usize,
+    projectiles: usize,
+) -> (World, Schedule) {
+    let mut world = World::new();
+    world.init_resource::<PhysicsWorld>();
+    let mut bounds = Rect::from_center_size(Vec2::ZERO, Vec2::ZERO);
+    for &(center, size) in walls {
+        bounds = bounds.union(Rect::from_center_size(center, size));
+        world.spawn((
+            Collider::cuboid(
+                size.x,
+                size.y,
+                InteractionGroups::new(GROUND, Group::all()),
+            ),
+            TransformBundle::from_transform(Transform::from_translation(
+                center.extend(10.0),
+            )),
+            StaticCollider,
+        ));
+    }
+    let mut rng = StdRng::seed_from_u64(0);
+    let random_pos = |rng: &mut StdRng| {
+        Vec2::new(
+            rng.gen_range(bounds.min.x..bounds.max.x),
+            rng.gen_range(bounds.min.y..bounds.max.y),
+        )
+    };
+    for _ in 0..enemies {
+        let pos = random_pos(&mut rng);
+        world.spawn((
+            Collider::cuboid(
+                16.0,
+                10.0,
+                InteractionGroups::new(ENEMY, Group::all()),
+            ),
+            TransformBundle::from_transform(Transform::from_translation(
+                pos.extend(0.0),
+            )),
+            Velocity(Vec2::new(
+                rng.gen_range(-40.0..40.0),
+                -60.0,
+            )),
+        ));
+    }
+    for _ in 0..projectiles {
+        let pos = random_pos(&mut rng);
+        world.spawn((
+            Collider::cuboid(
+                4.0,
+                4.0,
+                InteractionGroups::new(ENEMY_ATTACK, PLAYER | GROUND),
+            ),
+            TransformBundle::from_transform(Transform::from_translation(
+                pos.extend(0.0),
+            )),
+            Velocity(Vec2::new(
+                rng.gen_range(-200.0..200.0),
+                rng.gen_range(-200.0..200.0),
+            )),
+            Projectile,
+        ));
+    }
+    world.insert_resource(Bounds(bounds));
+
+    let mut schedule = Schedule::default();
+    schedule.add_systems(
+        (
+            move_things,
+            sync_simple_transforms,
+            update_query_pipeline,
+            run_queries,
+        )
+            .chain(),
+    );
+    for _ in 0..WARMUP_TICKS {
+        schedule.run(&mut world);
+    }
+    (world, schedule)
+}
+
+/// Move at 96 ticks per second, bouncing off the edges of the level
+fn move_things(
+    mut q: Query<(&mut Transform, &mut Velocity)>,
+    bounds: Res<Bounds>,
+) {
+    for (mut transform, mut velocity) in &mut q {
+        let pos = transform.translation.xy() + velocity.0 / 96.0;
+        if !(bounds.0.min.x..bounds.0.max.x).contains(&pos.x) {
+            velocity.0.x = -velocity.0.x;
+        }
+        if !(bounds.0.min.y..bounds.0.max.y).contains(&pos.y) {
+            velocity.0.y = -velocity.0.y;
+        }
+        transform.translation.x = pos.x;
+        transform.translation.y = pos.y;
+    }
+}
+
+fn run_queries(
+    physics: Res<PhysicsWorld>,
+    q: Query<(
+        Entity,
+        &Transform,
+        &Velocity,
+        &Collider,
+        Has<Projectile>,
+    )>,
+) {
+    let ground = InteractionGroups::new(ENEMY, GROUND);
+    for (entity, transform, velocity, collider, is_projectile) in &q {
+        let pos = transform.translation.xy();
+        let shape = collider.0.shape();
+        if is_projectile {
+            black_box(physics.ray_cast(
+                pos,
+                velocity.0.normalize_or_zero(),
+                velocity.0.length() / 96.0,
+                true,
+                InteractionGroups::new(ENEMY_ATTACK, PLAYER | GROUND),
+                Some(entity),
+            ));
+            black_box(physics.intersect(
+                pos,
+                shape,
+                InteractionGroups::new(ENEMY_ATTACK, PLAYER),
+                Some(entity),
+            ));
+        } else {
+            black_box(physics.shape_cast(
+                pos,
+                Direction2d::NEG_Y,
+                shape,
+                1.0,
+                ground,
+                Some(entity),
+            ));
+            if let Ok(direction) = Direction2d::new(velocity.0) {
+                black_box(physics.shape_cast(
+                    pos,
+                    direction,
+                    shape,
+                    velocity.0.length() / 96.0 + 0.5,
+                    ground,
+                    Some(entity),
+                ));
+            }
+        }
+    }
+}
+
+fn physics_tick(c: &mut Criterion) {
+    let walls = level_walls();
+    let mut group = c.benchmark_group("physics_tick");
+    for count in [100, 300, 600] {
+        let (mut world, mut schedule) = setup(&walls, count, count);
+        group.bench_function(
+            BenchmarkId::from_parameter(count),
+            |b| b.iter(|| schedule.run(&mut world)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, physics_tick);
+criterion_main!(benches);
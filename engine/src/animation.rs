@@ -49,6 +49,10 @@ pub struct SpriteAnimationTracker {
     frame_max: FrameId,
     ticks_per_frame: u32,
     ticks_remain: u32,
+    /// Multiplier for the playback speed, set from code
+    speed: f32,
+    /// Fraction of a tick left over from previous updates
+    speed_carry: f32,
     bookmarks: HashMap<String, FrameId>,
     q_extra: Vec<QueuedAction>,
 }
@@ -95,6 +99,19 @@ impl SpriteAnimationTracker {
         }
     }
 
+    /// Play faster (or slower), on top of the script's ticks per frame
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
+    }
+
+    /// How many ticks of the animation pass in one game tick
+    fn elapsed_ticks(&mut self) -> u32 {
+        self.speed_carry += self.speed;
+        let ticks = self.speed_carry as u32;
+        self.speed_carry -= ticks as f32;
+        ticks
+    }
+
     fn set_auto_next_frame(&mut self, current: FrameId) {
         self.next_frame = if self.reversed {
             if current > self.frame_min {
@@ -455,6 +472,8 @@ impl ScriptTracker for SpriteAnimationTracker {
         self.carryover = carryover;
         self.ticks_per_frame = settings.ticks_per_frame;
         self.ticks_remain = 0;
+        self.speed = 1.0;
+        self.speed_carry = 0.0;
         self.next_frame = Some(settings.frame_start);
         self.frame_min = settings.frame_min;
         self.frame_max = settings.frame_max;
@@ -537,33 +556,41 @@ impl ScriptTracker for SpriteAnimationTracker {
             .get_mut(entity)
             .expect("Animation entity must have TextureAtlas component");
 
-        if self.ticks_remain == 0 {
-            let Some(next_frame) = self.next_frame else {
-                return ScriptUpdateResult::Finished;
-            };
-            if let Some(actions) = self.frame_actions.get(&next_frame) {
-                queue.extend(
-                    actions.iter().map(|&action| QueuedAction {
-                        timing: ScriptActionTiming::Tick(gt.tick()),
-                        action,
-                    }),
-                );
-            }
-            for (quant, action_id) in &self.framequant_actions {
-                if quant.check(next_frame.as_sprite_index() as i64) {
-                    queue.push(QueuedAction {
-                        timing: ScriptActionTiming::Tick(gt.tick()),
-                        action: *action_id,
-                    });
+        // when playing faster, more than one frame can pass in a tick
+        let mut elapsed = self.elapsed_ticks();
+        loop {
+            if self.ticks_remain == 0 {
+                let Some(next_frame) = self.next_frame else {
+                    return ScriptUpdateResult::Finished;
+                };
+                if let Some(actions) = self.frame_actions.get(&next_frame) {
+                    queue.extend(
+                        actions.iter().map(|&action| QueuedAction {
+                            timing: ScriptActionTiming::Tick(gt.tick()),
+                            action,
+                        }),
+                    );
+                }
+                for (quant, action_id) in &self.framequant_actions {
+                    if quant.check(next_frame.as_sprite_index() as i64) {
+                        queue.push(QueuedAction {
+                            timing: ScriptActionTiming::Tick(gt.tick()),
+                            action: *action_id,
+                        });
+                    }
                 }
+                atlas.index = next_frame.as_sprite_index();
+                self.ticks_remain = self.ticks_per_frame;
+                self.set_auto_next_frame(next_frame);
+            }
+            let step = elapsed.min(self.ticks_remain);
+            self.ticks_remain -= step;
+            elapsed -= step;
+            if elapsed == 0 {
+                break;
             }
-            atlas.index = next_frame.as_sprite_index();
-            self.ticks_remain = self.ticks_per_frame;
-            self.set_auto_next_frame(next_frame);
         }
 
-        self.ticks_remain -= 1;
-
         ScriptUpdateResult::NormalRun
     }
 
@@ -638,3 +665,69 @@ impl ScriptAsset for SpriteAnimation {
         builder
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::ecs::system::SystemState;
+
+    use super::*;
+
+    /// The sprite index shown on each tick, playing frames 1 to 8 with 4
+    /// ticks per frame
+    fn play(speed: f32, ticks: usize) -> Vec<usize> {
+        let mut world = World::new();
+        world.insert_resource(GameTime::default());
+        let entity = world.spawn(TextureAtlas::default()).id();
+        let mut state: SystemState<
+            <SpriteAnimationTracker as ScriptTracker>::UpdateParam,
+        > = SystemState::new(&mut world);
+        let settings = SpriteAnimationSettings {
+            atlas_asset_key: None,
+            image_asset_key: None,
+            ticks_per_frame: 4,
+            frame_start: FrameId(1),
+            frame_min: FrameId(1),
+            frame_max: FrameId(8),
+            play_reversed: false,
+            concave_colliders: false,
+            collider_colors: default(),
+        };
+        let mut tracker = SpriteAnimationTracker {
+            ticks_per_frame: settings.ticks_per_frame,
+            next_frame: Some(settings.frame_start),
+            frame_min: settings.frame_min,
+            frame_max: settings.frame_max,
+            ..default()
+        };
+        tracker.set_speed(speed);
+        let mut queue = vec![];
+        (0..ticks)
+            .map(|_| {
+                let mut param = state.get_mut(&mut world);
+                tracker.update(
+                    entity, &settings, &mut param, &mut queue,
+                );
+                world.get::<TextureAtlas>(entity).unwrap().index
+            })
+            .collect()
+    }
+
+    #[test]
+    fn playback_speed() {
+        assert_eq!(
+            play(1.0, 9),
+            [0, 0, 0, 0, 1, 1, 1, 1, 2]
+        );
+        assert_eq!(
+            play(2.0, 9),
+            [0, 0, 1, 1, 2, 2, 3, 3, 4]
+        );
+        // frames are skipped rather than slowing down
+        assert_eq!(play(8.0, 4), [1, 3, 5, 7]);
+        assert_eq!(play(1.5, 6), [0, 0, 0, 1, 1, 2]);
+        assert_eq!(
+            play(0.5, 9),
+            [0, 0, 0, 0, 0, 0, 0, 0, 1]
+        );
+    }
+}
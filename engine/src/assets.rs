@@ -7,15 +7,23 @@ use bevy_common_assets::toml::TomlAssetPlugin;
 use rapier2d::geometry::SharedShape;
 use rapier2d::prelude::Point;
 
-use crate::physics::SpriteShapeMap;
+use self::manifest::StreamedAssets;
+use crate::physics::{
+    shapes_from_pixels, ColliderLayer, KeyColor, SpriteShapeMap,
+};
 use crate::prelude::*;
 
 pub mod animation;
 pub mod config;
+pub mod lint;
+pub mod manifest;
 pub mod script;
 
 pub struct AssetsPlugin<S: States> {
     pub loading_state: S,
+    /// Asset manifest file, for dynamic assets that should be loaded
+    /// on demand instead of preloaded (see [`manifest`])
+    pub manifests: Option<&'static str>,
 }
 
 impl<S: States> Plugin for AssetsPlugin<S> {
@@ -26,8 +34,12 @@ impl<S: States> Plugin for AssetsPlugin<S> {
             TomlAssetPlugin::<self::animation::SpriteAnimation>::new(&[
                 "anim.toml",
             ]),
-            TomlAssetPlugin::<self::config::DynamicConfig>::new(&["cfg.toml"]),
+            TomlAssetPlugin::<self::manifest::AssetManifests>::new(&[
+                "manifest.toml",
+            ]),
         ));
+        app.init_asset::<self::config::DynamicConfig>();
+        app.init_asset_loader::<self::config::DynamicConfigLoader>();
         // dynamic key resolvers for whatever we need
         // we want to be able to do things per-game-tick, so put this in `GameTickUpdate`
         app.add_systems(
@@ -41,6 +53,10 @@ impl<S: States> Plugin for AssetsPlugin<S> {
 
         // asset preloading
         app.init_resource::<PreloadedAssets>();
+        app.insert_resource(self::manifest::StreamedAssets {
+            manifests_path: self.manifests,
+            ..Default::default()
+        });
         app.add_systems(
             Update,
             watch_preload_dynamic_collections
@@ -57,6 +73,24 @@ impl<S: States> Plugin for AssetsPlugin<S> {
                 populate_collider_map.after(finalize_preloaded_dynamic_assets),
             ),
         );
+
+        // on-demand loading of assets listed in manifests
+        app.add_systems(
+            Update,
+            (
+                self::manifest::update_streamed_assets
+                    .run_if(resource_exists::<DynamicAssets>),
+                populate_collider_map
+                    .run_if(|streamed: Res<StreamedAssets>| streamed.built_new),
+            )
+                .chain(),
+        );
+        app.add_systems(
+            Update,
+            self::manifest::streamed_assets_progress
+                .track_progress()
+                .run_if(in_state(self.loading_state.clone())),
+        );
     }
 }
 
@@ -113,6 +147,49 @@ pub fn resolve_asset_keys<T: Asset>(
     }
 }
 
+/// System that outputs the asset with the given key when it becomes
+/// available, and again whenever it is modified (hot reload)
+///
+/// Pipe it into the system that uses the asset:
+///
+/// ```ignore
+/// app.add_systems(
+///     GameTickUpdate,
+///     reload_on_modified::<MyList>("my.list").pipe(apply_my_list),
+/// );
+/// ```
+///
+/// The reason we do not use `AssetEvent::Added` for the first load is
+/// that it fires before `PreloadedAssets` knows the asset key; as a result
+/// you can't tell which specific asset loaded in like that.
+pub fn reload_on_modified<A: Asset + Clone>(
+    key: &'static str,
+) -> impl FnMut(
+    EventReader<AssetEvent<A>>,
+    Res<Assets<A>>,
+    Res<PreloadedAssets>,
+    Local<bool>,
+) -> Option<A> {
+    move |mut ev_asset, assets, preloaded, mut initialized| {
+        // convert from asset key string to bevy handle
+        let id = preloaded.get_single_assetid::<A>(key)?;
+        let mut changed = !*initialized;
+        for ev in ev_asset.read() {
+            if let AssetEvent::Modified { id: modified } = ev {
+                if *modified == id {
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return None;
+        }
+        let asset = assets.get(id)?;
+        *initialized = true;
+        Some(asset.clone())
+    }
+}
+
 /// Holds onto all handles for all "preloaded" assets.
 ///
 /// That is, assets that should be loaded during the loading screen,
@@ -125,6 +202,36 @@ pub struct PreloadedAssets {
 }
 
 impl PreloadedAssets {
+    fn insert(&mut self, key: String, dat: DynamicAssetType) {
+        match &dat {
+            DynamicAssetType::Single(handle) => {
+                self.map_reverse.insert(handle.id(), key.clone());
+            },
+            DynamicAssetType::Collection(handles) => {
+                for handle in handles {
+                    self.map_reverse.insert(handle.id(), key.clone());
+                }
+            },
+        }
+        self.map.insert(key, Some(dat));
+    }
+
+    fn remove(&mut self, key: &str) {
+        let Some(Some(dat)) = self.map.remove(key) else {
+            return;
+        };
+        match dat {
+            DynamicAssetType::Single(handle) => {
+                self.map_reverse.remove(&handle.id());
+            },
+            DynamicAssetType::Collection(handles) => {
+                for handle in handles {
+                    self.map_reverse.remove(&handle.id());
+                }
+            },
+        }
+    }
+
     pub fn get_asset(&self, key: &str) -> Option<&DynamicAssetType> {
         if let Some(v) = self.map.get(key) {
             v.as_ref()
@@ -174,21 +281,47 @@ impl PreloadedAssets {
 
 /// Detects any "dynamic assets", as they get discovered by `bevy_asset_loader`,
 /// and preloads the things we want preloaded.
+///
+/// Anything listed in the asset manifests is skipped, so we need to wait
+/// for the manifest file to load first.
+#[allow(clippy::too_many_arguments)]
 fn watch_preload_dynamic_collections(
     dynamic_ass: Res<DynamicAssets>,
     mut assets_progress: ResMut<AssetsLoading>,
     mut assets_preloaded: ResMut<PreloadedAssets>,
+    mut streamed: ResMut<StreamedAssets>,
+    manifests: Res<Assets<manifest::AssetManifests>>,
     ass: Res<AssetServer>,
     progress: Res<ProgressCounter>,
+    mut manifests_loaded: Local<bool>,
     mut done: Local<bool>,
 ) -> HiddenProgress {
-    if dynamic_ass.is_changed() {
+    let mut manifests_changed = false;
+    if !*manifests_loaded {
+        if let Some(path) = streamed.manifests_path {
+            let handle = streamed.manifests.get_or_insert_with(|| {
+                let handle = ass.load(path);
+                assets_progress.add(handle.clone());
+                handle
+            });
+            if !manifests.contains(&*handle) {
+                return HiddenProgress(Progress::from(false));
+            }
+        }
+        *manifests_loaded = true;
+        manifests_changed = true;
+    }
+    let manifest = streamed
+        .manifests
+        .as_ref()
+        .and_then(|handle| manifests.get(handle));
+
+    if dynamic_ass.is_changed() || manifests_changed {
         for (key, asset) in dynamic_ass.iter_assets() {
-            // TODO: uncomment this when we have per-level asset management
-            // skip preloading level-specific assets
-            // if key.starts_with("level.") {
-            //     continue;
-            // }
+            // skip assets that are loaded on demand
+            if manifest.is_some_and(|m| m.contains_key(key)) {
+                continue;
+            }
 
             for handle in asset.load(&ass) {
                 assets_preloaded.handles.insert(handle.clone());
@@ -225,25 +358,18 @@ fn finalize_preloaded_dynamic_assets(world: &mut World) {
         .remove_resource::<PreloadedAssets>()
         .expect("PreloadedAssets resource must exist!");
 
-    for (key, entry) in preloaded_ass.map.iter_mut() {
+    // (streamed assets requested during loading might already be built)
+    let keys: Vec<String> = preloaded_ass
+        .map
+        .iter()
+        .filter(|(_, entry)| entry.is_none())
+        .map(|(key, _)| key.clone())
+        .collect();
+    for key in keys {
         if let Some(dynass) = dynamic_ass.get_asset(key.as_str()) {
             match dynass.build(world) {
                 Ok(dat) => {
-                    match &dat {
-                        DynamicAssetType::Single(handle) => {
-                            preloaded_ass
-                                .map_reverse
-                                .insert(handle.id(), key.clone());
-                        },
-                        DynamicAssetType::Collection(handles) => {
-                            for handle in handles {
-                                preloaded_ass
-                                    .map_reverse
-                                    .insert(handle.id(), key.clone());
-                            }
-                        },
-                    }
-                    *entry = Some(dat);
+                    preloaded_ass.insert(key, dat);
                 },
                 Err(e) => {
                     error!(
@@ -265,8 +391,17 @@ fn finalize_preloaded_dynamic_assets(world: &mut World) {
     world.insert_resource(preloaded_ass);
 }
 
+/// What [`populate_collider_map`] needs to build the shapes of an image
+struct ImageColliders {
+    layout: Handle<TextureAtlasLayout>,
+    /// The layer of each key color
+    colors: HashMap<KeyColor, ColliderLayer>,
+    concave: bool,
+}
+
 fn populate_collider_map(
     preloaded: Res<PreloadedAssets>,
+    mut streamed: ResMut<StreamedAssets>,
     animations: Res<Assets<animation::SpriteAnimation>>,
     mut images: ResMut<Assets<Image>>,
     layouts: Res<Assets<TextureAtlasLayout>>,
@@ -275,13 +410,31 @@ fn populate_collider_map(
     // we only want to process images that are actually used
     // by animations, so first we need to collect a list of
     // relevant image assets by going through all loaded
-    // animations and resolving their image and layout asset keys
-    let iter_assets = animations.iter().filter_map(|(anim_id, anim)| {
-        anim.resolve_image_atlas(
+    // animations and resolving their image and layout asset keys.
+    // an image gets the key colors of all animations using it, and
+    // concave shapes if any of them asks for them
+    let mut assets: HashMap<Handle<Image>, ImageColliders> = HashMap::default();
+    for (anim_id, anim) in animations.iter() {
+        let Some((h_image, h_layout)) = anim.resolve_image_atlas(
             &preloaded,
             preloaded.get_key_for_asset(anim_id),
-        )
-    });
+        ) else {
+            continue;
+        };
+        let settings = &anim.settings.extended;
+        let colliders = assets.entry(h_image).or_insert(ImageColliders {
+            layout: h_layout,
+            colors: HashMap::default(),
+            concave: false,
+        });
+        colliders.colors.extend(
+            settings
+                .collider_colors
+                .layers()
+                .map(|(layer, color)| (color, layer)),
+        );
+        colliders.concave |= settings.concave_colliders;
+    }
 
     // A dummy collider that gets used when the image has no shape generated.
     // (need to do it this way because it removes any requirements for tracking the collider component
@@ -292,30 +445,40 @@ fn populate_collider_map(
     ])
     .unwrap();
     // fun thing about shared shapes is the are arc, so clones don't use more memory.
-    collider_map.shapes.push((
-        null_shape.clone(),
-        null_shape.clone(),
-        null_shape.clone(),
-        null_shape,
-    ));
-    for (h_image, h_layout) in iter_assets {
+    if collider_map.shapes.is_empty() {
+        collider_map.shapes.push((
+            null_shape.clone(),
+            null_shape.clone(),
+            null_shape.clone(),
+            null_shape,
+        ));
+    }
+    // this runs again whenever streamed assets are loaded;
+    // images that were already processed have had their key colors erased
+    streamed.built_new = false;
+    for (h_image, colliders) in assets {
+        if collider_map.map.contains_key(&h_image.id()) {
+            continue;
+        }
         let Some(image_origin) = images.get_mut(&h_image) else {
             continue;
         };
-        let Some(layout) = layouts.get(&h_layout) else {
+        let Some(layout) = layouts.get(&colliders.layout) else {
             continue;
         };
 
-        let mut collider_ids = vec![];
+        let mut collider_ids: HashMap<ColliderLayer, Vec<usize>> =
+            HashMap::default();
         let mut image =
             image_origin.convert(TextureFormat::Rgba8UnormSrgb).unwrap();
         let width = image.width() as usize;
-        let mut data = &mut image.data;
-        for (i, anim_frame_rect) in layout.textures.iter().enumerate() {
+        let data = &mut image.data;
+        for anim_frame_rect in layout.textures.iter() {
             let min = anim_frame_rect.min;
             let max = anim_frame_rect.max;
             let size = anim_frame_rect.size();
-            let mut collider_points = vec![];
+            let mut layer_pixels: HashMap<ColliderLayer, Vec<UVec2>> =
+                HashMap::default();
             for y in min.y as usize..max.y as usize {
                 for x in min.x as usize..max.x as usize {
                     let pixel_index = (y * width + x) * 4;
@@ -323,54 +486,36 @@ fn populate_collider_map(
                     // Read the pixel values (assuming RGBA format)
                     let pixel = &mut data[pixel_index..pixel_index + 4];
 
-                    // Any pixels with the bright Magenta color will be used for
-                    // building the collider
-                    if pixel[0] == 255
-                        && pixel[1] == 0
-                        && pixel[2] == 255
-                        && pixel[3] == 255
-                    {
-                        collider_points.push(Point::new(
-                            (0.5 + x as f32 - min.x) - size.x * 0.5,
-                            // the siz.y - flips it on y since texture coords are inverted y
-                            size.y - ((0.5 + y as f32 - min.y) + size.y * 0.5),
-                        ));
+                    // Any pixels with a layer's key color will be used for
+                    // building the collider of that layer
+                    let layer = colliders
+                        .colors
+                        .iter()
+                        .find(|(color, _)| color.matches(pixel))
+                        .map(|(_, layer)| *layer);
+                    if let Some(layer) = layer {
+                        layer_pixels.entry(layer).or_default().push(
+                            UVec2::new(x as u32, y as u32) - min.as_uvec2(),
+                        );
                         // Overwrites it with an empty color
                         pixel.copy_from_slice(&[0, 0, 0, 0]);
                     }
                 }
             }
-            if collider_points.len() < 2 {
-                collider_ids.push(0);
-                continue;
+            for layer in ColliderLayer::ALL {
+                let shapes = layer_pixels.get(&layer).and_then(|pixels| {
+                    shapes_from_pixels(pixels, size, colliders.concave)
+                });
+                let i_shapes = if let Some(shapes) = shapes {
+                    collider_map.shapes.push(shapes);
+                    collider_map.shapes.len() - 1
+                } else {
+                    0
+                };
+                collider_ids.entry(layer).or_default().push(i_shapes);
             }
-            let shape = SharedShape::convex_hull(&*collider_points)
-                .expect("Cannot build convex hull");
-            collider_points.iter_mut().for_each(|p| p.x = -p.x);
-            let shape_flipped_x =
-                SharedShape::convex_hull(&*collider_points).unwrap();
-            collider_points.iter_mut().for_each(|p| {
-                p.x = -p.x;
-                p.y = -p.y;
-            });
-            let shape_flipped_y =
-                SharedShape::convex_hull(&*collider_points).unwrap();
-            collider_points.iter_mut().for_each(|p| p.y = -p.y);
-            let shape_flipped_xy =
-                SharedShape::convex_hull(&*collider_points).unwrap();
-
-            let i_new = collider_map.shapes.len();
-            collider_map.shapes.push((
-                shape,
-                shape_flipped_x,
-                shape_flipped_y,
-                shape_flipped_xy,
-            ));
-            collider_ids.push(i_new);
         }
         *image_origin = image.into();
-        // collider ids is either 0 or an index into shapes?
-        // could it be option instead..
         collider_map.map.insert(h_image.id(), collider_ids);
     }
 }
@@ -2,6 +2,7 @@ use bevy::reflect::TypePath;
 
 use super::script::*;
 use crate::data::*;
+use crate::physics::{ColliderLayer, KeyColor};
 use crate::prelude::*;
 
 /// Sprite Animation Asset type
@@ -41,6 +42,53 @@ pub struct SpriteAnimationSettings {
     pub frame_max: FrameId,
     #[serde(default)]
     pub play_reversed: bool,
+    /// Build concave collision shapes from the key-colored pixels of the
+    /// frames, instead of one convex hull around them (see
+    /// [`shapes_from_pixels`])
+    ///
+    /// [`shapes_from_pixels`]: crate::physics::shapes_from_pixels
+    #[serde(default)]
+    pub concave_colliders: bool,
+    /// Key colors of the collider layers drawn into the frames
+    #[serde(default)]
+    pub collider_colors: ColliderColors,
+}
+
+/// The key color of each [`ColliderLayer`] in an animation's frames
+///
+/// Pixels of these colors are erased from the image when it is loaded.
+/// Layers without a color have no shapes, so other colors in the art are
+/// left alone.
+#[derive(Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColliderColors {
+    pub hit: Option<KeyColor>,
+    pub hurt: Option<KeyColor>,
+    pub ground: Option<KeyColor>,
+}
+
+impl Default for ColliderColors {
+    fn default() -> Self {
+        Self {
+            hit: Some(KeyColor::MAGENTA),
+            hurt: None,
+            ground: None,
+        }
+    }
+}
+
+impl ColliderColors {
+    /// The layers that have a key color
+    pub fn layers(&self) -> impl Iterator<Item = (ColliderLayer, KeyColor)> {
+        [
+            (ColliderLayer::Hit, self.hit),
+            (ColliderLayer::Hurt, self.hurt),
+            (ColliderLayer::Ground, self.ground),
+        ]
+        .into_iter()
+        .filter_map(|(layer, color)| Some((layer, color?)))
+    }
 }
 
 #[derive(Debug, Clone)]
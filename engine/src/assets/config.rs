@@ -1,15 +1,268 @@
+use std::fmt::Debug;
+use std::ops::RangeBounds;
+
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
 use bevy::reflect::TypePath;
+use bevy::utils::BoxedFuture;
 
 use crate::prelude::*;
 
-/// Arbitrary config asset type
+/// Config file asset (`*.cfg.toml`)
 ///
-/// Would typically be loaded from TOML files.
+/// Holds the source text, so it can be deserialized into any
+/// [`TypedConfig`] with precise error reporting.
 #[derive(Asset, Debug, Clone)]
-#[derive(Serialize, Deserialize)]
 #[derive(TypePath)]
-#[serde(transparent)]
-pub struct DynamicConfig(pub HashMap<String, DynamicConfigValue>);
+pub struct DynamicConfig {
+    /// Asset path of the file, for error messages
+    pub path: String,
+    source: String,
+}
+
+impl DynamicConfig {
+    /// Deserialize and validate the config
+    ///
+    /// Keys missing from the file keep their default values. On failure,
+    /// returns a list of errors that include the file, key and expected type.
+    pub fn parse<T: TypedConfig>(&self) -> Result<T, Vec<String>> {
+        let config: T = toml::from_str(&self.source)
+            .map_err(|e| vec![format!("{}: {}", self.path, e)])?;
+        let mut errors = Vec::new();
+        config.check_ranges(&mut errors);
+        config.validate(&mut errors);
+        if !errors.is_empty() {
+            return Err(errors
+                .into_iter()
+                .map(|e| format!("{}: {}", self.path, e))
+                .collect());
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Default)]
+pub(super) struct DynamicConfigLoader;
+
+impl AssetLoader for DynamicConfigLoader {
+    type Asset = DynamicConfig;
+    type Settings = ();
+    type Error = AnyError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut source = String::new();
+            reader.read_to_string(&mut source).await?;
+            let path = load_context.path().display().to_string();
+            // catch syntax errors early, when the file is loaded
+            toml::from_str::<toml::Table>(&source)
+                .with_context(|| format!("Invalid config file {}", path))?;
+            Ok(DynamicConfig { path, source })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cfg.toml"]
+    }
+}
+
+/// A config struct that is loaded from a `*.cfg.toml` file
+///
+/// Declare the struct with [`typed_config!`](crate::typed_config), so
+/// missing keys use the default values, and typos are reported.
+/// Fields are documented with doc comments, the same as in the TOML file,
+/// and can declare the range of valid values. Use like:
+///
+/// ```ignore
+/// app.init_resource::<MyConfig>();
+/// app.add_systems(
+///     GameTickUpdate,
+///     reload_on_modified::<DynamicConfig>(MyConfig::KEY)
+///         .pipe(load_config::<MyConfig>),
+/// );
+/// ```
+pub trait TypedConfig:
+    ConfigRanges + Resource + DeserializeOwned + Default
+{
+    /// Asset key of the config file
+    const KEY: &'static str;
+
+    /// Check that values are valid, pushing an error for each invalid one
+    ///
+    /// For checks that can not be declared as the range of a single field,
+    /// like one field that must be smaller than another. See [`check_range`].
+    fn validate(&self, _errors: &mut Vec<String>) {}
+}
+
+/// Checks the ranges declared in [`typed_config!`](crate::typed_config)
+pub trait ConfigRanges {
+    /// Push an error for each field that is outside of its range
+    fn check_ranges(&self, errors: &mut Vec<String>);
+}
+
+/// Declare a [`TypedConfig`] struct
+///
+/// Each field is written once, with its type, optional range in brackets,
+/// and default value. The struct gets `Deserialize` with
+/// `#[serde(default, deny_unknown_fields)]`, a `Default` impl made of the
+/// field defaults, and a [`ConfigRanges`] impl that checks the ranges.
+///
+/// Durations and colors use [`duration_secs`] and [`color_hex`].
+///
+/// ```ignore
+/// typed_config! {
+///     /// Doc comment of the struct
+///     #[derive(Resource, Debug)]
+///     pub struct MyConfig {
+///         /// How fast is it?
+///         speed: f32 [0.0..] = 1.0,
+///         /// (in seconds)
+///         #[serde(with = "duration_secs")]
+///         cooldown: Duration = Duration::from_secs(1),
+///         #[serde(with = "color_hex")]
+///         color: Color = Color::WHITE,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! typed_config {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident: $ty:ty
+                    $([$range:expr])? = $default:expr
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive($crate::prelude::Deserialize)]
+        #[serde(default, deny_unknown_fields)]
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self {
+                    $($field: $default,)*
+                }
+            }
+        }
+
+        impl $crate::assets::config::ConfigRanges for $name {
+            fn check_ranges(&self, _errors: &mut Vec<String>) {
+                $($(
+                    $crate::assets::config::check_range(
+                        _errors,
+                        stringify!($field),
+                        self.$field,
+                        $range,
+                    );
+                )?)*
+            }
+        }
+    };
+}
+
+/// Push an error if `value` is outside of `range`
+pub fn check_range<T, R>(
+    errors: &mut Vec<String>,
+    field: &str,
+    value: T,
+    range: R,
+) where
+    T: PartialOrd + Debug,
+    R: RangeBounds<T> + Debug,
+{
+    if !range.contains(&value) {
+        errors.push(format!(
+            "'{}' is {:?}, expected a value in {:?}",
+            field, value, range
+        ));
+    }
+}
+
+/// System that updates the config resource from its file, when it is loaded
+/// and whenever it is modified (hot reload)
+///
+/// Pipe [`reload_on_modified`](crate::assets::reload_on_modified) into it.
+/// If the file has errors, they are logged and the config is not changed.
+pub fn load_config<T: TypedConfig>(
+    In(cfg): In<Option<DynamicConfig>>,
+    mut config: ResMut<T>,
+) {
+    let Some(cfg) = cfg else {
+        return;
+    };
+    match cfg.parse::<T>() {
+        Ok(new) => *config = new,
+        Err(errors) => {
+            for error in errors {
+                error!("Failed to load config: {}", error);
+            }
+        },
+    }
+}
+
+/// For `#[serde(with = "...")]`: a [`Duration`], written as seconds
+pub mod duration_secs {
+    use super::*;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let secs = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom)
+    }
+
+    pub fn serialize<S>(
+        value: &Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        value.as_secs_f64().serialize(serializer)
+    }
+}
+
+/// For `#[serde(with = "...")]`: a [`Color`], written as a hex string
+/// (`"RRGGBB"` or `"RRGGBBAA"`, optionally starting with `#`)
+pub mod color_hex {
+    use super::*;
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Color, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let hex = String::deserialize(deserializer)?;
+        Color::hex(&hex).map_err(|_| {
+            serde::de::Error::custom(format!(
+                "invalid color \"{}\", expected a hex color like \"#7aa7ff\"",
+                hex
+            ))
+        })
+    }
+
+    pub fn serialize<S>(value: &Color, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let [r, g, b, a] = value.as_rgba_u8();
+        format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a).serialize(serializer)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 #[derive(Serialize, Deserialize)]
@@ -57,34 +310,86 @@ impl DynamicConfigValue {
     }
 }
 
-/// A utility type for loading config values. Use like:
-///
-/// ```
-/// let mut errors = Vec::new();
-/// update_field(&mut errors, &dynamic_cfg.0, "jump_vel_init", |val| struct_config.jump_vel_init = val);
-/// ```
-pub fn update_field<F>(
-    errors: &mut Vec<String>,
-    config: &HashMap<String, DynamicConfigValue>,
-    field: &str,
-    setter: F,
-) where
-    F: FnOnce(f32),
-{
-    if let Some(value) = config.get(field) {
-        match value.as_float() {
-            Ok(val) => setter(val as f32),
-            Err(err) => {
-                errors.push(format!(
-                    "Error parsing '{}': {}",
-                    field, err
-                ))
-            },
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    crate::typed_config! {
+        #[derive(Resource, Debug, PartialEq)]
+        struct TestConfig {
+            speed: f32 [0.0..] = 1.0,
+            hp: u32 [1..=100] = 10,
+            enabled: bool = false,
+            #[serde(with = "duration_secs")]
+            cooldown: Duration = Duration::from_secs(1),
+            #[serde(with = "color_hex")]
+            color: Color = Color::WHITE,
         }
-    } else {
-        errors.push(format!(
-            "Missing '{}' field in the config",
-            field
-        ));
+    }
+
+    impl TypedConfig for TestConfig {
+        const KEY: &'static str = "cfg.test";
+    }
+
+    fn cfg(source: &str) -> DynamicConfig {
+        DynamicConfig {
+            path: "test.cfg.toml".into(),
+            source: source.into(),
+        }
+    }
+
+    #[test]
+    fn typed_values_and_defaults() {
+        let source = r##"
+            speed = 2
+            enabled = true
+            cooldown = 0.5
+            color = "#ff0000"
+        "##;
+        let config: TestConfig = cfg(source).parse().unwrap();
+        assert_eq!(config.speed, 2.0);
+        assert_eq!(config.hp, 10);
+        assert!(config.enabled);
+        assert_eq!(
+            config.cooldown,
+            Duration::from_millis(500)
+        );
+        assert_eq!(config.color, Color::rgb(1.0, 0.0, 0.0));
+
+        let config: TestConfig = cfg("").parse().unwrap();
+        assert_eq!(config, TestConfig::default());
+    }
+
+    #[test]
+    fn errors_name_file_and_key() {
+        let errors = cfg("hp = 1.5").parse::<TestConfig>().unwrap_err();
+        assert!(errors[0].starts_with("test.cfg.toml"));
+        assert!(errors[0].contains("hp = 1.5"));
+        assert!(errors[0].contains("expected u32"));
+
+        let errors = cfg("sped = 1.0").parse::<TestConfig>().unwrap_err();
+        assert!(errors[0].contains("unknown field `sped`"));
+
+        let errors = cfg("cooldown = -1.0").parse::<TestConfig>().unwrap_err();
+        assert!(errors[0].contains("cooldown"));
+
+        let errors = cfg("color = \"red\"").parse::<TestConfig>().unwrap_err();
+        assert!(errors[0].contains("invalid color \"red\""));
+    }
+
+    #[test]
+    fn declared_ranges_are_checked() {
+        let errors = cfg("speed = -1.0").parse::<TestConfig>().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("test.cfg.toml"));
+        assert!(errors[0].contains("'speed'"));
+
+        let errors = cfg("speed = -1.0\nhp = 0")
+            .parse::<TestConfig>()
+            .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[1].contains("'hp' is 0"));
+
+        assert!(cfg("hp = 100").parse::<TestConfig>().is_ok());
     }
 }
@@ -0,0 +1,668 @@
+//! Offline validation of script and animation assets
+//!
+//! Loads the dynamic asset collections (`*.assets.ron`) and every script
+//! (`*.script.toml`) and animation (`*.anim.toml`) file they reference,
+//! without running the game. Reports mistakes that would otherwise only
+//! show up at runtime (if ever):
+//!  - files that are missing or fail to parse
+//!  - unknown frame bookmarks
+//!  - `frame_start`/`frame_min`/`frame_max` (and frame indices used by the
+//!    script) outside of the atlas layout
+//!  - `asset_key`s (`SpawnScript`, `SpawnScene`, `PlayAudio`, ...) that are
+//!    not in any collection
+//!  - slots that actions depend on, but that are never set
+//!  - actions that can never run
+//!
+//! Every problem is a [`Diagnostic`], formatted as `file: severity: message`.
+
+use std::fmt;
+use std::path::Path;
+
+use super::animation::*;
+use super::script::*;
+use crate::data::OneOrMany;
+use crate::prelude::*;
+
+/// How bad is it?
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Suspicious, but might be intentional
+    Warning,
+    /// Definitely broken
+    Error,
+}
+
+/// A problem found in an asset file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Path of the file, relative to the assets folder
+    pub file: String,
+    pub message: String,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}: {}",
+            self.file, self.severity, self.message
+        )
+    }
+}
+
+/// Where the lint gets the asset files from
+pub trait LintSource {
+    /// Read a text file
+    fn read(&self, path: &str) -> Option<String>;
+    /// Check that a (possibly binary) file exists
+    fn exists(&self, path: &str) -> bool;
+}
+
+impl LintSource for Path {
+    fn read(&self, path: &str) -> Option<String> {
+        std::fs::read_to_string(self.join(path)).ok()
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.join(path).exists()
+    }
+}
+
+impl LintSource for HashMap<&str, &str> {
+    fn read(&self, path: &str) -> Option<String> {
+        self.get(path).map(|s| s.to_string())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.contains_key(path)
+    }
+}
+
+/// The parts of `bevy_asset_loader`'s dynamic asset format that we care about
+#[derive(Deserialize)]
+struct Collection(HashMap<String, CollectionEntry>);
+
+#[derive(Deserialize)]
+enum CollectionEntry {
+    File { path: String },
+    Files { paths: Vec<String> },
+    Folder {},
+    TextureAtlasLayout { columns: u32, rows: u32 },
+}
+
+/// Check all script and animation assets
+///
+/// `collections` are the dynamic asset files to load, relative to the asset
+/// folder. `code_slots` are the slots that are set from Rust code (rather
+/// than by scripts); they may start or end with a `*` wildcard.
+pub fn lint_assets<S: LintSource + ?Sized>(
+    source: &S,
+    collections: &[&str],
+    code_slots: &[&str],
+) -> Vec<Diagnostic> {
+    let mut lint = Lint {
+        diagnostics: vec![],
+        entries: HashMap::new(),
+        slots_set: HashSet::new(),
+        slots_used: vec![],
+    };
+    for &file in collections {
+        let Some(text) = source.read(file) else {
+            lint.error(file, "collection file not found".into());
+            continue;
+        };
+        match ron::from_str::<Collection>(&text) {
+            Ok(collection) => {
+                for (key, entry) in collection.0 {
+                    if lint.entries.contains_key(&key) {
+                        lint.warn(
+                            file,
+                            format!("asset key '{}' is defined twice", key),
+                        );
+                    }
+                    lint.entries.insert(key, entry);
+                }
+            },
+            Err(e) => lint.error(
+                file,
+                format!("invalid collection: {}", e),
+            ),
+        }
+    }
+
+    let mut keys: Vec<_> = lint.entries.keys().cloned().collect();
+    keys.sort();
+    for key in keys {
+        let paths = match &lint.entries[&key] {
+            CollectionEntry::File { path } => vec![path.clone()],
+            CollectionEntry::Files { paths } => paths.clone(),
+            _ => vec![],
+        };
+        for path in paths {
+            if !source.exists(&path) {
+                lint.error(
+                    &path,
+                    format!("file not found (asset key '{}')", key),
+                );
+            } else if path.ends_with(".script.toml") {
+                let text = source.read(&path).unwrap_or_default();
+                match toml::from_str::<Script>(&text) {
+                    Ok(script) => lint.check_script(&path, &script),
+                    Err(e) => {
+                        lint.error(&path, format!("invalid script: {}", e))
+                    },
+                }
+            } else if path.ends_with(".anim.toml") {
+                let text = source.read(&path).unwrap_or_default();
+                match toml::from_str::<SpriteAnimation>(&text) {
+                    Ok(anim) => lint.check_animation(&path, &key, &anim),
+                    Err(e) => lint.error(
+                        &path,
+                        format!("invalid animation: {}", e),
+                    ),
+                }
+            }
+        }
+    }
+
+    lint.check_slots(code_slots);
+    lint.diagnostics.sort_by(|a, b| a.file.cmp(&b.file));
+    lint.diagnostics
+}
+
+struct Lint {
+    diagnostics: Vec<Diagnostic>,
+    entries: HashMap<String, CollectionEntry>,
+    /// Slots enabled by any script action
+    slots_set: HashSet<String>,
+    /// Slots that script actions depend on, and where
+    slots_used: Vec<(String, String)>,
+}
+
+impl Lint {
+    fn error(&mut self, file: &str, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            file: file.to_owned(),
+            message,
+        });
+    }
+
+    fn warn(&mut self, file: &str, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            file: file.to_owned(),
+            message,
+        });
+    }
+
+    fn check_script(&mut self, file: &str, script: &Script) {
+        for (i, action) in script.script.iter().enumerate() {
+            let what = format!("[[script]] #{}", i + 1);
+            self.check_params(file, &what, &action.params);
+            self.check_run_if(file, &action.run_if);
+            self.check_action(file, &what, &action.action);
+        }
+    }
+
+    fn check_animation(
+        &mut self,
+        file: &str,
+        key: &str,
+        anim: &SpriteAnimation,
+    ) {
+        let settings = &anim.settings.extended;
+        let image_key = settings
+            .image_asset_key
+            .clone()
+            .unwrap_or_else(|| format!("{}.image", key));
+        if !self.entries.contains_key(&image_key) {
+            self.error(
+                file,
+                format!(
+                    "image asset key '{}' not found",
+                    image_key
+                ),
+            );
+        }
+        let atlas_key = settings
+            .atlas_asset_key
+            .clone()
+            .unwrap_or_else(|| format!("{}.atlas", key));
+        let n_frames = match self.entries.get(&atlas_key) {
+            Some(CollectionEntry::TextureAtlasLayout { columns, rows }) => {
+                Some(columns * rows)
+            },
+            Some(_) => {
+                self.error(
+                    file,
+                    format!(
+                        "'{}' is not a texture atlas layout",
+                        atlas_key
+                    ),
+                );
+                None
+            },
+            None => {
+                self.error(
+                    file,
+                    format!(
+                        "atlas asset key '{}' not found",
+                        atlas_key
+                    ),
+                );
+                None
+            },
+        };
+        let in_atlas = |frame: FrameId| {
+            frame.0 >= 1 && n_frames.map_or(true, |n| frame.0 <= n)
+        };
+        let atlas_desc = match n_frames {
+            Some(n) => format!("the atlas has frames 1..={}", n),
+            None => "frames start at 1".to_owned(),
+        };
+        for (name, frame) in [
+            ("frame_start", settings.frame_start),
+            ("frame_min", settings.frame_min),
+            ("frame_max", settings.frame_max),
+        ] {
+            if !in_atlas(frame) {
+                self.error(
+                    file,
+                    format!(
+                        "{} = {} is outside the atlas ({})",
+                        name, frame.0, atlas_desc
+                    ),
+                );
+            }
+        }
+        let (min, max) = (settings.frame_min, settings.frame_max);
+        if min > max {
+            self.error(
+                file,
+                format!(
+                    "frame_min = {} is greater than frame_max = {}",
+                    min.0, max.0
+                ),
+            );
+        } else if settings.frame_start < min || settings.frame_start > max {
+            self.error(
+                file,
+                format!(
+                    "frame_start = {} is outside of frame_min..=frame_max",
+                    settings.frame_start.0
+                ),
+            );
+        }
+        for (name, frame) in &anim.frame_bookmarks {
+            if !in_atlas(*frame) {
+                self.error(
+                    file,
+                    format!(
+                        "bookmark '{}' = {} is outside the atlas ({})",
+                        name, frame.0, atlas_desc
+                    ),
+                );
+            }
+        }
+
+        let bookmark = |name: &String| anim.frame_bookmarks.get(name).copied();
+        for (i, action) in anim.script.iter().enumerate() {
+            let what = format!("[[script]] #{}", i + 1);
+            let params = &action.params.extended;
+            self.check_params(file, &what, &action.params.common);
+            let mut offset = FrameId::default();
+            let mut unknown = vec![];
+            if let Some(name) = &params.frame_bookmark {
+                match bookmark(name) {
+                    Some(frame) => offset = frame,
+                    None => unknown.push(name.clone()),
+                }
+            }
+            let mut resolve = |frame: &FrameIndexOrBookmark| match frame {
+                FrameIndexOrBookmark::Index(i) => Some(*i + offset),
+                FrameIndexOrBookmark::Bookmark(name) => {
+                    let frame = bookmark(name);
+                    if frame.is_none() {
+                        unknown.push(name.clone());
+                    }
+                    frame
+                },
+            };
+            let lt = params.if_frame_lt.as_ref().and_then(&mut resolve);
+            let le = params.if_frame_le.as_ref().and_then(&mut resolve);
+            let gt = params.if_frame_gt.as_ref().and_then(&mut resolve);
+            let ge = params.if_frame_ge.as_ref().and_then(&mut resolve);
+            let is: Option<Vec<_>> = params.if_frame_is.as_ref().map(|x| {
+                one_or_many(x).iter().filter_map(&mut resolve).collect()
+            });
+            for x in params.if_frame_is_not.iter() {
+                one_or_many(x).iter().for_each(|f| {
+                    resolve(f);
+                });
+            }
+            let run_at: Option<Vec<_>> = match &action.run_if {
+                ExtendedScriptRunIf::Extended(
+                    SpriteAnimationScriptRunIf::Frame(frames),
+                ) => Some(
+                    one_or_many(frames)
+                        .iter()
+                        .filter_map(&mut resolve)
+                        .collect(),
+                ),
+                _ => None,
+            };
+            let target = match &action.action {
+                ExtendedScriptAction::Extended(
+                    SpriteAnimationScriptAction::SetFrameNow {
+                        to_frame_bookmark,
+                        frame_index,
+                    }
+                    | SpriteAnimationScriptAction::SetFrameNext {
+                        to_frame_bookmark,
+                        frame_index,
+                    },
+                ) => {
+                    let base = match to_frame_bookmark {
+                        Some(name) => {
+                            let frame = bookmark(name);
+                            if frame.is_none() {
+                                unknown.push(name.clone());
+                            }
+                            frame
+                        },
+                        None => Some(offset),
+                    };
+                    base.map(|base| frame_index.unwrap_or_default() + base)
+                },
+                _ => None,
+            };
+            for name in unknown {
+                self.error(
+                    file,
+                    format!(
+                        "{}: unknown frame bookmark '{}'",
+                        what, name
+                    ),
+                );
+            }
+            if let Some(frame) = target {
+                if frame < min || frame > max {
+                    self.error(
+                        file,
+                        format!(
+                            "{}: sets frame {}, outside of frame_min..=frame_max",
+                            what, frame.0
+                        ),
+                    );
+                }
+            }
+
+            // can the frame conditions ever be true?
+            let mut frames: Vec<FrameId> =
+                (min.0..=max.0).map(FrameId).collect();
+            if let Some(run_at) = &run_at {
+                frames.retain(|f| run_at.contains(f));
+            }
+            if let Some(is) = &is {
+                frames.retain(|f| is.contains(f));
+            }
+            frames.retain(|f| {
+                lt.map_or(true, |x| *f < x)
+                    && le.map_or(true, |x| *f <= x)
+                    && gt.map_or(true, |x| *f > x)
+                    && ge.map_or(true, |x| *f >= x)
+            });
+            if frames.is_empty() && min <= max {
+                self.warn(
+                    file,
+                    format!(
+                        "{}: unreachable, its frame conditions never match frames {}..={}",
+                        what, min.0, max.0
+                    ),
+                );
+            }
+
+            match &action.run_if {
+                ExtendedScriptRunIf::Common(run_if) => {
+                    self.check_run_if(file, run_if);
+                },
+                ExtendedScriptRunIf::Extended(_) => {},
+            }
+            match &action.action {
+                ExtendedScriptAction::Common(action) => {
+                    self.check_action(file, &what, action);
+                },
+                ExtendedScriptAction::Extended(_) => {},
+            }
+        }
+    }
+
+    fn check_params(
+        &mut self,
+        file: &str,
+        what: &str,
+        params: &CommonScriptParams,
+    ) {
+        for slot in params
+            .require_slots_all
+            .iter()
+            .chain(params.require_slots_any.iter())
+        {
+            self.slots_used.push((file.to_owned(), slot.clone()));
+        }
+        if params.rng_pct.is_some_and(|pct| pct <= 0.0) {
+            self.warn(
+                file,
+                format!(
+                    "{}: unreachable, rng_pct is not positive",
+                    what
+                ),
+            );
+        }
+        if params.if_runcount_lt == Some(0) {
+            self.warn(
+                file,
+                format!(
+                    "{}: unreachable, if_runcount_lt is 0",
+                    what
+                ),
+            );
+        }
+        let contradiction = params.require_slots_all.iter().find(|slot| {
+            params.forbid_slots_any.contains(slot)
+                || params.forbid_slots_all == [(*slot).clone()]
+        });
+        if let Some(slot) = contradiction {
+            self.warn(
+                file,
+                format!(
+                    "{}: unreachable, slot '{}' is both required and forbidden",
+                    what, slot
+                ),
+            );
+        }
+    }
+
+    fn check_run_if(&mut self, file: &str, run_if: &CommonScriptRunIf) {
+        match run_if {
+            CommonScriptRunIf::SlotEnable(slot)
+            | CommonScriptRunIf::SlotDisable(slot) => {
+                self.slots_used.push((file.to_owned(), slot.clone()));
+            },
+            _ => {},
+        }
+    }
+
+    fn check_action(
+        &mut self,
+        file: &str,
+        what: &str,
+        action: &CommonScriptAction,
+    ) {
+        let asset_key = match action {
+            CommonScriptAction::SlotEnable { slot }
+            | CommonScriptAction::SlotToggle { slot } => {
+                self.slots_set.insert(slot.clone());
+                None
+            },
+            CommonScriptAction::SpawnScript { asset_key } => {
+                if let Some(CollectionEntry::File { path }) =
+                    self.entries.get(asset_key)
+                {
+                    if !path.ends_with(".script.toml") {
+                        self.error(
+                            file,
+                            format!(
+                                "{}: SpawnScript asset key '{}' is not a script",
+                                what, asset_key
+                            ),
+                        );
+                    }
+                }
+                Some(asset_key)
+            },
+            CommonScriptAction::SpawnScene { asset_key, .. }
+            | CommonScriptAction::PlayAudio { asset_key, .. }
+            | CommonScriptAction::PlayBackgroundAudio { asset_key, .. } => {
+                Some(asset_key)
+            },
+            _ => None,
+        };
+        if let Some(key) = asset_key {
+            if !self.entries.contains_key(key) {
+                self.error(
+                    file,
+                    format!("{}: unknown asset key '{}'", what, key),
+                );
+            }
+        }
+    }
+
+    fn check_slots(&mut self, code_slots: &[&str]) {
+        let set_from_code = |slot: &str| {
+            code_slots.iter().any(|pattern| {
+                if let Some(prefix) = pattern.strip_suffix('*') {
+                    slot.starts_with(prefix)
+                } else if let Some(suffix) = pattern.strip_prefix('*') {
+                    slot.ends_with(suffix)
+                } else {
+                    slot == *pattern
+                }
+            })
+        };
+        let mut reported = HashSet::new();
+        for (file, slot) in std::mem::take(&mut self.slots_used) {
+            if self.slots_set.contains(&slot) || set_from_code(&slot) {
+                continue;
+            }
+            if reported.insert((file.clone(), slot.clone())) {
+                self.warn(
+                    &file,
+                    format!(
+                        "slot '{}' is used, but never set by scripts or code",
+                        slot
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn one_or_many<T>(x: &OneOrMany<T>) -> &[T] {
+    match x {
+        OneOrMany::Single(x) => std::slice::from_ref(x),
+        OneOrMany::Many(x) => x,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const COLLECTION: &str = r#"({
+        "anim.test": File(path: "test.anim.toml"),
+        "anim.test.image": File(path: "test.png"),
+        "anim.test.atlas": TextureAtlasLayout(
+            tile_size_x: 16.,
+            tile_size_y: 16.,
+            rows: 1,
+            columns: 4,
+        ),
+        "script.test": File(path: "test.script.toml"),
+    })"#;
+
+    fn lint(anim: &str, script: &str) -> Vec<String> {
+        let files = HashMap::from_iter([
+            ("test.assets.ron", COLLECTION),
+            ("test.anim.toml", anim),
+            ("test.png", ""),
+            ("test.script.toml", script),
+        ]);
+        lint_assets(&files, &["test.assets.ron"], &["Code*"])
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    const ANIM: &str = r#"
+        frame_bookmarks = { loop = 2 }
+        [settings]
+        ticks_per_frame = 8
+        frame_start = 1
+        frame_min = 1
+        frame_max = 4
+        [[script]]
+        run_at_frame = 4
+        action = "SetFrameNext"
+        to_frame_bookmark = "loop"
+    "#;
+
+    #[test]
+    fn valid_assets() {
+        let script = r#"
+            [[script]]
+            run_on_slot_enable = "CodeSlot"
+            action = "SpawnScript"
+            asset_key = "script.test"
+        "#;
+        assert_eq!(lint(ANIM, script), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_mistakes() {
+        let anim = ANIM
+            .replace("frame_max = 4", "frame_max = 5")
+            .replace("\"loop\"", "\"lop\"");
+        let script = r#"
+            [[script]]
+            run_on_slot_enable = "Typo"
+            action = "PlayAudio"
+            asset_key = "audio.missing"
+        "#;
+        let errors = lint(&anim, script);
+        assert_eq!(errors.len(), 4, "{:#?}", errors);
+        assert!(errors[0].contains("frame_max = 5 is outside the atlas"));
+        assert!(errors[1].contains("unknown frame bookmark 'lop'"));
+        assert!(errors[2].contains("unknown asset key 'audio.missing'"));
+        assert!(errors[3].contains("slot 'Typo'"));
+    }
+
+    #[test]
+    fn reports_unreachable_actions() {
+        let anim = ANIM.replace("run_at_frame = 4", "run_at_frame = 7");
+        let errors = lint(&anim, "");
+        assert_eq!(errors.len(), 1, "{:#?}", errors);
+        assert!(errors[0].contains("#1: unreachable"));
+    }
+}
@@ -0,0 +1,402 @@
+//! Asset manifests: groups of dynamic asset keys that are loaded on demand
+//!
+//! A manifest file (`*.manifest.toml`) has a table for each group, listing
+//! the dynamic asset keys it needs:
+//!
+//! ```toml
+//! ["level.01/Level_0"]
+//! keys = ["anim.yak.*", "audio.ambient.cave"]
+//! ```
+//!
+//! A `*` at the end of a key matches all keys that start with the rest.
+//!
+//! Keys that are in any manifest are not preloaded. Instead, they are
+//! loaded in the background while their group is requested with
+//! [`StreamedAssets::request`], and dropped again when it is released.
+//! Once loaded, they are available from [`PreloadedAssets`], just like
+//! preloaded assets, so `AssetKey` resolution works the same for both.
+
+use bevy::asset::RecursiveDependencyLoadState;
+use bevy::reflect::TypePath;
+
+use crate::prelude::*;
+
+/// Manifest file asset (`*.manifest.toml`)
+#[derive(Asset, Debug, Clone, Default)]
+#[derive(Deserialize)]
+#[derive(TypePath)]
+pub struct AssetManifests {
+    #[serde(flatten)]
+    pub groups: HashMap<String, AssetManifest>,
+}
+
+/// The dynamic asset keys needed by one group
+#[derive(Debug, Clone, Default)]
+#[derive(Deserialize)]
+pub struct AssetManifest {
+    #[serde(default)]
+    pub keys: Vec<String>,
+}
+
+impl AssetManifests {
+    /// Is the key listed in any group (and should therefore not be preloaded)?
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.groups.values().any(|group| group.matches(key))
+    }
+}
+
+impl AssetManifest {
+    pub fn matches(&self, key: &str) -> bool {
+        self.keys.iter().any(|pattern| {
+            if let Some(prefix) = pattern.strip_suffix('*') {
+                key.starts_with(prefix)
+            } else {
+                pattern == key
+            }
+        })
+    }
+}
+
+/// Loading state of a streamed dynamic asset key
+enum StreamedKey {
+    /// Waiting for these handles to load
+    Loading(Vec<UntypedHandle>),
+    /// Built and added to `PreloadedAssets`
+    Ready,
+    /// Failed to load; don't try again until it is released
+    Failed,
+}
+
+/// Tracks which manifest groups are wanted, and loads/unloads their assets
+#[derive(Resource, Default)]
+pub struct StreamedAssets {
+    /// Path of the manifest file, if the game uses one
+    pub(super) manifests_path: Option<&'static str>,
+    pub(super) manifests: Option<Handle<AssetManifests>>,
+    /// Groups that have been requested, with their keys (once known)
+    requested: HashMap<String, Option<Vec<String>>>,
+    keys: HashMap<String, StreamedKey>,
+    /// Set when new assets were built, until the collider map is updated
+    pub(super) built_new: bool,
+}
+
+impl StreamedAssets {
+    /// Start loading the assets of a group (if not already)
+    ///
+    /// Requesting a group that is not in the manifest is not an error:
+    /// it simply has no assets of its own.
+    pub fn request(&mut self, group: &str) {
+        if !self.requested.contains_key(group) {
+            self.requested.insert(group.to_owned(), None);
+        }
+    }
+
+    /// Stop holding on to the assets of a group
+    ///
+    /// They are unloaded once no entity uses them anymore, unless
+    /// another requested group also needs them.
+    pub fn release(&mut self, group: &str) {
+        self.requested.remove(group);
+    }
+
+    /// Release all groups
+    pub fn release_all(&mut self) {
+        self.requested.clear();
+    }
+
+    pub fn is_requested(&self, group: &str) -> bool {
+        self.requested.contains_key(group)
+    }
+
+    /// Iterate over the names of all requested groups
+    pub fn iter_requested(&self) -> impl Iterator<Item = &str> {
+        self.requested.keys().map(|s| s.as_str())
+    }
+
+    /// How many of the group's assets have finished loading
+    ///
+    /// Assets that failed to load count as done, so that a broken
+    /// asset does not stall whoever is waiting for the group.
+    pub fn progress(&self, group: &str) -> Progress {
+        let Some(Some(keys)) = self.requested.get(group) else {
+            // we don't know the keys yet
+            return Progress { done: 0, total: 1 };
+        };
+        let done = keys
+            .iter()
+            .filter(|key| {
+                !matches!(
+                    self.keys.get(key.as_str()),
+                    None | Some(StreamedKey::Loading(_))
+                )
+            })
+            .count();
+        Progress {
+            done: done as u32,
+            total: keys.len() as u32,
+        }
+    }
+
+    /// The still loading keys of all groups whose keys have all loaded
+    ///
+    /// `loaded` are the loading keys whose handles have finished loading.
+    fn keys_to_build(&self, loaded: &HashSet<String>) -> HashSet<String> {
+        let mut r = HashSet::default();
+        for keys in self.requested.values().flatten() {
+            let loading = keys.iter().filter(|key| {
+                matches!(
+                    self.keys.get(key.as_str()),
+                    Some(StreamedKey::Loading(_))
+                )
+            });
+            if loading.clone().all(|key| loaded.contains(key)) {
+                r.extend(loading.cloned());
+            }
+        }
+        r
+    }
+
+    pub fn is_ready(&self, group: &str) -> bool {
+        let progress = self.progress(group);
+        progress.done >= progress.total
+    }
+
+    /// Combined progress of all requested groups
+    pub fn progress_all(&self) -> Progress {
+        self.requested
+            .keys()
+            .map(|group| self.progress(group))
+            .fold(
+                Progress { done: 0, total: 0 },
+                |a, b| Progress {
+                    done: a.done + b.done,
+                    total: a.total + b.total,
+                },
+            )
+    }
+}
+
+/// Load the assets of requested groups, and drop those of released groups
+pub(super) fn update_streamed_assets(world: &mut World) {
+    let Some(manifests_handle) =
+        world.resource::<StreamedAssets>().manifests.clone()
+    else {
+        return;
+    };
+    let Some(manifests) = world
+        .resource::<Assets<AssetManifests>>()
+        .get(&manifests_handle)
+        .cloned()
+    else {
+        return;
+    };
+    // take them out of the world, to avoid mut conflicts
+    // (building dynamic assets needs the world)
+    let dynamic_ass = world
+        .remove_resource::<DynamicAssets>()
+        .expect("DynamicAssets resource must exist!");
+    let mut preloaded = world
+        .remove_resource::<PreloadedAssets>()
+        .expect("PreloadedAssets resource must exist!");
+    let mut streamed = world
+        .remove_resource::<StreamedAssets>()
+        .expect("StreamedAssets resource must exist!");
+    let ass = world.resource::<AssetServer>().clone();
+
+    // find out the keys of newly requested groups
+    for (group, keys) in streamed.requested.iter_mut() {
+        if keys.is_some() {
+            continue;
+        }
+        let mut found = vec![];
+        if let Some(manifest) = manifests.groups.get(group) {
+            for (key, _) in dynamic_ass.iter_assets() {
+                if manifest.matches(key) {
+                    found.push(key.to_owned());
+                }
+            }
+            if found.is_empty() {
+                warn!(
+                    "Asset manifest group {:?} matches no dynamic assets",
+                    group
+                );
+            }
+        }
+        *keys = Some(found);
+    }
+    let wanted: HashSet<String> = streamed
+        .requested
+        .values()
+        .flatten()
+        .flatten()
+        .cloned()
+        .collect();
+
+    // drop keys that are no longer wanted
+    streamed.keys.retain(|key, state| {
+        if wanted.contains(key) {
+            return true;
+        }
+        if let StreamedKey::Ready = state {
+            preloaded.remove(key);
+        }
+        trace!("Released streamed asset {:?}", key);
+        false
+    });
+
+    // start loading keys that are newly wanted
+    for key in wanted {
+        if streamed.keys.contains_key(&key) {
+            continue;
+        }
+        let Some(dynass) = dynamic_ass.get_asset(&key) else {
+            error!(
+                "Dynamic asset for key {:?} does not exist!",
+                key
+            );
+            streamed.keys.insert(key, StreamedKey::Failed);
+            continue;
+        };
+        let handles = dynass.load(&ass);
+        streamed.keys.insert(key, StreamedKey::Loading(handles));
+    }
+
+    // find the keys whose handles have all finished loading
+    let mut loaded = HashSet::default();
+    for (key, state) in streamed.keys.iter_mut() {
+        let StreamedKey::Loading(handles) = state else {
+            continue;
+        };
+        let mut key_loaded = true;
+        for handle in handles.iter() {
+            match ass.get_recursive_dependency_load_state(handle.id()) {
+                Some(RecursiveDependencyLoadState::Loaded) => {},
+                Some(RecursiveDependencyLoadState::Failed) => {
+                    error!(
+                        "Failed to load streamed asset {:?}",
+                        key
+                    );
+                    *state = StreamedKey::Failed;
+                    key_loaded = false;
+                    break;
+                },
+                _ => {
+                    key_loaded = false;
+                    break;
+                },
+            }
+        }
+        if key_loaded {
+            loaded.insert(key.clone());
+        }
+    }
+
+    // build each group once all of its keys have loaded, all at once,
+    // because keys often refer to each other (such as animations and
+    // their images) and should become available together
+    for key in streamed.keys_to_build(&loaded) {
+        let dynass = dynamic_ass
+            .get_asset(&key)
+            .expect("key was checked when loading started");
+        let state = match dynass.build(world) {
+            Ok(dat) => {
+                trace!("Loaded streamed asset {:?}", key);
+                preloaded.insert(key.clone(), dat);
+                streamed.built_new = true;
+                StreamedKey::Ready
+            },
+            Err(e) => {
+                error!(
+                    "Failed to build dynamic asset for key {:?}: {:#}",
+                    key, e
+                );
+                StreamedKey::Failed
+            },
+        };
+        streamed.keys.insert(key, state);
+    }
+
+    // put them back
+    world.insert_resource(dynamic_ass);
+    world.insert_resource(preloaded);
+    world.insert_resource(streamed);
+}
+
+/// While in the loading state, wait for any groups requested so far
+pub(super) fn streamed_assets_progress(
+    streamed: Res<StreamedAssets>,
+) -> Progress {
+    streamed.progress_all()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn manifest_key_patterns() {
+        let manifests: AssetManifests = toml::from_str(
+            r#"
+            ["level.01/Level_0"]
+            keys = ["anim.yak.*", "audio.cave"]
+
+            ["level.01/Level_1"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(manifests.groups.len(), 2);
+        assert!(manifests.contains_key("anim.yak.Idle"));
+        assert!(manifests.contains_key("anim.yak.Idle.image"));
+        assert!(manifests.contains_key("audio.cave"));
+        assert!(!manifests.contains_key("audio.cave2"));
+        assert!(!manifests.contains_key("anim.yakk.Idle"));
+        assert!(manifests.groups["level.01/Level_1"].keys.is_empty());
+    }
+
+    #[test]
+    fn progress_of_requested_groups() {
+        let mut streamed = StreamedAssets::default();
+        assert!(!streamed.is_ready("a"));
+        streamed.request("a");
+        assert!(!streamed.is_ready("a"));
+        streamed.requested.insert(
+            "a".into(),
+            Some(vec!["x".into(), "y".into()]),
+        );
+        streamed.keys.insert("x".into(), StreamedKey::Ready);
+        streamed
+            .keys
+            .insert("y".into(), StreamedKey::Loading(vec![]));
+        assert_eq!(streamed.progress("a").done, 1);
+        assert!(!streamed.is_ready("a"));
+        streamed.keys.insert("y".into(), StreamedKey::Failed);
+        assert!(streamed.is_ready("a"));
+        streamed.release("a");
+        assert!(!streamed.is_requested("a"));
+    }
+
+    #[test]
+    fn groups_are_built_separately() {
+        let mut streamed = StreamedAssets::default();
+        streamed.requested.insert(
+            "a".into(),
+            Some(vec!["x".into(), "shared".into()]),
+        );
+        streamed.requested.insert(
+            "b".into(),
+            Some(vec!["y".into(), "shared".into()]),
+        );
+        for key in ["x", "y", "shared"] {
+            streamed
+                .keys
+                .insert(key.into(), StreamedKey::Loading(vec![]));
+        }
+        let loaded: HashSet<String> =
+            ["x".into(), "shared".into()].into_iter().collect();
+        // "b" is still waiting for "y", but that does not hold up "a"
+        assert_eq!(streamed.keys_to_build(&loaded), loaded);
+        streamed.keys.insert("x".into(), StreamedKey::Ready);
+        streamed.keys.insert("shared".into(), StreamedKey::Ready);
+        assert!(streamed.keys_to_build(&HashSet::default()).is_empty());
+    }
+}
@@ -3,6 +3,7 @@ use bevy::reflect::TypePath;
 use super::config::DynamicConfigValue;
 use crate::data::*;
 use crate::prelude::*;
+use crate::script::expr::ScriptExpr;
 
 /// Scripted Sequence Asset type
 ///
@@ -12,6 +13,8 @@ use crate::prelude::*;
 #[derive(TypePath)]
 pub struct Script {
     /// Any customization configs
+    ///
+    /// Also used as the initial values of the script's variables.
     #[serde(default)]
     pub config: ScriptConfig,
     /// Settings for the script runtime
@@ -87,6 +90,18 @@ pub struct CommonScriptParams {
     pub if_runcount_gt: Option<u32>,
     pub if_runcount_ge: Option<u32>,
     pub if_runcount_quant: Option<Quant>,
+    #[serde(default)]
+    pub if_var_eq: HashMap<String, ScriptExpr>,
+    #[serde(default)]
+    pub if_var_ne: HashMap<String, ScriptExpr>,
+    #[serde(default)]
+    pub if_var_lt: HashMap<String, ScriptExpr>,
+    #[serde(default)]
+    pub if_var_le: HashMap<String, ScriptExpr>,
+    #[serde(default)]
+    pub if_var_gt: HashMap<String, ScriptExpr>,
+    #[serde(default)]
+    pub if_var_ge: HashMap<String, ScriptExpr>,
 }
 
 #[derive(Debug, Clone)]
@@ -151,6 +166,10 @@ pub enum CommonScriptAction {
     SlotDisable { slot: String },
     /// Toggle a Slot
     SlotToggle { slot: String },
+    /// Set a variable to the value of an expression
+    SetVar { var: String, value: ScriptExpr },
+    /// Add the value of an expression to a numeric variable
+    AddVar { var: String, value: ScriptExpr },
     /// Play a sound (precise timing based on action's trigger condition)
     PlayAudio {
         asset_key: String,
@@ -1,10 +1,15 @@
 use crate::physics::{Collider, LinearVelocity, ShapeCaster};
 use crate::prelude::*;
+use crate::time::GameTickPost;
 
 pub struct GentPlugin;
 
 impl Plugin for GentPlugin {
     fn build(&self, app: &mut App) {
+        app.add_systems(
+            GameTickPost,
+            track_interpolated_transforms,
+        );
         app.add_systems(
             PostUpdate,
             (transform_gfx_from_gent
@@ -19,6 +24,7 @@ pub struct GentPhysicsBundle {
     pub collider: Collider,
     pub shapecast: ShapeCaster,
     pub linear_velocity: LinearVelocity,
+    pub interpolation: InterpolateTransform,
 }
 
 #[derive(Component, Debug)]
@@ -35,18 +41,86 @@ pub struct TransformGfxFromGent {
     // potential to add offset here?... or does it not make sense
 }
 
+/// Smooth out the movement of a gent's gfx, between game ticks
+///
+/// The gent's translation is recorded after every game tick, and its gfx
+/// entities are displayed between the previous and current tick, using
+/// [`GameTime::overstep`]. This means the gfx lag up to one tick behind the
+/// simulation, but move smoothly at any display refresh rate.
+///
+/// Gents without this component have their gfx snap to the latest tick.
+#[derive(Component, Debug, Clone)]
+pub struct InterpolateTransform {
+    previous: Vec3,
+    current: Vec3,
+    snap: bool,
+}
+
+impl Default for InterpolateTransform {
+    fn default() -> Self {
+        Self {
+            previous: Vec3::ZERO,
+            current: Vec3::ZERO,
+            // don't interpolate from the origin when spawned
+            snap: true,
+        }
+    }
+}
+
+impl InterpolateTransform {
+    /// Don't interpolate the movement during the current tick
+    ///
+    /// Call this when teleporting the gent, so that its gfx jump directly
+    /// to the new position, instead of sliding there.
+    pub fn teleport(&mut self) {
+        self.snap = true;
+    }
+
+    fn record(&mut self, translation: Vec3) {
+        self.previous = if self.snap { translation } else { self.current };
+        self.current = translation;
+        self.snap = false;
+    }
+
+    /// How far the interpolated position is from the current tick's position
+    fn offset(&self, overstep: f32) -> Vec3 {
+        self.previous.lerp(self.current, overstep) - self.current
+    }
+}
+
+fn track_interpolated_transforms(
+    mut q: Query<(&Transform, &mut InterpolateTransform)>,
+) {
+    for (xf, mut interpolation) in &mut q {
+        interpolation.record(xf.translation);
+    }
+}
+
 fn transform_gfx_from_gent(
     mut q_target: Query<(
         &mut GlobalTransform,
         &TransformGfxFromGent,
     )>,
-    q_src: Query<&GlobalTransform, Without<TransformGfxFromGent>>,
+    q_src: Query<
+        (
+            &GlobalTransform,
+            Option<&InterpolateTransform>,
+        ),
+        Without<TransformGfxFromGent>,
+    >,
+    gametime: Res<GameTime>,
 ) {
+    let overstep = gametime.overstep() as f32;
     for (mut xf_target, gfx2gent) in &mut q_target {
-        let Ok(xf_src) = q_src.get(gfx2gent.gent) else {
+        let Ok((xf_src, interpolation)) = q_src.get(gfx2gent.gent) else {
             continue;
         };
         *xf_target = *xf_src;
+        if let Some(interpolation) = interpolation {
+            let mut xf = xf_target.compute_transform();
+            xf.translation += interpolation.offset(overstep);
+            *xf_target = xf.into();
+        }
         if gfx2gent.pixel_aligned {
             let mut xf = xf_target.compute_transform();
             xf.translation = xf.translation.round();
@@ -54,3 +128,23 @@ fn transform_gfx_from_gent(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn interpolate_between_ticks() {
+        let mut interpolation = InterpolateTransform::default();
+        interpolation.record(Vec3::new(10.0, 0.0, 0.0));
+        assert_eq!(interpolation.offset(0.5), Vec3::ZERO);
+        interpolation.record(Vec3::new(14.0, 0.0, 0.0));
+        assert_eq!(
+            interpolation.offset(0.25),
+            Vec3::new(-3.0, 0.0, 0.0)
+        );
+        interpolation.teleport();
+        interpolation.record(Vec3::new(100.0, 0.0, 0.0));
+        assert_eq!(interpolation.offset(0.25), Vec3::ZERO);
+    }
+}
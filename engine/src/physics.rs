@@ -1,12 +1,20 @@
 use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
 
 use bevy::prelude::*;
 use bevy::transform::TransformSystem::TransformPropagate;
 use rapier2d::na::{Unit, UnitComplex};
 use rapier2d::parry;
-use rapier2d::prelude::{Collider as RapierCollider, *};
+use rapier2d::parry::transformation::vhacd::{VHACDParameters, VHACD};
+use rapier2d::prelude::{
+    Collider as RapierCollider, ColliderHandle as RapierColliderHandle, *,
+};
 
-use crate::prelude::{GameTickUpdate, HashMap};
+use crate::prelude::{
+    DeserializeFromStr, Error, GameTickUpdate, GameTimeAppExt, HashMap,
+    HashSet, SerializeDisplay,
+};
 use crate::script::ScriptSet;
 
 /// A manual implementation of rapier to only use the features required by our project
@@ -23,6 +31,8 @@ impl Plugin for PhysicsPlugin {
             GameTickUpdate,
             PhysicsSet.after(TransformPropagate),
         );
+        app.add_gametick_event::<CollisionStarted>();
+        app.add_gametick_event::<CollisionEnded>();
         app.add_systems(
             GameTickUpdate,
             (
@@ -30,6 +40,9 @@ impl Plugin for PhysicsPlugin {
                     .before(PhysicsSet)
                     .after(ScriptSet::Run),
                 update_query_pipeline.in_set(PhysicsSet),
+                detect_sensor_collisions
+                    .in_set(PhysicsSet)
+                    .after(update_query_pipeline),
             ),
         );
         #[cfg(feature = "dev")]
@@ -63,6 +76,96 @@ pub const ENEMY_INSIDE: Group = Group::from_bits_truncate(0b1000000);
 /// Combination of ENEMY and ENEMY_INSIDE,
 /// used for checking the players attacks
 pub const ENEMY_HURT: Group = Group::from_bits_truncate(0b1000010);
+/// Platforms that can be jumped through from below
+///
+/// Shape casts and ray casts only hit them while moving downwards, from
+/// above their top. Leave this out of a filter to fall through them.
+pub const ONE_WAY: Group = Group::from_bits_truncate(0b10000000);
+
+/// How far into a one-way platform a cast can start, and still hit it
+///
+/// Gents can end up resting slightly inside the ground, and should
+/// still stand on the platform.
+const ONE_WAY_TOLERANCE: f32 = 1.5;
+
+/// Can a cast in the (vertical) direction, starting at the height
+/// `bottom`, hit the collider?
+fn can_hit_one_way(
+    collider: &RapierCollider,
+    direction_y: f32,
+    bottom: f32,
+) -> bool {
+    if !collider.collision_groups().memberships.contains(ONE_WAY) {
+        return true;
+    }
+    let top = collider.compute_aabb().maxs.y;
+    direction_y < 0.0 && bottom >= top - ONE_WAY_TOLERANCE
+}
+
+/// Layers of collision shapes that can be drawn into animation frames
+///
+/// Each layer is drawn with its own key color. Only [`ColliderLayer::Hit`]
+/// has one by default; animations pick the colors of the other layers in
+/// their settings (see [`ColliderColors`]).
+///
+/// [`ColliderColors`]: crate::assets::animation::ColliderColors
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColliderLayer {
+    /// Where an attack hits (magenta, `#ff00ff`, by default)
+    #[default]
+    Hit,
+    /// Where the gent can be hit
+    Hurt,
+    /// What the gent collides with the world with
+    Ground,
+}
+
+impl ColliderLayer {
+    pub const ALL: [Self; 3] = [Self::Hit, Self::Hurt, Self::Ground];
+}
+
+/// The opaque RGB color that the pixels of a collider layer are drawn with
+///
+/// Written as a hex string, like `"#00ffff"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(SerializeDisplay, DeserializeFromStr)]
+pub struct KeyColor(pub [u8; 3]);
+
+impl KeyColor {
+    /// Bright magenta, the color of [`ColliderLayer::Hit`] by default
+    pub const MAGENTA: Self = KeyColor([255, 0, 255]);
+
+    /// Is the RGBA pixel drawn in this color?
+    pub fn matches(self, pixel: &[u8]) -> bool {
+        pixel[..3] == self.0 && pixel[3] == 255
+    }
+}
+
+impl fmt::Display for KeyColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid key color {0:?}, expected a hex color like \"#00ffff\"")]
+pub struct ParseKeyColorError(String);
+
+impl FromStr for KeyColor {
+    type Err = ParseKeyColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        let err = || ParseKeyColorError(s.to_owned());
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(err());
+        }
+        let rgb = u32::from_str_radix(hex, 16).map_err(|_| err())?;
+        let [_, r, g, b] = rgb.to_be_bytes();
+        Ok(KeyColor([r, g, b]))
+    }
+}
 
 #[derive(Resource, Default)]
 pub struct SpriteShapeMap {
@@ -73,7 +176,126 @@ pub struct SpriteShapeMap {
         SharedShape,
         SharedShape,
     )>,
-    pub map: HashMap<AssetId<Image>, Vec<usize>>,
+    /// For each image, the index into `shapes` of every atlas frame,
+    /// per layer (0 if the frame has no shape on that layer)
+    pub map: HashMap<AssetId<Image>, HashMap<ColliderLayer, Vec<usize>>>,
+}
+
+/// Build the shapes of one layer of an animation frame, from its
+/// key-colored pixels
+///
+/// `pixels` are coordinates within the frame (with y pointing down),
+/// and `size` is the size of the frame. The shape is centered on the
+/// frame.
+///
+/// Normally, all pixels are the corners of one convex hull. If `concave`
+/// is set (see [`SpriteAnimationSettings::concave_colliders`]), pixels
+/// that touch other pixels form filled areas instead, which are split
+/// into convex parts, and only lone pixels are hull corners. Returns the
+/// shape normal, flipped x, flipped y, and flipped x & y, like
+/// [`SpriteShapeMap::shapes`].
+///
+/// [`SpriteAnimationSettings::concave_colliders`]: crate::assets::animation::SpriteAnimationSettings::concave_colliders
+pub fn shapes_from_pixels(
+    pixels: &[UVec2],
+    size: Vec2,
+    concave: bool,
+) -> Option<(
+    SharedShape,
+    SharedShape,
+    SharedShape,
+    SharedShape,
+)> {
+    let to_point =
+        |x: f32, y: f32| Point::new(x - size.x * 0.5, size.y * 0.5 - y);
+    let set: HashSet<IVec2> = pixels.iter().map(|p| p.as_ivec2()).collect();
+    let touches_others = |p: &IVec2| {
+        (-1..=1).any(|dy| {
+            (-1..=1).any(|dx| {
+                (dx, dy) != (0, 0) && set.contains(&(*p + IVec2::new(dx, dy)))
+            })
+        })
+    };
+    let (area, lone): (HashSet<IVec2>, HashSet<IVec2>) = if concave {
+        set.iter().copied().partition(touches_others)
+    } else {
+        (HashSet::default(), set.clone())
+    };
+
+    let mut parts: Vec<Vec<Point<f32>>> = vec![];
+    if lone.len() >= 2 {
+        parts.push(
+            lone.iter()
+                .map(|p| to_point(p.x as f32 + 0.5, p.y as f32 + 0.5))
+                .collect(),
+        );
+    }
+    if !area.is_empty() {
+        // the outline of the areas, along the edges of their pixels
+        let mut vertices = vec![];
+        let mut vertex_ids = HashMap::default();
+        let mut vertex = |corner: IVec2| -> u32 {
+            *vertex_ids.entry(corner).or_insert_with(|| {
+                vertices.push(to_point(
+                    corner.x as f32,
+                    corner.y as f32,
+                ));
+                vertices.len() as u32 - 1
+            })
+        };
+        let mut segments = vec![];
+        for p in area.iter() {
+            let edges = [
+                (IVec2::NEG_X, IVec2::ZERO, IVec2::Y),
+                (IVec2::X, IVec2::X, IVec2::ONE),
+                (IVec2::NEG_Y, IVec2::ZERO, IVec2::X),
+                (IVec2::Y, IVec2::Y, IVec2::ONE),
+            ];
+            for (side, a, b) in edges {
+                if !area.contains(&(*p + side)) {
+                    segments.push([vertex(*p + a), vertex(*p + b)]);
+                }
+            }
+        }
+        let decomposition = VHACD::decompose(
+            &VHACDParameters::default(),
+            &vertices,
+            &segments,
+            true,
+        );
+        parts.extend(
+            decomposition.compute_exact_convex_hulls(&vertices, &segments),
+        );
+    }
+
+    let build = |flip: Vec2| {
+        let mut shapes: Vec<SharedShape> = parts
+            .iter()
+            .filter_map(|part| {
+                let points: Vec<_> = part
+                    .iter()
+                    .map(|p| Point::new(p.x * flip.x, p.y * flip.y))
+                    .collect();
+                SharedShape::convex_hull(&points)
+            })
+            .collect();
+        if shapes.len() > 1 {
+            Some(SharedShape::compound(
+                shapes
+                    .into_iter()
+                    .map(|shape| (Isometry::identity(), shape))
+                    .collect(),
+            ))
+        } else {
+            shapes.pop()
+        }
+    };
+    Some((
+        build(Vec2::new(1.0, 1.0))?,
+        build(Vec2::new(-1.0, 1.0))?,
+        build(Vec2::new(1.0, -1.0))?,
+        build(Vec2::new(-1.0, -1.0))?,
+    ))
 }
 
 #[rustfmt::skip]
@@ -94,14 +316,15 @@ pub fn update_sprite_colliders(
     >,
     mut q_collider: Query<(&mut Collider, &AnimationCollider)>,
 ) {
-    for (mut collider, anim_entity) in &mut q_collider {
-        match q_sprite.get(anim_entity.0) {
+    for (mut collider, anim_collider) in &mut q_collider {
+        match q_sprite.get(anim_collider.0) {
             Ok((h_image, atlas, sprite)) => {
                 let Some(shapes_i) = shape_map
                     .map
                     .get(&h_image.id())
                     .expect("Sprite image not found in collider map!")
-                    .get(atlas.index)
+                    .get(&anim_collider.1)
+                    .and_then(|frames| frames.get(atlas.index))
                 else {
                     println!(
                         "er finding collider associated with image {}",
@@ -131,15 +354,15 @@ pub fn update_sprite_colliders(
 /// collider shape.
 /// - If there are no shapes generated by the frame of the animation,
 /// the shape will not collide with anything.
-/// - Shapes are generated from the *convex_hull* of magenta pixels in the animation.
-/// (ie: if you put a rubber band around the magenta pixel center points)
+/// - Shapes are generated from the pixels of the [`ColliderLayer`]'s key color
+/// in the animation (see [`shapes_from_pixels`]).
 ///
 /// If you want to do a PhysicsWorld query on a ([`Collider`], [`AnimationCollider`]) entity,
 /// make sure the query runs *after* [`update_sprite_colliders`]
 ///
 /// Also note: rotations are not currently applied to the *debug visuals* for these colliders.
 #[derive(Component)]
-pub struct AnimationCollider(pub Entity);
+pub struct AnimationCollider(pub Entity, pub ColliderLayer);
 
 /// Objects marked with this and a transform component will be updated in the
 /// collision scene. Parenting is not currently kept in sync; global transforms are used instead.
@@ -176,6 +399,157 @@ impl Collider {
     }
 }
 
+/// Makes the entity's [`Collider`] send [`CollisionStarted`] and
+/// [`CollisionEnded`] events, when other colliders start and stop
+/// overlapping with it
+///
+/// Sensors are checked after the [`PhysicsSet`], so the events can be
+/// read on the following tick.
+#[derive(Component, Debug, Default)]
+pub struct CollisionSensor {
+    /// Which colliders to detect; if `None`, the collider's own
+    /// collision groups are used
+    pub interaction: Option<InteractionGroups>,
+    touching: HashSet<Entity>,
+}
+
+impl CollisionSensor {
+    pub fn with_groups(interaction: InteractionGroups) -> Self {
+        Self {
+            interaction: Some(interaction),
+            touching: HashSet::default(),
+        }
+    }
+
+    /// Start out as already touching `other`, so only a
+    /// [`CollisionEnded`] is sent for it
+    pub fn with_touching(mut self, other: Entity) -> Self {
+        self.touching.insert(other);
+        self
+    }
+
+    /// Is anything overlapping with the sensor?
+    pub fn is_touching(&self) -> bool {
+        !self.touching.is_empty()
+    }
+
+    /// The entities overlapping with the sensor
+    pub fn touching(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.touching.iter().copied()
+    }
+}
+
+/// Sent when a collider starts overlapping with a [`CollisionSensor`]
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionStarted {
+    pub sensor: Entity,
+    pub other: Entity,
+}
+
+/// Sent when a collider stops overlapping with a [`CollisionSensor`]
+///
+/// Also sent when the other entity no longer exists.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionEnded {
+    pub sensor: Entity,
+    pub other: Entity,
+}
+
+/// Run condition: did something start overlapping with a sensor that has
+/// the component `T`?
+pub fn on_collision_started<T: Component>(
+    mut events: EventReader<CollisionStarted>,
+    q_sensor: Query<(), With<T>>,
+) -> bool {
+    // read all of them, so they are not seen again the next time
+    events
+        .read()
+        .filter(|ev| q_sensor.contains(ev.sensor))
+        .count()
+        > 0
+}
+
+/// Run condition: did something stop overlapping with a sensor that has
+/// the component `T`?
+pub fn on_collision_ended<T: Component>(
+    mut events: EventReader<CollisionEnded>,
+    q_sensor: Query<(), With<T>>,
+) -> bool {
+    events
+        .read()
+        .filter(|ev| q_sensor.contains(ev.sensor))
+        .count()
+        > 0
+}
+
+/// Run condition: did something start or stop overlapping with a sensor
+/// that has the component `T`?
+///
+/// Use this instead of chaining [`on_collision_started`] and
+/// [`on_collision_ended`] with `or_else`, which would skip reading the
+/// ended events whenever there are started events.
+pub fn on_collision_changed<T: Component>(
+    mut ev_started: EventReader<CollisionStarted>,
+    mut ev_ended: EventReader<CollisionEnded>,
+    q_sensor: Query<(), With<T>>,
+) -> bool {
+    // read both of them every time, so they are not seen again next time
+    let started = ev_started
+        .read()
+        .filter(|ev| q_sensor.contains(ev.sensor))
+        .count();
+    let ended = ev_ended
+        .read()
+        .filter(|ev| q_sensor.contains(ev.sensor))
+        .count();
+    started + ended > 0
+}
+
+/// Compare what each sensor overlaps with now, to the previous tick
+pub fn detect_sensor_collisions(
+    world: Res<PhysicsWorld>,
+    mut q_sensor: Query<(
+        Entity,
+        &GlobalTransform,
+        &Collider,
+        &mut CollisionSensor,
+    )>,
+    mut ev_started: EventWriter<CollisionStarted>,
+    mut ev_ended: EventWriter<CollisionEnded>,
+) {
+    for (entity, transform, collider, mut sensor) in &mut q_sensor {
+        let interaction = sensor
+            .interaction
+            .unwrap_or_else(|| collider.0.collision_groups());
+        let touching: HashSet<Entity> = world
+            .intersect(
+                transform.translation().xy(),
+                collider.0.shape(),
+                interaction,
+                Some(entity),
+            )
+            .into_iter()
+            .collect();
+        // only trigger change detection if something changed
+        if touching == sensor.touching {
+            continue;
+        }
+        for &other in touching.difference(&sensor.touching) {
+            ev_started.send(CollisionStarted {
+                sensor: entity,
+                other,
+            });
+        }
+        for &other in sensor.touching.difference(&touching) {
+            ev_ended.send(CollisionEnded {
+                sensor: entity,
+                other,
+            });
+        }
+        sensor.touching = touching;
+    }
+}
+
 /// Just a wrapper that lets us treat rapiers collider handle as a component
 #[derive(Component)]
 pub struct ColliderHandle(pub rapier2d::prelude::ColliderHandle);
@@ -213,6 +587,15 @@ impl ShapeCaster {
     }
 }
 
+/// Marks colliders that are part of the level geometry (walls, etc.)
+///
+/// They are kept in their own acceleration structure, which is only updated
+/// when one of them is added, changed or removed, instead of every tick
+/// like the one for everything else. Insert it together with the
+/// [`Collider`]; adding or removing it later has no effect.
+#[derive(Component, Default, Debug, Clone, Copy)]
+pub struct StaticCollider;
+
 /// Used to create queries on a physics world.
 ///
 /// To add a collider, you don't need this Resource, instead
@@ -223,12 +606,17 @@ impl ShapeCaster {
 pub struct PhysicsWorld {
     // Can't make query's on this without most of the other structures,
     // so it makes sense to group them.
-    pub query_pipeline: rapier2d::prelude::QueryPipeline,
+    /// Acceleration structure for the [`StaticCollider`]s
+    pub static_pipeline: QueryPipeline,
+    /// Acceleration structure for all the other colliders
+    pub dynamic_pipeline: QueryPipeline,
     pub col_set: ColliderSet,
     pub islands: IslandManager,
     pub rb_set: RigidBodySet,
     /// Used internally to track if any entities where removed.
     id_tracker: HashMap<Entity, rapier2d::prelude::ColliderHandle>,
+    /// Which colliders are in the static pipeline
+    static_colliders: HashSet<rapier2d::prelude::ColliderHandle>,
 }
 
 impl PhysicsWorld {
@@ -241,23 +629,34 @@ impl PhysicsWorld {
         interaction: InteractionGroups,
         exclude: Option<Entity>,
     ) -> Option<(Entity, parry::query::TOI)> {
-        let mut filter = QueryFilter::new().groups(interaction);
+        let bottom = origin.y + shape.compute_local_aabb().mins.y;
+        let one_way = |_: RapierColliderHandle, collider: &RapierCollider| {
+            can_hit_one_way(collider, direction.y, bottom)
+        };
+        let mut filter =
+            QueryFilter::new().groups(interaction).predicate(&one_way);
         if let Some(exclude) = exclude {
             // Entity might not be added yet; or even exist.
             if let Some(col_id) = self.id_tracker.get(&exclude) {
                 filter = filter.exclude_collider(*col_id)
             }
         }
-        let result = self.query_pipeline.cast_shape(
-            &self.rb_set,
-            &self.col_set,
-            &into_vec(origin).into(),
-            &into_vec(direction.xy()).into(),
-            shape,
-            max_toi,
-            true,
-            filter,
-        );
+        let result = self
+            .pipelines()
+            .into_iter()
+            .filter_map(|pipeline| {
+                pipeline.cast_shape(
+                    &self.rb_set,
+                    &self.col_set,
+                    &into_vec(origin).into(),
+                    &into_vec(direction.xy()).into(),
+                    shape,
+                    max_toi,
+                    true,
+                    filter,
+                )
+            })
+            .min_by(|(_, a), (_, b)| a.toi.total_cmp(&b.toi));
         if let Some((collider, toi)) = result {
             let entity: Entity = self.collider2entity(collider)?;
             Some((entity, toi))
@@ -275,7 +674,11 @@ impl PhysicsWorld {
         interaction: InteractionGroups,
         exclude: Option<Entity>,
     ) -> Option<(Entity, parry::query::RayIntersection)> {
-        let mut filter = QueryFilter::new().groups(interaction);
+        let one_way = |_: RapierColliderHandle, collider: &RapierCollider| {
+            can_hit_one_way(collider, cast.y, origin.y)
+        };
+        let mut filter =
+            QueryFilter::new().groups(interaction).predicate(&one_way);
         if let Some(exclude) = exclude {
             if let Some(col_id) = self.id_tracker.get(&exclude) {
                 filter = filter.exclude_collider(*col_id)
@@ -285,14 +688,20 @@ impl PhysicsWorld {
             into_vec(origin).into(),
             into_vec(cast).into(),
         );
-        let result = self.query_pipeline.cast_ray_and_get_normal(
-            &self.rb_set,
-            &self.col_set,
-            &ray,
-            max_toi,
-            solid,
-            filter,
-        );
+        let result = self
+            .pipelines()
+            .into_iter()
+            .filter_map(|pipeline| {
+                pipeline.cast_ray_and_get_normal(
+                    &self.rb_set,
+                    &self.col_set,
+                    &ray,
+                    max_toi,
+                    solid,
+                    filter,
+                )
+            })
+            .min_by(|(_, a), (_, b)| a.toi.total_cmp(&b.toi));
         if let Some((collider, intersection)) = result {
             let entity: Entity = self.collider2entity(collider)?;
             Some((entity, intersection))
@@ -315,18 +724,21 @@ impl PhysicsWorld {
             }
         }
         let mut intersections = Vec::new();
-        self.query_pipeline.intersections_with_shape(
-            &self.rb_set,
-            &self.col_set,
-            &into_vec(origin).into(),
-            shape,
-            filter,
-            |collider| {
-                let entity: Entity = self.collider2entity(collider).unwrap();
-                intersections.push(entity);
-                true
-            },
-        );
+        for pipeline in self.pipelines() {
+            pipeline.intersections_with_shape(
+                &self.rb_set,
+                &self.col_set,
+                &into_vec(origin).into(),
+                shape,
+                filter,
+                |collider| {
+                    let entity: Entity =
+                        self.collider2entity(collider).unwrap();
+                    intersections.push(entity);
+                    true
+                },
+            );
+        }
         intersections
     }
 
@@ -343,13 +755,25 @@ impl PhysicsWorld {
                 filter = filter.exclude_collider(*col_id)
             }
         }
-        if let Some((collider, point)) = self.query_pipeline.project_point(
-            &self.rb_set,
-            &self.col_set,
-            &into_vec(point).into(),
-            true,
-            filter,
-        ) {
+        let point = into_vec(point).into();
+        let result = self
+            .pipelines()
+            .into_iter()
+            .filter_map(|pipeline| {
+                pipeline.project_point(
+                    &self.rb_set,
+                    &self.col_set,
+                    &point,
+                    true,
+                    filter,
+                )
+            })
+            .min_by(|(_, a), (_, b)| {
+                let a = (a.point - point).norm();
+                let b = (b.point - point).norm();
+                a.total_cmp(&b)
+            });
+        if let Some((collider, point)) = result {
             let entity: Entity = self.collider2entity(collider)?;
             Some((entity, point))
         } else {
@@ -357,6 +781,40 @@ impl PhysicsWorld {
         }
     }
 
+    /// The collision groups of the entity's collider, if it has one
+    pub fn collision_groups(
+        &self,
+        entity: Entity,
+    ) -> Option<InteractionGroups> {
+        let handle = self.id_tracker.get(&entity)?;
+        Some(self.col_set.get(*handle)?.collision_groups())
+    }
+
+    /// Move the entity's collider right away, so that queries later in
+    /// the same tick see it in its new place
+    ///
+    /// Otherwise, it only moves when [`update_query_pipeline`] runs.
+    pub fn set_collider_translation(&mut self, entity: Entity, pos: Vec2) {
+        let Some(handle) = self.id_tracker.get(&entity).copied() else {
+            return;
+        };
+        let Some(collider) = self.col_set.get_mut(handle) else {
+            return;
+        };
+        collider.set_translation(into_vec(pos));
+        let pipeline = if self.static_colliders.contains(&handle) {
+            &mut self.static_pipeline
+        } else {
+            &mut self.dynamic_pipeline
+        };
+        pipeline.update_incremental(&self.col_set, &[handle], &[], true);
+    }
+
+    /// Both acceleration structures, to run queries on
+    fn pipelines(&self) -> [&QueryPipeline; 2] {
+        [&self.static_pipeline, &self.dynamic_pipeline]
+    }
+
     /// Small utility function that gets the entity associated with the collider;
     /// panics if entity does not exist.
     pub fn collider2entity(
@@ -382,48 +840,63 @@ impl PhysicsWorld {
 
 fn init_physics_world(mut world: ResMut<PhysicsWorld>) {
     let PhysicsWorld {
-        query_pipeline,
+        static_pipeline,
+        dynamic_pipeline,
         col_set,
-        islands,
         rb_set,
-        id_tracker,
+        ..
     } = &mut *world;
-    query_pipeline.update(&rb_set, &col_set);
+    static_pipeline.update(rb_set, col_set);
+    dynamic_pipeline.update(rb_set, col_set);
 }
 
-/// Updates the pipeline by reading all the positions/components with colliders
+/// Updates the pipelines by reading all the positions/components with colliders
 ///
 /// Make sure if you are reading from this in a system, you run after this finishes
 ///
+/// The static pipeline is only touched when a [`StaticCollider`] was added,
+/// changed or removed, so level geometry costs nothing on most ticks.
+///
 /// TODO make sure this always runs after the GameTickUpdate; consider creating a separate
 /// [`ScheduleLabel`] for immediately after transform propagation
 pub fn update_query_pipeline(
     // Mutable reference because collider data is stored in an Arena that pipeline modifies
     mut world: ResMut<PhysicsWorld>,
-    phys_obj_query: Query<(
-        Entity,
-        &Transform,
-        Ref<GlobalTransform>,
-        Ref<Collider>,
-        Option<&ColliderHandle>,
-    )>,
+    phys_obj_query: Query<
+        (
+            Entity,
+            Ref<GlobalTransform>,
+            Ref<Collider>,
+            Option<&ColliderHandle>,
+            Has<StaticCollider>,
+        ),
+        Or<(
+            Changed<GlobalTransform>,
+            Changed<Collider>,
+        )>,
+    >,
     mut removed: RemovedComponents<Collider>,
     mut commands: Commands,
 ) {
     let PhysicsWorld {
-        query_pipeline,
+        static_pipeline,
+        dynamic_pipeline,
         col_set,
         islands,
         rb_set,
         id_tracker,
+        static_colliders,
     } = &mut *world;
-    // query_pipeline.cast_shape()
-    let mut modified_colliders = vec![];
-    for (entity, trnsfm, transform, collider_info, handle) in &phys_obj_query {
+    let mut modified_static = vec![];
+    let mut modified_dynamic = vec![];
+    for (entity, transform, collider_info, handle, is_static) in &phys_obj_query
+    {
         let col_id = if collider_info.is_added() && handle.is_none() {
             let col_id = col_set.insert(collider_info.0.clone());
-            modified_colliders.push(col_id);
             id_tracker.insert(entity, col_id);
+            if is_static {
+                static_colliders.insert(col_id);
+            }
             commands
                 .get_entity(entity)
                 .unwrap()
@@ -433,66 +906,59 @@ pub fn update_query_pipeline(
             // what entity its associated with.
             col_set.get_mut(col_id).unwrap().user_data =
                 entity.to_bits() as u128;
-            // println!("new collider added: {col_id:?}");
-
-            col_set
-                .get_mut(col_id)
-                .unwrap()
-                .set_translation(into_vec(transform.translation().xy()));
-            col_set
-                .get_mut(col_id)
-                .unwrap()
-                .set_rotation(UnitComplex::new(
-                    transform
-                        .compute_transform()
-                        .rotation
-                        .to_euler(EulerRot::XYZ)
-                        .2,
-                ));
-
             col_id
         } else {
             handle.unwrap().0
         };
 
-        if collider_info.is_changed() {
+        if collider_info.is_changed() && !collider_info.is_added() {
             let old_entity = col_set.get(col_id).unwrap().user_data;
             *col_set.get_mut(col_id).unwrap() = collider_info.0.clone();
             col_set.get_mut(col_id).unwrap().user_data = old_entity;
-            modified_colliders.push(col_id);
         }
-        if transform.is_changed() {
-            col_set
-                .get_mut(col_id)
-                .unwrap()
-                .set_translation(into_vec(transform.translation().xy()));
-            col_set
-                .get_mut(col_id)
-                .unwrap()
-                .set_rotation(UnitComplex::new(
-                    transform
-                        .compute_transform()
-                        .rotation
-                        .to_euler(EulerRot::XYZ)
-                        .2,
-                ));
-            modified_colliders.push(col_id);
+        // a replaced collider also needs its position set again
+        let collider = col_set.get_mut(col_id).unwrap();
+        collider.set_translation(into_vec(transform.translation().xy()));
+        collider.set_rotation(UnitComplex::new(
+            transform
+                .compute_transform()
+                .rotation
+                .to_euler(EulerRot::XYZ)
+                .2,
+        ));
+        if static_colliders.contains(&col_id) {
+            modified_static.push(col_id);
+        } else {
+            modified_dynamic.push(col_id);
         }
     }
-    let mut removed_colliders = vec![];
+    let mut removed_static = vec![];
+    let mut removed_dynamic = vec![];
     // Go through the col_set, and see if any are there that aren't in our collider_set.
     for removed in removed.read() {
         let Some(removed_id) = id_tracker.get(&removed).copied() else {
             continue;
         };
         id_tracker.remove(&removed);
-        removed_colliders.push(removed_id);
+        if static_colliders.remove(&removed_id) {
+            removed_static.push(removed_id);
+        } else {
+            removed_dynamic.push(removed_id);
+        }
         col_set.remove(removed_id, islands, rb_set, false);
     }
-    query_pipeline.update_incremental(
-        &col_set,
-        modified_colliders.as_slice(),
-        removed_colliders.as_slice(),
+    if !modified_static.is_empty() || !removed_static.is_empty() {
+        static_pipeline.update_incremental(
+            col_set,
+            &modified_static,
+            &removed_static,
+            true,
+        );
+    }
+    dynamic_pipeline.update_incremental(
+        col_set,
+        &modified_dynamic,
+        &removed_dynamic,
         true,
     );
 }
@@ -522,7 +988,19 @@ pub fn debug_colliders(
                 Color::GREEN,
             );
         }
+        let mut convex_parts = vec![];
         if let Some(convex) = collider.shared_shape().as_convex_polygon() {
+            convex_parts.push(convex);
+        }
+        if let Some(compound) = collider.shared_shape().as_compound() {
+            convex_parts.extend(
+                compound
+                    .shapes()
+                    .iter()
+                    .filter_map(|(_, shape)| shape.as_convex_polygon()),
+            );
+        }
+        for convex in convex_parts {
             let points = convex.points();
             let num_points = points.len();
             for i in 0..num_points {
@@ -555,3 +1033,263 @@ pub fn into_vec2(vec: Unit<Vector<f32>>) -> Vec2 {
 /// Doesn't do anything on its own, but character controllers use it.
 #[derive(Component, Deref, DerefMut, Debug)]
 pub struct LinearVelocity(pub Vec2);
+
+#[cfg(test)]
+mod test {
+    use bevy::ecs::system::RunSystemOnce;
+    use rapier2d::parry::query::PointQuery;
+
+    use super::*;
+
+    /// An L shape in an 8x8 frame: two columns on the left,
+    /// and two rows at the bottom
+    fn l_shape_pixels() -> Vec<UVec2> {
+        (0..8)
+            .flat_map(|y| (0..8).map(move |x| UVec2::new(x, y)))
+            .filter(|p| p.x < 2 || p.y >= 6)
+            .collect()
+    }
+
+    #[test]
+    fn pixels_are_hull_corners() {
+        let (shape, ..) = shapes_from_pixels(
+            &l_shape_pixels(),
+            Vec2::splat(8.0),
+            false,
+        )
+        .unwrap();
+        // the frame is centered, with y pointing up
+        assert!(shape.contains_local_point(&Point::new(-3.0, 3.0)));
+        assert!(shape.contains_local_point(&Point::new(3.0, -3.0)));
+        assert!(shape.contains_local_point(&Point::new(0.0, 0.0)));
+        let single = [UVec2::new(0, 0)];
+        assert!(shapes_from_pixels(&single, Vec2::splat(8.0), false).is_none());
+    }
+
+    #[test]
+    fn key_colors() {
+        let cyan: KeyColor = "#00ffff".parse().unwrap();
+        assert_eq!(cyan, KeyColor([0, 255, 255]));
+        assert_eq!(
+            "FF00FF".parse::<KeyColor>().unwrap(),
+            KeyColor::MAGENTA
+        );
+        assert_eq!(cyan.to_string(), "#00ffff");
+        assert!("#00ffff80".parse::<KeyColor>().is_err());
+        assert!("#00fgff".parse::<KeyColor>().is_err());
+        assert!(cyan.matches(&[0, 255, 255, 255]));
+        assert!(!cyan.matches(&[0, 255, 255, 128]));
+    }
+
+    #[test]
+    fn concave_shape_from_pixels() {
+        let (shape, flipped_x, ..) = shapes_from_pixels(
+            &l_shape_pixels(),
+            Vec2::splat(8.0),
+            true,
+        )
+        .unwrap();
+        assert!(shape.contains_local_point(&Point::new(-3.0, 3.0)));
+        assert!(shape.contains_local_point(&Point::new(3.0, -3.0)));
+        assert!(!shape.contains_local_point(&Point::new(2.0, 2.0)));
+        assert!(flipped_x.contains_local_point(&Point::new(3.0, 3.0)));
+        assert!(!flipped_x.contains_local_point(&Point::new(-2.0, 2.0)));
+    }
+
+    #[test]
+    fn lone_pixels_are_hull_corners_in_concave_shapes() {
+        let pixels = [
+            UVec2::new(0, 0),
+            UVec2::new(7, 0),
+            UVec2::new(0, 7),
+            UVec2::new(7, 7),
+        ];
+        let (shape, ..) =
+            shapes_from_pixels(&pixels, Vec2::splat(8.0), true).unwrap();
+        assert!(shape.contains_local_point(&Point::new(0.0, 0.0)));
+    }
+
+    #[test]
+    fn one_way_platforms_only_stop_from_above() {
+        let platform = ColliderBuilder::cuboid(8.0, 2.0)
+            .translation(into_vec(Vec2::new(0.0, 10.0)))
+            .collision_groups(InteractionGroups::new(
+                ONE_WAY,
+                Group::all(),
+            ))
+            .build();
+        // falling onto it, or resting slightly inside it
+        assert!(can_hit_one_way(&platform, -1.0, 20.0));
+        assert!(can_hit_one_way(&platform, -1.0, 11.0));
+        // jumping up through it, or falling from below its top
+        assert!(!can_hit_one_way(&platform, 1.0, 20.0));
+        assert!(!can_hit_one_way(&platform, -1.0, 9.0));
+
+        let wall = ColliderBuilder::cuboid(8.0, 2.0)
+            .collision_groups(InteractionGroups::new(
+                GROUND,
+                Group::all(),
+            ))
+            .build();
+        assert!(can_hit_one_way(&wall, 1.0, -20.0));
+    }
+
+    #[test]
+    fn queries_see_static_and_dynamic_colliders() {
+        let mut world = World::new();
+        world.init_resource::<PhysicsWorld>();
+        let groups = InteractionGroups::new(GROUND, Group::all());
+        let spawn = |world: &mut World, pos: Vec2, size: Vec2| {
+            let transform = Transform::from_translation(pos.extend(0.0));
+            world
+                .spawn((
+                    Collider::cuboid(size.x, size.y, groups),
+                    transform,
+                    GlobalTransform::from(transform),
+                ))
+                .id()
+        };
+        let wall = spawn(
+            &mut world,
+            Vec2::ZERO,
+            Vec2::new(100.0, 10.0),
+        );
+        world.entity_mut(wall).insert(StaticCollider);
+        let block = spawn(
+            &mut world,
+            Vec2::new(0.0, 50.0),
+            Vec2::new(10.0, 10.0),
+        );
+        world.run_system_once(update_query_pipeline);
+
+        let cast_down = |world: &World| {
+            world
+                .resource::<PhysicsWorld>()
+                .ray_cast(
+                    Vec2::new(0.0, 100.0),
+                    Vec2::NEG_Y,
+                    f32::MAX,
+                    true,
+                    groups,
+                    None,
+                )
+                .map(|(e, _)| e)
+        };
+        assert_eq!(cast_down(&world), Some(block));
+        world.entity_mut(block).despawn();
+        world.run_system_once(update_query_pipeline);
+        assert_eq!(cast_down(&world), Some(wall));
+    }
+
+    #[derive(Component)]
+    struct TestSensor;
+
+    #[test]
+    fn sensors_send_started_and_ended() {
+        let mut world = World::new();
+        world.init_resource::<PhysicsWorld>();
+        world.init_resource::<Events<CollisionStarted>>();
+        world.init_resource::<Events<CollisionEnded>>();
+        let at = |pos: Vec2| {
+            let transform = Transform::from_translation(pos.extend(0.0));
+            (
+                transform,
+                GlobalTransform::from(transform),
+            )
+        };
+        let sensor = world
+            .spawn((
+                Collider::cuboid(
+                    10.0,
+                    10.0,
+                    InteractionGroups::new(SENSOR, PLAYER),
+                ),
+                CollisionSensor::default(),
+                at(Vec2::ZERO),
+            ))
+            .id();
+        let player = world
+            .spawn((
+                Collider::cuboid(
+                    4.0,
+                    4.0,
+                    InteractionGroups::new(PLAYER, Group::all()),
+                ),
+                at(Vec2::new(100.0, 0.0)),
+            ))
+            .id();
+        let step = |world: &mut World, pos: Vec2| {
+            world.entity_mut(player).insert(at(pos));
+            world.run_system_once(update_query_pipeline);
+            world.run_system_once(detect_sensor_collisions);
+            let started: Vec<_> = world
+                .resource_mut::<Events<CollisionStarted>>()
+                .drain()
+                .collect();
+            let ended: Vec<_> = world
+                .resource_mut::<Events<CollisionEnded>>()
+                .drain()
+                .collect();
+            (started, ended)
+        };
+
+        assert_eq!(
+            step(&mut world, Vec2::new(100.0, 0.0)),
+            (vec![], vec![])
+        );
+        assert_eq!(
+            step(&mut world, Vec2::new(5.0, 0.0)),
+            (
+                vec![CollisionStarted {
+                    sensor,
+                    other: player
+                }],
+                vec![]
+            )
+        );
+        assert!(world.get::<CollisionSensor>(sensor).unwrap().is_touching());
+        assert_eq!(
+            step(&mut world, Vec2::new(6.0, 0.0)),
+            (vec![], vec![])
+        );
+        assert_eq!(
+            step(&mut world, Vec2::new(100.0, 0.0)),
+            (
+                vec![],
+                vec![CollisionEnded {
+                    sensor,
+                    other: player
+                }]
+            )
+        );
+        assert!(!world.get::<CollisionSensor>(sensor).unwrap().is_touching());
+    }
+
+    #[test]
+    fn collision_changed_reads_both_events() {
+        #[derive(Resource, Default)]
+        struct Runs(u32);
+
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionStarted>>();
+        world.init_resource::<Events<CollisionEnded>>();
+        world.init_resource::<Runs>();
+        let sensor = world.spawn(TestSensor).id();
+        let other = world.spawn_empty().id();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (|mut runs: ResMut<Runs>| runs.0 += 1)
+                .run_if(on_collision_changed::<TestSensor>),
+        );
+
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<Runs>().0, 0);
+        world.send_event(CollisionStarted { sensor, other });
+        world.send_event(CollisionEnded { sensor, other });
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<Runs>().0, 1);
+        // the events are still around, but have already been read
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<Runs>().0, 1);
+    }
+}
@@ -0,0 +1,355 @@
+//! Recording and deterministic playback of input actions
+//!
+//! Records the [`ActionState`] of an [`Actionlike`] type, as seen by every
+//! game tick, so that it can be fed back into the simulation later. Together
+//! with the [`GameRng`](crate::rng::GameRng) seed, that is enough to reproduce
+//! a run exactly (for bug reports and regression tests).
+//!
+//! Ticks are counted from the first tick where an entity with an
+//! `ActionState<A>` exists (i.e. when the player has been spawned).
+//!
+//! The systems run in `GameTickUpdate`, in [`InputManagerSystem::ManualControl`].
+//! Anything that reads the actions must be ordered after that set.
+
+use std::marker::PhantomData;
+
+use leafwing_input_manager::action_state::ActionState;
+use leafwing_input_manager::buttonlike::ButtonState;
+use leafwing_input_manager::Actionlike;
+use strum::IntoEnumIterator;
+
+use crate::input::InputManagerSystem;
+use crate::prelude::*;
+
+/// Bump this whenever the format of [`InputReplay`] changes
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+pub struct InputReplayPlugin<A: Actionlike> {
+    _phantom: PhantomData<A>,
+}
+
+// Deriving default induces an undesired bound on the generic
+impl<A: Actionlike> Default for InputReplayPlugin<A> {
+    fn default() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<A> Plugin for InputReplayPlugin<A>
+where
+    A: Actionlike + IntoEnumIterator + Serialize + DeserializeOwned,
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputReplayer<A>>();
+        app.add_systems(
+            GameTickUpdate,
+            replay_input::<A>
+                .in_set(InputManagerSystem::ManualControl)
+                .run_if(|r: Res<InputReplayer<A>>| r.is_active()),
+        );
+    }
+}
+
+/// The state of one action, as seen by one game tick
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RecordedAction {
+    pub state: RecordedButton,
+    pub value: f32,
+}
+
+impl Default for RecordedAction {
+    fn default() -> Self {
+        Self {
+            state: RecordedButton::Released,
+            value: 0.0,
+        }
+    }
+}
+
+/// Serializable mirror of leafwing's [`ButtonState`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordedButton {
+    JustPressed,
+    Pressed,
+    JustReleased,
+    Released,
+}
+
+impl From<RecordedButton> for ButtonState {
+    fn from(value: RecordedButton) -> Self {
+        match value {
+            RecordedButton::JustPressed => ButtonState::JustPressed,
+            RecordedButton::Pressed => ButtonState::Pressed,
+            RecordedButton::JustReleased => ButtonState::JustReleased,
+            RecordedButton::Released => ButtonState::Released,
+        }
+    }
+}
+
+impl RecordedAction {
+    pub fn from_action_state<A: Actionlike>(
+        state: &ActionState<A>,
+        action: &A,
+    ) -> Self {
+        let button = if state.just_pressed(action) {
+            RecordedButton::JustPressed
+        } else if state.pressed(action) {
+            RecordedButton::Pressed
+        } else if state.just_released(action) {
+            RecordedButton::JustReleased
+        } else {
+            RecordedButton::Released
+        };
+        Self {
+            state: button,
+            value: state.value(action),
+        }
+    }
+
+    /// Overwrite the action in the `ActionState` to exactly match this recording
+    pub fn apply<A: Actionlike>(&self, state: &mut ActionState<A>, action: &A) {
+        // make sure the action has data we can overwrite
+        match self.state {
+            RecordedButton::JustPressed | RecordedButton::Pressed => {
+                state.press(action)
+            },
+            RecordedButton::JustReleased | RecordedButton::Released => {
+                state.release(action)
+            },
+        }
+        if let Some(data) = state.action_data_mut(action) {
+            data.state = self.state.into();
+            data.value = self.value;
+        }
+    }
+}
+
+/// The actions that changed on a given tick
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "A: Serialize + DeserializeOwned")]
+pub struct ReplayFrame<A> {
+    /// Tick number, relative to the start of the replay
+    pub tick: u64,
+    pub changes: Vec<ReplayChange<A>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "A: Serialize + DeserializeOwned")]
+pub struct ReplayChange<A> {
+    pub action: A,
+    #[serde(flatten)]
+    pub recorded: RecordedAction,
+}
+
+/// A complete recording, as stored in a replay file
+#[serde_as]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "A: Serialize + DeserializeOwned")]
+pub struct InputReplay<A> {
+    pub version: u32,
+    /// The `GameRng` seed the simulation was started with
+    // (as a string, because TOML integers are signed 64-bit)
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub seed: u64,
+    /// Asset key of the level the recording was made in
+    pub level: String,
+    /// Total number of ticks recorded
+    pub len: u64,
+    /// Only ticks on which something changed are stored
+    pub frames: Vec<ReplayFrame<A>>,
+}
+
+impl<A> InputReplay<A>
+where
+    A: Actionlike + Serialize + DeserializeOwned,
+{
+    pub fn new(seed: u64, level: &str) -> Self {
+        Self {
+            version: REPLAY_FORMAT_VERSION,
+            seed,
+            level: level.into(),
+            len: 0,
+            frames: vec![],
+        }
+    }
+
+    pub fn load(path: &std::path::Path) -> AnyResult<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read replay file {:?}", path))?;
+        let replay: Self = toml::from_str(&text)
+            .with_context(|| format!("Invalid replay file {:?}", path))?;
+        ensure!(
+            replay.version == REPLAY_FORMAT_VERSION,
+            "Replay file {:?} has format version {}, expected {}",
+            path,
+            replay.version,
+            REPLAY_FORMAT_VERSION
+        );
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> AnyResult<()> {
+        let text = toml::to_string(self)?;
+        std::fs::write(path, text)
+            .with_context(|| format!("Cannot write replay file {:?}", path))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayMode {
+    Off,
+    Recording,
+    Playing,
+}
+
+/// Controls recording/playback of `ActionState<A>`
+#[derive(Resource)]
+pub struct InputReplayer<A: Actionlike> {
+    mode: ReplayMode,
+    replay: Option<InputReplay<A>>,
+    /// The tick that is tick 0 of the replay; set when the first tick runs
+    start_tick: Option<u64>,
+    /// State of all actions as of the last tick
+    current: HashMap<A, RecordedAction>,
+    next_frame: usize,
+    ticks: u64,
+}
+
+impl<A: Actionlike> Default for InputReplayer<A> {
+    fn default() -> Self {
+        Self {
+            mode: ReplayMode::Off,
+            replay: None,
+            start_tick: None,
+            current: HashMap::default(),
+            next_frame: 0,
+            ticks: 0,
+        }
+    }
+}
+
+impl<A> InputReplayer<A>
+where
+    A: Actionlike + Serialize + DeserializeOwned,
+{
+    pub fn mode(&self) -> ReplayMode {
+        self.mode
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.mode != ReplayMode::Off
+    }
+
+    /// Start a new recording (discarding any in progress)
+    ///
+    /// The caller is responsible for actually (re)starting the simulation
+    /// with the given seed and level.
+    pub fn start_recording(&mut self, seed: u64, level: &str) {
+        *self = Self {
+            mode: ReplayMode::Recording,
+            replay: Some(InputReplay::new(seed, level)),
+            ..Default::default()
+        };
+    }
+
+    /// Start feeding the given recording into the simulation
+    ///
+    /// The caller is responsible for actually (re)starting the simulation
+    /// with the seed and level of the replay.
+    pub fn start_playback(&mut self, replay: InputReplay<A>) {
+        *self = Self {
+            mode: ReplayMode::Playing,
+            replay: Some(replay),
+            ..Default::default()
+        };
+    }
+
+    /// Stop whatever we are doing
+    ///
+    /// Returns the recording, if we were recording.
+    pub fn stop(&mut self) -> Option<InputReplay<A>> {
+        let mode = self.mode;
+        let replay = std::mem::take(self).replay;
+        if mode == ReplayMode::Recording {
+            replay
+        } else {
+            None
+        }
+    }
+
+    /// How many ticks have been recorded/played back so far
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+}
+
+fn replay_input<A>(
+    gt: Res<GameTime>,
+    mut replayer: ResMut<InputReplayer<A>>,
+    mut q_action: Query<&mut ActionState<A>>,
+) where
+    A: Actionlike + IntoEnumIterator + Serialize + DeserializeOwned,
+{
+    let Some(mut action_state) = q_action.iter_mut().next() else {
+        return;
+    };
+    let replayer = &mut *replayer;
+    let start_tick = *replayer.start_tick.get_or_insert(gt.tick());
+    let tick = gt.tick() - start_tick;
+    let Some(replay) = replayer.replay.as_mut() else {
+        replayer.mode = ReplayMode::Off;
+        return;
+    };
+
+    match replayer.mode {
+        ReplayMode::Off => {},
+        ReplayMode::Recording => {
+            let mut changes = vec![];
+            for action in A::iter() {
+                let recorded =
+                    RecordedAction::from_action_state(&action_state, &action);
+                let old = replayer.current.get(&action).copied();
+                if old.unwrap_or_default() != recorded {
+                    replayer.current.insert(action.clone(), recorded);
+                    changes.push(ReplayChange { action, recorded });
+                }
+            }
+            if !changes.is_empty() {
+                replay.frames.push(ReplayFrame { tick, changes });
+            }
+            replay.len = tick + 1;
+            replayer.ticks = tick + 1;
+        },
+        ReplayMode::Playing => {
+            if tick >= replay.len {
+                info!(
+                    "Replay finished after {} ticks.",
+                    replay.len
+                );
+                replayer.mode = ReplayMode::Off;
+                return;
+            }
+            while let Some(frame) = replay.frames.get(replayer.next_frame) {
+                if frame.tick > tick {
+                    break;
+                }
+                for change in frame.changes.iter() {
+                    replayer
+                        .current
+                        .insert(change.action.clone(), change.recorded);
+                }
+                replayer.next_frame += 1;
+            }
+            // override everything, so real input does not interfere
+            for action in A::iter() {
+                let recorded =
+                    replayer.current.get(&action).copied().unwrap_or_default();
+                recorded.apply(&mut action_state, &action);
+            }
+            replayer.ticks = tick + 1;
+        },
+    }
+}
@@ -0,0 +1,66 @@
+//! Deterministic random numbers for gameplay
+//!
+//! Anything random that can affect the outcome of gameplay (AI decisions,
+//! drops, etc.) must use the [`GameRng`] resource rather than `thread_rng`,
+//! so that a run can be reproduced from its seed (see [`crate::replay`]).
+//! Purely cosmetic randomness (particles, sound variations) does not matter.
+
+use crate::prelude::*;
+
+pub struct GameRngPlugin;
+
+impl Plugin for GameRngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameRng>();
+    }
+}
+
+/// Seeded RNG to be used by all gameplay code
+#[derive(Resource, Debug, Clone)]
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_seed(thread_rng().gen())
+    }
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// The seed this RNG was last (re)seeded with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Restart the random sequence from the given seed
+    pub fn reseed(&mut self, seed: u64) {
+        *self = Self::from_seed(seed);
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
@@ -6,8 +6,10 @@ use bevy::ecs::system::{StaticSystemParam, SystemParam};
 use crate::assets::config::DynamicConfigValue;
 use crate::assets::script::ScriptConfig;
 use crate::prelude::*;
+use crate::script::expr::ScriptVars;
 
 pub mod common;
+pub mod expr;
 pub mod label;
 
 pub struct ScriptPlugin;
@@ -164,6 +166,13 @@ pub trait ScriptTracker: Default + Send + Sync + 'static {
         Default::default()
     }
     fn clear_slots(&mut self, _timing: ScriptActionTiming) {}
+    fn init_vars(&mut self, _config: &ScriptConfig) {}
+    fn vars(&self) -> Option<&ScriptVars> {
+        None
+    }
+    fn vars_mut(&mut self) -> Option<&mut ScriptVars> {
+        None
+    }
     fn do_start(
         &mut self,
         _entity: Entity,
@@ -298,6 +307,7 @@ impl<T: ScriptAsset> ScriptRuntimeBuilder<T> {
 
     pub fn replace_config(&mut self, config: &ScriptConfig) {
         self.runtime.config = config.clone();
+        self.runtime.tracker.init_vars(config);
     }
 
     pub fn add_action(
@@ -884,6 +894,18 @@ impl<T: ScriptAsset> ScriptPlayer<T> {
         }
     }
 
+    /// The tracker of the script that is running (if any)
+    pub fn tracker_mut(&mut self) -> Option<&mut T::Tracker> {
+        match &mut self.state {
+            ScriptPlayerState::Starting { runtime }
+            | ScriptPlayerState::Playing { runtime }
+            | ScriptPlayerState::Stopping { runtime } => {
+                Some(&mut runtime.tracker)
+            },
+            _ => None,
+        }
+    }
+
     /// Toggles the value of a slot and returns the new value
     pub fn toggle_slot(&mut self, slot: &str) -> bool {
         if self.has_slot(slot) {
@@ -924,6 +946,58 @@ impl<T: ScriptAsset> ScriptPlayer<T> {
         }
     }
 
+    /// Get the current value of a script variable
+    pub fn var(&self, name: &str) -> Option<DynamicConfigValue> {
+        self.runtime()
+            .and_then(|rt| rt.tracker.vars())
+            .and_then(|vars| vars.get(name).cloned())
+    }
+
+    /// Set the value of a script variable
+    ///
+    /// Does nothing if no script is loaded.
+    pub fn set_var(&mut self, name: &str, value: DynamicConfigValue) {
+        if let Some(vars) =
+            self.runtime_mut().and_then(|rt| rt.tracker.vars_mut())
+        {
+            vars.insert(name.to_owned(), value);
+        }
+    }
+
+    fn runtime(&self) -> Option<&ScriptRuntime<T>> {
+        match &self.state {
+            ScriptPlayerState::Playing { runtime }
+            | ScriptPlayerState::Starting { runtime }
+            | ScriptPlayerState::Stopping { runtime } => Some(runtime),
+            ScriptPlayerState::PrePlayHandle { old_runtime, .. }
+            | ScriptPlayerState::PrePlayKey { old_runtime, .. } => {
+                old_runtime.as_ref()
+            },
+            ScriptPlayerState::ChangingHandle { old_runtime, .. }
+            | ScriptPlayerState::ChangingKey { old_runtime, .. } => {
+                Some(old_runtime)
+            },
+            ScriptPlayerState::Stopped => None,
+        }
+    }
+
+    fn runtime_mut(&mut self) -> Option<&mut ScriptRuntime<T>> {
+        match &mut self.state {
+            ScriptPlayerState::Playing { runtime }
+            | ScriptPlayerState::Starting { runtime }
+            | ScriptPlayerState::Stopping { runtime } => Some(runtime),
+            ScriptPlayerState::PrePlayHandle { old_runtime, .. }
+            | ScriptPlayerState::PrePlayKey { old_runtime, .. } => {
+                old_runtime.as_mut()
+            },
+            ScriptPlayerState::ChangingHandle { old_runtime, .. }
+            | ScriptPlayerState::ChangingKey { old_runtime, .. } => {
+                Some(old_runtime)
+            },
+            ScriptPlayerState::Stopped => None,
+        }
+    }
+
     /// Get the asset key of the script that is currently playing, if known.
     ///
     /// Note: since it is possible to play assets using handles, the key
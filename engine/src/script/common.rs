@@ -1,3 +1,5 @@
+use std::cmp::Ordering;
+
 use bevy::audio::{PlaybackMode, Volume};
 use bevy::ecs::system::lifetimeless::*;
 
@@ -5,6 +7,7 @@ use super::*;
 use crate::assets::script::*;
 use crate::audio::{LabeledBackgroundSound, PreciseAudioId, PrecisionMixerControl};
 use crate::data::OneOrMany;
+use crate::script::expr::{add_to_var, compare, ScriptExpr, ScriptVars};
 use crate::script::label::EntityLabels;
 
 pub struct CommonScriptPlugin;
@@ -57,6 +60,7 @@ pub struct CommonScriptTracker {
     runcount: u32,
     my_sounds: Vec<PreciseAudioId>,
     my_background_sounds: Vec<Entity>,
+    vars: ScriptVars,
 }
 
 #[derive(Default)]
@@ -408,6 +412,41 @@ impl ScriptTracker for CommonScriptTracker {
         }
         self.slots_enabled.clear()
     }
+
+    fn init_vars(&mut self, config: &ScriptConfig) {
+        self.vars = config.0.clone();
+    }
+
+    fn vars(&self) -> Option<&ScriptVars> {
+        Some(&self.vars)
+    }
+
+    fn vars_mut(&mut self) -> Option<&mut ScriptVars> {
+        Some(&mut self.vars)
+    }
+}
+
+/// Check `if_var_*` conditions against the current variables
+///
+/// Missing variables, invalid expressions, and values that cannot be
+/// compared, all count as the condition not being met.
+fn check_vars(
+    conditions: &HashMap<String, ScriptExpr>,
+    vars: &ScriptVars,
+    f: fn(Ordering) -> bool,
+) -> bool {
+    conditions.iter().all(|(name, expr)| {
+        let Some(value) = vars.get(name) else {
+            return false;
+        };
+        match expr.eval(vars) {
+            Ok(other) => compare(value, &other).is_some_and(f),
+            Err(e) => {
+                warn!("Script condition on variable '{}': {}", name, e);
+                false
+            },
+        }
+    })
 }
 
 impl ScriptRunIf for CommonScriptRunIf {
@@ -484,6 +523,19 @@ impl ScriptActionParams for CommonScriptParams {
                 return Err(ScriptUpdateResult::NormalRun);
             }
         }
+        let var_conditions: [(_, fn(Ordering) -> bool); 6] = [
+            (&self.if_var_eq, |o| o.is_eq()),
+            (&self.if_var_ne, |o| o.is_ne()),
+            (&self.if_var_lt, |o| o.is_lt()),
+            (&self.if_var_le, |o| o.is_le()),
+            (&self.if_var_gt, |o| o.is_gt()),
+            (&self.if_var_ge, |o| o.is_ge()),
+        ];
+        for (conditions, f) in var_conditions {
+            if !check_vars(conditions, &tracker.vars, f) {
+                return Err(ScriptUpdateResult::NormalRun);
+            }
+        }
         match (
             &self.if_previous_script_key,
             &tracker.old_key,
@@ -608,6 +660,24 @@ impl ScriptAction for CommonScriptAction {
                 }
                 ScriptUpdateResult::NormalRun
             },
+            CommonScriptAction::SetVar { var, value } => {
+                match value.eval(&tracker.vars) {
+                    Ok(value) => {
+                        tracker.vars.insert(var.clone(), value);
+                    },
+                    Err(e) => warn!("Script SetVar '{}': {}", var, e),
+                }
+                ScriptUpdateResult::NormalRun
+            },
+            CommonScriptAction::AddVar { var, value } => {
+                if let Err(e) = value
+                    .eval(&tracker.vars)
+                    .and_then(|value| add_to_var(&mut tracker.vars, var, value))
+                {
+                    warn!("Script AddVar '{}': {}", var, e);
+                }
+                ScriptUpdateResult::NormalRun
+            },
             CommonScriptAction::PlayBackgroundAudio { asset_key, label, volume, r#loop } => {
                 let sounds: Vec<Handle<AudioSource>> = preloaded
                     .get_multi_asset(asset_key)
@@ -961,6 +1031,18 @@ impl<T: ScriptTracker> ScriptTracker for ExtendedScriptTracker<T> {
         self.common.clear_slots(timing);
         self.extended.clear_slots(timing);
     }
+
+    fn init_vars(&mut self, config: &ScriptConfig) {
+        self.common.init_vars(config);
+    }
+
+    fn vars(&self) -> Option<&ScriptVars> {
+        self.common.vars()
+    }
+
+    fn vars_mut(&mut self) -> Option<&mut ScriptVars> {
+        self.common.vars_mut()
+    }
 }
 
 impl<T: ScriptRunIf> ScriptRunIf for ExtendedScriptRunIf<T> {
@@ -0,0 +1,383 @@
+//! Simple expressions, for script variables
+//!
+//! Supports numbers, `'strings'`, variable names, parentheses, and the
+//! `+ - * / %` operators. Numbers can be integers or floats; integers stay
+//! integers as long as the result is exact. `+` on two strings concatenates.
+
+use std::cmp::Ordering;
+
+use crate::assets::config::DynamicConfigValue;
+use crate::prelude::*;
+
+/// Script variables, by name
+pub type ScriptVars = HashMap<String, DynamicConfigValue>;
+
+/// An expression in a script asset
+///
+/// In the asset file, can be a number (literal value) or a string
+/// (expression to evaluate).
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "DynamicConfigValue", into = "DynamicConfigValue")]
+pub struct ScriptExpr {
+    source: DynamicConfigValue,
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Value(DynamicConfigValue),
+    Var(String),
+    Neg(Box<Expr>),
+    BinOp(Box<Expr>, Op, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl TryFrom<DynamicConfigValue> for ScriptExpr {
+    type Error = String;
+
+    fn try_from(source: DynamicConfigValue) -> Result<Self, Self::Error> {
+        let expr = match &source {
+            DynamicConfigValue::String(s) => Parser::new(s).parse()?,
+            value => Expr::Value(value.clone()),
+        };
+        Ok(ScriptExpr { source, expr })
+    }
+}
+
+impl From<ScriptExpr> for DynamicConfigValue {
+    fn from(value: ScriptExpr) -> Self {
+        value.source
+    }
+}
+
+impl std::str::FromStr for ScriptExpr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DynamicConfigValue::String(s.to_owned()).try_into()
+    }
+}
+
+impl ScriptExpr {
+    pub fn eval(
+        &self,
+        vars: &ScriptVars,
+    ) -> Result<DynamicConfigValue, String> {
+        self.expr.eval(vars)
+    }
+}
+
+impl Expr {
+    fn eval(&self, vars: &ScriptVars) -> Result<DynamicConfigValue, String> {
+        match self {
+            Expr::Value(value) => Ok(value.clone()),
+            Expr::Var(name) => vars
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("Unknown variable '{}'", name)),
+            Expr::Neg(expr) => match expr.eval(vars)? {
+                DynamicConfigValue::Int(x) => x
+                    .checked_neg()
+                    .map(DynamicConfigValue::Int)
+                    .ok_or_else(|| format!("Invalid integer math: -{}", x)),
+                DynamicConfigValue::Float(x) => {
+                    Ok(DynamicConfigValue::Float(-x))
+                },
+                other => Err(format!("Cannot negate {:?}", other)),
+            },
+            Expr::BinOp(a, op, b) => apply(a.eval(vars)?, *op, b.eval(vars)?),
+        }
+    }
+}
+
+fn apply(
+    a: DynamicConfigValue,
+    op: Op,
+    b: DynamicConfigValue,
+) -> Result<DynamicConfigValue, String> {
+    use DynamicConfigValue::*;
+    match (a, op, b) {
+        (String(a), Op::Add, String(b)) => Ok(String(a + &b)),
+        (Int(a), op, Int(b)) => {
+            let r = match op {
+                Op::Add => a.checked_add(b),
+                Op::Sub => a.checked_sub(b),
+                Op::Mul => a.checked_mul(b),
+                Op::Div if a.checked_rem(b).is_some_and(|r| r != 0) => {
+                    return Ok(Float(a as f64 / b as f64));
+                },
+                Op::Div => a.checked_div(b),
+                Op::Rem => a.checked_rem(b),
+            };
+            r.map(Int).ok_or_else(|| {
+                format!(
+                    "Invalid integer math: {} {:?} {}",
+                    a, op, b
+                )
+            })
+        },
+        (a, op, b) => {
+            let (Some(x), Some(y)) = (as_number(&a), as_number(&b)) else {
+                return Err(format!(
+                    "Cannot apply {:?} to {:?} and {:?}",
+                    op, a, b
+                ));
+            };
+            Ok(Float(match op {
+                Op::Add => x + y,
+                Op::Sub => x - y,
+                Op::Mul => x * y,
+                Op::Div => x / y,
+                Op::Rem => x % y,
+            }))
+        },
+    }
+}
+
+fn as_number(value: &DynamicConfigValue) -> Option<f64> {
+    match value {
+        DynamicConfigValue::Int(x) => Some(*x as f64),
+        DynamicConfigValue::Float(x) => Some(*x),
+        DynamicConfigValue::String(_) => None,
+    }
+}
+
+/// Compare two values
+///
+/// Numbers compare with numbers and strings with strings.
+/// Anything else cannot be compared.
+pub fn compare(
+    a: &DynamicConfigValue,
+    b: &DynamicConfigValue,
+) -> Option<Ordering> {
+    match (a, b) {
+        (DynamicConfigValue::String(a), DynamicConfigValue::String(b)) => {
+            Some(a.cmp(b))
+        },
+        (DynamicConfigValue::Int(a), DynamicConfigValue::Int(b)) => {
+            Some(a.cmp(b))
+        },
+        (a, b) => as_number(a)?.partial_cmp(&as_number(b)?),
+    }
+}
+
+/// Add a number to a variable (missing variables count as zero)
+pub fn add_to_var(
+    vars: &mut ScriptVars,
+    name: &str,
+    value: DynamicConfigValue,
+) -> Result<(), String> {
+    let old = vars
+        .get(name)
+        .cloned()
+        .unwrap_or(DynamicConfigValue::Int(0));
+    let new = apply(old, Op::Add, value)?;
+    vars.insert(name.to_owned(), new);
+    Ok(())
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Parser { src, pos: 0 }
+    }
+
+    fn parse(mut self) -> Result<Expr, String> {
+        let expr = self.expr()?;
+        self.skip_ws();
+        if self.pos < self.src.len() {
+            return Err(self.error("unexpected character"));
+        }
+        Ok(expr)
+    }
+
+    fn error(&self, msg: &str) -> String {
+        format!(
+            "Invalid expression \"{}\": {} at position {}",
+            self.src, msg, self.pos
+        )
+    }
+
+    fn skip_ws(&mut self) {
+        let rest = &self.src[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_ws();
+        self.src[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// `term (('+' | '-') term)*`
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                Op::Add
+            } else if self.eat('-') {
+                Op::Sub
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::BinOp(
+                Box::new(lhs),
+                op,
+                Box::new(self.term()?),
+            );
+        }
+    }
+
+    /// `unary (('*' | '/' | '%') unary)*`
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            } else if self.eat('/') {
+                Op::Div
+            } else if self.eat('%') {
+                Op::Rem
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::BinOp(
+                Box::new(lhs),
+                op,
+                Box::new(self.unary()?),
+            );
+        }
+    }
+
+    /// `'-' unary | atom`
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat('-') {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else {
+            self.atom()
+        }
+    }
+
+    /// number, variable, string, or parenthesized expression
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                if !self.eat(')') {
+                    return Err(self.error("expected ')'"));
+                }
+                Ok(expr)
+            },
+            Some('\'') => {
+                self.pos += 1;
+                let rest = &self.src[self.pos..];
+                let Some(len) = rest.find('\'') else {
+                    return Err(self.error("unterminated string"));
+                };
+                self.pos += len + 1;
+                Ok(Expr::Value(DynamicConfigValue::String(
+                    rest[..len].to_owned(),
+                )))
+            },
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let s = self.take_while(|c| c.is_ascii_digit() || c == '.');
+                if let Ok(x) = s.parse::<i64>() {
+                    Ok(Expr::Value(DynamicConfigValue::Int(x)))
+                } else if let Ok(x) = s.parse::<f64>() {
+                    Ok(Expr::Value(DynamicConfigValue::Float(
+                        x,
+                    )))
+                } else {
+                    Err(self.error("invalid number"))
+                }
+            },
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let s = self.take_while(|c| {
+                    c.is_alphanumeric() || c == '_' || c == '.'
+                });
+                Ok(Expr::Var(s.to_owned()))
+            },
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let rest = &self.src[self.pos..];
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assets::config::DynamicConfigValue::{Float, Int};
+
+    fn eval(s: &str, vars: &ScriptVars) -> Result<DynamicConfigValue, String> {
+        s.parse::<ScriptExpr>()?.eval(vars)
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut vars = ScriptVars::default();
+        vars.insert("hits".into(), Int(3));
+        vars.insert("cooldown".into(), Float(1.5));
+        assert_eq!(eval("1 + 2 * 3", &vars), Ok(Int(7)));
+        assert_eq!(eval("(1 + 2) * -3", &vars), Ok(Int(-9)));
+        assert_eq!(eval("hits % 2", &vars), Ok(Int(1)));
+        assert_eq!(eval("hits / 2", &vars), Ok(Float(1.5)));
+        assert_eq!(
+            eval("cooldown * 2", &vars),
+            Ok(Float(3.0))
+        );
+        assert_eq!(
+            eval("'a' + 'b'", &vars),
+            Ok(DynamicConfigValue::String("ab".into()))
+        );
+        assert!(eval("missing + 1", &vars).is_err());
+        assert!(eval("1 +", &vars).is_err());
+        assert!(eval("'a' * 2", &vars).is_err());
+
+        vars.insert("min".into(), Int(i64::MIN));
+        assert!(eval("-min", &vars).is_err());
+    }
+
+    #[test]
+    fn compare_and_add() {
+        let mut vars = ScriptVars::default();
+        add_to_var(&mut vars, "count", Int(2)).unwrap();
+        add_to_var(&mut vars, "count", Float(0.5)).unwrap();
+        assert_eq!(vars["count"], Float(2.5));
+        assert_eq!(
+            compare(&Int(3), &Float(2.5)),
+            Some(Ordering::Greater)
+        );
+        let a = DynamicConfigValue::String("a".into());
+        assert_eq!(compare(&a, &a), Some(Ordering::Equal));
+        assert_eq!(compare(&a, &Int(1)), None);
+    }
+}
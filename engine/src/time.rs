@@ -50,6 +50,11 @@ pub struct GameTickEventClearSet;
 
 pub trait GameTimeAppExt {
     fn add_gametick_event<T: Event>(&mut self) -> &mut Self;
+    /// Run exactly `ticks` game ticks, as part of a single Bevy frame update
+    ///
+    /// Requires `GameTime` to be in manual mode (see [`GameTime::new_manual`]),
+    /// otherwise wall-clock time would also be added on top.
+    fn step_gameticks(&mut self, ticks: u64) -> &mut Self;
 }
 
 impl GameTimeAppExt for App {
@@ -65,6 +70,16 @@ impl GameTimeAppExt for App {
         }
         self
     }
+
+    fn step_gameticks(&mut self, ticks: u64) -> &mut Self {
+        let mut gametime = self.world.resource_mut::<GameTime>();
+        if !gametime.is_manual() {
+            warn!("Stepping GameTime manually, but it is also following wall-clock time!");
+        }
+        gametime.step(ticks);
+        self.update();
+        self
+    }
 }
 
 fn minimal_event_update_system<T: Event>(mut events: ResMut<Events<T>>) {
@@ -89,6 +104,8 @@ pub struct GameTime {
     total_ticks: u64,
     overstep: f64,
     last_update: Duration,
+    /// If true, wall-clock time is ignored and ticks only advance via `step`
+    manual: bool,
 }
 
 impl Default for GameTime {
@@ -100,6 +117,7 @@ impl Default for GameTime {
             total_ticks: 0,
             overstep: 0.0,
             last_update: Duration::new(0, 0),
+            manual: false,
         }
     }
 }
@@ -113,6 +131,29 @@ impl GameTime {
         }
     }
 
+    /// Create a `GameTime` that does not follow wall-clock time
+    ///
+    /// Ticks are only added by calling [`GameTime::step`]. Use this for
+    /// headless/deterministic runs of the simulation (such as from tests).
+    pub fn new_manual(hz: f64) -> Self {
+        Self {
+            hz,
+            manual: true,
+            ..Default::default()
+        }
+    }
+
+    /// Is this `GameTime` advanced manually, rather than from wall-clock time?
+    pub fn is_manual(&self) -> bool {
+        self.manual
+    }
+
+    /// Add `ticks` to be simulated on the next Bevy frame update
+    pub fn step(&mut self, ticks: u64) {
+        self.total_ticks += ticks;
+        self.new_ticks = self.total_ticks - self.tick;
+    }
+
     /// Get the current tick number to be simulated
     ///
     /// Increments with every run of the `GameTickUpdate` schedule, until it reaches
@@ -145,6 +186,7 @@ impl GameTime {
     pub fn reset(&mut self, now: Duration) {
         *self = Self {
             hz: self.hz,
+            manual: self.manual,
             last_update: now,
             ..Default::default()
         };
@@ -152,6 +194,11 @@ impl GameTime {
 
     /// Every Bevy frame, this gets called to advance the tick counters
     pub fn update(&mut self, time: &Time) {
+        if self.manual {
+            self.new_ticks = self.total_ticks - self.tick;
+            return;
+        }
+
         let now = time.elapsed();
         let delta = now - self.last_update;
         self.last_update = now;
@@ -29,6 +29,8 @@ pub enum AppState {
     AssetsLoading,
     /// Main Menu
     MainMenu,
+    /// Settings screen (from the main menu)
+    Settings,
     /// Gameplay
     InGame,
     Restart,
@@ -4,37 +4,41 @@ use crate::prelude::*;
 
 pub struct AssetsPlugin;
 
+/// The dynamic asset collections that declare all of our assets
+pub const DYNAMIC_ASSET_FILES: &[&str] = &[
+    "animations.assets.ron",
+    "sprites.assets.ron",
+    "audio.assets.ron",
+    "levels.assets.ron",
+    "gameplay.assets.ron",
+    "ui.assets.ron",
+];
+
+/// Which assets each level needs, to load them only when the level is near
+///
+/// Groups are named `"<ldtk project key>/<level identifier>"`.
+pub const LEVEL_ASSET_MANIFESTS: &str = "levels.manifest.toml";
+
 impl Plugin for AssetsPlugin {
     fn build(&self, app: &mut App) {
         // add custom asset infra
         app.add_plugins(theseeker_engine::assets::AssetsPlugin {
             loading_state: AppState::AssetsLoading,
+            manifests: Some(LEVEL_ASSET_MANIFESTS),
         });
 
         // bevy_asset_loader
-        app.add_loading_state(
-            LoadingState::new(AppState::AssetsLoading)
-                .with_dynamic_assets_file::<StandardDynamicAssetCollection>(
-                    "animations.assets.ron",
-                )
-                .with_dynamic_assets_file::<StandardDynamicAssetCollection>(
-                    "sprites.assets.ron",
-                )
-                .with_dynamic_assets_file::<StandardDynamicAssetCollection>(
-                    "audio.assets.ron",
-                )
+        let mut loading_state = LoadingState::new(AppState::AssetsLoading);
+        for file in DYNAMIC_ASSET_FILES {
+            loading_state = loading_state
                 .with_dynamic_assets_file::<StandardDynamicAssetCollection>(
-                    "levels.assets.ron",
-                )
-                .with_dynamic_assets_file::<StandardDynamicAssetCollection>(
-                    "gameplay.assets.ron",
-                )
-                .with_dynamic_assets_file::<StandardDynamicAssetCollection>(
-                    "ui.assets.ron",
-                )
+                    file,
+                );
+        }
+        app.add_loading_state(
+            loading_state
                 .load_collection::<UiAssets>()
-                .load_collection::<MainMenuAssets>()
-                .load_collection::<DialogAssets>(),
+                .load_collection::<MainMenuAssets>(),
         );
     }
 }
@@ -54,11 +58,3 @@ pub struct MainMenuAssets {
     #[asset(key = "ui.mainmenu.background")]
     pub background: Handle<Image>,
 }
-
-#[derive(AssetCollection, Resource)]
-pub struct DialogAssets {
-    #[asset(key = "dialog.MrSnafflesBackground")]
-    pub mr_snaffles_background: Handle<Image>,
-    #[asset(key = "dialog.VagrantBackground")]
-    pub vagrant_background: Handle<Image>,
-}
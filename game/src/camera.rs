@@ -27,6 +27,7 @@ impl Plugin for CameraPlugin {
             cli_camera_limits_noargs,
         );
         app.register_clicommand_args("camera_limits", cli_camera_limits_args);
+        app.register_clicommand_args("camera_shake", cli_camera_shake);
         app.add_systems(
             OnEnter(AppState::InGame),
             setup_main_camera,
@@ -49,6 +50,7 @@ impl Plugin for CameraPlugin {
                 update_screen_shake.run_if(resource_exists::<CameraShake>),
             ),
         );
+        app.add_systems(Update, update_view_limits);
     }
 }
 
@@ -83,6 +85,15 @@ pub struct CameraRig {
     lead_buffer: f32,
 }
 
+impl CameraRig {
+    /// Move the camera to a position immediately (such as when the player
+    /// enters a new level), instead of smoothly following the player there
+    pub fn jump_to(&mut self, position: Vec2) {
+        self.target = position;
+        self.camera_position = position;
+    }
+}
+
 enum LeadDirection {
     Backward,
     Forward,
@@ -133,7 +144,7 @@ pub(crate) fn setup_main_camera(mut commands: Commands) {
             camera: camera3d,
             marker: MainCamera,
             despawn: StateDespawnMarker,
-            // TODO: manage this from somewhere
+            // updated when the level is spawned
             limits: GameViewLimits(Rect::new(0.0, 0.0, 640.0, 480.0)),
         },
         // Needed so that depth buffers are stored so depth of field works
@@ -211,15 +222,19 @@ fn camera_rig_follow_player(
 /// Camera updates the camera position to smoothly interpolate to the
 /// rig location. also applies camera shake, and limits camera within the level boundaries
 pub(crate) fn update_camera(
-    mut camera_query: Query<(&mut Transform, &Projection), With<MainCamera>>,
-    rig: Res<CameraRig>,
-    backround_query: Query<
-        (&LayerMetadata, &Transform),
-        (With<MainBackround>, Without<MainCamera>),
+    mut camera_query: Query<
+        (
+            &mut Transform,
+            &Projection,
+            &GameViewLimits,
+        ),
+        With<MainCamera>,
     >,
+    rig: Res<CameraRig>,
     camera_shake: Option<Res<CameraShake>>,
 ) {
-    let Ok((mut camera_transform, projection)) = camera_query.get_single_mut()
+    let Ok((mut camera_transform, projection, limits)) =
+        camera_query.get_single_mut()
     else {
         return;
     };
@@ -231,27 +246,37 @@ pub(crate) fn update_camera(
     camera_transform.translation.x = rig.camera_position.x;
     camera_transform.translation.y = rig.camera_position.y;
 
-    if let Ok((bg_layer, bg_transform)) = backround_query.get_single() {
-        let camera_rect = ortho_projection.area;
-        let background_rect = background_rect(bg_layer, bg_transform);
+    let camera_rect = ortho_projection.area;
 
-        clamp_camera_to_edge(
-            &mut camera_transform,
-            background_rect,
-            camera_rect,
-        );
+    clamp_camera_to_edge(
+        &mut camera_transform,
+        limits.0,
+        camera_rect,
+    );
 
-        // Apply screen shake after camera is clamped so that camera still shakes at the edges
-        if let Some(camera_shake) = camera_shake {
-            camera_shake.apply(&mut camera_transform);
-        }
+    // Apply screen shake after camera is clamped so that camera still shakes at the edges
+    if let Some(camera_shake) = camera_shake {
+        camera_shake.apply(&mut camera_transform);
+    }
 
-        // Apply another clamp so we don't show the edge of the level
-        clamp_camera_to_edge(
-            &mut camera_transform,
-            background_rect,
-            camera_rect,
-        );
+    // Apply another clamp so we don't show the edge of the level
+    clamp_camera_to_edge(
+        &mut camera_transform,
+        limits.0,
+        camera_rect,
+    );
+}
+
+/// Set the camera limits to the bounds of the level, whenever a level is spawned
+fn update_view_limits(
+    mut camera_query: Query<&mut GameViewLimits, With<MainCamera>>,
+    background_query: Query<(&LayerMetadata, &Transform), Added<MainBackround>>,
+) {
+    let Ok(mut limits) = camera_query.get_single_mut() else {
+        return;
+    };
+    for (bg_layer, bg_transform) in &background_query {
+        limits.0 = background_rect(bg_layer, bg_transform);
     }
 }
 
@@ -374,6 +399,20 @@ fn cli_camera_at(
     }
 }
 
+fn cli_camera_shake(In(args): In<Vec<String>>, mut commands: Commands) {
+    if args.len() != 3 {
+        error!("\"camera_shake <strength> <seconds> <frequency>\"");
+        return;
+    }
+    if let (Ok(strength), Ok(t), Ok(freq)) =
+        (args[0].parse(), args[1].parse(), args[2].parse())
+    {
+        commands.insert_resource(CameraShake::new(strength, t, freq));
+    } else {
+        error!("\"camera_shake <strength> <seconds> <frequency>\": args must be numeric values");
+    }
+}
+
 fn cli_camera_limits_noargs(q_cam: Query<&GameViewLimits, With<MainCamera>>) {
     if let Ok(limits) = q_cam.get_single() {
         info!(
@@ -4,23 +4,31 @@
 
 use switches::{PuzzleBundle, SwitchBundle};
 
+use self::boss::{BossArenaBundle, BossBlueprintBundle};
 use self::enemy::{EnemyBlueprintBundle, EnemySpawnerBundle};
 use self::player::PlayerBlueprintBundle;
 use crate::game::merchant::MerchantBlueprintBundle;
+use crate::game::pickups::WeaponPickupBlueprintBundle;
 use crate::game::yak::YakBlueprintBundle;
+use crate::level::LevelDoorBundle;
 use crate::prelude::*;
 
 pub mod attack;
+pub mod boss;
+pub mod dialogue;
 pub mod enemy;
 mod game_over;
 pub mod gentstate;
 mod merchant;
 pub mod physics;
 pub mod pickups;
+mod platform;
 pub mod player;
+pub mod shop;
+pub mod status;
 mod switches;
 mod wall;
-mod xp_orbs;
+pub mod xp_orbs;
 mod yak;
 
 pub struct GameplayPlugin;
@@ -34,11 +42,19 @@ impl Plugin for GameplayPlugin {
         app.register_ldtk_int_cell::<wall::WallBundle>(18);
         app.register_ldtk_int_cell::<wall::WallBundle>(19);
         app.register_ldtk_int_cell::<wall::WallBundle>(20);
+        app.register_ldtk_int_cell::<platform::OneWayPlatformBundle>(21);
         app.register_ldtk_entity::<PlayerBlueprintBundle>("Player");
         app.register_ldtk_entity::<MerchantBlueprintBundle>("Merchant");
         app.register_ldtk_entity::<YakBlueprintBundle>("Yak");
+        app.register_ldtk_entity::<WeaponPickupBlueprintBundle>("WeaponPickup");
         app.register_ldtk_entity::<EnemyBlueprintBundle>("Enemy");
         app.register_ldtk_entity::<EnemySpawnerBundle>("EnemySpawner");
+        app.register_ldtk_entity::<LevelDoorBundle>("Door");
+        app.register_ldtk_entity::<BossBlueprintBundle>("Boss");
+        app.register_ldtk_entity::<BossArenaBundle>("BossArena");
+        app.register_ldtk_entity::<platform::PlatformBlueprintBundle>(
+            "Platform",
+        );
 
         app.register_ldtk_entity::<SwitchBundle>("Switch1")
             .register_ldtk_entity::<SwitchBundle>("Switch2")
@@ -70,14 +86,19 @@ impl Plugin for GameplayPlugin {
         app.add_plugins((
             player::PlayerPlugin,
             enemy::EnemyPlugin,
+            dialogue::DialoguePlugin,
             merchant::MerchantPlugin,
             yak::YakPlugin,
             attack::AttackPlugin,
+            boss::BossPlugin,
             wall::WallPlugin,
+            platform::PlatformPlugin,
             game_over::GameOverPlugin,
             xp_orbs::XpPlugin,
             switches::SwitchesPlugin,
             pickups::PickupPlugin,
+            shop::ShopPlugin,
+            status::StatusPlugin,
         ));
     }
 }
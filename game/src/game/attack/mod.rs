@@ -10,15 +10,16 @@ use theseeker_engine::physics::{
     update_sprite_colliders, Collider, PhysicsWorld, GROUND, PLAYER_ATTACK,
 };
 
+use super::boss::BossGfx;
 use super::enemy::{Defense, EnemyGfx, EnemyStateSet};
 use super::gentstate::{Dead, Facing};
 use super::physics::Knockback;
 use super::player::player_weapon::CurrentWeapon;
 use super::player::{
-    on_crit_cooldown_reduce, on_crit_heal, on_hit_exit_stealthing,
-    on_stealth_hit_cooldown_reset, Passive, Passives, Player, PlayerConfig,
-    PlayerGfx, PlayerStateSet, StatusModifier,
+    on_hit_exit_stealthing, on_passive_trigger, on_stealth_hit_cooldown_reset,
+    Passive, Passives, Player, PlayerConfig, PlayerGfx, PlayerStateSet,
 };
+use super::status::{StatusModifier, StatusModifiers};
 use crate::game::attack::arc_attack::{arc_projectile, Projectile};
 use crate::game::attack::particles::AttackParticlesPlugin;
 use crate::prelude::*;
@@ -31,6 +32,7 @@ impl Plugin for AttackPlugin {
         app.register_type::<Crits>();
         app.add_plugins(AttackParticlesPlugin);
         app.add_gametick_event::<DamageInfo>();
+        app.add_gametick_event::<Killed>();
         app.init_resource::<KillCount>();
         app.add_systems(
             GameTickUpdate,
@@ -49,8 +51,7 @@ impl Plugin for AttackPlugin {
                     on_hit_cam_shake,
                     on_hit_self_pushback,
                     on_hit_lifesteal,
-                    on_crit_cooldown_reduce,
-                    on_crit_heal,
+                    on_passive_trigger,
                     on_stealth_hit_cooldown_reset,
                     on_hit_exit_stealthing,
                 )
@@ -98,6 +99,8 @@ pub struct Health {
 pub struct DamageFlash {
     pub current_ticks: u32,
     pub max_ticks: u32,
+    /// Color the sprite goes back to when the flash is over
+    pub restore_color: Color,
 }
 
 #[derive(Bundle)]
@@ -327,6 +330,7 @@ pub fn apply_attack_damage(
             &GlobalTransform,
             &Facing,
             Option<&PlayerStatMod>,
+            Option<&mut StatusModifiers>,
             Has<Defense>,
         ),
         With<Gent>,
@@ -356,6 +360,7 @@ pub fn apply_attack_damage(
                 t_transform,
                 t_facing,
                 maybe_player_statmod,
+                maybe_status_modifiers,
                 is_defending,
             )) = target_query.get_mut(*target)
             {
@@ -382,10 +387,11 @@ pub fn apply_attack_damage(
                     damage /= 5.;
                 }
 
-                // TODO:
                 // Apply Stat Modifier (if exists)
                 if let Some(stat_modifier) = &attack.status_mod {
-                    commands.entity(t_entity).insert(stat_modifier.clone());
+                    if let Some(mut status_modifiers) = maybe_status_modifiers {
+                        status_modifiers.apply(stat_modifier.clone());
+                    }
                 }
 
                 // apply player defense modifier if it exists
@@ -469,7 +475,10 @@ pub fn kill_on_damage(
     for damage_info in damage_events.read() {
         if let Ok((entity, health)) = query.get(damage_info.target) {
             if health.current == 0 {
-                commands.entity(entity).insert(Dead::default());
+                commands.entity(entity).insert(Dead {
+                    killer: Some(damage_info.attacker),
+                    ..default()
+                });
             }
         }
     }
@@ -485,19 +494,14 @@ fn on_hit_cam_shake(
 ) {
     for attack in query.iter() {
         if let Ok(_entity) = p_query.get(attack.attacker) {
-            let camera_shake = if weapon.is_wielding_hammer() {
-                CameraShake::new(
-                    config.hammer_on_hit_screenshake_strength,
-                    config.hammer_on_hit_screenshake_duration_secs,
-                    config.hammer_on_hit_screenshake_frequency,
-                )
-            } else {
-                CameraShake::new(
-                    config.default_on_hit_screenshake_strength,
-                    config.default_on_hit_screenshake_duration_secs,
-                    config.default_on_hit_screenshake_frequency,
-                )
-            };
+            let camera_shake =
+                weapon.on_hit_screenshake().unwrap_or_else(|| {
+                    CameraShake::new(
+                        config.default_on_hit_screenshake_strength,
+                        config.default_on_hit_screenshake_duration_secs,
+                        config.default_on_hit_screenshake_frequency,
+                    )
+                });
 
             commands.insert_resource(camera_shake);
         }
@@ -535,10 +539,9 @@ fn on_hit_lifesteal(
 
 fn apply_damage_flash(
     sprite_query: Query<
-        Entity,
+        (Entity, &Sprite, Option<&DamageFlash>),
         (
-            With<Sprite>,
-            Or<(With<EnemyGfx>, With<PlayerGfx>)>,
+            Or<(With<EnemyGfx>, With<PlayerGfx>, With<BossGfx>)>,
             Without<Gent>,
         ),
     >,
@@ -548,10 +551,13 @@ fn apply_damage_flash(
 ) {
     for damage_info in damage_events.read() {
         if let Ok(gent) = gent_query.get(damage_info.target) {
-            if let Ok(entity) = sprite_query.get(gent.e_gfx) {
+            if let Ok((entity, sprite, flash)) = sprite_query.get(gent.e_gfx) {
                 commands.entity(entity).insert(DamageFlash {
                     current_ticks: 0,
                     max_ticks: 8,
+                    restore_color: flash
+                        .map(|f| f.restore_color)
+                        .unwrap_or(sprite.color),
                 });
             }
         }
@@ -567,7 +573,7 @@ fn damage_flash(
 
         if damage_flash.current_ticks == damage_flash.max_ticks {
             commands.entity(entity).remove::<DamageFlash>();
-            sprite.color = Color::rgb(1., 1., 1.);
+            sprite.color = damage_flash.restore_color;
         }
         damage_flash.current_ticks += 1;
     }
@@ -592,6 +598,14 @@ fn attack_cleanup(query: Query<(Entity, &Attack)>, mut commands: Commands) {
 #[derive(Resource, Debug, Default, Deref, DerefMut)]
 pub struct KillCount(pub u32);
 
+/// Event sent when an enemy dies, at the same time the [`KillCount`] goes up
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Killed {
+    pub entity: Entity,
+    /// See [`Dead::killer`]
+    pub killer: Option<Entity>,
+}
+
 fn track_crits(mut query: Query<(&mut Crits, Option<&Passives>, &Health)>) {
     for (mut crits, maybe_passives, health) in query.iter_mut() {
         if crits.hit_count != 0
@@ -0,0 +1,888 @@
+//! Boss fights
+//!
+//! A boss is placed in a level as an LDtk `Boss` entity, whose `boss` field
+//! is the id of its definition in `enemy.bosses.toml` (asset key
+//! `bosses.enemy`). A boss fights in phases: each phase starts when the
+//! boss's health drops to the given fraction (`below`), plays an optional
+//! [`Script`](theseeker_engine::assets::script::Script) (for camera moves,
+//! audio stingers, ...), and loops over its own pattern of attack steps:
+//!
+//! ```toml
+//! [[boss]]
+//! id = "broodmother"
+//! name = "Broodmother"
+//! health = 1500
+//! size = [40.0, 24.0]
+//! idle_anim = "anim.spider3.Idle"
+//! death_anim = "anim.spider3.Death"
+//!
+//! [[boss.phases]]
+//! below = 0.5
+//! script = "script.boss.broodmother.enraged"
+//!
+//! [[boss.phases.pattern]]
+//! attack = "volley"
+//! ticks = 96
+//! at = 24
+//! count = 3
+//! spread = 24.0
+//! speed = 200.0
+//! damage = 20.0
+//! ```
+//!
+//! Step attacks: `wait`, `volley` (arcing projectiles at the player),
+//! `slam` (a hitbox around the boss) and `charge` (run at the player).
+//! Each step lasts `ticks`, and attacks at tick `at` within it.
+//!
+//! A `BossArena` LDtk entity marks the area of a fight. When the player
+//! enters it while a boss inside is alive, the fight starts and the arena
+//! locks: its doors stop working and barriers close off its sides, until
+//! all the bosses inside are dead. Bosses outside of any arena start
+//! fighting when the player comes within their `engage_range`.
+//!
+//! Defeated bosses stay dead for the rest of the run, including after
+//! loading it from a save file (see [`DefeatedBosses`]).
+
+use bevy::reflect::TypePath;
+use bevy_common_assets::toml::TomlAssetPlugin;
+use rapier2d::parry::query::TOIStatus;
+use rapier2d::prelude::{Group, InteractionGroups};
+use theseeker_engine::animation::SpriteAnimationBundle;
+use theseeker_engine::assets::animation::SpriteAnimation;
+use theseeker_engine::assets::reload_on_modified;
+use theseeker_engine::gent::{
+    Gent, InterpolateTransform, TransformGfxFromGent,
+};
+use theseeker_engine::physics::{
+    update_sprite_colliders, Collider, PhysicsWorld, StaticCollider, ENEMY,
+    ENEMY_ATTACK, ENEMY_INSIDE, GROUND, PLAYER,
+};
+use theseeker_engine::script::common::ScriptBundle;
+use theseeker_engine::script::ScriptPlayer;
+
+use super::attack::arc_attack::Projectile;
+use super::attack::particles::ArcParticleEffectHandle;
+use super::attack::{Attack, Health, KillCount, Killed};
+use super::enemy::EnemyStateSet;
+use super::gentstate::{Dead, Facing};
+use super::player::{Player, PlayerConfig};
+use crate::graphics::particles_util::BuildParticles;
+use crate::level::{LevelDespawnMarker, LevelDoor, LockedDoor};
+use crate::prelude::*;
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(TomlAssetPlugin::<BossList>::new(&[
+            "bosses.toml",
+        ]));
+        app.init_resource::<BossDefs>();
+        app.init_resource::<DefeatedBosses>();
+        app.add_systems(
+            GameTickUpdate,
+            reload_on_modified::<BossList>("bosses.enemy")
+                .pipe(load_boss_defs)
+                .before(EnemyStateSet::Behavior),
+        );
+        app.add_systems(
+            GameTickUpdate,
+            (
+                setup_bosses.run_if(any_with_component::<BossBlueprint>),
+                update_boss_arenas.run_if(any_with_component::<BossArena>),
+                engage_bosses,
+                boss_phases,
+                boss_pattern,
+                boss_death.run_if(any_with_component::<Dead>),
+            )
+                .chain()
+                .in_set(EnemyStateSet::Behavior)
+                .before(update_sprite_colliders)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+/// How long the dead boss stays around, to play its death animation
+const BOSS_DESPAWN_TICKS: u32 = 8 * 16;
+
+/// Thickness of the barriers that close off a locked arena
+const ARENA_BARRIER_WIDTH: f32 = 16.0;
+
+/// Asset type for files with boss definitions
+#[derive(Asset, Debug, Clone)]
+#[derive(Deserialize)]
+#[derive(TypePath)]
+pub struct BossList {
+    #[serde(default)]
+    pub boss: Vec<BossDef>,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct BossDef {
+    pub id: String,
+    /// Shown with the health bar
+    pub name: String,
+    pub health: u32,
+    /// Size of the hurtbox (in pixels)
+    pub size: [f32; 2],
+    /// If not in an arena, the fight starts when the player is this close
+    #[serde(default = "default_engage_range")]
+    pub engage_range: f32,
+    /// Animation asset key, for steps that don't have their own
+    pub idle_anim: String,
+    pub death_anim: String,
+    pub phases: Vec<BossPhase>,
+}
+
+fn default_engage_range() -> f32 {
+    120.0
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct BossPhase {
+    /// The phase starts once health is at or below this fraction
+    /// of max health
+    #[serde(default = "one")]
+    pub below: f32,
+    /// Script asset key, played when the phase starts
+    pub script: Option<String>,
+    pub pattern: Vec<BossStep>,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct BossStep {
+    /// How long the step lasts
+    pub ticks: u32,
+    /// When to attack, counted from the start of the step
+    #[serde(default)]
+    pub at: u32,
+    /// Animation asset key to play during the step
+    pub anim: Option<String>,
+    #[serde(flatten)]
+    pub attack: BossAttack,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Deserialize)]
+#[serde(tag = "attack", rename_all = "snake_case")]
+pub enum BossAttack {
+    Wait,
+    /// Lob `count` projectiles at the player, `spread` pixels apart
+    Volley {
+        count: u32,
+        #[serde(default)]
+        spread: f32,
+        /// Launch speed (in pixels/second); increased if the player
+        /// cannot be reached with it
+        speed: f32,
+        damage: f32,
+    },
+    /// A hitbox of the given size around the boss
+    Slam {
+        width: f32,
+        height: f32,
+        damage: f32,
+        /// How long the hitbox stays (in ticks)
+        lifetime: u32,
+    },
+    /// Run towards the player (at the time of `at`) until the step ends,
+    /// hurting them on contact
+    Charge {
+        /// (in pixels/second)
+        speed: f32,
+        damage: f32,
+    },
+}
+
+impl BossDef {
+    /// Index of the phase the boss should be in, at the given fraction
+    /// of max health
+    ///
+    /// Phases are in order, with decreasing `below`.
+    pub fn phase_at(&self, health_factor: f32) -> usize {
+        self.phases
+            .iter()
+            .rposition(|phase| health_factor <= phase.below)
+            .unwrap_or(0)
+    }
+
+    fn validate(&self) {
+        if self.phases.is_empty() {
+            warn!("Boss {:?} has no phases", self.id);
+        }
+        for (i, phase) in self.phases.iter().enumerate() {
+            if i > 0 && phase.below >= self.phases[i - 1].below {
+                warn!(
+                    "Boss {:?}: phase {} must have a lower 'below' than the phase before it",
+                    self.id, i
+                );
+            }
+            if phase.pattern.is_empty() {
+                warn!(
+                    "Boss {:?}: phase {} has no pattern",
+                    self.id, i
+                );
+            }
+            for step in phase.pattern.iter() {
+                if step.at >= step.ticks {
+                    warn!(
+                        "Boss {:?}: step {:?} attacks after it ends",
+                        self.id, step.attack
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// All known bosses, from the bosses asset
+#[derive(Resource, Default)]
+pub struct BossDefs {
+    defs: Vec<BossDef>,
+}
+
+impl BossDefs {
+    pub fn get(&self, id: &str) -> Option<&BossDef> {
+        self.defs.iter().find(|def| def.id == id)
+    }
+}
+
+fn load_boss_defs(In(list): In<Option<BossList>>, mut defs: ResMut<BossDefs>) {
+    let Some(list) = list else {
+        return;
+    };
+    for def in list.boss.iter() {
+        def.validate();
+    }
+    *defs = BossDefs { defs: list.boss };
+}
+
+/// LDtk entity that becomes a boss
+#[derive(Component, Debug, Default)]
+pub struct BossBlueprint {
+    /// Id of the [`BossDef`]
+    id: String,
+}
+
+impl BossBlueprint {
+    fn from_entity_instance(entity: &EntityInstance) -> Self {
+        let Ok(id) = entity.get_string_field("boss") else {
+            error!(
+                "Boss {} has no string field \"boss\"",
+                entity.iid
+            );
+            return Self::default();
+        };
+        Self { id: id.clone() }
+    }
+}
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct BossBlueprintBundle {
+    #[with(BossBlueprint::from_entity_instance)]
+    blueprint: BossBlueprint,
+}
+
+#[derive(Component, Debug)]
+pub struct Boss {
+    pub id: String,
+    /// Shown with the health bar
+    pub name: String,
+    phase: usize,
+    /// Index into the current phase's pattern
+    step: usize,
+    /// Ticks since the current step started
+    ticks: u32,
+    /// Direction of the current charge
+    charge_dir: f32,
+}
+
+#[derive(Component)]
+pub struct BossGfx;
+
+/// LDtk entity iids of the bosses defeated during this run
+///
+/// They do not come back when their level is spawned again (or the run
+/// is loaded from a save file).
+#[derive(Resource, Debug, Default, Clone)]
+pub struct DefeatedBosses(pub HashSet<String>);
+
+/// Present on bosses while they are fighting the player
+#[derive(Component, Debug, Default)]
+pub struct BossFight {
+    /// The boss cannot move out of this area (of its arena)
+    bounds: Option<Rect>,
+}
+
+/// The area of a boss fight, as an LDtk entity
+///
+/// The LDtk entity's size is the area.
+#[derive(Component, Debug, Default)]
+pub struct BossArena {
+    half_size: Vec2,
+    state: ArenaState,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ArenaState {
+    #[default]
+    Open,
+    Locked,
+    Cleared,
+}
+
+impl BossArena {
+    fn from_entity_instance(entity: &EntityInstance) -> Self {
+        Self {
+            half_size: Vec2::new(
+                entity.width as f32,
+                entity.height as f32,
+            ) / 2.0,
+            state: ArenaState::Open,
+        }
+    }
+
+    fn rect(&self, transform: &GlobalTransform) -> Rect {
+        Rect::from_center_half_size(
+            transform.translation().xy(),
+            self.half_size,
+        )
+    }
+}
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct BossArenaBundle {
+    #[with(BossArena::from_entity_instance)]
+    arena: BossArena,
+}
+
+/// Closes off a side of a locked arena
+#[derive(Component)]
+struct ArenaBarrier(Entity);
+
+fn setup_bosses(
+    q: Query<(
+        Entity,
+        &BossBlueprint,
+        &Transform,
+        Option<&EntityIid>,
+    )>,
+    defs: Res<BossDefs>,
+    defeated: Res<DefeatedBosses>,
+    mut commands: Commands,
+) {
+    for (e_gent, blueprint, xf_gent, iid) in q.iter() {
+        if iid.is_some_and(|iid| defeated.0.contains(iid.as_str())) {
+            commands.entity(e_gent).despawn_recursive();
+            continue;
+        }
+        let Some(def) = defs.get(&blueprint.id) else {
+            // the definitions might not be loaded yet
+            if !defs.defs.is_empty() {
+                error!("Unknown boss {:?}", blueprint.id);
+                commands.entity(e_gent).remove::<BossBlueprint>();
+            }
+            continue;
+        };
+        let e_gfx = commands.spawn(()).id();
+        let e_effects_gfx = commands
+            .spawn((StateDespawnMarker, LevelDespawnMarker))
+            .id();
+        commands
+            .entity(e_gent)
+            .remove::<BossBlueprint>()
+            .insert((
+                Name::new("Boss"),
+                Boss {
+                    id: def.id.clone(),
+                    name: def.name.clone(),
+                    phase: 0,
+                    step: 0,
+                    ticks: 0,
+                    charge_dir: 0.0,
+                },
+                Gent {
+                    e_gfx,
+                    e_effects_gfx,
+                },
+                // like an enemy that the player is inside of:
+                // the player can pass through, but their attacks hit
+                Collider::cuboid(
+                    def.size[0],
+                    def.size[1],
+                    InteractionGroups {
+                        memberships: ENEMY_INSIDE,
+                        filter: Group::all(),
+                    },
+                ),
+                Health {
+                    current: def.health,
+                    max: def.health,
+                },
+                Facing::Left,
+                InterpolateTransform::default(),
+                StateDespawnMarker,
+                LevelDespawnMarker,
+            ))
+            .remove_parent();
+        let mut animation = ScriptPlayer::<SpriteAnimation>::default();
+        animation.play_key(&def.idle_anim);
+        commands.entity(e_gfx).insert((
+            BossGfx,
+            TransformGfxFromGent {
+                pixel_aligned: false,
+                gent: e_gent,
+            },
+            SpriteSheetBundle {
+                transform: *xf_gent,
+                ..Default::default()
+            },
+            SpriteAnimationBundle { player: animation },
+            StateDespawnMarker,
+            LevelDespawnMarker,
+        ));
+    }
+}
+
+/// Lock arenas when the player enters them, and unlock them when their
+/// bosses are dead
+fn update_boss_arenas(
+    mut q_arena: Query<(Entity, &mut BossArena, &GlobalTransform)>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+    q_boss: Query<
+        (Entity, &GlobalTransform, Has<BossFight>),
+        (With<Boss>, Without<Dead>),
+    >,
+    q_doors: Query<(Entity, &GlobalTransform), With<LevelDoor>>,
+    q_barriers: Query<(Entity, &ArenaBarrier)>,
+    mut commands: Commands,
+) {
+    let player_pos = q_player.get_single().ok().map(|xf| xf.translation().xy());
+    for (e_arena, mut arena, xf_arena) in q_arena.iter_mut() {
+        let rect = arena.rect(xf_arena);
+        let mut bosses_inside = q_boss
+            .iter()
+            .filter(|(_, xf, _)| rect.contains(xf.translation().xy()))
+            .peekable();
+        let doors_inside = q_doors
+            .iter()
+            .filter(|(_, xf)| rect.contains(xf.translation().xy()))
+            .map(|(e, _)| e);
+        match arena.state {
+            ArenaState::Open => {
+                let player_inside =
+                    player_pos.is_some_and(|p| rect.contains(p));
+                if !player_inside || bosses_inside.peek().is_none() {
+                    continue;
+                }
+                debug!("Locking boss arena {:?}", e_arena);
+                for (e_boss, _, fighting) in bosses_inside {
+                    if !fighting {
+                        commands
+                            .entity(e_boss)
+                            .insert(BossFight { bounds: Some(rect) });
+                    }
+                }
+                for e_door in doors_inside {
+                    commands.entity(e_door).insert(LockedDoor);
+                }
+                for x in [rect.min.x, rect.max.x] {
+                    commands.spawn((
+                        ArenaBarrier(e_arena),
+                        Collider::cuboid(
+                            ARENA_BARRIER_WIDTH,
+                            rect.height(),
+                            InteractionGroups {
+                                memberships: GROUND,
+                                filter: Group::all(),
+                            },
+                        ),
+                        TransformBundle::from_transform(Transform::from_xyz(
+                            x,
+                            rect.center().y,
+                            10.0,
+                        )),
+                        StaticCollider,
+                        StateDespawnMarker,
+                        LevelDespawnMarker,
+                    ));
+                }
+                arena.state = ArenaState::Locked;
+            },
+            ArenaState::Locked => {
+                if bosses_inside.peek().is_some() {
+                    continue;
+                }
+                debug!("Unlocking boss arena {:?}", e_arena);
+                for e_door in doors_inside {
+                    commands.entity(e_door).remove::<LockedDoor>();
+                }
+                for (e_barrier, barrier) in q_barriers.iter() {
+                    if barrier.0 == e_arena {
+                        commands.entity(e_barrier).despawn_recursive();
+                    }
+                }
+                arena.state = ArenaState::Cleared;
+            },
+            ArenaState::Cleared => {},
+        }
+    }
+}
+
+/// Start fighting bosses that are not in an arena, when the player
+/// comes close
+fn engage_bosses(
+    q_boss: Query<
+        (Entity, &Boss, &GlobalTransform),
+        (Without<BossFight>, Without<Dead>),
+    >,
+    q_arena: Query<(&BossArena, &GlobalTransform)>,
+    q_player: Query<&GlobalTransform, With<Player>>,
+    defs: Res<BossDefs>,
+    mut commands: Commands,
+) {
+    let Ok(xf_player) = q_player.get_single() else {
+        return;
+    };
+    for (e_boss, boss, xf_boss) in q_boss.iter() {
+        let pos = xf_boss.translation().xy();
+        // arenas start their own fights
+        if q_arena
+            .iter()
+            .any(|(arena, xf)| arena.rect(xf).contains(pos))
+        {
+            continue;
+        }
+        let Some(def) = defs.get(&boss.id) else {
+            continue;
+        };
+        if pos.distance(xf_player.translation().xy()) <= def.engage_range {
+            commands.entity(e_boss).insert(BossFight::default());
+        }
+    }
+}
+
+/// Switch phases based on health, and play the scripts of new phases
+fn boss_phases(
+    mut q_boss: Query<(&mut Boss, &Health, Ref<BossFight>), Without<Dead>>,
+    defs: Res<BossDefs>,
+    mut commands: Commands,
+) {
+    for (mut boss, health, fight) in q_boss.iter_mut() {
+        let Some(def) = defs.get(&boss.id) else {
+            continue;
+        };
+        let health_factor = health.current as f32 / health.max as f32;
+        let phase = def.phase_at(health_factor);
+        // the first phase starts with the fight
+        if !fight.is_added() && phase <= boss.phase {
+            continue;
+        }
+        debug!(
+            "Boss {:?} enters phase {}",
+            boss.id, phase
+        );
+        boss.phase = phase;
+        boss.step = 0;
+        boss.ticks = 0;
+        if let Some(script) = &def.phases[phase].script {
+            commands.spawn((
+                ScriptBundle::new_play_key(script),
+                StateDespawnMarker,
+                LevelDespawnMarker,
+            ));
+        }
+    }
+}
+
+/// Perform the steps of the current phase's pattern
+#[allow(clippy::too_many_arguments)]
+fn boss_pattern(
+    mut q_boss: Query<
+        (
+            Entity,
+            &mut Boss,
+            &BossFight,
+            &Gent,
+            &mut Transform,
+            &mut Facing,
+            &Collider,
+        ),
+        (Without<Dead>, Without<Player>),
+    >,
+    q_player: Query<&Transform, With<Player>>,
+    mut gfx_query: Query<&mut ScriptPlayer<SpriteAnimation>, With<BossGfx>>,
+    defs: Res<BossDefs>,
+    config: Res<PlayerConfig>,
+    time: Res<GameTime>,
+    particle_effect: Res<ArcParticleEffectHandle>,
+    spatial_query: Res<PhysicsWorld>,
+    mut commands: Commands,
+) {
+    let player_pos = q_player.get_single().ok().map(|xf| xf.translation.xy());
+    for (entity, mut boss, fight, gent, mut transform, mut facing, collider) in
+        q_boss.iter_mut()
+    {
+        let Some(def) = defs.get(&boss.id) else {
+            continue;
+        };
+        let Some(pattern) = def.phases.get(boss.phase).map(|p| &p.pattern)
+        else {
+            continue;
+        };
+        if pattern.is_empty() {
+            continue;
+        }
+        let step = &pattern[boss.step % pattern.len()];
+        let pos = transform.translation.xy();
+
+        if boss.ticks == 0 {
+            if let Some(player_pos) = player_pos {
+                *facing = if player_pos.x < pos.x {
+                    Facing::Left
+                } else {
+                    Facing::Right
+                };
+            }
+            if let Ok(mut anim) = gfx_query.get_mut(gent.e_gfx) {
+                anim.play_key(step.anim.as_ref().unwrap_or(&def.idle_anim));
+                let right = matches!(*facing, Facing::Right);
+                anim.set_slot("DirectionRight", right);
+                anim.set_slot("DirectionLeft", !right);
+            }
+        }
+
+        if boss.ticks == step.at {
+            match &step.attack {
+                BossAttack::Wait => {},
+                BossAttack::Volley {
+                    count,
+                    spread,
+                    speed,
+                    damage,
+                } => {
+                    let gravity = config.fall_accel * time.hz as f32;
+                    let targets = (0..*count).filter_map(|i| {
+                        let offset =
+                            (i as f32 - (*count as f32 - 1.0) / 2.0) * spread;
+                        Some(player_pos? + Vec2::new(offset, 0.0))
+                    });
+                    for target in targets {
+                        // if the target is out of reach, throw harder
+                        let Some(projectile) = (0..10).find_map(|i| {
+                            Projectile::with_vel(
+                                target,
+                                pos,
+                                speed * 1.15f32.powi(i),
+                                gravity,
+                            )
+                        }) else {
+                            warn!("No solution for boss projectile trajectory");
+                            continue;
+                        };
+                        commands
+                            .spawn((
+                                Attack::new(192, entity, *damage)
+                                    .with_max_targets(1),
+                                projectile,
+                                Collider::cuboid(
+                                    5.,
+                                    5.,
+                                    InteractionGroups::new(
+                                        ENEMY_ATTACK,
+                                        PLAYER,
+                                    ),
+                                ),
+                                TransformBundle::from(
+                                    Transform::from_translation(
+                                        pos.extend(1.0),
+                                    ),
+                                ),
+                                VisibilityBundle::default(),
+                            ))
+                            .with_lingering_particles(
+                                particle_effect.0.clone(),
+                            );
+                    }
+                },
+                BossAttack::Slam {
+                    width,
+                    height,
+                    damage,
+                    lifetime,
+                } => {
+                    commands.spawn((
+                        Attack::new(*lifetime, entity, *damage),
+                        Collider::cuboid(
+                            *width,
+                            *height,
+                            InteractionGroups::new(ENEMY_ATTACK, PLAYER),
+                        ),
+                        TransformBundle::from(Transform::from_translation(
+                            pos.extend(1.0),
+                        )),
+                    ));
+                },
+                BossAttack::Charge { damage, .. } => {
+                    boss.charge_dir = facing.direction();
+                    commands
+                        .spawn((
+                            Attack::new(
+                                step.ticks.saturating_sub(step.at),
+                                entity,
+                                *damage,
+                            ),
+                            Collider::cuboid(
+                                def.size[0],
+                                def.size[1],
+                                InteractionGroups::new(ENEMY_ATTACK, PLAYER),
+                            ),
+                            TransformBundle::default(),
+                        ))
+                        .set_parent(entity);
+                },
+            }
+        }
+
+        if let BossAttack::Charge { speed, .. } = step.attack {
+            if boss.ticks >= step.at {
+                let mut dx = boss.charge_dir * speed / time.hz as f32;
+                // stop at walls, like enemies do in `move_collide`
+                let shape = collider.0.shared_shape().clone();
+                if let Ok(dir) = Direction2d::new(Vec2::new(dx, 0.0)) {
+                    if let Some((_, hit)) = spatial_query.shape_cast(
+                        pos,
+                        dir,
+                        &*shape,
+                        dx.abs(),
+                        InteractionGroups {
+                            memberships: ENEMY,
+                            filter: GROUND,
+                        },
+                        Some(entity),
+                    ) {
+                        if hit.status != TOIStatus::Penetrating {
+                            dx = hit.toi * dx.signum();
+                        }
+                    }
+                }
+                let mut x = transform.translation.x + dx;
+                if let Some(bounds) = fight.bounds {
+                    let half_width = def.size[0] / 2.0;
+                    x = x.clamp(
+                        bounds.min.x + ARENA_BARRIER_WIDTH / 2.0 + half_width,
+                        bounds.max.x - ARENA_BARRIER_WIDTH / 2.0 - half_width,
+                    );
+                }
+                transform.translation.x = x;
+            }
+        }
+
+        boss.ticks += 1;
+        if boss.ticks >= step.ticks {
+            boss.step = (boss.step + 1) % pattern.len();
+            boss.ticks = 0;
+        }
+    }
+}
+
+/// Play the death animation, and despawn the boss after a while
+fn boss_death(
+    mut q_boss: Query<(
+        Entity,
+        &Boss,
+        &Gent,
+        &mut Dead,
+        Option<&EntityIid>,
+    )>,
+    mut gfx_query: Query<&mut ScriptPlayer<SpriteAnimation>, With<BossGfx>>,
+    mut kill_count: ResMut<KillCount>,
+    mut killed_events: EventWriter<Killed>,
+    mut defeated: ResMut<DefeatedBosses>,
+    defs: Res<BossDefs>,
+    mut commands: Commands,
+) {
+    for (entity, boss, gent, mut dead, iid) in q_boss.iter_mut() {
+        if dead.ticks == 0 {
+            **kill_count += 1;
+            killed_events.send(Killed {
+                entity,
+                killer: dead.killer,
+            });
+            if let Some(iid) = iid {
+                defeated.0.insert(iid.as_str().to_owned());
+            }
+            commands.entity(entity).remove::<(Collider, BossFight)>();
+            if let (Ok(mut anim), Some(def)) = (
+                gfx_query.get_mut(gent.e_gfx),
+                defs.get(&boss.id),
+            ) {
+                anim.play_key(&def.death_anim);
+            }
+        }
+        if dead.ticks >= BOSS_DESPAWN_TICKS {
+            commands.entity(gent.e_gfx).despawn_recursive();
+            commands.entity(gent.e_effects_gfx).despawn_recursive();
+            commands.entity(entity).despawn_recursive();
+        }
+        dead.ticks += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn boss_phases_from_toml() {
+        let list: BossList = toml::from_str(
+            r#"
+            [[boss]]
+            id = "test"
+            name = "Test"
+            health = 100
+            size = [10.0, 10.0]
+            idle_anim = "anim.test.Idle"
+            death_anim = "anim.test.Death"
+
+            [[boss.phases]]
+            [[boss.phases.pattern]]
+            attack = "wait"
+            ticks = 10
+
+            [[boss.phases]]
+            below = 0.5
+            script = "script.test"
+            [[boss.phases.pattern]]
+            attack = "charge"
+            ticks = 20
+            at = 5
+            speed = 100.0
+            damage = 10.0
+            "#,
+        )
+        .unwrap();
+        let def = &list.boss[0];
+        assert_eq!(def.engage_range, default_engage_range());
+        assert_eq!(def.phases[0].below, 1.0);
+        assert_eq!(
+            def.phases[1].pattern[0].attack,
+            BossAttack::Charge {
+                speed: 100.0,
+                damage: 10.0
+            }
+        );
+        assert_eq!(def.phase_at(1.0), 0);
+        assert_eq!(def.phase_at(0.51), 0);
+        assert_eq!(def.phase_at(0.5), 1);
+        assert_eq!(def.phase_at(0.0), 1);
+    }
+}
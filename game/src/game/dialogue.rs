@@ -0,0 +1,840 @@
+//! Branching dialogue with NPCs
+//!
+//! A dialogue is a `*.dialogue.toml` asset, made of nodes. Each node is one
+//! line said by a speaker. After it, the conversation either continues to
+//! the `next` node, lets the player pick one of the `choices`, or ends (if
+//! there is neither).
+//!
+//! ```toml
+//! [speakers.vagrant]
+//! name = "dialogue-speaker-vagrant"
+//! background = "dialog.VagrantBackground"
+//!
+//! [[start]]
+//! node = "again"
+//! if = [{ flag = "merchant.met" }]
+//!
+//! [[start]]
+//! node = "hello"
+//!
+//! [nodes.hello]
+//! speaker = "vagrant"
+//! text = "dialogue-merchant-hello"
+//! do = [{ set_flag = "merchant.met" }]
+//! choices = [
+//!     { text = "dialogue-merchant-ask-seeds", next = "seeds", if = [{ seeds = 1 }] },
+//!     { text = "dialogue-merchant-bye" },
+//! ]
+//! ```
+//!
+//! All the text (speaker names, lines, choices) is given as Fluent message
+//! ids, which are looked up in the current locale (`dialogue.ftl`). A line
+//! can also be an `image` (asset key) with the text drawn into it, instead
+//! of `text`; that is for lines that have not been transcribed yet.
+//!
+//! The conversation starts at the first `start` entry whose conditions
+//! hold. Choices are only offered if their conditions hold. Conditions can
+//! check the flags set by earlier dialogue, the passives the player holds,
+//! and how many seeds they have collected (see [`DialogueCondition`]).
+//! Actions (`do`) happen when a node is shown or a choice is picked (see
+//! [`DialogueAction`]).
+//!
+//! Any entity with a [`CollisionSensor`] (that the player can touch) can be
+//! talked to, by giving it a [`DialogueNpc`] with the asset key of its
+//! dialogue.
+
+use std::collections::BTreeMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::reflect::TypePath;
+use bevy_common_assets::toml::TomlAssetPlugin;
+use leafwing_input_manager::prelude::ActionState;
+use theseeker_engine::physics::CollisionSensor;
+
+use crate::assets::UiAssets;
+use crate::game::pickups::CollectedSeeds;
+use crate::game::player::{
+    Passive, Passives, Player, PlayerAction, PlayerStateSet,
+};
+use crate::keybindings::{Control, Keybindings};
+use crate::locale::L10nKey;
+use crate::prelude::*;
+use crate::ui::popup::PopupUi;
+
+const PANEL_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.8);
+const TEXT_COLOR: Color = Color::rgb(0.98, 0.99, 0.94);
+const CHOICE_COLOR: Color = Color::rgb(0.55, 0.58, 0.50);
+const SELECTED_CHOICE_COLOR: Color = Color::rgb(0.98, 0.85, 0.45);
+
+/// Player actions that do nothing while talking
+pub(crate) const BLOCKED_ACTIONS: [PlayerAction; 7] = [
+    PlayerAction::Move,
+    PlayerAction::Jump,
+    PlayerAction::Attack,
+    PlayerAction::Dash,
+    PlayerAction::Whirl,
+    PlayerAction::Stealth,
+    PlayerAction::Fall,
+];
+
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(TomlAssetPlugin::<DialogueTree>::new(&[
+            "dialogue.toml",
+        ]));
+        app.init_resource::<DialogueFlags>();
+        app.add_gametick_event::<DialogueTrigger>();
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            reset_dialogue,
+        );
+        app.add_systems(
+            GameTickUpdate,
+            (
+                advance_dialogue.run_if(resource_exists::<ActiveDialogue>),
+                start_dialogue.run_if(not(resource_exists::<ActiveDialogue>)),
+                restore_npc_interactability
+                    .run_if(any_with_component::<DialogueFinished>),
+                update_talk_hint,
+            )
+                .chain()
+                .in_set(DialogueSet)
+                .before(PlayerStateSet::Behavior)
+                .run_if(
+                    in_state(GameState::Playing)
+                        .and_then(in_state(AppState::InGame)),
+                ),
+        );
+        app.add_systems(
+            Update,
+            (
+                check_dialogue_trees,
+                update_dialogue_ui.run_if(in_state(AppState::InGame)),
+            ),
+        );
+    }
+}
+
+/// Where the player's input is handled, and dialogue actions happen
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DialogueSet;
+
+/// Asset type for a dialogue
+#[derive(Asset, Debug, Clone, Default)]
+#[derive(Deserialize)]
+#[derive(TypePath)]
+pub struct DialogueTree {
+    #[serde(default)]
+    pub speakers: BTreeMap<String, Speaker>,
+    /// Where the conversation can start (the first one that is possible)
+    pub start: Vec<DialogueStart>,
+    pub nodes: BTreeMap<String, DialogueNode>,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct Speaker {
+    /// Fluent id of the speaker's name
+    pub name: String,
+    /// Asset key of the image shown behind the text
+    #[serde(default)]
+    pub background: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct DialogueStart {
+    pub node: String,
+    #[serde(default, rename = "if")]
+    pub conditions: Vec<DialogueCondition>,
+}
+
+/// One line of dialogue
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct DialogueNode {
+    pub speaker: String,
+    /// Fluent id of the line
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Asset key of an image of the line, shown if there is no `text`
+    #[serde(default)]
+    pub image: Option<String>,
+    /// Where to continue, if there are no choices (end if none)
+    #[serde(default)]
+    pub next: Option<String>,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+    /// Done when the line is shown
+    #[serde(default, rename = "do")]
+    pub actions: Vec<DialogueAction>,
+}
+
+/// Something the player can answer
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct DialogueChoice {
+    /// Fluent id of the answer
+    pub text: String,
+    /// Where to continue (end if none)
+    #[serde(default)]
+    pub next: Option<String>,
+    /// Only offered if these all hold
+    #[serde(default, rename = "if")]
+    pub conditions: Vec<DialogueCondition>,
+    /// Done when the answer is picked
+    #[serde(default, rename = "do")]
+    pub actions: Vec<DialogueAction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DialogueCondition {
+    /// The flag has been set (by a [`DialogueAction::SetFlag`])
+    Flag(String),
+    /// The flag has not been set
+    NotFlag(String),
+    /// The player holds the passive
+    HasPassive(Passive),
+    /// The player does not hold the passive
+    LacksPassive(Passive),
+    /// The player has collected at least this many seeds
+    Seeds(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DialogueAction {
+    SetFlag(String),
+    ClearFlag(String),
+    /// Give the player a passive (if they have room for it)
+    GivePassive(Passive),
+    /// Send a [`DialogueTrigger`], for things implemented in code
+    Trigger(String),
+}
+
+/// Flags set by dialogue during the current run (saved with it)
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct DialogueFlags(pub HashSet<String>);
+
+/// Sent by [`DialogueAction::Trigger`]
+#[derive(Event, Debug, Clone)]
+pub struct DialogueTrigger {
+    /// Who the player is talking to
+    pub npc: Entity,
+    pub id: String,
+}
+
+/// What conditions are checked against
+pub struct DialogueContext<'a> {
+    pub flags: &'a HashSet<String>,
+    pub passives: Option<&'a Passives>,
+    pub seeds: u32,
+}
+
+impl DialogueCondition {
+    pub fn holds(&self, ctx: &DialogueContext) -> bool {
+        match self {
+            Self::Flag(flag) => ctx.flags.contains(flag),
+            Self::NotFlag(flag) => !ctx.flags.contains(flag),
+            Self::HasPassive(passive) => {
+                ctx.passives.is_some_and(|p| p.contains(passive))
+            },
+            Self::LacksPassive(passive) => {
+                !ctx.passives.is_some_and(|p| p.contains(passive))
+            },
+            Self::Seeds(count) => ctx.seeds >= *count,
+        }
+    }
+}
+
+fn all_hold(conditions: &[DialogueCondition], ctx: &DialogueContext) -> bool {
+    conditions.iter().all(|c| c.holds(ctx))
+}
+
+impl DialogueTree {
+    /// The node where the conversation starts, if any start is possible
+    pub fn start_node(&self, ctx: &DialogueContext) -> Option<&str> {
+        self.start
+            .iter()
+            .find(|start| all_hold(&start.conditions, ctx))
+            .map(|start| start.node.as_str())
+    }
+
+    /// Indices of the choices of the node that can be picked
+    pub fn available_choices(
+        &self,
+        node: &DialogueNode,
+        ctx: &DialogueContext,
+    ) -> Vec<usize> {
+        node.choices
+            .iter()
+            .enumerate()
+            .filter(|(_, choice)| all_hold(&choice.conditions, ctx))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Check that all referenced nodes and speakers exist
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = vec![];
+        let mut check_node = |id: &str, from: &str| {
+            if !self.nodes.contains_key(id) {
+                errors.push(format!(
+                    "unknown node {:?} (in {})",
+                    id, from
+                ));
+            }
+        };
+        for start in &self.start {
+            check_node(&start.node, "start");
+        }
+        for (id, node) in &self.nodes {
+            let nexts = node
+                .next
+                .iter()
+                .chain(node.choices.iter().filter_map(|c| c.next.as_ref()));
+            for next in nexts {
+                check_node(next, id);
+            }
+        }
+        for (id, node) in &self.nodes {
+            if node.text.is_none() && node.image.is_none() {
+                errors.push(format!("no text or image (in {})", id));
+            }
+            if !self.speakers.contains_key(&node.speaker) {
+                errors.push(format!(
+                    "unknown speaker {:?} (in {})",
+                    node.speaker, id
+                ));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+
+    /// All the Fluent ids used by the dialogue
+    pub fn text_ids(&self) -> impl Iterator<Item = &str> {
+        let speakers = self.speakers.values().map(|s| s.name.as_str());
+        let nodes = self.nodes.values().flat_map(|node| {
+            node.text
+                .iter()
+                .map(|text| text.as_str())
+                .chain(node.choices.iter().map(|c| c.text.as_str()))
+        });
+        speakers.chain(nodes)
+    }
+}
+
+/// Can be talked to, when the player touches its [`CollisionSensor`]
+#[derive(Component, Debug, Clone)]
+pub struct DialogueNpc {
+    /// Asset key of the [`DialogueTree`]
+    pub dialogue: String,
+}
+
+impl DialogueNpc {
+    pub fn new(dialogue: impl Into<String>) -> Self {
+        Self {
+            dialogue: dialogue.into(),
+        }
+    }
+}
+
+/// The conversation with this NPC ended, and the player has not left yet
+#[derive(Component)]
+struct DialogueFinished;
+
+#[derive(Component)]
+struct TalkHint;
+
+#[derive(Component)]
+struct DialoguePanel;
+
+/// The conversation that is going on
+#[derive(Resource, Debug)]
+pub struct ActiveDialogue {
+    pub npc: Entity,
+    pub tree: Handle<DialogueTree>,
+    pub node: String,
+    /// Indices of the node's choices that can be picked
+    pub choices: Vec<usize>,
+    /// Index into `choices`
+    pub selected: usize,
+}
+
+/// Everything that dialogue can look at or change
+#[derive(SystemParam)]
+struct DialogueWorld<'w, 's> {
+    flags: ResMut<'w, DialogueFlags>,
+    seeds: Res<'w, CollectedSeeds>,
+    q_passives: Query<'w, 's, &'static mut Passives, With<Player>>,
+    triggers: EventWriter<'w, DialogueTrigger>,
+}
+
+impl DialogueWorld<'_, '_> {
+    fn context(&self) -> DialogueContext {
+        DialogueContext {
+            flags: &self.flags,
+            passives: self.q_passives.get_single().ok(),
+            seeds: self.seeds.len() as u32,
+        }
+    }
+
+    fn apply(&mut self, actions: &[DialogueAction], npc: Entity) {
+        for action in actions {
+            match action {
+                DialogueAction::SetFlag(flag) => {
+                    self.flags.insert(flag.clone());
+                },
+                DialogueAction::ClearFlag(flag) => {
+                    self.flags.remove(flag);
+                },
+                DialogueAction::GivePassive(passive) => {
+                    let Ok(mut passives) = self.q_passives.get_single_mut()
+                    else {
+                        continue;
+                    };
+                    if !passives.is_full() {
+                        passives.add_passive(passive.clone());
+                    }
+                },
+                DialogueAction::Trigger(id) => {
+                    self.triggers.send(DialogueTrigger {
+                        npc,
+                        id: id.clone(),
+                    });
+                },
+            }
+        }
+    }
+
+    /// Show a node: do its actions and find out what can be answered
+    fn enter(
+        &mut self,
+        tree: &DialogueTree,
+        node_id: &str,
+        npc: Entity,
+    ) -> Option<Vec<usize>> {
+        let Some(node) = tree.nodes.get(node_id) else {
+            error!(
+                "Dialogue node {:?} does not exist",
+                node_id
+            );
+            return None;
+        };
+        self.apply(&node.actions, npc);
+        Some(tree.available_choices(node, &self.context()))
+    }
+}
+
+fn reset_dialogue(mut commands: Commands) {
+    commands.remove_resource::<ActiveDialogue>();
+}
+
+/// Report mistakes in dialogue files when they are (re)loaded
+fn check_dialogue_trees(
+    mut ev_asset: EventReader<AssetEvent<DialogueTree>>,
+    trees: Res<Assets<DialogueTree>>,
+) {
+    for ev in ev_asset.read() {
+        if let AssetEvent::LoadedWithDependencies { id }
+        | AssetEvent::Modified { id } = ev
+        {
+            if let Some(Err(e)) = trees.get(*id).map(|tree| tree.validate()) {
+                error!("Invalid dialogue: {}", e);
+            }
+        }
+    }
+}
+
+fn start_dialogue(
+    mut commands: Commands,
+    mut q_player: Query<&mut ActionState<PlayerAction>, With<Player>>,
+    q_npc: Query<
+        (Entity, &DialogueNpc, &CollisionSensor),
+        Without<DialogueFinished>,
+    >,
+    preloaded: Res<PreloadedAssets>,
+    trees: Res<Assets<DialogueTree>>,
+    mut world: DialogueWorld,
+) {
+    let Ok(mut action_state) = q_player.get_single_mut() else {
+        return;
+    };
+    if !action_state.just_pressed(&PlayerAction::Interact) {
+        return;
+    }
+    let Some((npc, dialogue_npc, _)) =
+        q_npc.iter().find(|(_, _, sensor)| sensor.is_touching())
+    else {
+        return;
+    };
+    let Some(handle) =
+        preloaded.get_single_asset::<DialogueTree>(&dialogue_npc.dialogue)
+    else {
+        error!(
+            "Dialogue {:?} does not exist",
+            dialogue_npc.dialogue
+        );
+        return;
+    };
+    let Some(tree) = trees.get(&handle) else {
+        return;
+    };
+    let Some(node) = tree.start_node(&world.context()).map(String::from) else {
+        return;
+    };
+    let Some(choices) = world.enter(tree, &node, npc) else {
+        return;
+    };
+    // so it does not also pick things up, or skip the first line
+    action_state.consume(&PlayerAction::Interact);
+    commands.insert_resource(ActiveDialogue {
+        npc,
+        tree: handle,
+        node,
+        choices,
+        selected: 0,
+    });
+}
+
+fn advance_dialogue(
+    mut commands: Commands,
+    mut q_player: Query<&mut ActionState<PlayerAction>, With<Player>>,
+    q_npc: Query<&CollisionSensor, With<DialogueNpc>>,
+    mut active: ResMut<ActiveDialogue>,
+    trees: Res<Assets<DialogueTree>>,
+    mut world: DialogueWorld,
+) {
+    let Ok(mut action_state) = q_player.get_single_mut() else {
+        return;
+    };
+    let in_range = q_npc
+        .get(active.npc)
+        .is_ok_and(|sensor| sensor.is_touching());
+    let node = trees
+        .get(&active.tree)
+        .and_then(|tree| Some((tree, tree.nodes.get(&active.node)?)));
+    let Some((tree, node)) = node.filter(|_| in_range) else {
+        commands.remove_resource::<ActiveDialogue>();
+        return;
+    };
+
+    if action_state.just_pressed(&PlayerAction::Move)
+        && !active.choices.is_empty()
+    {
+        let n = active.choices.len();
+        active.selected = if action_state.value(&PlayerAction::Move) < 0.0 {
+            (active.selected + n - 1) % n
+        } else {
+            (active.selected + 1) % n
+        };
+    }
+    for action in BLOCKED_ACTIONS {
+        action_state.consume(&action);
+    }
+
+    if !action_state.just_pressed(&PlayerAction::Interact) {
+        return;
+    }
+    action_state.consume(&PlayerAction::Interact);
+    let next = match active.choices.get(active.selected) {
+        Some(&i) => {
+            let choice = &node.choices[i];
+            world.apply(&choice.actions, active.npc);
+            choice.next.clone()
+        },
+        None => node.next.clone(),
+    };
+    let choices = next
+        .as_ref()
+        .and_then(|next| world.enter(tree, next, active.npc));
+    match (next, choices) {
+        (Some(next), Some(choices)) => {
+            active.node = next;
+            active.choices = choices;
+            active.selected = 0;
+        },
+        _ => {
+            commands.entity(active.npc).insert(DialogueFinished);
+            commands.remove_resource::<ActiveDialogue>();
+        },
+    }
+}
+
+/// The NPC can be talked to again after the player walks away
+fn restore_npc_interactability(
+    mut commands: Commands,
+    q_npc: Query<(Entity, &CollisionSensor), With<DialogueFinished>>,
+) {
+    for (entity, sensor) in &q_npc {
+        if !sensor.is_touching() {
+            commands.entity(entity).remove::<DialogueFinished>();
+        }
+    }
+}
+
+fn update_talk_hint(
+    mut commands: Commands,
+    q_npc: Query<
+        &CollisionSensor,
+        (
+            With<DialogueNpc>,
+            Without<DialogueFinished>,
+        ),
+    >,
+    q_hint: Query<Entity, With<TalkHint>>,
+    active: Option<Res<ActiveDialogue>>,
+    keybindings: Res<Keybindings>,
+) {
+    let show = active.is_none() && q_npc.iter().any(|s| s.is_touching());
+    if show && q_hint.is_empty() {
+        commands.popup().insert(TalkHint).with_children(|popup| {
+            popup.row().with_children(|row| {
+                row.text("Press ");
+                row.control_icons(&keybindings, Control::Interact);
+                row.text(" to talk");
+            });
+        });
+    } else if !show {
+        for entity in &q_hint {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Rebuild the dialogue panel whenever the conversation moves on
+fn update_dialogue_ui(
+    mut commands: Commands,
+    active: Option<Res<ActiveDialogue>>,
+    q_panel: Query<Entity, With<DialoguePanel>>,
+    trees: Res<Assets<DialogueTree>>,
+    preloaded: Res<PreloadedAssets>,
+    uiassets: Res<UiAssets>,
+) {
+    let Some(active) = active else {
+        for entity in &q_panel {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    if !active.is_changed() {
+        return;
+    }
+    for entity in &q_panel {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(tree) = trees.get(&active.tree) else {
+        return;
+    };
+    let Some(node) = tree.nodes.get(&active.node) else {
+        return;
+    };
+    let speaker = tree.speakers.get(&node.speaker);
+    let background = speaker
+        .and_then(|s| s.background.as_deref())
+        .and_then(|key| preloaded.get_single_asset::<Image>(key));
+    let line_image = node
+        .image
+        .as_deref()
+        .and_then(|key| preloaded.get_single_asset::<Image>(key));
+    let text_style = |font_size: f32, color: Color| TextStyle {
+        font: uiassets.font_regular.clone(),
+        font_size,
+        color,
+    };
+
+    let style = Style {
+        position_type: PositionType::Absolute,
+        left: Val::Percent(20.0),
+        width: Val::Percent(60.0),
+        bottom: Val::Percent(6.0),
+        flex_direction: FlexDirection::Column,
+        row_gap: Val::Px(8.0),
+        padding: UiRect::axes(Val::Px(24.0), Val::Px(16.0)),
+        ..default()
+    };
+    let mut panel = commands.spawn((
+        Name::new("dialogue_panel"),
+        DialoguePanel,
+        StateDespawnMarker,
+    ));
+    match background {
+        Some(background) => panel.insert(ImageBundle {
+            style,
+            image: UiImage::new(background),
+            ..default()
+        }),
+        None => panel.insert(NodeBundle {
+            style,
+            background_color: BackgroundColor(PANEL_COLOR),
+            ..default()
+        }),
+    };
+    panel.with_children(|panel| {
+        if let Some(speaker) = speaker {
+            panel.spawn((
+                TextBundle::from_section(
+                    "",
+                    text_style(16.0, SELECTED_CHOICE_COLOR),
+                ),
+                L10nKey(speaker.name.clone()),
+            ));
+        }
+        if let Some(text) = &node.text {
+            panel.spawn((
+                TextBundle::from_section("", text_style(20.0, TEXT_COLOR)),
+                L10nKey(text.clone()),
+            ));
+        } else if let Some(image) = line_image {
+            panel.spawn(ImageBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    ..default()
+                },
+                image: UiImage::new(image),
+                ..default()
+            });
+        }
+        if active.choices.is_empty() {
+            return;
+        }
+        panel
+            .spawn(NodeBundle {
+                style: Style {
+                    flex_wrap: FlexWrap::Wrap,
+                    column_gap: Val::Px(24.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|row| {
+                for (i, &choice) in active.choices.iter().enumerate() {
+                    let color = if i == active.selected {
+                        SELECTED_CHOICE_COLOR
+                    } else {
+                        CHOICE_COLOR
+                    };
+                    row.spawn((
+                        TextBundle::from_section("", text_style(18.0, color)),
+                        L10nKey(node.choices[choice].text.clone()),
+                    ));
+                }
+            });
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TREE: &str = r#"
+        [speakers.vagrant]
+        name = "speaker"
+
+        [[start]]
+        node = "again"
+        if = [{ flag = "met" }]
+
+        [[start]]
+        node = "hello"
+
+        [nodes.hello]
+        speaker = "vagrant"
+        text = "hello"
+        do = [{ set_flag = "met" }]
+        choices = [
+            { text = "seeds", next = "again", if = [{ seeds = 2 }] },
+            { text = "dagger", if = [{ has_passive = "IceDagger" }] },
+            { text = "bye" },
+        ]
+
+        [nodes.again]
+        speaker = "vagrant"
+        text = "again"
+    "#;
+
+    fn context<'a>(
+        flags: &'a HashSet<String>,
+        passives: &'a Passives,
+        seeds: u32,
+    ) -> DialogueContext<'a> {
+        DialogueContext {
+            flags,
+            passives: Some(passives),
+            seeds,
+        }
+    }
+
+    #[test]
+    fn dialogue_branches_on_game_state() {
+        let tree: DialogueTree = toml::from_str(TREE).unwrap();
+        tree.validate().unwrap();
+        let hello = &tree.nodes["hello"];
+        assert_eq!(
+            hello.actions,
+            vec![DialogueAction::SetFlag("met".into())]
+        );
+
+        let mut flags = HashSet::default();
+        let mut passives = Passives::new([]);
+        let ctx = context(&flags, &passives, 0);
+        assert_eq!(tree.start_node(&ctx), Some("hello"));
+        assert_eq!(
+            tree.available_choices(hello, &ctx),
+            vec![2]
+        );
+
+        flags.insert("met".to_owned());
+        passives.add_passive(Passive::IceDagger);
+        let ctx = context(&flags, &passives, 2);
+        assert_eq!(tree.start_node(&ctx), Some("again"));
+        assert_eq!(
+            tree.available_choices(hello, &ctx),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn dialogue_validation_finds_missing_nodes() {
+        let mut tree: DialogueTree = toml::from_str(TREE).unwrap();
+        tree.nodes.get_mut("again").unwrap().next = Some("nowhere".into());
+        tree.nodes.get_mut("again").unwrap().speaker = "nobody".into();
+        tree.nodes.get_mut("again").unwrap().text = None;
+        let err = tree.validate().unwrap_err();
+        assert!(err.contains("\"nowhere\""), "{}", err);
+        assert!(err.contains("\"nobody\""), "{}", err);
+        assert!(err.contains("no text or image (in again)"), "{}", err);
+    }
+
+    /// The shipped dialogues are valid and have all their text
+    #[test]
+    fn shipped_dialogues_are_complete() {
+        let dialogues = [
+            include_str!("../../../assets/merchant.dialogue.toml"),
+        ];
+        // other locales fall back to this one
+        let locale = include_str!("../../../assets/locale/en-US/dialogue.ftl");
+        for dialogue in dialogues {
+            let tree: DialogueTree = toml::from_str(dialogue).unwrap();
+            tree.validate().unwrap();
+            for id in tree.text_ids() {
+                assert!(
+                    locale
+                        .lines()
+                        .any(|line| line.starts_with(&format!("{id} ="))),
+                    "{} is not translated",
+                    id
+                );
+            }
+        }
+    }
+}
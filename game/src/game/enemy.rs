@@ -1,3 +1,4 @@
+mod ai;
 #[cfg(feature = "dev")]
 use bevy_inspector_egui::quick::FilterQueryInspectorPlugin;
 use rand::distributions::Standard;
@@ -6,25 +7,32 @@ use rapier2d::parry::query::TOIStatus;
 use rapier2d::prelude::{Group, InteractionGroups};
 use theseeker_engine::animation::SpriteAnimationBundle;
 use theseeker_engine::assets::animation::SpriteAnimation;
-use theseeker_engine::assets::config::{update_field, DynamicConfig};
+use theseeker_engine::assets::config::{
+    check_range, load_config, DynamicConfig, TypedConfig,
+};
+use theseeker_engine::assets::reload_on_modified;
 use theseeker_engine::ballistics_math::ballistic_speed;
 use theseeker_engine::gent::{Gent, GentPhysicsBundle, TransformGfxFromGent};
 use theseeker_engine::physics::{
     into_vec2, update_sprite_colliders, AnimationCollider, Collider,
-    LinearVelocity, PhysicsWorld, ShapeCaster, ENEMY, ENEMY_ATTACK,
-    ENEMY_INSIDE, GROUND, PLAYER, SENSOR,
+    ColliderLayer, CollisionEnded, CollisionSensor, LinearVelocity,
+    PhysicsWorld, ShapeCaster, ENEMY, ENEMY_ATTACK, ENEMY_INSIDE, GROUND,
+    ONE_WAY, PLAYER, SENSOR,
 };
 use theseeker_engine::script::ScriptPlayer;
+use theseeker_engine::typed_config;
 
 use super::physics::Knockback;
 use super::player::player_weapon::CurrentWeapon;
-use super::player::{Player, PlayerConfig, StatusModifier, Stealthing};
+use super::player::{Player, PlayerConfig, Stealthing};
+use super::status::{StatType, Stats, StatusModifier, StatusModifiers};
 use crate::game::attack::arc_attack::Projectile;
 use crate::game::attack::particles::ArcParticleEffectHandle;
 use crate::game::attack::*;
 use crate::game::gentstate::*;
 use crate::game::player::EnemiesNearby;
 use crate::graphics::particles_util::BuildParticles;
+use crate::level::LevelDespawnMarker;
 use crate::prelude::*;
 
 pub struct EnemyPlugin;
@@ -41,15 +49,22 @@ impl Plugin for EnemyPlugin {
             GameTickUpdate,
             spawn_enemies.after(setup_enemy),
         );
-        app.insert_resource(EnemyConfig::default());
+        app.init_resource::<EnemyConfig>();
         app.add_systems(
             GameTickUpdate,
-            load_enemy_config.before(EnemyStateSet::Behavior),
+            (
+                reload_on_modified::<DynamicConfig>(EnemyConfig::KEY)
+                    .pipe(load_config::<EnemyConfig>),
+                load_enemy_stats.run_if(resource_changed::<EnemyConfig>),
+            )
+                .chain()
+                .before(EnemyStateSet::Behavior),
         );
         app.add_plugins((
             EnemyBehaviorPlugin,
             EnemyTransitionPlugin,
             EnemyAnimationPlugin,
+            ai::EnemyAiPlugin,
         ));
         app.register_type::<Range>();
         app.register_type::<Role>();
@@ -67,105 +82,82 @@ pub fn debug_enemy(world: &World, query: Query<Entity, With<Gent>>) {
     }
 }
 
-#[derive(Resource, Debug, Default)]
-struct EnemyConfig {
-    fall_accel: f32,
-    jump_accel: f32,
+typed_config! {
+    /// Enemy tuning values, loaded from `enemy.cfg.toml`
+    #[derive(Resource, Debug)]
+    struct EnemyConfig {
+        /// How fast does an enemy accelerate downward while in the falling state?
+        fall_accel: f32 = 4.5,
+        jump_accel: f32 = 3.5,
 
-    start_hp: u32,
+        start_hp: u32 [1..] = 100,
 
-    range_melee_melee: f32,
-    range_melee_aggro: f32,
-    range_melee_deaggro: f32,
-    range_ranged_melee: f32,
-    range_ranged_aggro: f32,
-    range_ranged_ranged: f32,
-    range_nearby: f32,
+        range_melee_melee: f32 = 12.0,
+        range_melee_aggro: f32 = 100.0,
+        range_melee_deaggro: f32 = 70.0,
+        range_ranged_melee: f32 = 29.0,
+        range_ranged_aggro: f32 = 100.0,
+        range_ranged_ranged: f32 = 100.0,
+        range_nearby: f32 = 50.0,
 
-    walking_min_time: u32,
-    walking_max_time: u32,
-    idle_time: u32,
+        /// (in ticks)
+        walking_min_time: u32 = 24,
+        /// (in ticks)
+        walking_max_time: u32 = 300,
+        /// (in ticks)
+        idle_time: u32 = 240,
 
-    projectile_arc_x: f32,
-    projectile_arc_y: f32,
-    projectile_damage: f32,
+        projectile_arc_x: f32 = 134.0,
+        projectile_arc_y: f32 = 151.0,
+        projectile_damage: f32 = 20.0,
 
-    melee_damage: f32,
+        melee_damage: f32 = 20.0,
 
-    walking_speed: f32,
-    chasing_speed: f32,
+        walking_speed: f32 [0.0..] = 20.0,
+        chasing_speed: f32 [0.0..] = 35.0,
 
-    fall_y_velocity: f32,
-    jump_y_velocity: f32,
-}
-fn load_enemy_config(
-    mut ev_asset: EventReader<AssetEvent<DynamicConfig>>,
-    cfgs: Res<Assets<DynamicConfig>>,
-    preloaded: Res<PreloadedAssets>,
-    mut enemy_config: ResMut<EnemyConfig>,
-    mut initialized_config: Local<bool>,
-) {
-    // convert from asset key string to bevy handle
-    let Some(cfg_handle) =
-        preloaded.get_single_asset::<DynamicConfig>("cfg.enemy")
-    else {
-        println!("Couldnt find enemy cfg file");
-        return;
-    };
-    // The reason we do this here instead of in an AssetEvent::Added match arm, is because
-    // the Added match arm fires before preloaded updates with the asset key; as a result
-    // you can't tell what specific DynamicConfig loaded in like that.
-    if !*initialized_config {
-        if let Some(cfg) = cfgs.get(cfg_handle.clone()) {
-            update_enemy_config(&mut enemy_config, cfg);
-        }
-        *initialized_config = true;
-    }
-    for ev in ev_asset.read() {
-        if let AssetEvent::Modified { id } = ev {
-            if let Some(cfg) = cfgs.get(*id) {
-                if cfg_handle.id() == *id {
-                    update_enemy_config(&mut enemy_config, cfg);
-                }
-            }
-        }
+        fall_y_velocity: f32 = 40.0,
+        jump_y_velocity: f32 = 140.0,
     }
 }
 
-#[rustfmt::skip]
-fn update_enemy_config(config: &mut EnemyConfig, cfg: &DynamicConfig) {
-    let mut errors = Vec::new();
-    update_field(&mut errors, &cfg.0, "fall_accel", |val| config.fall_accel = val);
-    update_field(&mut errors, &cfg.0, "jump_accel", |val| config.jump_accel = val);
-
-    update_field(&mut errors, &cfg.0, "start_hp", |val| config.start_hp = val as u32);
+impl TypedConfig for EnemyConfig {
+    const KEY: &'static str = "cfg.enemy";
 
-    update_field(&mut errors, &cfg.0, "range_melee_melee", |val| config.range_melee_melee = val);
-    update_field(&mut errors, &cfg.0, "range_melee_aggro", |val| config.range_melee_aggro = val);
-    update_field(&mut errors, &cfg.0, "range_melee_deaggro", |val| config.range_melee_deaggro = val);
-    update_field(&mut errors, &cfg.0, "range_ranged_melee", |val| config.range_ranged_melee = val);
-    update_field(&mut errors, &cfg.0, "range_ranged_aggro", |val| config.range_ranged_aggro = val);
-    update_field(&mut errors, &cfg.0, "range_ranged_ranged", |val| config.range_ranged_ranged = val);
-    update_field(&mut errors, &cfg.0, "range_nearby", |val| config.range_nearby = val);
-
-    update_field(&mut errors, &cfg.0, "walking_min_time", |val| config.walking_min_time = val as u32);
-    update_field(&mut errors, &cfg.0, "walking_max_time", |val| config.walking_max_time = val as u32);
-    update_field(&mut errors, &cfg.0, "idle_time", |val| config.idle_time = val as u32);
-
-    update_field(&mut errors, &cfg.0, "projectile_arc_x", |val| config.projectile_arc_x = val);
-    update_field(&mut errors, &cfg.0, "projectile_arc_y", |val| config.projectile_arc_y = val);
-    update_field(&mut errors, &cfg.0, "projectile_damage", |val| config.projectile_damage = val);
-    update_field(&mut errors, &cfg.0, "melee_damage", |val| config.melee_damage = val);
-
-    update_field(&mut errors, &cfg.0, "walking_speed", |val| config.walking_speed = val);
-    update_field(&mut errors, &cfg.0, "chasing_speed", |val| config.chasing_speed = val);
+    fn validate(&self, errors: &mut Vec<String>) {
+        // walking picks a random duration in this range
+        check_range(
+            errors,
+            "walking_min_time",
+            self.walking_min_time,
+            ..self.walking_max_time,
+        );
+    }
+}
 
-    update_field(&mut errors, &cfg.0, "fall_y_velocity", |val| config.fall_y_velocity = val);
-    update_field(&mut errors, &cfg.0, "jump_y_velocity", |val| config.jump_y_velocity = val);
+impl EnemyConfig {
+    /// Base values of an enemy's [`Stats`]
+    fn base_stats(&self) -> [(StatType, f32); 2] {
+        [
+            (
+                StatType::WalkingSpeed,
+                self.walking_speed,
+            ),
+            (
+                StatType::ChasingSpeed,
+                self.chasing_speed,
+            ),
+        ]
+    }
+}
 
-    for error in errors{
-       warn!("failed to load enemy cfg value: {}", error);
-   }
+fn load_enemy_stats(
+    enemy_config: Res<EnemyConfig>,
+    mut stat_q: Query<&mut Stats, With<Enemy>>,
+) {
+    stat_q.iter_mut().for_each(|mut stats| {
+        stats.set_base_stats(enemy_config.base_stats());
+    });
 }
 
 #[derive(SystemSet, Clone, PartialEq, Eq, Debug, Hash)]
@@ -277,7 +269,7 @@ pub struct EnemyEffectGfx {
 // tier one at a time
 // when ranged should be capped at 2 per spawner
 // only tick cooldown when spawner is cleared
-fn spawn_enemies(
+pub(crate) fn spawn_enemies(
     mut spawner_q: Query<(&Transform, &mut EnemySpawner)>,
     // dead enemies to clear
     enemy_q: Query<
@@ -289,6 +281,7 @@ fn spawn_enemies(
         ),
     >,
     player_query: Query<&Transform, (Without<Enemy>, With<Player>)>,
+    mut rng: ResMut<GameRng>,
     mut commands: Commands,
 ) {
     let p_transform = player_query.get_single();
@@ -350,7 +343,7 @@ fn spawn_enemies(
                         for slot in spawner.slots.iter_mut() {
                             // generate a random roll, max 2 per spawner
                             let role = if ranged_role < 2 {
-                                let r = Role::random();
+                                let r = Role::random(&mut *rng);
                                 if matches!(r, Role::Ranged) {
                                     ranged_role += 1;
                                 };
@@ -458,26 +451,31 @@ fn setup_enemy(
                         max_toi: 0.0,
                         interaction: InteractionGroups {
                             memberships: ENEMY,
-                            filter: GROUND,
+                            filter: GROUND | ONE_WAY,
                         },
                     },
                     linear_velocity: LinearVelocity(Vec2::ZERO),
+                    interpolation: default(),
                 },
             },
             Navigation::Grounded,
             Range::None,
             Target(None),
+            AssetKey::<ai::BehaviourTree>::new(role.ai_key()),
             Health {
                 current: health,
                 max: health,
             },
             Facing::Right,
+            Stats::new(enemy_config.base_stats()),
+            StatusModifiers::default(),
             Patrolling,
             Idle,
             Waiting::new(12),
             AddQueue::default(),
             TransitionQueue::default(),
             StateDespawnMarker,
+            LevelDespawnMarker,
         ));
         commands.entity(e_gfx).insert((
             EnemyGfxBundle {
@@ -493,6 +491,7 @@ fn setup_enemy(
                 animation: Default::default(),
             },
             StateDespawnMarker,
+            LevelDespawnMarker,
         ));
         let mut animation = ScriptPlayer::<SpriteAnimation>::default();
         animation.play_key("anim.spider.Sparks");
@@ -510,6 +509,7 @@ fn setup_enemy(
                 animation: SpriteAnimationBundle { player: animation },
             },
             StateDespawnMarker,
+            LevelDespawnMarker,
         ));
         commands.entity(e_gfx).remove::<EnemyBlueprint>();
     }
@@ -530,7 +530,7 @@ impl Plugin for EnemyBehaviorPlugin {
                         check_player_range,
                         (
                             patrolling.run_if(any_with_component::<Patrolling>),
-                            aggro.run_if(any_with_component::<Aggroed>),
+                            ai::think.run_if(any_with_component::<Aggroed>),
                             waiting.run_if(any_with_component::<Waiting>),
                             defense.run_if(any_with_component::<Defense>),
                             ranged_attack
@@ -687,14 +687,14 @@ enum Role {
 }
 
 impl Role {
-    fn random() -> Role {
-        let mut rng = rand::thread_rng();
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Role {
         rng.gen()
     }
 }
 
 // Spider upgrade/scaling tier
 #[derive(Component, Default, Debug, Reflect, Clone, Copy)]
+#[derive(Serialize, Deserialize)]
 pub enum Tier {
     #[default]
     Base = 1,
@@ -745,7 +745,8 @@ impl Role {
     }
 }
 
-#[derive(Component, Debug, Reflect, PartialEq, Eq)]
+#[derive(Component, Debug, Reflect, Clone, PartialEq, Eq)]
+#[derive(Deserialize)]
 enum Range {
     Melee,
     Ranged,
@@ -760,7 +761,8 @@ struct Target(Option<Entity>);
 
 /// Component that indicates that the player is inside of this enemy,
 /// and has its usual collision layer membership modified to ENEMY_INSIDE
-/// it is removed once the player stops intersecting in the remove_inside system
+/// it is removed once the player stops intersecting in the remove_inside system,
+/// which listens to the [`CollisionSensor`] added along with it
 #[derive(Component)]
 pub struct Inside;
 
@@ -895,6 +897,7 @@ fn patrolling(
         (With<Patrolling>, With<Enemy>),
     >,
     enemy_config: Res<EnemyConfig>,
+    mut rng: ResMut<GameRng>,
 ) {
     for (range, mut transitions, mut additions, maybe_waiting) in
         query.iter_mut()
@@ -910,7 +913,7 @@ fn patrolling(
                 if let Some(waiting) = maybe_waiting {
                     if waiting.ticks >= waiting.max_ticks {
                         transitions.push(Waiting::new_transition(Walking {
-                            max_ticks: rand::thread_rng().gen_range(
+                            max_ticks: rng.gen_range(
                                 (enemy_config.walking_min_time)
                                     ..(enemy_config.walking_max_time),
                             ),
@@ -975,7 +978,7 @@ fn defense(
 //                         filter: PLAYER,
 //                     }),
 //                     TransformBundle::from_transform(Transform::default()),
-//                     AnimationCollider(gent.e_gfx),
+//                     AnimationCollider(gent.e_gfx, ColliderLayer::Hit),
 //                     Attack::new(8, entity),
 //                     Pushback(Knockback::new(
 //                         Vec2::new(-facing.direction() * 100., 0.),
@@ -992,58 +995,6 @@ fn defense(
 //     }
 // }
 
-fn aggro(
-    mut query: Query<
-        (
-            &Role,
-            &Range,
-            &Target,
-            &mut LinearVelocity,
-            &mut TransitionQueue,
-        ),
-        (
-            With<Enemy>,
-            With<Aggroed>,
-            // each "substate" of aggro should return back to waiting when with wants to return control
-            // to aggro
-            With<Waiting>,
-        ),
-    >,
-) {
-    for (role, range, target, mut velocity, mut transitions) in query.iter_mut()
-    {
-        if let Some(p_entity) = target.0 {
-            // return to patrol if out of aggro range
-            if matches!(range, Range::Far) {
-                transitions.push(Aggroed::new_transition(Patrolling));
-            } else if matches!(range, Range::Melee) {
-                match role {
-                    Role::Melee => {
-                        transitions.push(Waiting::new_transition(
-                            MeleeAttack::default(),
-                        ))
-                    },
-                    Role::Ranged => {
-                        velocity.x = 0.;
-                        transitions.push(Waiting::new_transition(Defense));
-                    },
-                }
-            } else if matches!(role, Role::Melee) {
-                transitions.push(Waiting::new_transition(Chasing));
-            } else if matches!(role, Role::Ranged) {
-                transitions.push(Waiting::new_transition(RangedAttack {
-                    target: p_entity,
-                    ticks: 0,
-                }));
-            }
-
-            // if there is no player it should also return to patrol state
-        } else {
-            transitions.push(Aggroed::new_transition(Patrolling));
-        }
-    }
-}
-
 fn ranged_attack(
     spatial_query: Res<PhysicsWorld>,
     mut query: Query<
@@ -1246,7 +1197,7 @@ fn melee_attack(
                         filter: PLAYER,
                     }),
                     TransformBundle::from_transform(Transform::default()),
-                    AnimationCollider(gent.e_gfx),
+                    AnimationCollider(gent.e_gfx, ColliderLayer::Hit),
                     Attack::new(
                         8,
                         entity,
@@ -1273,6 +1224,7 @@ fn walking(
             &mut TransitionQueue,
             &mut AddQueue,
             // TODO: remove addqueue
+            &Stats,
         ),
         (
             With<Enemy>,
@@ -1289,10 +1241,11 @@ fn walking(
         mut walking,
         mut transitions,
         mut add_q,
+        stats,
     ) in query.iter_mut()
     {
         // set initial velocity
-        velocity.x = -enemy_config.walking_speed * facing.direction();
+        velocity.x = -stats.get(StatType::WalkingSpeed) * facing.direction();
         if walking.ticks >= walking.max_ticks {
             velocity.x = 0.;
             transitions.push(Walking::new_transition(Waiting {
@@ -1360,7 +1313,7 @@ fn falling(
                 GROUNDED_THRESHOLD,
                 InteractionGroups {
                     memberships: ENEMY,
-                    filter: GROUND,
+                    filter: GROUND | ONE_WAY,
                 },
                 Some(entity),
             ) {
@@ -1421,7 +1374,7 @@ fn falling(
                         + GROUND_BUFFER,
                     InteractionGroups {
                         memberships: ENEMY,
-                        filter: GROUND,
+                        filter: GROUND | ONE_WAY,
                     },
                     Some(entity),
                 )
@@ -1529,6 +1482,7 @@ fn chasing(
             &Transform,
             &Gent,
             &Tier,
+            &Stats,
         ),
         (
             With<Enemy>,
@@ -1552,12 +1506,9 @@ fn chasing(
         trans,
         gent,
         tier,
+        stats,
     ) in query.iter_mut()
     {
-        // only melee chase
-        if !matches!(role, Role::Melee) {
-            continue;
-        }
         if let Some(p_entity) = target.0 {
             // check if we need to transition
             match *range {
@@ -1569,7 +1520,7 @@ fn chasing(
                 },
                 Range::Ranged | Range::Aggro | Range::Deaggro => {
                     velocity.x =
-                        -enemy_config.chasing_speed * facing.direction();
+                        -stats.get(StatType::ChasingSpeed) * facing.direction();
                     // if we cant get any closer because of edge
                     if let Navigation::Blocked = *nav {
                         // velocity.x = 0.;
@@ -1676,7 +1627,7 @@ fn move_collide(
                 InteractionGroups {
                     memberships: ENEMY,
                     // Ground group
-                    filter: Group::from_bits_truncate(0b10000),
+                    filter: GROUND | ONE_WAY,
                 },
                 None,
             ) {
@@ -1732,30 +1683,27 @@ fn move_collide(
 }
 
 fn remove_inside(
+    mut ev_ended: EventReader<CollisionEnded>,
     mut query: Query<
-        (Entity, &GlobalTransform, &mut Collider),
+        (&mut Collider, &CollisionSensor),
         (With<Inside>, With<Enemy>),
     >,
     mut commands: Commands,
-    spatial_query: Res<PhysicsWorld>,
 ) {
-    for (entity, transform, mut collider) in query.iter_mut() {
-        let intersections = spatial_query.intersect(
-            transform.translation().xy(),
-            collider.0.shape(),
-            InteractionGroups {
-                memberships: ENEMY_INSIDE,
-                filter: PLAYER,
-            },
-            Some(entity),
-        );
-        if intersections.is_empty() {
-            collider.0.set_collision_groups(InteractionGroups {
-                memberships: ENEMY,
-                filter: Group::all(),
-            });
-            commands.entity(entity).remove::<Inside>();
+    for ev in ev_ended.read() {
+        let Ok((mut collider, sensor)) = query.get_mut(ev.sensor) else {
+            continue;
+        };
+        if sensor.is_touching() {
+            continue;
         }
+        collider.0.set_collision_groups(InteractionGroups {
+            memberships: ENEMY,
+            filter: Group::all(),
+        });
+        commands
+            .entity(ev.sensor)
+            .remove::<(Inside, CollisionSensor)>();
     }
 }
 
@@ -1764,11 +1712,16 @@ fn remove_inside(
 pub fn dead(
     mut query: Query<(Entity, &mut Dead), With<Enemy>>,
     mut kill_count: ResMut<KillCount>,
+    mut killed_events: EventWriter<Killed>,
     mut commands: Commands,
 ) {
     for (entity, mut dead) in query.iter_mut() {
         if dead.ticks == 0 {
             **kill_count += 1;
+            killed_events.send(Killed {
+                entity,
+                killer: dead.killer,
+            });
             commands.entity(entity).retain::<(
                 TransformBundle,
                 Gent,
@@ -1880,9 +1833,9 @@ fn enemy_hit_sfx_gfx(
                 // set hit
                 hit_gfx.set_slot("AttackHit", true);
                 // set weapon slot hit used for playing correct hit sfx
-                let current_weapon_name = current_weapon.to_string();
-                let slot = &format!("{current_weapon_name}Hit");
-                hit_gfx.set_slot(slot, true);
+                if let Some(slot) = current_weapon.hit_slot() {
+                    hit_gfx.set_slot(slot, true);
+                }
                 if let Ok(direction) = player_facing_dir.get_single() {
                     match direction {
                         Facing::Right => {
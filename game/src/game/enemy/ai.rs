@@ -0,0 +1,308 @@
+//! Enemy decision making, as a behaviour tree asset
+//!
+//! Whenever an aggroed enemy is [`Waiting`] for something to do, its
+//! behaviour tree is evaluated to pick the next state. The leaves of the tree
+//! ([`Action`]) map onto the enemy `GentState`s, and are performed by pushing
+//! transitions onto the enemy's `TransitionQueue`. The states themselves are
+//! still implemented in code.
+//!
+//! The tree can look at these inputs (the "blackboard"):
+//!  - the enemy's [`Range`] to its target
+//!  - whether it has a [`Target`]
+//!  - its [`Navigation`] state
+//!
+//! Trees are RON files (`*.ai.ron`). The asset key of the tree used by each
+//! enemy [`Role`] is given by [`Role::ai_key`]. Example:
+//!
+//! ```ron
+//! (
+//!     root: Selector([
+//!         Sequence([Not(Check(HasTarget)), Do(Patrol)]),
+//!         Sequence([Check(InRange([Melee])), Do(MeleeAttack)]),
+//!         Do(Chase),
+//!     ]),
+//! )
+//! ```
+
+use bevy::reflect::TypePath;
+use bevy_common_assets::ron::RonAssetPlugin;
+use theseeker_engine::assets::resolve_asset_keys;
+
+use super::*;
+
+pub(super) struct EnemyAiPlugin;
+
+impl Plugin for EnemyAiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(RonAssetPlugin::<BehaviourTree>::new(&[
+            "ai.ron",
+        ]));
+        app.add_systems(
+            GameTickUpdate,
+            resolve_asset_keys::<BehaviourTree>.in_set(AssetsSet::ResolveKeys),
+        );
+    }
+}
+
+#[derive(Asset, Debug, Clone)]
+#[derive(Deserialize)]
+#[derive(TypePath)]
+pub(super) struct BehaviourTree {
+    root: Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Deserialize)]
+pub(super) enum Node {
+    /// Try the children in order, until one does not fail
+    Selector(Vec<Node>),
+    /// Run the children in order, until one does not succeed
+    Sequence(Vec<Node>),
+    /// Swap success and failure
+    Not(Box<Node>),
+    /// Succeed if the condition holds
+    Check(Condition),
+    /// Commit to doing something (ends evaluation)
+    Do(Action),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Deserialize)]
+pub(super) enum Condition {
+    HasTarget,
+    InRange(Vec<Range>),
+    OnGround,
+    Falling,
+    /// Can't go further forward
+    Blocked,
+    /// Succeed randomly, with the given probability (0.0 - 1.0)
+    Chance(f32),
+}
+
+/// The things an enemy can decide to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Deserialize)]
+pub(super) enum Action {
+    /// Stop being aggroed, go back to patrolling
+    Patrol,
+    /// Run towards the target
+    Chase,
+    MeleeAttack,
+    /// Shoot at the target
+    RangedAttack,
+    /// Stand still and defend
+    Defend,
+    /// Do nothing (yet), decide again next tick
+    Wait,
+}
+
+/// The result of evaluating a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Success,
+    Failure,
+    Act(Action),
+}
+
+/// What the tree can look at, for one enemy
+pub(super) struct Blackboard<'a> {
+    range: &'a Range,
+    target: &'a Target,
+    navigation: &'a Navigation,
+}
+
+impl Condition {
+    fn check<R: Rng + ?Sized>(&self, bb: &Blackboard, rng: &mut R) -> bool {
+        match self {
+            Condition::HasTarget => bb.target.0.is_some(),
+            Condition::InRange(ranges) => ranges.contains(bb.range),
+            Condition::OnGround => {
+                matches!(bb.navigation, Navigation::Grounded)
+            },
+            Condition::Falling => {
+                matches!(
+                    bb.navigation,
+                    Navigation::Falling { .. }
+                )
+            },
+            Condition::Blocked => matches!(bb.navigation, Navigation::Blocked),
+            Condition::Chance(p) => rng.gen_bool(p.clamp(0.0, 1.0) as f64),
+        }
+    }
+}
+
+impl Node {
+    fn eval<R: Rng + ?Sized>(&self, bb: &Blackboard, rng: &mut R) -> Status {
+        match self {
+            Node::Selector(children) => {
+                for child in children {
+                    match child.eval(bb, rng) {
+                        Status::Failure => continue,
+                        other => return other,
+                    }
+                }
+                Status::Failure
+            },
+            Node::Sequence(children) => {
+                for child in children {
+                    match child.eval(bb, rng) {
+                        Status::Success => continue,
+                        other => return other,
+                    }
+                }
+                Status::Success
+            },
+            Node::Not(child) => match child.eval(bb, rng) {
+                Status::Success => Status::Failure,
+                Status::Failure => Status::Success,
+                other => other,
+            },
+            Node::Check(condition) => {
+                if condition.check(bb, rng) {
+                    Status::Success
+                } else {
+                    Status::Failure
+                }
+            },
+            Node::Do(action) => Status::Act(*action),
+        }
+    }
+}
+
+impl BehaviourTree {
+    /// What should the enemy do next?
+    fn decide<R: Rng + ?Sized>(
+        &self,
+        bb: &Blackboard,
+        rng: &mut R,
+    ) -> Option<Action> {
+        match self.root.eval(bb, rng) {
+            Status::Act(action) => Some(action),
+            _ => None,
+        }
+    }
+}
+
+impl Role {
+    /// Asset key of the behaviour tree for enemies of this role
+    pub(super) fn ai_key(&self) -> &'static str {
+        match self {
+            Role::Melee => "ai.spider.melee",
+            Role::Ranged => "ai.spider.ranged",
+        }
+    }
+}
+
+/// Let aggroed enemies that are waiting decide what to do next
+pub(super) fn think(
+    mut query: Query<
+        (
+            &Handle<BehaviourTree>,
+            &Range,
+            &Target,
+            &Navigation,
+            &mut LinearVelocity,
+            &mut TransitionQueue,
+        ),
+        (
+            With<Enemy>,
+            With<Aggroed>,
+            // each "substate" of aggro should return back to waiting when with wants to return control
+            // to aggro
+            With<Waiting>,
+        ),
+    >,
+    trees: Res<Assets<BehaviourTree>>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (handle, range, target, navigation, mut velocity, mut transitions) in
+        query.iter_mut()
+    {
+        let Some(tree) = trees.get(handle) else {
+            continue;
+        };
+        let bb = Blackboard {
+            range,
+            target,
+            navigation,
+        };
+        let Some(action) = tree.decide(&bb, &mut *rng) else {
+            continue;
+        };
+        match action {
+            Action::Patrol => {
+                transitions.push(Aggroed::new_transition(Patrolling));
+            },
+            Action::Chase => {
+                transitions.push(Waiting::new_transition(Chasing));
+            },
+            Action::MeleeAttack => {
+                transitions.push(Waiting::new_transition(
+                    MeleeAttack::default(),
+                ));
+            },
+            Action::RangedAttack => {
+                // can't shoot at nothing
+                let Some(target) = target.0 else {
+                    continue;
+                };
+                transitions.push(Waiting::new_transition(RangedAttack {
+                    target,
+                    ticks: 0,
+                }));
+            },
+            Action::Defend => {
+                velocity.x = 0.;
+                transitions.push(Waiting::new_transition(Defense));
+            },
+            Action::Wait => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn first_matching_branch_decides() {
+        let tree = BehaviourTree {
+            root: Node::Selector(vec![
+                Node::Sequence(vec![
+                    Node::Not(Box::new(Node::Check(
+                        Condition::HasTarget,
+                    ))),
+                    Node::Do(Action::Patrol),
+                ]),
+                Node::Sequence(vec![
+                    Node::Check(Condition::InRange(vec![Range::Melee])),
+                    Node::Do(Action::Defend),
+                ]),
+                Node::Do(Action::RangedAttack),
+            ]),
+        };
+        let mut rng = GameRng::from_seed(0);
+        let decide =
+            |range: Range, target: Option<Entity>, rng: &mut GameRng| {
+                let bb = Blackboard {
+                    range: &range,
+                    target: &Target(target),
+                    navigation: &Navigation::Grounded,
+                };
+                tree.decide(&bb, rng)
+            };
+        let player = Some(Entity::PLACEHOLDER);
+        assert_eq!(
+            decide(Range::Far, None, &mut rng),
+            Some(Action::Patrol)
+        );
+        assert_eq!(
+            decide(Range::Melee, player, &mut rng),
+            Some(Action::Defend)
+        );
+        assert_eq!(
+            decide(Range::Aggro, player, &mut rng),
+            Some(Action::RangedAttack)
+        );
+    }
+}
@@ -22,7 +22,10 @@ use crate::prelude::{
 };
 use crate::ui::button;
 
-use super::pickups::DropTracker;
+use super::boss::DefeatedBosses;
+use super::dialogue::DialogueFlags;
+use super::pickups::{CollectedSeeds, DropTracker};
+use super::xp_orbs::Xp;
 
 /// A plugin that handles when the player has a game over
 pub struct GameOverPlugin;
@@ -182,8 +185,15 @@ pub fn on_game_over(
         });
     });
     kill_count.0 = 0;
+    commands.insert_resource(Xp::default());
 
     // TODO: Move this to some less obscure system that resets game state.
-    commands.insert_resource(DropTracker::default());
+    commands.remove_resource::<DropTracker>();
+    commands.init_resource::<DropTracker>();
+    commands.insert_resource(CollectedSeeds::default());
+    commands.insert_resource(DefeatedBosses::default());
+    commands.insert_resource(DialogueFlags::default());
+    // death ends the run, there is nothing to continue
+    crate::save::wipe_save();
     commands.remove_resource::<GameOver>();
 }
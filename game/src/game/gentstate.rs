@@ -121,6 +121,8 @@ impl GentState for Idle {}
 #[derive(Component, Default, Debug)]
 pub struct Dead {
     pub ticks: u32,
+    /// Whoever dealt the killing blow (if it came from an attack)
+    pub killer: Option<Entity>,
 }
 
 // #[derive(Component, Default, Debug)]
@@ -3,18 +3,25 @@ use bevy::{
     transform::TransformSystem, ui::UiSystem, utils::hashbrown::HashMap,
 };
 use rand::Rng;
-use strum::IntoEnumIterator;
+use serde::{Deserialize, Serialize};
+use theseeker_engine::prelude::{EntityInstance, LdtkEntity};
+use theseeker_engine::rng::GameRng;
 use theseeker_engine::time::GameTickUpdate;
 
 use crate::{
-    camera::MainCamera, prelude::StateDespawnMarker, ui::popup::PopupUi,
+    camera::MainCamera,
+    keybindings::{Control, Keybindings},
+    level::LevelDespawnMarker,
+    prelude::StateDespawnMarker,
+    ui::popup::PopupUi,
 };
 
 use super::{
     attack::KillCount,
     enemy::{dead, Enemy, Tier},
     gentstate::Dead,
-    player::{Passive, Passives, Player},
+    player::player_weapon::{WeaponDefs, WeaponId},
+    player::{Passive, PassiveDefs, Passives, Player},
 };
 
 pub const PICKUP_RANGE_SQUARED: f32 = 100.0;
@@ -22,12 +29,15 @@ pub const PICKUP_RANGE_SQUARED: f32 = 100.0;
 pub struct PickupPlugin;
 impl Plugin for PickupPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<CollectedSeeds>();
         app.add_systems(Startup, load_pickup_assets).add_systems(
             GameTickUpdate,
             (
                 spawn_pickups_on_death
                     .after(dead)
                     .run_if(resource_exists::<DropTracker>),
+                setup_weapon_pickups
+                    .run_if(any_with_component::<WeaponPickupBlueprint>),
                 display_passives_description
                     .after(UiSystem::Layout)
                     .before(TransformSystem::TransformPropagate)
@@ -67,83 +77,10 @@ impl PickupDrop {
 
 #[derive(Resource)]
 pub struct PickupAssetHandles {
-    passive_map: HashMap<Passive, Handle<Image>>,
     seed_map: HashMap<PlanetarySeed, String>,
 }
 
-impl PickupAssetHandles {
-    pub fn get_passive_handle(
-        &self,
-        passive: &Passive,
-    ) -> Option<&Handle<Image>> {
-        self.passive_map.get(passive)
-    }
-}
-
-pub fn load_pickup_assets(assets: Res<AssetServer>, mut commands: Commands) {
-    let passive_mappings: Vec<(Passive, &str)> = vec![
-        (
-            Passive::Bloodstone,
-            "items/passives/Bloodstone.png",
-        ),
-        (
-            Passive::FlamingHeart,
-            "items/passives/FlamingHeart.png",
-        ),
-        (
-            Passive::IceDagger,
-            "items/passives/IceDagger.png",
-        ),
-        (
-            Passive::GlowingShard,
-            "items/passives/GlowingShard.png",
-        ),
-        (
-            Passive::ObsidianNecklace,
-            "items/passives/ObsidianNecklace.png",
-        ),
-        (
-            Passive::HeavyBoots,
-            "items/passives/HeavyBoots.png",
-        ),
-        (
-            Passive::SerpentRing,
-            "items/passives/SerpentRing.png",
-        ),
-        (
-            Passive::FrenziedAttack,
-            "items/passives/FrenziedAttack.png",
-        ),
-        (
-            Passive::PackKiller,
-            "items/passives/PackKiller.png",
-        ),
-        (
-            Passive::DeadlyFeather,
-            "items/passives/DeadlyFeather.png",
-        ),
-        (
-            Passive::Sharpshooter,
-            "items/passives/Sharpshooter.png",
-        ),
-        (
-            Passive::ProtectiveSpirit,
-            "items/passives/ProtectiveSpirit.png",
-        ),
-        (
-            Passive::RabbitsFoot,
-            "items/passives/RabbitsFoot.png",
-        ),
-        (
-            Passive::CriticalRegeneration,
-            "items/passives/CriticalRegeneration.png",
-        ),
-        (
-            Passive::VitalityOverclock,
-            "items/passives/VitalityOverclock.png",
-        ),
-    ];
-
+pub fn load_pickup_assets(mut commands: Commands) {
     let seed_mappings: Vec<(PlanetarySeed, &str)> = vec![
         (
             PlanetarySeed::CategoryA,
@@ -168,12 +105,6 @@ pub fn load_pickup_assets(assets: Res<AssetServer>, mut commands: Commands) {
     ];
 
     commands.insert_resource(PickupAssetHandles {
-        passive_map: HashMap::from_iter(
-            passive_mappings
-                .iter()
-                .map(|(x, y)| (x.clone(), assets.load(*y)))
-                .collect::<Vec<_>>(),
-        ),
         seed_map: HashMap::from_iter(
             seed_mappings
                 .iter()
@@ -183,6 +114,51 @@ pub fn load_pickup_assets(assets: Res<AssetServer>, mut commands: Commands) {
     });
 }
 
+/// A weapon placed in the level, to be picked up
+#[derive(Component, Default)]
+pub struct WeaponPickupBlueprint {
+    weapon: String,
+}
+
+impl WeaponPickupBlueprint {
+    fn from_entity_instance(entity: &EntityInstance) -> Self {
+        Self {
+            weapon: entity
+                .get_string_field("weapon")
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct WeaponPickupBlueprintBundle {
+    #[with(WeaponPickupBlueprint::from_entity_instance)]
+    marker: WeaponPickupBlueprint,
+}
+
+fn setup_weapon_pickups(
+    query: Query<
+        (
+            Entity,
+            &Transform,
+            &WeaponPickupBlueprint,
+        ),
+        Added<WeaponPickupBlueprint>,
+    >,
+    mut commands: Commands,
+) {
+    for (entity, transform, blueprint) in query.iter() {
+        // same space as the player, like the range checks
+        let weapon = WeaponId::new(blueprint.weapon.clone());
+        commands.add(SpawnPickupCommand {
+            pos: transform.translation,
+            p_type: PickupType::Weapon(weapon),
+        });
+        commands.entity(entity).remove::<WeaponPickupBlueprint>();
+    }
+}
+
 pub struct SpawnPickupCommand {
     pos: Vec3,
     p_type: PickupType,
@@ -196,63 +172,42 @@ impl Command for SpawnPickupCommand {
 
         match self.p_type.clone() {
             PickupType::PassiveDrop(passive) => {
-                let texture_handle = handles.passive_map.get(&passive).unwrap();
-                let transform = Transform::from_xyz(pos.x, pos.y, 50.0);
-
-                let entity = world
-                    .spawn((
-                        Name::new("PickupDrop"),
-                        PickupDrop::new(self.p_type),
-                        SpriteBundle {
-                            transform,
-                            texture: texture_handle.clone(),
-                            ..default()
-                        },
-                        StateDespawnMarker,
-                    ))
-                    .id();
-
-                world.spawn((
-                    Name::new("PassiveDescription"),
-                    PassiveDescriptionNode,
-                    PassiveEntity(entity),
-                    TextBundle {
-                        text: Text {
-                            sections: vec![
-                                TextSection::new(
-                                    passive.name(),
-                                    TextStyle {
-                                        font_size: 24.0,
-                                        ..Default::default()
-                                    },
-                                ),
-                                TextSection::from("\n"),
-                                TextSection::new(
-                                    passive.description(),
-                                    TextStyle {
-                                        font_size: 24.0,
-                                        ..Default::default()
-                                    },
-                                ),
-                            ],
-                            justify: JustifyText::Center,
-                            linebreak_behavior: BreakLineOn::WordBoundary,
-                        },
-                        style: Style {
-                            max_width: Val::Percent(33.0),
-                            ..Default::default()
-                        },
-                        global_transform: GlobalTransform::from_translation(
-                            Vec3::new(pos.x, pos.y, 50.0),
-                        ),
-                        background_color: BackgroundColor::from(
-                            Color::BLACK.with_a(0.75),
-                        ),
-                        visibility: Visibility::Hidden,
-                        ..Default::default()
-                    },
-                    StateDespawnMarker,
-                ));
+                let defs = world.resource::<PassiveDefs>();
+                let Some(def) = defs.get(&passive) else {
+                    error!("Passive {:?} is not defined!", passive);
+                    return;
+                };
+                let name = def.name.clone();
+                let description = def.description.clone();
+                let texture_handle =
+                    defs.icon(&passive).cloned().unwrap_or_default();
+                spawn_described_pickup(
+                    world,
+                    pos,
+                    self.p_type,
+                    texture_handle,
+                    name,
+                    description,
+                );
+            },
+            PickupType::Weapon(weapon) => {
+                let defs = world.resource::<WeaponDefs>();
+                let Some(def) = defs.get(&weapon) else {
+                    error!("Weapon {} is not defined!", weapon);
+                    return;
+                };
+                let name = def.name.clone();
+                let description = def.description.clone();
+                let texture_handle =
+                    defs.icon(&weapon).cloned().unwrap_or_default();
+                spawn_described_pickup(
+                    world,
+                    pos,
+                    self.p_type,
+                    texture_handle,
+                    name,
+                    description,
+                );
             },
             PickupType::Seed(categ, (id, _)) => {
                 let path = &handles.seed_map[&categ];
@@ -275,13 +230,81 @@ impl Command for SpawnPickupCommand {
     }
 }
 
+/// A pickup with its name and description shown above it, while in range
+fn spawn_described_pickup(
+    world: &mut World,
+    pos: Vec3,
+    p_type: PickupType,
+    texture_handle: Handle<Image>,
+    name: String,
+    description: String,
+) {
+    let transform = Transform::from_xyz(pos.x, pos.y, 50.0);
+
+    let entity = world
+        .spawn((
+            Name::new("PickupDrop"),
+            PickupDrop::new(p_type),
+            SpriteBundle {
+                transform,
+                texture: texture_handle,
+                ..default()
+            },
+            StateDespawnMarker,
+            LevelDespawnMarker,
+        ))
+        .id();
+
+    world.spawn((
+        Name::new("PassiveDescription"),
+        PassiveDescriptionNode,
+        PassiveEntity(entity),
+        TextBundle {
+            text: Text {
+                sections: vec![
+                    TextSection::new(
+                        name,
+                        TextStyle {
+                            font_size: 24.0,
+                            ..Default::default()
+                        },
+                    ),
+                    TextSection::from("\n"),
+                    TextSection::new(
+                        description,
+                        TextStyle {
+                            font_size: 24.0,
+                            ..Default::default()
+                        },
+                    ),
+                ],
+                justify: JustifyText::Center,
+                linebreak_behavior: BreakLineOn::WordBoundary,
+            },
+            style: Style {
+                max_width: Val::Percent(33.0),
+                ..Default::default()
+            },
+            global_transform: GlobalTransform::from_translation(Vec3::new(
+                pos.x, pos.y, 50.0,
+            )),
+            background_color: BackgroundColor::from(Color::BLACK.with_a(0.75)),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        StateDespawnMarker,
+        LevelDespawnMarker,
+    ));
+}
+
 #[derive(Clone)]
 pub enum PickupType {
     PassiveDrop(Passive),
+    Weapon(WeaponId),
     Seed(PlanetarySeed, (u32, String)),
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PlanetarySeed {
     CategoryA,
     CategoryB,
@@ -391,6 +414,10 @@ impl PlanetarySeed {
     }
 }
 
+/// The seeds the player has picked up during this run
+#[derive(Resource, Debug, Default, Deref, DerefMut)]
+pub struct CollectedSeeds(pub Vec<(PlanetarySeed, u32)>);
+
 #[derive(Resource)]
 pub struct DropTracker {
     pub progress: usize,
@@ -398,9 +425,11 @@ pub struct DropTracker {
     pub seeds: HashMap<PlanetarySeed, Vec<(u32, String)>>,
 }
 
-impl Default for DropTracker {
-    fn default() -> Self {
-        DropTracker::new(Passive::iter().count())
+impl FromWorld for DropTracker {
+    fn from_world(world: &mut World) -> Self {
+        let passive_count = world.resource::<PassiveDefs>().ids().count();
+        let mut rng = world.resource_mut::<GameRng>();
+        DropTracker::new(passive_count, &mut *rng)
     }
 }
 
@@ -414,11 +443,9 @@ impl DropTracker {
         self.passive_rolls.get(self.progress)
     }
 
-    fn new(passive_count: usize) -> Self {
+    fn new<R: Rng + ?Sized>(passive_count: usize, rng: &mut R) -> Self {
         const SPAN: u32 = 5;
 
-        let mut rng = rand::thread_rng();
-
         let mut rolls = Vec::new();
 
         for i in 0..passive_count {
@@ -434,12 +461,11 @@ impl DropTracker {
         }
     }
 
-    pub fn drop_random_seed(
+    pub fn drop_random_seed<R: Rng + ?Sized>(
         &mut self,
         seed_type: &PlanetarySeed,
+        rng: &mut R,
     ) -> Option<(u32, String)> {
-        let mut rng = rand::thread_rng();
-
         if !self.seeds[seed_type].is_empty() {
             let i = rng.gen_range(0..self.seeds[seed_type].len());
             let seed = self.seeds.get_mut(seed_type).unwrap().swap_remove(i);
@@ -454,21 +480,28 @@ fn spawn_pickups_on_death(
     mut kill_count: ResMut<KillCount>,
     mut drop_tracker: ResMut<DropTracker>,
     enemy_q: Query<(&GlobalTransform, &Tier), (With<Enemy>, Added<Dead>)>,
-    mut p_query: Query<&mut Passives, With<Player>>,
+    p_query: Query<&Passives, With<Player>>,
+    pickup_q: Query<&PickupDrop>,
+    mut rng: ResMut<GameRng>,
     mut commands: Commands,
 ) {
     //ASSUMES ONLY 1 PLAYER
-    let Ok(mut passives) = p_query.get_single_mut() else {
+    let Ok(passives) = p_query.get_single() else {
         return;
     };
+    let mut on_ground: Vec<Passive> = pickup_q
+        .iter()
+        .filter_map(|pickup| match &pickup.p_type {
+            PickupType::PassiveDrop(passive) => Some(passive.clone()),
+            _ => None,
+        })
+        .collect();
 
     for (tr, tier) in enemy_q.iter() {
         let translation = tr.translation();
 
         println!("PRE-DROPPING PASSIVE");
 
-        let mut rng = rand::thread_rng();
-
         let seed_roll = rng.gen_range(0.0..1.0);
 
         println!("seed roll: {}", seed_roll);
@@ -514,7 +547,8 @@ fn spawn_pickups_on_death(
         };
 
         if let Some(seed_category) = seed_category {
-            let seed_id = drop_tracker.drop_random_seed(&seed_category);
+            let seed_id =
+                drop_tracker.drop_random_seed(&seed_category, &mut *rng);
 
             if let Some(seed_id) = seed_id {
                 commands.add(SpawnPickupCommand {
@@ -539,8 +573,11 @@ fn spawn_pickups_on_death(
             if kill_count.0 >= *milestone {
                 drop_tracker.progress += 1;
 
-                if let Some(passive) = passives.drop_random() {
+                if let Some(passive) =
+                    passives.drop_random(&on_ground, &mut *rng)
+                {
                     println!("DROPPING PASSIVE");
+                    on_ground.push(passive.clone());
                     commands.add(SpawnPickupCommand {
                         pos: translation,
                         p_type: PickupType::PassiveDrop(passive),
@@ -569,6 +606,7 @@ fn display_passives_description(
     pickup_query: Query<(Entity, &Transform), With<PickupDrop>>,
     camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
     pickup_hint: Query<Entity, With<PickupHint>>,
+    keybindings: Res<Keybindings>,
 ) {
     let Ok(p_transform) = player_query.get_single() else {
         return;
@@ -590,7 +628,7 @@ fn display_passives_description(
                 commands.popup().insert(PickupHint).with_children(|popup| {
                     popup.row().with_children(|row| {
                         row.text("Press ");
-                        row.control_icon("F");
+                        row.control_icons(&keybindings, Control::Interact);
                         row.text(" to pick up");
                     });
                 });
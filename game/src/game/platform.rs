@@ -0,0 +1,416 @@
+//! One-way and moving platforms
+//!
+//! One-way platforms can be jumped through from below, and stood on from
+//! above. The player drops through them with `PlayerAction::Fall`. Their
+//! colliders are in the [`ONE_WAY`] group (instead of `GROUND`), so
+//! anything that should stand on them must include it in its filters.
+//!
+//! They can be placed in two ways:
+//!  - As int grid cells (value 21). Each row of cells becomes one thin
+//!    collider at the top of the tiles.
+//!  - As `Platform` LDtk entities, whose size is the size of the platform.
+//!    The entity can move along a path, carrying whoever stands on it.
+//!
+//! The fields of the `Platform` entity:
+//!  - `path`: an array of points (cells) to move between. The platform
+//!    starts where it was placed, and moves by as many cells as the
+//!    entity's own cell is away from each point. It goes back and forth
+//!    along the path. If empty, the platform does not move.
+//!  - `speed`: in pixels per second (default 32)
+//!  - `wait`: seconds to stay at each end of the path (default 0.5)
+
+use rapier2d::geometry::{Cuboid, Group, InteractionGroups};
+use theseeker_engine::gent::Gent;
+use theseeker_engine::physics::{
+    into_vec, Collider, PhysicsWorld, StaticCollider, ENEMY, ENEMY_INSIDE,
+    ONE_WAY, PLAYER, SENSOR,
+};
+
+use super::enemy::EnemyStateSet;
+use super::player::PlayerStateSet;
+use crate::level::LevelDespawnMarker;
+use crate::prelude::*;
+
+pub struct PlatformPlugin;
+
+impl Plugin for PlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, spawn_platform_collision);
+        app.add_systems(
+            GameTickUpdate,
+            (
+                setup_platforms.run_if(any_with_component::<PlatformBlueprint>),
+                move_platforms.run_if(any_with_component::<MovingPlatform>),
+            )
+                .chain()
+                .before(PlayerStateSet::Behavior)
+                .before(EnemyStateSet::Behavior)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+/// Thickness of the colliders of one-way platform tiles
+const TILE_PLATFORM_HEIGHT: f32 = 4.0;
+
+/// How far above a moving platform a gent can be, and still ride it
+const RIDE_HEIGHT: f32 = 3.0;
+
+const PLATFORM_COLOR: Color = Color::rgb(0.36, 0.3, 0.24);
+
+/// Marker for one-way platform tiles
+#[derive(Component, Default)]
+pub struct OneWayPlatform;
+
+/// Bundle for one-way platform tiles
+#[derive(Bundle, Default, LdtkIntCell)]
+pub struct OneWayPlatformBundle {
+    platform: OneWayPlatform,
+}
+
+/// Spawns the colliders for the one-way platform tiles of a level
+///
+/// Like [`spawn_wall_collision`](super::wall::spawn_wall_collision),
+/// but only combines tiles within each row, because only the top of the
+/// platform matters.
+fn spawn_platform_collision(
+    mut commands: Commands,
+    platform_query: Query<(&GridCoords, &LdtkParent), Added<OneWayPlatform>>,
+    parent_query: Query<&Parent, Without<OneWayPlatform>>,
+    level_query: Query<(Entity, &LevelIid)>,
+    ldtk_projects: Query<&Handle<LdtkProject>>,
+    ldtk_project_assets: Res<Assets<LdtkProject>>,
+) {
+    if platform_query.is_empty() {
+        return;
+    }
+
+    let mut level_to_platform_locations: HashMap<Entity, HashSet<GridCoords>> =
+        HashMap::new();
+    platform_query.iter().for_each(|(&grid_coords, parent)| {
+        // the tile's parent is the layer, whose parent is the level
+        if let Ok(grandparent) = parent_query.get(parent.0) {
+            level_to_platform_locations
+                .entry(grandparent.get())
+                .or_default()
+                .insert(grid_coords);
+        }
+    });
+
+    for (level_entity, level_iid) in level_query.iter() {
+        let Some(level_platforms) =
+            level_to_platform_locations.get(&level_entity)
+        else {
+            continue;
+        };
+        let ldtk_project = ldtk_project_assets
+            .get(ldtk_projects.single())
+            .expect("Project should be loaded if level has spawned");
+        let level = ldtk_project
+            .as_standalone()
+            .get_loaded_level_by_iid(&level_iid.to_string())
+            .expect("Spawned level should exist in LDtk project");
+        let LayerInstance {
+            c_wid: width,
+            c_hei: height,
+            grid_size,
+            ..
+        } = level.layer_instances()[0];
+        let grid_size = grid_size as f32;
+
+        commands.entity(level_entity).with_children(|level| {
+            for y in 0..height {
+                let mut plate_start = None;
+                // + 1 to the width to finish plates that touch the right edge
+                for x in 0..width + 1 {
+                    let is_platform =
+                        level_platforms.contains(&GridCoords { x, y });
+                    match (plate_start, is_platform) {
+                        (Some(left), false) => {
+                            level.spawn((
+                                Collider::cuboid(
+                                    (x - left) as f32 * grid_size,
+                                    TILE_PLATFORM_HEIGHT,
+                                    InteractionGroups {
+                                        memberships: ONE_WAY,
+                                        filter: Group::all(),
+                                    },
+                                ),
+                                Transform::from_xyz(
+                                    (left + x) as f32 * grid_size / 2.,
+                                    (y + 1) as f32 * grid_size
+                                        - TILE_PLATFORM_HEIGHT / 2.,
+                                    10.,
+                                ),
+                                GlobalTransform::default(),
+                                StaticCollider,
+                                StateDespawnMarker,
+                            ));
+                            plate_start = None;
+                        },
+                        (None, true) => plate_start = Some(x),
+                        _ => (),
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// A one-way platform placed as an LDtk entity
+#[derive(Component, Default, Debug, Clone)]
+pub struct PlatformBlueprint {
+    size: Vec2,
+    /// Cells to move between, relative to the entity's own cell (y down)
+    path: Vec<IVec2>,
+    speed: f32,
+    wait: f32,
+}
+
+impl PlatformBlueprint {
+    fn from_entity_instance(entity: &EntityInstance) -> Self {
+        let path = match entity.get_points_field("path") {
+            Ok(points) => {
+                points.into_iter().map(|p| *p - entity.grid).collect()
+            },
+            Err(e) => {
+                error!(
+                    "Invalid path of platform {}: {}",
+                    entity.iid, e
+                );
+                vec![]
+            },
+        };
+        Self {
+            size: Vec2::new(
+                entity.width as f32,
+                entity.height as f32,
+            ),
+            path,
+            speed: entity
+                .get_float_field("speed")
+                .ok()
+                .copied()
+                .unwrap_or(32.0),
+            wait: entity.get_float_field("wait").ok().copied().unwrap_or(0.5),
+        }
+    }
+}
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct PlatformBlueprintBundle {
+    #[with(PlatformBlueprint::from_entity_instance)]
+    blueprint: PlatformBlueprint,
+}
+
+/// Convert a path of cells into pixel offsets (y up), and prepend the
+/// starting point
+fn path_offsets(path: &[IVec2], grid_size: f32) -> Vec<Vec2> {
+    std::iter::once(Vec2::ZERO)
+        .chain(
+            path.iter().map(|cell| {
+                Vec2::new(cell.x as f32, -cell.y as f32) * grid_size
+            }),
+        )
+        .collect()
+}
+
+/// A platform that goes back and forth along a path
+#[derive(Component, Debug, Clone)]
+pub struct MovingPlatform {
+    /// Where the path starts from
+    start: Vec2,
+    /// Points to move between, relative to `start`
+    path: Vec<Vec2>,
+    /// In pixels per second
+    speed: f32,
+    /// How long to wait at the ends of the path
+    wait_ticks: u32,
+    /// Index of the point we are moving towards
+    target: usize,
+    /// Moving towards the end of the path (or back to the start)?
+    forward: bool,
+    /// Ticks left to wait before moving on
+    waiting: u32,
+}
+
+impl MovingPlatform {
+    /// Where to be on the next tick, moving at most `max_dist` from `pos`
+    fn step(&mut self, pos: Vec2, max_dist: f32) -> Vec2 {
+        if self.waiting > 0 {
+            self.waiting -= 1;
+            return pos;
+        }
+        let target = self.start + self.path[self.target];
+        let to_target = target - pos;
+        if to_target.length() > max_dist {
+            return pos + to_target.normalize() * max_dist;
+        }
+        // arrived; turn around at the ends of the path
+        if self.target == self.path.len() - 1 {
+            self.forward = false;
+            self.waiting = self.wait_ticks;
+        } else if self.target == 0 {
+            self.forward = true;
+            self.waiting = self.wait_ticks;
+        }
+        if self.forward {
+            self.target += 1;
+        } else {
+            self.target -= 1;
+        }
+        target
+    }
+}
+
+fn setup_platforms(
+    q: Query<(
+        Entity,
+        &PlatformBlueprint,
+        &Transform,
+        &Parent,
+    )>,
+    q_layer: Query<&LayerMetadata>,
+    time: Res<GameTime>,
+    mut commands: Commands,
+) {
+    for (e_platform, blueprint, xf_platform, parent) in q.iter() {
+        // the entity's parent is its LDtk layer
+        let Ok(layer) = q_layer.get(parent.get()) else {
+            continue;
+        };
+        let path = path_offsets(&blueprint.path, layer.grid_size as f32);
+        let mut e = commands.entity(e_platform);
+        e.insert((
+            Name::new("Platform"),
+            Collider::cuboid(
+                blueprint.size.x,
+                blueprint.size.y,
+                InteractionGroups {
+                    memberships: ONE_WAY,
+                    filter: Group::all(),
+                },
+            ),
+            Sprite {
+                color: PLATFORM_COLOR,
+                custom_size: Some(blueprint.size),
+                ..default()
+            },
+            Handle::<Image>::default(),
+            StateDespawnMarker,
+            LevelDespawnMarker,
+        ))
+        .remove::<PlatformBlueprint>()
+        .remove_parent();
+        if path.len() >= 2 {
+            e.insert(MovingPlatform {
+                start: xf_platform.translation.xy(),
+                path,
+                speed: blueprint.speed,
+                wait_ticks: (blueprint.wait * time.hz as f32) as u32,
+                target: 1,
+                forward: true,
+                waiting: 0,
+            });
+        }
+    }
+}
+
+/// Move the platforms, and whoever stands on them
+///
+/// Runs before the player and enemies move, so that they collide with
+/// the platforms where they are on this tick.
+fn move_platforms(
+    mut spatial_query: ResMut<PhysicsWorld>,
+    mut q_platform: Query<(
+        Entity,
+        &mut MovingPlatform,
+        &mut Transform,
+        &Collider,
+    )>,
+    mut q_rider: Query<&mut Transform, (With<Gent>, Without<MovingPlatform>)>,
+    time: Res<GameTime>,
+) {
+    for (e_platform, mut platform, mut xf_platform, collider) in
+        q_platform.iter_mut()
+    {
+        let pos = xf_platform.translation.xy();
+        let new_pos = platform.step(pos, platform.speed / time.hz as f32);
+        let delta = new_pos - pos;
+        if delta == Vec2::ZERO {
+            continue;
+        }
+
+        // find the riders before moving, while they still stand on it
+        let aabb = collider.0.shape().compute_local_aabb();
+        let half_width = aabb.half_extents().x;
+        let riders = spatial_query.intersect(
+            Vec2::new(
+                pos.x,
+                pos.y + aabb.maxs.y + RIDE_HEIGHT / 2.0,
+            ),
+            &Cuboid::new(into_vec(Vec2::new(
+                half_width,
+                RIDE_HEIGHT / 2.0,
+            ))),
+            InteractionGroups {
+                memberships: SENSOR,
+                filter: PLAYER | ENEMY | ENEMY_INSIDE,
+            },
+            Some(e_platform),
+        );
+
+        xf_platform.translation.x = new_pos.x;
+        xf_platform.translation.y = new_pos.y;
+        spatial_query.set_collider_translation(e_platform, new_pos);
+        for e_rider in riders {
+            if let Ok(mut xf_rider) = q_rider.get_mut(e_rider) {
+                xf_rider.translation.x += delta.x;
+                xf_rider.translation.y += delta.y;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn platform_path() {
+        assert_eq!(
+            path_offsets(
+                &[IVec2::new(4, 0), IVec2::new(4, 3)],
+                16.0
+            ),
+            vec![Vec2::ZERO, Vec2::new(64.0, 0.0), Vec2::new(64.0, -48.0)]
+        );
+        assert_eq!(
+            path_offsets(&[], 16.0),
+            vec![Vec2::ZERO]
+        );
+    }
+
+    #[test]
+    fn platform_goes_back_and_forth() {
+        let mut platform = MovingPlatform {
+            start: Vec2::new(100.0, 0.0),
+            path: vec![Vec2::ZERO, Vec2::new(10.0, 0.0)],
+            speed: 4.0,
+            wait_ticks: 1,
+            target: 1,
+            forward: true,
+            waiting: 0,
+        };
+        let mut pos = platform.start;
+        let mut xs = vec![];
+        for _ in 0..8 {
+            pos = platform.step(pos, 4.0);
+            xs.push(pos.x);
+        }
+        assert_eq!(
+            xs,
+            vec![104.0, 108.0, 110.0, 110.0, 106.0, 102.0, 100.0, 100.0]
+        );
+    }
+}
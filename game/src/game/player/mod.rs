@@ -1,27 +1,33 @@
 mod player_action;
 mod player_anim;
 mod player_behaviour;
+mod player_passives;
 pub mod player_weapon;
-use bevy::utils::hashbrown::HashMap;
 use leafwing_input_manager::action_state::ActionState;
 use player_action::PlayerActionPlugin;
 use player_anim::PlayerAnimationPlugin;
 use player_behaviour::PlayerBehaviorPlugin;
-use player_weapon::PlayerWeaponPlugin;
+use player_passives::{PassiveContext, PassivesPlugin};
+use player_weapon::{AttackDirection, PlayerWeaponPlugin};
 use rapier2d::geometry::{Group, InteractionGroups};
-use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
 use theseeker_engine::animation::SpriteAnimationBundle;
-use theseeker_engine::assets::config::{update_field, DynamicConfig};
+use theseeker_engine::assets::config::{
+    load_config, DynamicConfig, TypedConfig,
+};
+use theseeker_engine::assets::reload_on_modified;
 use theseeker_engine::gent::{Gent, GentPhysicsBundle, TransformGfxFromGent};
+use theseeker_engine::input::InputManagerSystem;
 use theseeker_engine::physics::{
-    Collider, LinearVelocity, ShapeCaster, GROUND, PLAYER,
+    Collider, LinearVelocity, ShapeCaster, GROUND, ONE_WAY, PLAYER,
 };
+use theseeker_engine::typed_config;
 
 use crate::game::attack::*;
 use crate::game::gentstate::*;
 use crate::game::pickups::DropTracker;
-use crate::game::xp_orbs::XpOrbPickup;
+use crate::game::shop::ShopUpgrades;
+use crate::game::status::{StatType, Stats, StatusModifiers};
+use crate::keybindings::Keybindings;
 use crate::prelude::*;
 
 use super::game_over::GameOver;
@@ -29,19 +35,22 @@ use super::physics::Knockback;
 use crate::game::enemy::Enemy;
 
 pub use player_action::PlayerAction;
+pub use player_passives::{
+    on_passive_trigger, Passive, PassiveDefs, StatMultipliers,
+};
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(PlayerConfig::default());
+        app.init_resource::<PlayerConfig>();
         app.add_systems(
             GameTickUpdate,
             (
-                load_player_config,
+                reload_on_modified::<DynamicConfig>(PlayerConfig::KEY)
+                    .pipe(load_config::<PlayerConfig>),
                 load_player_stats.run_if(resource_changed::<PlayerConfig>),
                 track_hits,
-                player_update_stats_mod,
                 player_update_passive_buffs,
             )
                 .chain()
@@ -49,13 +58,15 @@ impl Plugin for PlayerPlugin {
         );
         app.add_systems(
             GameTickUpdate,
-            on_xp_heal.after(PlayerStateSet::Behavior),
+            buffer_player_inputs
+                .after(InputManagerSystem::ManualControl)
+                .before(PlayerStateSet::Behavior),
         );
         app.add_systems(
             GameTickUpdate,
             (
                 apply_vitality_overclock,
-                update_serpentring_health,
+                update_max_health,
             ),
         );
         app.add_systems(Startup, load_dash_asset);
@@ -80,6 +91,7 @@ impl Plugin for PlayerPlugin {
             PlayerTransitionPlugin,
             PlayerAnimationPlugin,
             PlayerWeaponPlugin,
+            PassivesPlugin,
         ));
 
         #[cfg(feature = "dev")]
@@ -143,115 +155,107 @@ pub struct Passives {
 impl Passives {
     /// Maximum number of passives player can hold at once
     pub const MAX: usize = 3;
-}
 
-impl Default for Passives {
-    fn default() -> Self {
-        let passives: Vec<Passive> = Passive::iter().collect();
+    /// No passives yet, all of the given ones can still drop
+    pub fn new(pool: impl IntoIterator<Item = Passive>) -> Self {
         Passives {
             current: HashSet::with_capacity(Passives::MAX),
-            locked: passives,
+            locked: pool.into_iter().collect(),
         }
     }
 }
 
 impl Passives {
-    pub fn drop_random(&mut self) -> Option<Passive> {
-        let mut rng = rand::thread_rng();
-
-        // dont drop more passives if full
-        if !self.locked.is_empty() && self.current.len() < Passives::MAX {
-            let i = rng.gen_range(0..self.locked.len());
-            let passive = self.locked.swap_remove(i);
+    /// Pick a locked passive to drop, skipping the ones already lying
+    /// on the ground
+    ///
+    /// The passive stays locked until it is picked up, so a pickup that
+    /// is left behind can drop again later.
+    pub fn drop_random<R: Rng + ?Sized>(
+        &self,
+        on_ground: &[Passive],
+        rng: &mut R,
+    ) -> Option<Passive> {
+        // keeps dropping when full, picking one up then asks what to replace
+        self.locked
+            .iter()
+            .filter(|p| !on_ground.contains(p))
+            .choose(rng)
+            .cloned()
+    }
 
-            return Some(passive);
-        }
-        None
+    pub fn is_full(&self) -> bool {
+        self.current.len() >= Passives::MAX
     }
 
     // TODO: return result?
     pub fn add_passive(&mut self, passive: Passive) {
-        if self.current.len() < Passives::MAX {
+        if !self.is_full() {
+            self.locked.retain(|p| *p != passive);
             self.current.insert(passive);
         }
     }
-}
 
-// they could also be components...limit only by the pickup/gain function instead of sized hashmap
-#[derive(Debug, Eq, PartialEq, Hash, EnumIter, Clone)]
-pub enum Passive {
-    /// Heal after killing an enemy
-    Bloodstone,
-    /// Crit on every 2nd and 3rd hit when on low health
-    FlamingHeart,
-    /// Deal double damage when backstabbing
-    IceDagger,
-    /// Defense scaling based on number of enemies nearby
-    GlowingShard,
-    /// Crits lower cooldown of all abilities by 0.5 seconds
-    ObsidianNecklace,
-    /// Doubled damage & defence while standing still,but halved while moving
-    HeavyBoots,
-    /// Move faster, get cooldown redudction, but take double damage
-    SerpentRing,
-    /// Sacrifice health but get increased cooldown reduction for every consecutive hit within 3 seconds
-    FrenziedAttack,
-    /// Deal more damage to packs of enemies
-    PackKiller,
-    /// Get increased defense when you're in the air, but become more vulnerable when on the ground.
-    DeadlyFeather,
-    /// Scale damage based on distance between you and nearest enemy
-    Sharpshooter,
-    /// Limits the damage taken from any attack to 1/3 of your maximum health.
-    ProtectiveSpirit,
-    /// Gain 1 extra jump.
-    RabbitsFoot,
-    /// Critical hits heal you
-    CriticalRegeneration,
-    /// Increases damage based on health percentage, at the cost of constant health degeneration.
-    VitalityOverclock,
-}
-
-impl Passive {
-    pub fn name(&self) -> &str {
-        match self {
-            Passive::Bloodstone => "Bloodstone",
-            Passive::FlamingHeart => "Flaming Heart",
-            Passive::IceDagger => "Ice Dagger",
-            Passive::GlowingShard => "Glowing Shard",
-            Passive::ObsidianNecklace => "Obsidian Necklace",
-            Passive::HeavyBoots => "Heavy Boots",
-            Passive::SerpentRing => "Serpent Ring",
-            Passive::FrenziedAttack => "Frenzied Attack",
-            Passive::PackKiller => "Pack Killer",
-            Passive::DeadlyFeather => "Deadly Feather",
-            Passive::Sharpshooter => "Sharpshooter",
-            Passive::ProtectiveSpirit => "Protective Spirit",
-            Passive::RabbitsFoot => "Elastic Accelerator",
-            Passive::CriticalRegeneration => "Critical Regeneration",
-            Passive::VitalityOverclock => "Vitality Overclock",
+    /// Give up a held passive for a new one
+    ///
+    /// The old passive goes back to the pool and can drop again.
+    /// Returns false (and changes nothing) if `old` is not held.
+    pub fn replace(&mut self, old: &Passive, new: Passive) -> bool {
+        if !self.current.remove(old) {
+            return false;
         }
+        self.locked.retain(|p| *p != new);
+        self.locked.push(old.clone());
+        self.current.insert(new);
+        true
     }
 
-    pub fn description(&self) -> &str {
-        match self {
-            Passive::Bloodstone => "Heal after kills",
-            Passive::FlamingHeart => "Crit on every 2nd and 3rd hit when on low health",
-            Passive::IceDagger => "Deal double damage when backstabbing",
-            Passive::GlowingShard => "Defense scaling based on number of enemies nearby",
-            Passive::ObsidianNecklace => "Get +11% crit chance. Crits lower cooldown of all abilities by 0.5 seconds",
-            Passive::HeavyBoots => "Doubled damage & defence while standing still,but halved while moving",
-            Passive::SerpentRing => "Move faster, get cooldown redudction, but your life gets cut in half",
-            Passive::FrenziedAttack => "Sacrifice health but get increased cooldown reduction for every consecutive hit within 3 seconds",
-            Passive::PackKiller => "Deal more damage to packs of enemies",
-            Passive::DeadlyFeather => "Deal 50% extra damage, get faster cdr, and +50% crit chance when you're in the air, but become more vulnerable when on the ground",
-            Passive::Sharpshooter => "Scale damage based on distance between you and nearest enemy. Distance value only updated when Running.",
-            Passive::ProtectiveSpirit => "Damage you take from any attack is limited to 1/3rd of your maximum health",
-            Passive::RabbitsFoot => "Gain 1 extra jump, move 20% faster, and get +9% crit chance",
-            Passive::CriticalRegeneration => "Critical hits heal you",
-            Passive::VitalityOverclock => "Gain increased damage based on health percentage, at the cost of constant health degeneration",
+    /// Turn down a dropped passive, so it can drop again later
+    pub fn discard(&mut self, passive: Passive) {
+        if !self.current.contains(&passive) && !self.locked.contains(&passive) {
+            self.locked.push(passive);
         }
     }
+
+    /// The held passives in a stable order, for menus
+    pub fn sorted(&self) -> Vec<Passive> {
+        let mut held: Vec<_> = self.current.iter().cloned().collect();
+        held.sort_by_key(|p| p.to_string());
+        held
+    }
+}
+
+/// A passive was picked up with every slot full, the player is choosing
+/// what to do with it
+#[derive(Resource, Debug)]
+pub struct PassiveChoice {
+    /// The pickup, left on the ground until the choice is made
+    pub pickup: Entity,
+    pub new: Passive,
+    pub selected: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PassiveChoiceOption {
+    /// Give up a held passive for the new one
+    Replace(Passive),
+    /// Keep the held passives, the new one stays in the pool
+    Discard,
+    /// Close the choice, the pickup stays on the ground
+    Leave,
+}
+
+impl PassiveChoice {
+    pub fn options(passives: &Passives) -> Vec<PassiveChoiceOption> {
+        let mut options: Vec<_> = passives
+            .sorted()
+            .into_iter()
+            .map(PassiveChoiceOption::Replace)
+            .collect();
+        options.push(PassiveChoiceOption::Discard);
+        options.push(PassiveChoiceOption::Leave);
+        options
+    }
 }
 
 #[cfg(feature = "dev")]
@@ -349,17 +353,29 @@ fn debug_player(world: &World, query: Query<Entity, With<Player>>) {
 }
 
 fn setup_player(
-    mut q: Query<(&mut Transform, Entity, &Parent), Added<PlayerBlueprint>>,
+    mut q: Query<
+        (&mut Transform, Entity, Option<&Parent>),
+        Added<PlayerBlueprint>,
+    >,
     parent_query: Query<Entity, With<Children>>,
+    existing_player: Query<(), With<Player>>,
     mut commands: Commands,
     config: Res<PlayerConfig>,
+    passive_defs: Res<PassiveDefs>,
+    keybindings: Res<Keybindings>,
 ) {
     for (mut xf_gent, e_gent, parent) in q.iter_mut() {
+        // the player carries over when entering another level, and arrives
+        // through a door instead of at the level's (LDtk) spawn point
+        if parent.is_some() && !existing_player.is_empty() {
+            commands.entity(e_gent).despawn_recursive();
+            continue;
+        }
         // TODO: proper way of ensuring z is correct
         xf_gent.translation.z = 15.0 * 0.000001;
         let e_gfx = commands.spawn(()).id();
         let e_effects_gfx = commands.spawn(()).id();
-        let mut passives = Passives::default();
+        let mut passives = Passives::new(passive_defs.ids().cloned());
         // uncomment for testing
         // passives.gain(Passive::HeavyBoots);
         commands.entity(e_gent).insert((
@@ -394,10 +410,11 @@ fn setup_player(
                         direction: Direction2d::NEG_Y,
                         interaction: InteractionGroups {
                             memberships: PLAYER,
-                            filter: GROUND,
+                            filter: GROUND | ONE_WAY,
                         },
                     },
                     linear_velocity: LinearVelocity(Vec2::ZERO),
+                    interpolation: Default::default(),
                 },
                 coyote_time: Default::default(),
             },
@@ -406,7 +423,7 @@ fn setup_player(
                 current: config.max_health,
                 max: config.max_health,
             },
-            PlayerAction::input_manager_bundle(),
+            PlayerAction::input_manager_bundle(&keybindings),
             // bundling things up because we reached max tuple
             (
                 Falling,
@@ -419,14 +436,15 @@ fn setup_player(
                 },
             ),
             (
-                PlayerStats::init_from_config(&config),
-                // maybe consolidate with PlayerStats
+                Stats::new(config.base_stats()),
+                StatusModifiers::default(),
+                // maybe consolidate with Stats
                 PlayerStatMod::new(),
                 EnemiesNearby(0),
             ),
             WallSlideTime(f32::MAX),
             HitFreezeTime(u32::MAX, None),
-            JumpCount(0),
+            (JumpCount(0), InputBuffer::default()),
             WhirlAbility::default(),
             Crits::new(2.0),
             TransitionQueue::default(),
@@ -434,7 +452,8 @@ fn setup_player(
             (passives, BuffTick::default()),
         ));
         // unparent from the level
-        if let Ok(parent) = parent_query.get(parent.get()) {
+        // (may not have one, if spawned manually rather than from LDtk)
+        if let Some(Ok(parent)) = parent.map(|p| parent_query.get(p.get())) {
             commands.entity(parent).remove_children(&[e_gent]);
         }
         commands.entity(e_gfx).insert((PlayerGfxBundle {
@@ -524,17 +543,28 @@ impl Transitionable<Falling> for Grounded {
     type Removals = (Grounded, Idle, Running, Whirling);
 }
 
+/// The attack lasts until its animation opens the cancel window.
+/// Cooldown reduction plays the animation faster, so the windows open sooner.
 #[derive(Component, Debug, Default)]
 #[component(storage = "SparseSet")]
 pub struct Attacking {
     pub ticks: u32,
     followup: bool,
+    /// How many attacks into the weapon's combo chain this one is
+    pub combo: usize,
+    /// Held as the attack started, for directional variants
+    pub direction: Option<AttackDirection>,
 }
 impl Attacking {
-    pub const MAX: u32 = 4;
-    // pub const MAX: u32 = 4;
-    // minimum amount of frames that should be played from attack animation
-    pub const MIN: u32 = 2;
+    /// Ticks after which the attack ends anyway, in case its animation
+    /// never opens the cancel window
+    pub const TIMEOUT: u32 = 96;
+
+    /// Has the attack gone on for longer than [`Attacking::TIMEOUT`],
+    /// shortened by cooldown reduction like the animation
+    pub fn timed_out(&self, cdr: f32) -> bool {
+        self.ticks as f32 * cdr >= Attacking::TIMEOUT as f32
+    }
 }
 impl GentState for Attacking {}
 
@@ -546,6 +576,8 @@ impl Transitionable<CanAttack> for Attacking {
 #[component(storage = "SparseSet")]
 pub struct CanAttack {
     pub immediate: bool,
+    /// Combo chain position of the attack that follows immediately
+    pub combo: usize,
 }
 impl GentState for CanAttack {}
 
@@ -740,6 +772,75 @@ impl JumpCount {
     }
 }
 
+/// Remembers recent presses of some actions for a few ticks
+///
+/// This way, a press that comes slightly too early (such as jumping just
+/// before landing, or attacking before the previous attack has ended)
+/// still happens once the current state allows it. Whoever acts on a
+/// buffered press must [`consume`](Self::consume) it, so that one press
+/// only triggers the action once.
+#[derive(Component, Default, Debug)]
+pub struct InputBuffer {
+    /// How many ticks ago each (not yet consumed) press happened
+    presses: HashMap<PlayerAction, u32>,
+}
+
+impl InputBuffer {
+    /// The actions that are buffered
+    pub const ACTIONS: [PlayerAction; 3] =
+        [PlayerAction::Jump, PlayerAction::Dash, PlayerAction::Attack];
+
+    /// Age the presses by one tick, forgetting those older than their window
+    fn tick(&mut self, window: impl Fn(PlayerAction) -> u32) {
+        self.presses.retain(|action, age| {
+            *age += 1;
+            *age <= window(*action)
+        });
+    }
+
+    fn press(&mut self, action: PlayerAction) {
+        self.presses.insert(action, 0);
+    }
+
+    /// Was the action pressed recently (and not consumed yet)?
+    pub fn pressed(&self, action: PlayerAction) -> bool {
+        self.presses.contains_key(&action)
+    }
+
+    /// Use up a buffered press; returns whether there was one
+    pub fn consume(&mut self, action: PlayerAction) -> bool {
+        self.presses.remove(&action).is_some()
+    }
+}
+
+fn buffer_player_inputs(
+    mut query: Query<
+        (
+            &mut InputBuffer,
+            &ActionState<PlayerAction>,
+        ),
+        With<Player>,
+    >,
+    config: Res<PlayerConfig>,
+) {
+    for (mut buffer, action_state) in query.iter_mut() {
+        buffer.tick(|action| config.input_buffer_ticks(action));
+        for action in InputBuffer::ACTIONS {
+            if action_state.just_pressed(&action) {
+                buffer.press(action);
+            }
+        }
+    }
+}
+
+/// The player is dropping through a one-way platform
+///
+/// While present, one-way platforms are left out of the player's collision
+/// filters. Counts down the remaining ticks.
+#[derive(Component, Debug)]
+#[component(storage = "SparseSet")]
+pub struct DropThrough(pub u32);
+
 /// Indicates that sliding is tracked for this entity
 #[derive(Component, Default, Debug)]
 pub struct WallSlideTime(f32);
@@ -768,381 +869,166 @@ pub struct WhirlAbility {
     pub energy: f32,
 }
 
-#[derive(Resource, Debug, Default)]
-pub struct PlayerConfig {
-    /// The maximum horizontal velocity the player can move at.
-    ///
-    /// (in pixels/second)
-    max_move_vel: f32,
+typed_config! {
+    /// Player tuning values, loaded from `player.cfg.toml`
+    #[derive(Resource, Debug)]
+    pub struct PlayerConfig {
+        /// The maximum horizontal velocity the player can move at.
+        ///
+        /// (in pixels/second)
+        max_move_vel: f32 = 70.0,
 
-    /// The maximum horizontal velocity the player can move at while using the Hammer weapon.
-    ///
-    /// (in pixels/second)
-    hammer_max_move_vel: f32,
+        /// The maximum downward velocity the player can fall at.
+        ///
+        /// (in pixels/second)
+        max_fall_vel: f32 = 180.0,
 
-    /// The maximum downward velocity the player can fall at.
-    ///
-    /// (in pixels/second)
-    max_fall_vel: f32,
+        /// The initial acceleration applied to the player for the first tick they start moving.
+        ///
+        /// (in pixels/second^2)
+        move_accel_init: f32 = 20.0,
 
-    /// The initial acceleration applied to the player for the first tick they start moving.
-    ///
-    /// (in pixels/second^2)
-    move_accel_init: f32,
+        /// The acceleration applied to the player while they continue moving horizontally.
+        ///
+        /// (in pixels/second^2)
+        move_accel: f32 = 20.0,
 
-    /// The acceleration applied to the player while they continue moving horizontally.
-    ///
-    /// (in pixels/second^2)
-    move_accel: f32,
+        /// How much velocity does the player have at the moment they jump?
+        ///
+        /// (in pixels/second)
+        jump_vel_init: f32 = 155.0,
 
-    /// How much velocity does the player have at the moment they jump?
-    ///
-    /// (in pixels/second)
-    jump_vel_init: f32,
+        /// How fast does the player accelerate downward while holding down the jump button?
+        ///
+        /// (in pixels/tick^2)
+        jump_fall_accel: f32 = 3.5,
 
-    /// How fast does the player accelerate downward while holding down the jump button?
-    ///
-    /// (in pixels/tick^2)
-    jump_fall_accel: f32,
+        /// How fast does the player accelerate downward while in the falling state?
+        /// (ie: after releasing the jump key)
+        ///
+        /// (in pixels/tick^2)
+        /// Note: sets the games global_gravity! (affects projectiles and other things that fall)
+        pub fall_accel: f32 = 4.5,
 
-    /// How fast does the player accelerate downward while in the falling state?
-    /// (ie: after releasing the jump key)
-    ///
-    /// (in pixels/tick^2)
-    /// Note: sets the games global_gravity! (affects projectiles and other things that fall)
-    pub fall_accel: f32,
+        /// How many seconds does our characters innate hover boots work?
+        max_coyote_time: f32 [0.0..] = 0.1,
 
-    /// How many seconds does our characters innate hover boots work?
-    max_coyote_time: f32,
+        /// For how many ticks is a jump press remembered, if the player
+        /// cannot jump at the moment? (ie: pressing jump just before landing)
+        jump_buffer_ticks: u32 = 6,
 
-    /// Only applies in the downward y direction while the player is falling
-    /// and trying to walk into the wall
-    sliding_friction: f32,
+        /// For how many ticks is a dash press remembered, if the dash
+        /// is still on cooldown?
+        dash_buffer_ticks: u32 = 4,
 
-    /// How many ticks is the players velocity locked to zero after landing an attack?
-    hitfreeze_ticks: u32,
+        /// For how many ticks is an attack press remembered, if the player
+        /// is still busy with the previous attack?
+        attack_buffer_ticks: u32 = 8,
 
-    /// How many seconds does our character dash for?
-    dash_duration: f32,
+        /// For how many ticks does the player ignore one-way platforms,
+        /// after dropping through one?
+        drop_through_ticks: u32 = 16,
 
-    /// How many seconds does our character dash for?
-    dash_down_duration: f32,
+        /// Only applies in the downward y direction while the player is falling
+        /// and trying to walk into the wall
+        sliding_friction: f32 = 0.25,
 
-    /// How many seconds does our character stealth for?
-    stealth_duration: f32,
+        /// How many ticks is the players velocity locked to zero after landing an attack?
+        hitfreeze_ticks: u32 = 45,
 
-    /// How many seconds does our character stealth for?
-    pub stealth_cooldown: f32,
+        /// How many seconds does our character dash for?
+        dash_duration: f32 [0.0..] = 0.06,
 
-    /// How many pixels/s do they dash with?
-    dash_velocity: f32,
+        /// How many seconds does our character dash for?
+        dash_down_duration: f32 [0.0..] = 0.3,
 
-    /// How many pixels/s (horizontally) do they dash with when doing a downward dash?
-    dash_down_horizontal_velocity: f32,
+        /// How many seconds does our character stealth for?
+        stealth_duration: f32 [0.0..] = 3.0,
 
-    /// How many pixels/s (vertically) do they dash with when doing a downward dash?
-    dash_down_vertical_velocity: f32,
+        /// How many seconds does our character stealth for?
+        pub stealth_cooldown: f32 [0.0..] = 5.0,
 
-    /// How long before the player can dash again?
-    pub dash_cooldown_duration: f32,
+        /// How many pixels/s do they dash with?
+        dash_velocity: f32 = 400.0,
 
-    /// How long before the player can dash again?
-    pub dash_down_cooldown_duration: f32,
+        /// How many pixels/s (horizontally) do they dash with when doing a downward dash?
+        dash_down_horizontal_velocity: f32 = 500.0,
 
-    pub max_whirl_energy: f32,
+        /// How many pixels/s (vertically) do they dash with when doing a downward dash?
+        dash_down_vertical_velocity: f32 = 500.0,
 
-    /// Spends this much energy per second when using whirl
-    whirl_cost: f32,
+        /// How long before the player can dash again?
+        pub dash_cooldown_duration: f32 [0.0..] = 1.0,
 
-    /// Spends this much energy per second when not using whirl
-    whirl_regen: f32,
+        /// How long before the player can dash again?
+        pub dash_down_cooldown_duration: f32 [0.0..] = 5.0,
 
-    /// How much max health the player has
-    pub max_health: u32,
+        pub max_whirl_energy: f32 = 2.0,
 
-    /// Pushback velocity on wall jumps
-    wall_pushback: f32,
+        /// Spends this much energy per second when using whirl
+        whirl_cost: f32 = 2.0,
 
-    /// Ticks for wall pushback velocity; determines how long movement is locked for
-    wall_pushback_ticks: u32,
+        /// Spends this much energy per second when not using whirl
+        whirl_regen: f32 = 0.2,
 
-    /// Self pushback velocity on basic sword hits
-    sword_self_pushback: f32,
+        /// How much max health the player has
+        pub max_health: u32 [1..] = 120,
 
-    /// Ticks for sword self pushback velocity; determines how long movement is locked for
-    sword_self_pushback_ticks: u32,
+        /// Pushback velocity on wall jumps
+        wall_pushback: f32 = 50.0,
 
-    /// Knockback velocity applied to enemy on basic sword hit
-    sword_pushback: f32,
+        /// Ticks for wall pushback velocity; determines how long movement is locked for
+        wall_pushback_ticks: u32 = 5,
 
-    /// Ticks for sword knockback velocity; determines how long movement is locked for
-    sword_pushback_ticks: u32,
+        /// Default strength for on hit camera screen shake
+        pub default_on_hit_screenshake_strength: f32 = 0.9,
+        /// Default duration (in seconds) for on hit camera screen shake
+        pub default_on_hit_screenshake_duration_secs: f32 [0.0..] = 0.1,
+        /// Default frequency for on hit camera screen shake
+        pub default_on_hit_screenshake_frequency: f32 = 2.0,
 
-    /// Self pushback velocity on basic hammer hits
-    hammer_self_pushback: f32,
-
-    /// Ticks for hammer self pushback velocity; determines how long movement is locked for
-    hammer_self_pushback_ticks: u32,
-
-    /// Knockback velocity applied to enemy on basic hammer hit
-    hammer_pushback: f32,
-
-    /// Ticks for hammer knockback velocity; determines how long movement is locked for
-    hammer_pushback_ticks: u32,
-
-    /// Pushback velocity on basic bow shots
-    bow_self_pushback: f32,
-
-    /// Ticks for bow pushback velocity; determines how long movement is locked for
-    bow_self_pushback_ticks: u32,
-
-    /// Knockback velocity applied to enemy on basic bow hit
-    bow_pushback: f32,
-
-    /// Ticks for melee knockback velocity; determines how long movement is locked for
-    bow_pushback_ticks: u32,
-
-    /// Base sword attack damage
-    sword_attack_damage: f32,
-
-    /// Base hammer attack damage
-    hammer_attack_damage: f32,
-
-    /// Base bow attack damage
-    bow_attack_damage: f32,
-
-    /// Velocity of the projectiles fired by the Bow weapon
-    arrow_velocity: f32,
-
-    /// Default strength for on hit camera screen shake
-    pub default_on_hit_screenshake_strength: f32,
-    /// Default duration (in seconds) for on hit camera screen shake
-    pub default_on_hit_screenshake_duration_secs: f32,
-    /// Default frequency for on hit camera screen shake
-    pub default_on_hit_screenshake_frequency: f32,
-
-    /// Hammer-specific strength for on hit camera screen shake
-    pub hammer_on_hit_screenshake_strength: f32,
-    /// Hammer-specific duration (in seconds) for on hit camera screen shake
-    pub hammer_on_hit_screenshake_duration_secs: f32,
-    /// Hammer-specific frequency for on hit camera screen shake
-    pub hammer_on_hit_screenshake_frequency: f32,
-
-    /// How many kills to trigger a passive gain
-    passive_gain_rate: u32,
-}
-
-fn load_player_config(
-    mut ev_asset: EventReader<AssetEvent<DynamicConfig>>,
-    cfgs: Res<Assets<DynamicConfig>>,
-    preloaded: Res<PreloadedAssets>,
-    mut player_config: ResMut<PlayerConfig>,
-    mut initialized_config: Local<bool>,
-) {
-    // convert from asset key string to bevy handle
-    let Some(cfg_handle) =
-        preloaded.get_single_asset::<DynamicConfig>("cfg.player")
-    else {
-        return;
-    };
-    // The reason we do this here instead of in an AssetEvent::Added match arm, is because
-    // the Added match arm fires before preloaded updates with the asset key; as a result
-    // you can't tell what specific DynamicConfig loaded in like that.
-    if !*initialized_config {
-        if let Some(cfg) = cfgs.get(cfg_handle.clone()) {
-            update_player_config(&mut player_config, cfg);
-        }
-        *initialized_config = true;
-    }
-    for ev in ev_asset.read() {
-        if let AssetEvent::Modified { id } = ev {
-            if let Some(cfg) = cfgs.get(*id) {
-                if cfg_handle.id() == *id {
-                    update_player_config(&mut player_config, cfg);
-                }
-            }
-        }
-    }
-}
-
-#[rustfmt::skip]
-fn update_player_config(config: &mut PlayerConfig, cfg: &DynamicConfig) {
-    let mut errors = Vec::new();
-    update_field(&mut errors, &cfg.0, "max_move_vel", |val| config.max_move_vel = val);
-    update_field(&mut errors, &cfg.0, "hammer_max_move_vel", |val| config.hammer_max_move_vel = val);
-    update_field(&mut errors, &cfg.0, "max_fall_vel", |val| config.max_fall_vel = val);
-    update_field(&mut errors, &cfg.0, "move_accel_init", |val| config.move_accel_init = val);
-    update_field(&mut errors, &cfg.0, "move_accel", |val| config.move_accel = val);
-    update_field(&mut errors, &cfg.0, "jump_vel_init", |val| config.jump_vel_init = val);
-    update_field(&mut errors, &cfg.0, "jump_fall_accel", |val| config.jump_fall_accel = val);
-    update_field(&mut errors, &cfg.0, "fall_accel", |val| config.fall_accel = val);
-    update_field(&mut errors, &cfg.0, "max_coyote_time", |val| config.max_coyote_time = val);
-    update_field(&mut errors, &cfg.0, "sliding_friction", |val| config.sliding_friction = val);
-    update_field(&mut errors, &cfg.0, "hitfreeze_ticks", |val| config.hitfreeze_ticks = val as u32);
-    update_field(&mut errors, &cfg.0, "dash_duration", |val| config.dash_duration = val);
-    update_field(&mut errors, &cfg.0, "dash_down_duration", |val| config.dash_down_duration = val);
-    update_field(&mut errors, &cfg.0, "dash_velocity", |val| config.dash_velocity = val);
-    update_field(&mut errors, &cfg.0, "dash_down_horizontal_velocity", |val| config.dash_down_horizontal_velocity = val);
-    update_field(&mut errors, &cfg.0, "dash_down_vertical_velocity", |val| config.dash_down_vertical_velocity = val);
-    update_field(&mut errors, &cfg.0, "dash_cooldown_duration", |val| config.dash_cooldown_duration = val);
-    update_field(&mut errors, &cfg.0, "dash_down_cooldown_duration", |val| config.dash_down_cooldown_duration = val);
-    update_field(&mut errors, &cfg.0, "stealth_duration", |val| config.stealth_duration = val);
-    update_field(&mut errors, &cfg.0, "stealth_cooldown", |val| config.stealth_cooldown = val);
-    update_field(&mut errors, &cfg.0, "max_whirl_energy", |val| config.max_whirl_energy = val);
-    update_field(&mut errors, &cfg.0, "whirl_cost", |val| config.whirl_cost = val);
-    update_field(&mut errors, &cfg.0, "whirl_regen", |val| config.whirl_regen = val);
-    update_field(&mut errors, &cfg.0, "max_health", |val| config.max_health = val as u32);
-    update_field(&mut errors, &cfg.0, "wall_pushback", |val| config.wall_pushback = val);
-    update_field(&mut errors, &cfg.0, "wall_pushback_ticks", |val| config.wall_pushback_ticks = val as u32);
-    update_field(&mut errors, &cfg.0, "sword_self_pushback", |val| config.sword_self_pushback = val);
-    update_field(&mut errors, &cfg.0, "sword_self_pushback_ticks", |val| config.sword_self_pushback_ticks = val as u32);
-    update_field(&mut errors, &cfg.0, "sword_pushback", |val| config.sword_pushback = val);
-    update_field(&mut errors, &cfg.0, "sword_pushback_ticks", |val| config.sword_pushback_ticks = val as u32);
-    update_field(&mut errors, &cfg.0, "hammer_self_pushback", |val| config.hammer_self_pushback = val);
-    update_field(&mut errors, &cfg.0, "hammer_self_pushback_ticks", |val| config.hammer_self_pushback_ticks = val as u32);
-    update_field(&mut errors, &cfg.0, "hammer_pushback", |val| config.hammer_pushback = val);
-    update_field(&mut errors, &cfg.0, "hammer_pushback_ticks", |val| config.hammer_pushback_ticks = val as u32);
-    update_field(&mut errors, &cfg.0, "sword_attack_damage", |val| config.sword_attack_damage = val);
-    update_field(&mut errors, &cfg.0, "hammer_attack_damage", |val| config.hammer_attack_damage = val);
-    update_field(&mut errors, &cfg.0, "bow_attack_damage", |val| config.bow_attack_damage = val);
-    update_field(&mut errors, &cfg.0, "bow_self_pushback", |val| config.bow_self_pushback = val);
-    update_field(&mut errors, &cfg.0, "bow_self_pushback_ticks", |val| config.bow_self_pushback_ticks = val as u32);
-    update_field(&mut errors, &cfg.0, "bow_pushback", |val| config.bow_pushback = val);
-    update_field(&mut errors, &cfg.0, "bow_pushback_ticks", |val| config.bow_pushback_ticks = val as u32);
-    update_field(&mut errors, &cfg.0, "arrow_velocity", |val| config.arrow_velocity = val);
-    update_field(&mut errors, &cfg.0, "default_on_hit_screenshake_strength", |val| config.default_on_hit_screenshake_strength = val);
-    update_field(&mut errors, &cfg.0, "default_on_hit_screenshake_duration_secs", |val| config.default_on_hit_screenshake_duration_secs = val);
-    update_field(&mut errors, &cfg.0, "default_on_hit_screenshake_frequency", |val| config.default_on_hit_screenshake_frequency = val);
-    update_field(&mut errors, &cfg.0, "hammer_on_hit_screenshake_strength", |val| config.hammer_on_hit_screenshake_strength = val);
-    update_field(&mut errors, &cfg.0, "hammer_on_hit_screenshake_duration_secs", |val| config.hammer_on_hit_screenshake_duration_secs = val);
-    update_field(&mut errors, &cfg.0, "hammer_on_hit_screenshake_frequency", |val| config.hammer_on_hit_screenshake_frequency = val);
-    update_field(&mut errors, &cfg.0, "passive_gain_rate", |val| config.passive_gain_rate = val as u32);
-
-    for error in errors{
-       warn!("failed to load player cfg value: {}", error);
-   }
-}
-
-fn load_player_stats(
-    player_config: Res<PlayerConfig>,
-    mut stat_q: Query<&mut PlayerStats>,
-) {
-    stat_q.iter_mut().for_each(|mut stats| {
-        *stats = PlayerStats::init_from_config(&player_config);
-    });
-}
-
-/// Extend with additional parameter Stats
-#[allow(clippy::enum_variant_names)]
-#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
-pub enum StatType {
-    MoveVelMax,
-    MoveAccelInit,
-    MoveAccel,
-}
-/// For now, Status Modifier is implemented so that only one Status Modifier is active at a time.
-/// However, a single Status Modifier can modify multiple Stats.
-/// scalar and delta will use the same coefficient for all Stats if there is only one.
-#[derive(Component, Clone, Debug)]
-pub struct StatusModifier {
-    status_types: Vec<StatType>,
-
-    /// Multiplying Factor on Stat, e.g. 102.0 * 0.5 = 51.0
-    scalar: Vec<f32>,
-    /// Offsetting Value on Stat, e.g. 100.0 - 10.0 = 90.0
-    delta: Vec<f32>,
-
-    effect_col: Color,
-
-    time_remaining: f32,
-}
-
-// TODO: move to attack
-impl StatusModifier {
-    pub fn basic_ice_spider() -> Self {
-        Self {
-            status_types: vec![
-                StatType::MoveVelMax,
-                StatType::MoveAccel,
-                StatType::MoveAccelInit,
-            ],
-            scalar: vec![0.5],
-            delta: vec![],
-            //            effect_col: Color::hex("C2C9C9").unwrap(),
-            effect_col: Color::hex("7aa7ff").unwrap(), /* For More Visible Effect */
-            time_remaining: 2.0,
-        }
+        /// How many kills to trigger a passive gain
+        passive_gain_rate: u32 = 10,
     }
 }
 
-#[derive(Component)]
-pub struct PlayerStats {
-    pub base_stats: HashMap<StatType, f32>,
-    pub effective_stats: HashMap<StatType, f32>,
+impl TypedConfig for PlayerConfig {
+    const KEY: &'static str = "cfg.player";
 }
 
-impl PlayerStats {
-    pub fn init_from_config(config: &PlayerConfig) -> Self {
-        let stats = HashMap::from_iter(vec![
-            (
-                StatType::MoveVelMax,
-                config.max_move_vel,
-            ),
-            (StatType::MoveAccel, config.move_accel),
+impl PlayerConfig {
+    /// Base values of the player's [`Stats`]
+    pub fn base_stats(&self) -> [(StatType, f32); 3] {
+        [
+            (StatType::MoveVelMax, self.max_move_vel),
+            (StatType::MoveAccel, self.move_accel),
             (
                 StatType::MoveAccelInit,
-                config.move_accel_init,
+                self.move_accel_init,
             ),
-        ]);
-
-        Self {
-            base_stats: stats.clone(),
-            effective_stats: stats,
-        }
-    }
-
-    pub fn get(&self, stat: StatType) -> f32 {
-        self.effective_stats[&stat]
+        ]
     }
 
-    pub fn set(&mut self, stat: StatType, value: f32) {
-        if let Some(effective_stat) = self.effective_stats.get_mut(&stat) {
-            *effective_stat = value;
-        }
-    }
-
-    pub fn reset_stat(&mut self, stat: StatType) {
-        if let Some(effective_stat) = self.effective_stats.get_mut(&stat) {
-            *effective_stat = self.base_stats[&stat];
+    /// How many ticks a press of the action stays in the [`InputBuffer`]
+    ///
+    /// Actions that are not buffered only count on the tick they are pressed.
+    pub fn input_buffer_ticks(&self, action: PlayerAction) -> u32 {
+        match action {
+            PlayerAction::Jump => self.jump_buffer_ticks,
+            PlayerAction::Dash => self.dash_buffer_ticks,
+            PlayerAction::Attack => self.attack_buffer_ticks,
+            _ => 0,
         }
     }
+}
 
-    pub fn reset_stats(&mut self) {
-        self.effective_stats = self.base_stats.clone();
-    }
-
-    pub fn update_stats(&mut self, modifier: &StatusModifier) {
-        self.effective_stats.clear();
-
-        let base_scalar = match modifier.scalar.len() {
-            0 => Some(1.0),
-            1 => Some(modifier.scalar[0]),
-            _ => None,
-        };
-        let base_delta = match modifier.delta.len() {
-            0 => Some(0.0),
-            1 => Some(modifier.delta[0]),
-            _ => None,
-        };
-
-        for (i, stat) in modifier.status_types.iter().enumerate() {
-            let val = self.base_stats[stat]
-                * base_scalar.unwrap_or_else(|| modifier.scalar[i])
-                + base_delta.unwrap_or_else(|| modifier.delta[i]);
-
-            self.effective_stats.insert(*stat, val);
-        }
-    }
+fn load_player_stats(
+    player_config: Res<PlayerConfig>,
+    mut stat_q: Query<&mut Stats, With<Player>>,
+) {
+    stat_q.iter_mut().for_each(|mut stats| {
+        stats.set_base_stats(player_config.base_stats());
+    });
 }
 
 #[derive(Component, Deref, DerefMut)]
@@ -1183,8 +1069,10 @@ fn player_update_passive_buffs(
         Option<&Idle>,
         Option<&Running>,
         Option<&Jumping>,
+        Option<&ShopUpgrades>,
     )>,
     enemy_q: Query<&GlobalTransform, With<Enemy>>,
+    passive_defs: Res<PassiveDefs>,
 ) {
     for (
         passives,
@@ -1199,44 +1087,29 @@ fn player_update_passive_buffs(
         idle,
         running,
         jumping,
+        upgrades,
     ) in query.iter_mut()
     {
-        let mut attack = 1.;
-        let mut defense = 1.;
-        let mut speed = 1.;
-        let mut cdr = 1.;
+        let mut multipliers = passive_defs.stat_multipliers(
+            passives.iter(),
+            &PassiveContext {
+                airborne: grounded.is_none(),
+                moving: vel.0.length() > 0.0001,
+                enemies_nearby: enemies_nearby.0,
+            },
+        );
+        if let Some(upgrades) = upgrades {
+            multipliers.stack(&upgrades.stats, 1.0);
+        }
+        let mut attack = multipliers.attack;
+        let defense = multipliers.defense;
+        let speed = multipliers.speed;
+        let mut cdr = multipliers.cdr;
         let mut sharpshooter_mult = stat_mod.sharpshooter_multiplier;
 
-        if passives.contains(&Passive::GlowingShard) {
-            defense *= 1. + 1. * enemies_nearby.0 as f32;
-        }
-        if passives.contains(&Passive::SerpentRing) {
-            speed *= 1.2;
-            cdr *= 1.33;
-        }
-        if passives.contains(&Passive::RabbitsFoot) {
-            speed *= 1.2;
-        }
-        if passives.contains(&Passive::HeavyBoots) {
-            if vel.0.length() > 0.0001 {
-                attack *= 0.5;
-                defense *= 0.5;
-            } else {
-                attack *= 2.;
-                defense *= 2.5;
-            }
-        }
         if passives.contains(&Passive::FrenziedAttack) {
             cdr *= 1. + (0.1 * buff_tick.stacks as f32).min(1.);
         }
-        if passives.contains(&Passive::DeadlyFeather) {
-            if grounded.is_none() {
-                attack *= 1.5;
-                cdr *= 1.3;
-            } else {
-                defense *= 0.5;
-            }
-        }
         if is_stealth {
             attack *= 2.;
         }
@@ -1297,42 +1170,6 @@ fn player_update_passive_buffs(
     }
 }
 
-fn player_update_stats_mod(
-    mut query: Query<(
-        Entity,
-        &mut StatusModifier,
-        &mut PlayerStats,
-    )>,
-    mut gfx_query: Query<(&PlayerGfx, &mut Sprite)>,
-    // TODO: switch to ticks
-    time: Res<Time<Virtual>>,
-    mut commands: Commands,
-) {
-    for (p_gfx, mut sprite) in gfx_query.iter_mut() {
-        let Ok((entity, mut modifier, mut player_stats)) =
-            query.get_mut(p_gfx.e_gent)
-        else {
-            return;
-        };
-
-        if modifier.is_changed() {
-            player_stats.update_stats(&modifier);
-        }
-
-        sprite.color = modifier.effect_col;
-
-        // TODO: switch to ticks
-        modifier.time_remaining -= time.delta_seconds();
-
-        if modifier.time_remaining < 0. {
-            commands.entity(entity).remove::<StatusModifier>();
-            player_stats.reset_stats();
-
-            sprite.color = Color::WHITE;
-        }
-    }
-}
-
 #[derive(Component)]
 pub struct DashIcon {
     time: f32,
@@ -1462,38 +1299,6 @@ pub fn dash_icon_fx(
     }
 }
 
-pub fn on_crit_cooldown_reduce(
-    attack_query: Query<&Attack, (With<Crit>, Added<Hit>)>,
-    mut attacker_query: Query<(
-        &Passives,
-        Option<&mut CanDash>,
-        Option<&mut WhirlAbility>,
-        Option<&mut CanStealth>,
-    )>,
-) {
-    for attack in attack_query.iter() {
-        if let Ok((
-            passives,
-            mut maybe_can_dash,
-            mut maybe_whirl_ability,
-            mut maybe_can_stealth,
-        )) = attacker_query.get_mut(attack.attacker)
-        {
-            if passives.contains(&Passive::ObsidianNecklace) {
-                if let Some(ref mut can_dash) = maybe_can_dash {
-                    can_dash.remaining_cooldown -= 0.5;
-                }
-                if let Some(ref mut whirl_ability) = maybe_whirl_ability {
-                    whirl_ability.energy += 0.5;
-                }
-                if let Some(ref mut can_stealth) = maybe_can_stealth {
-                    can_stealth.remaining_cooldown -= 0.5;
-                }
-            }
-        }
-    }
-}
-
 /// Resets the players cooldowns/energy on hit of a stealthed critical hit
 pub fn on_stealth_hit_cooldown_reset(
     query: Query<&Attack, (Added<Hit>, With<Crit>, With<Stealthed>)>,
@@ -1544,19 +1349,6 @@ pub fn on_hit_exit_stealthing(
     }
 }
 
-pub fn on_xp_heal(
-    mut query: Query<(&Passives, &mut Health), With<Player>>,
-    mut xp_event: EventReader<XpOrbPickup>,
-) {
-    if let Ok((passives, mut health)) = query.get_single_mut() {
-        if passives.contains(&Passive::Bloodstone) {
-            for _event in xp_event.read() {
-                health.current = (health.current + 2).min(health.max);
-            }
-        }
-    }
-}
-
 #[derive(Default, Component)]
 pub struct BuffTick {
     pub falloff: u32,
@@ -1597,37 +1389,29 @@ fn track_hits(
     }
 }
 
-//on gain passive event?
-fn update_serpentring_health(
-    mut query: Query<(&mut Health, &Passives), With<Player>>,
+/// Keep the max health at its base value plus bought upgrades, halved
+/// while holding the Serpent Ring
+fn update_max_health(
+    mut query: Query<
+        (
+            &mut Health,
+            &Passives,
+            Option<&ShopUpgrades>,
+        ),
+        With<Player>,
+    >,
     config: Res<PlayerConfig>,
 ) {
-    if let Ok((mut health, passives)) = query.get_single_mut() {
+    if let Ok((mut health, passives, upgrades)) = query.get_single_mut() {
+        let bonus = upgrades.map(|u| u.max_health).unwrap_or(0);
+        let mut new_max = config.max_health + bonus;
         if passives.contains(&Passive::SerpentRing) {
-            // Halve the max health when grounded.
-            let new_max = ((config.max_health as f32) / 2.0) as u32;
-            health.max = new_max;
-            // Ensure current health does not exceed the new maximum.
-            if health.current > new_max {
-                health.current = new_max;
-            }
+            new_max /= 2;
         }
-    }
-}
-
-/// When an attack hit is critical, heal the attacker if they have CriticalRegeneration.
-pub fn on_crit_heal(
-    attack_query: Query<&Attack, (With<Crit>, Added<Hit>)>,
-    mut player_query: Query<(&Passives, &mut Health), With<Player>>,
-) {
-    for attack in attack_query.iter() {
-        if let Ok((passives, mut health)) =
-            player_query.get_mut(attack.attacker)
-        {
-            if passives.contains(&Passive::CriticalRegeneration) {
-                // Heal 2 points, capped at player's max health.
-                health.current = (health.current + 24).min(health.max);
-            }
+        health.max = new_max;
+        // Ensure current health does not exceed the new maximum.
+        if health.current > new_max {
+            health.current = new_max;
         }
     }
 }
@@ -1653,3 +1437,99 @@ fn apply_vitality_overclock(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn input_buffer_window() {
+        let window = |action| match action {
+            PlayerAction::Jump => 2,
+            _ => 0,
+        };
+        let mut buffer = InputBuffer::default();
+        buffer.press(PlayerAction::Jump);
+        buffer.press(PlayerAction::Dash);
+        assert!(buffer.pressed(PlayerAction::Jump));
+        assert!(buffer.pressed(PlayerAction::Dash));
+        buffer.tick(window);
+        // unbuffered actions only count on the tick they were pressed
+        assert!(!buffer.pressed(PlayerAction::Dash));
+        buffer.tick(window);
+        assert!(buffer.pressed(PlayerAction::Jump));
+        buffer.tick(window);
+        assert!(!buffer.pressed(PlayerAction::Jump));
+
+        buffer.press(PlayerAction::Jump);
+        assert!(buffer.consume(PlayerAction::Jump));
+        assert!(!buffer.consume(PlayerAction::Jump));
+    }
+
+    #[test]
+    fn attack_timeout_scales_with_cdr() {
+        let attacking = |ticks| Attacking {
+            ticks,
+            ..Default::default()
+        };
+        assert!(!attacking(Attacking::TIMEOUT - 1).timed_out(1.0));
+        assert!(attacking(Attacking::TIMEOUT).timed_out(1.0));
+        assert!(attacking(Attacking::TIMEOUT / 2).timed_out(2.0));
+        assert!(!attacking(Attacking::TIMEOUT / 2).timed_out(1.5));
+    }
+
+    #[test]
+    fn passives_replace_and_discard() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut passives = Passives::new([
+            Passive::Bloodstone,
+            Passive::FlamingHeart,
+            Passive::IceDagger,
+            Passive::SerpentRing,
+            Passive::RabbitsFoot,
+        ]);
+        let total = passives.locked.len();
+        assert!(total > Passives::MAX);
+        while !passives.is_full() {
+            let passive = passives.drop_random(&[], &mut rng).unwrap();
+            passives.add_passive(passive);
+        }
+
+        // still drops when full, but picking up does nothing
+        let new = passives.drop_random(&[], &mut rng).unwrap();
+        // left on the ground, so it is still locked but won't drop twice
+        assert!(passives.locked.contains(&new));
+        for _ in 0..10 {
+            assert_ne!(
+                passives.drop_random(&[new.clone()], &mut rng),
+                Some(new.clone())
+            );
+        }
+        passives.add_passive(new.clone());
+        assert!(!passives.contains(&new));
+        assert_eq!(passives.len(), Passives::MAX);
+
+        let old = passives.sorted()[0].clone();
+        assert!(!passives.replace(&new, old.clone()));
+        assert!(passives.replace(&old, new.clone()));
+        assert!(passives.contains(&new));
+        assert!(passives.locked.contains(&old));
+        assert_eq!(
+            passives.len() + passives.locked.len(),
+            total
+        );
+
+        let new = passives.drop_random(&[], &mut rng).unwrap();
+        passives.discard(new.clone());
+        passives.discard(new.clone());
+        assert!(!passives.contains(&new));
+        assert_eq!(
+            passives.locked.iter().filter(|p| **p == new).count(),
+            1
+        );
+        assert_eq!(
+            passives.len() + passives.locked.len(),
+            total
+        );
+    }
+}
@@ -183,9 +183,29 @@ fn player_attacking_animation(
                 .unwrap_or(false);
             player.set_slot("AttackTransition", false);
             player.set_slot("DownwardAttack", false);
-            let basic_air_anim_key_str = &weapon.get_anim_key("BasicAir");
-            let basic_run_anim_key_str = &weapon.get_anim_key("BasicRun");
-            let basic_idle_anim_key_str = &weapon.get_anim_key("BasicIdle");
+            let (
+                Some(basic_air_anim_key_str),
+                Some(basic_run_anim_key_str),
+                Some(basic_idle_anim_key_str),
+            ) = (
+                &weapon.attack_anim_key(&attacking, "Air"),
+                &weapon.attack_anim_key(&attacking, "Run"),
+                &weapon.attack_anim_key(&attacking, "Idle"),
+            )
+            else {
+                continue;
+            };
+            // a new attack restarts its animation, even if it is the same
+            // as the previous attack of the combo
+            let restart = attacking.is_added();
+            if restart {
+                let slot = weapon
+                    .attack(&attacking)
+                    .and_then(|step| step.slot.as_deref());
+                for attack_slot in weapon.attack_slots() {
+                    player.set_slot(attack_slot, Some(attack_slot) == slot);
+                }
+            }
 
             if is_falling || is_jumping {
                 if player_action.pressed(&PlayerAction::Fall) {
@@ -193,20 +213,26 @@ fn player_attacking_animation(
                 }
                 // TODO: These need a way to resume the new animation from the current frame index
                 // or specified offset
-                if player.current_key() != Some(basic_air_anim_key_str) {
+                if restart
+                    || player.current_key() != Some(basic_air_anim_key_str)
+                {
                     player.play_key(basic_air_anim_key_str);
                     if !attacking.is_added() {
                         player.set_slot("AttackTransition", true);
                     }
                 }
             } else if is_running && !hitfrozen {
-                if player.current_key() != Some(basic_run_anim_key_str) {
+                if restart
+                    || player.current_key() != Some(basic_run_anim_key_str)
+                {
                     player.play_key(basic_run_anim_key_str);
                     if !attacking.is_added() {
                         player.set_slot("AttackTransition", true);
                     }
                 }
-            } else if player.current_key() != Some(basic_idle_anim_key_str) {
+            } else if restart
+                || player.current_key() != Some(basic_idle_anim_key_str)
+            {
                 player.play_key(basic_idle_anim_key_str);
                 if !attacking.is_added() {
                     player.set_slot("AttackTransition", true);
@@ -223,7 +249,9 @@ fn player_whirling_animation(
 ) {
     for gent in query.iter() {
         if let Ok(mut player) = gfx_query.get_mut(gent.e_gfx) {
-            player.play_key(&weapon.whirling_anim_key());
+            if let Some(key) = weapon.whirling_anim_key() {
+                player.play_key(key);
+            }
         }
     }
 }
@@ -270,8 +298,9 @@ fn sprite_flip(
             // Have the player face away from the wall if they are attacking while wall sliding
             let pressed_on_wall = wall_slide_time
                 .is_some_and(|s| s.is_pressed_against_wall(&time));
-            let is_attacking_while_falling =
-                player.current_key() == Some(&weapon.get_anim_key("BasicAir"));
+            let is_attacking_while_falling = player
+                .current_key()
+                .is_some_and(|key| weapon.is_air_attack_anim(key));
             if pressed_on_wall && is_attacking_while_falling {
                 facing = match facing {
                     Facing::Right => Facing::Left,
@@ -11,34 +11,39 @@ use theseeker_engine::assets::animation::SpriteAnimation;
 use theseeker_engine::gent::Gent;
 use theseeker_engine::physics::{
     into_vec2, update_sprite_colliders, AnimationCollider, Collider,
-    LinearVelocity, PhysicsWorld, ShapeCaster, ENEMY, ENEMY_HURT, ENEMY_INSIDE,
-    GROUND, PLAYER, PLAYER_ATTACK,
+    ColliderLayer, CollisionSensor, LinearVelocity, PhysicsWorld, ShapeCaster,
+    ENEMY, ENEMY_HURT, ENEMY_INSIDE, GROUND, ONE_WAY, PLAYER, PLAYER_ATTACK,
 };
 use theseeker_engine::script::ScriptPlayer;
 
 use super::arc_attack::{Arrow, Projectile};
 use super::player_weapon::{
-    PlayerCombatStyle, PlayerMeleeWeapon, PushbackValues,
+    AttackDirection, CurrentWeapon, PlayerCombatStyle, PlayerWeapons,
+    PushbackValues, WeaponDefs, CANCEL_WINDOW_SLOT, CHAIN_WINDOW_SLOT,
 };
 use super::{
     dash_icon_fx, player_dash_fx, AttackBundle, CanStealth, DashIcon,
-    DashStrike, DashType, JumpCount, Knockback, Passives, PlayerStats,
-    Pushback, StatType, Stealthing, Whirling,
+    DashStrike, DashType, JumpCount, Knockback, Passives, Pushback, Stealthing,
+    Whirling,
 };
 use crate::game::attack::{
     Attack, DownwardAttack, Hit, SelfPushback, Stealthed,
 };
+use crate::game::dialogue::{DialogueSet, BLOCKED_ACTIONS};
 use crate::game::enemy::Enemy;
 use crate::game::gentstate::{Facing, TransitionQueue, Transitionable};
 use crate::game::pickups::{
-    PassiveDescriptionNode, PassiveEntity, PickupDrop, PickupHint, PickupType,
-    PICKUP_RANGE_SQUARED,
+    CollectedSeeds, PassiveDescriptionNode, PassiveEntity, PickupDrop,
+    PickupHint, PickupType, PICKUP_RANGE_SQUARED,
 };
 use crate::game::player::{
-    Attacking, CanAttack, CanDash, CoyoteTime, Dashing, Falling, Grounded,
-    HitFreezeTime, Idle, Jumping, Player, PlayerAction, PlayerConfig,
-    PlayerGfx, PlayerStateSet, Running, WallSlideTime, WhirlAbility,
+    Attacking, CanAttack, CanDash, CoyoteTime, Dashing, DropThrough, Falling,
+    Grounded, HitFreezeTime, Idle, InputBuffer, Jumping, PassiveChoice,
+    PassiveChoiceOption, Player, PlayerAction, PlayerConfig, PlayerGfx,
+    PlayerStateSet, Running, WallSlideTime, WhirlAbility,
 };
+use crate::game::status::{StatType, Stats};
+use crate::level::LevelDespawnMarker;
 use crate::prelude::*;
 use crate::ui::popup::{PopupTimer, PopupUi};
 use crate::StateDespawnMarker;
@@ -87,9 +92,15 @@ impl Plugin for PlayerBehaviorPlugin {
                         .after(player_dash_fx)
                         .run_if(any_with_component::<DashIcon>),
                     player_grounded.run_if(any_with_component::<Grounded>),
+                    player_drop_through
+                        .before(player_grounded)
+                        .run_if(any_with_component::<DropThrough>),
                     (
                         player_falling,
-                        player_sliding.before(player_jump),
+                        // wall jumps take priority over double jumps
+                        player_sliding
+                            .before(player_falling)
+                            .before(player_jump),
                     )
                         .run_if(any_with_component::<Falling>),
                     reset_player_jump_on_downward_attack.after(player_falling),
@@ -114,6 +125,13 @@ impl Plugin for PlayerBehaviorPlugin {
                 .chain()
                 .run_if(in_state(AppState::InGame)),
         );
+        app.add_systems(
+            GameTickUpdate,
+            player_passive_choice
+                .run_if(resource_exists::<PassiveChoice>)
+                .before(DialogueSet)
+                .run_if(in_state(AppState::InGame)),
+        );
     }
 }
 
@@ -263,7 +281,7 @@ fn player_idle(
 fn player_move(
     mut q_gent: Query<
         (
-            &PlayerStats,
+            &Stats,
             &PlayerStatMod,
             &mut LinearVelocity,
             &ActionState<PlayerAction>,
@@ -467,6 +485,7 @@ pub fn player_can_dash(
     mut q_gent: Query<
         (
             &ActionState<PlayerAction>,
+            &mut InputBuffer,
             &PlayerStatMod,
             &Facing,
             &mut CanDash,
@@ -482,6 +501,7 @@ pub fn player_can_dash(
 ) {
     for (
         action_state,
+        mut input_buffer,
         statmod,
         facing,
         mut can_dash,
@@ -491,18 +511,20 @@ pub fn player_can_dash(
     ) in q_gent.iter_mut()
     {
         can_dash.remaining_cooldown -= statmod.cdr / time.hz as f32;
-        if action_state.just_pressed(&PlayerAction::Dash) {
-            if can_dash.remaining_cooldown <= 0.0 {
-                let dash_action = Dashing::from_action_state(action_state);
-                dash_action.set_player_velocity(&mut velocity, facing, &config);
-                transition_queue.push(CanDash::new_transition(dash_action));
-
-                if let Some(mut hitfreeze) = hitfreeze {
-                    *hitfreeze = HitFreezeTime(u32::MAX, None)
-                }
-            } else {
-                commands.insert_resource(CameraShake::new(2.0, 1.0, 5.0));
+        // a press during the cooldown still dashes if the cooldown ends
+        // within the buffer window
+        if can_dash.remaining_cooldown <= 0.0
+            && input_buffer.consume(PlayerAction::Dash)
+        {
+            let dash_action = Dashing::from_action_state(action_state);
+            dash_action.set_player_velocity(&mut velocity, facing, &config);
+            transition_queue.push(CanDash::new_transition(dash_action));
+
+            if let Some(mut hitfreeze) = hitfreeze {
+                *hitfreeze = HitFreezeTime(u32::MAX, None)
             }
+        } else if action_state.just_pressed(&PlayerAction::Dash) {
+            commands.insert_resource(CameraShake::new(2.0, 1.0, 5.0));
         }
     }
 }
@@ -523,7 +545,7 @@ pub fn player_dash_strike(
     >,
     mut commands: Commands,
     config: Res<PlayerConfig>,
-    melee_weapon: Res<PlayerMeleeWeapon>,
+    weapon: CurrentWeapon,
 ) {
     for (
         entity,
@@ -544,7 +566,11 @@ pub fn player_dash_strike(
                 facing,
                 is_stealthed,
                 player_stat_mod.attack,
-                melee_weapon.pushback_values(&config),
+                weapon
+                    .melee()
+                    .and_then(|melee| melee.combo.first())
+                    .map(|step| step.pushback_values())
+                    .unwrap_or_default(),
             );
         }
         strike.ticks += 1;
@@ -560,7 +586,7 @@ pub fn player_dash_strike(
 pub fn player_dash(
     mut query: Query<
         (
-            &PlayerStats,
+            &Stats,
             &Facing,
             &mut LinearVelocity,
             &mut Dashing,
@@ -666,7 +692,7 @@ fn add_dash_strike_collider(
     let attack = commands
         .spawn((
             TransformBundle::from_transform(Transform::from_xyz(0.0, 0.0, 0.0)),
-            AnimationCollider(gent.e_gfx),
+            AnimationCollider(gent.e_gfx, ColliderLayer::Hit),
             // TODO: ? ColliderMeta
             Collider::empty(InteractionGroups::new(
                 PLAYER_ATTACK,
@@ -702,6 +728,7 @@ pub fn player_collisions(
             Option<&mut Dashing>,
             Has<DashStrike>,
             Option<&mut Whirling>,
+            Has<DropThrough>,
         ),
         With<Player>,
     >,
@@ -719,6 +746,7 @@ pub fn player_collisions(
         mut dashing,
         is_dash_strike,
         whirling,
+        is_dropping,
     ) in q_gent.iter_mut()
     {
         let mut shape = collider.0.shared_shape().clone();
@@ -726,9 +754,10 @@ pub fn player_collisions(
         let mut possible_pos = pos.translation.xy();
         let z = pos.translation.z;
         let mut projected_velocity = linear_velocity.xy();
+        let platforms = if is_dropping { Group::empty() } else { ONE_WAY };
         let mut interaction = InteractionGroups {
             memberships: PLAYER,
-            filter: Group::from_bits_truncate(0b10010),
+            filter: Group::from_bits_truncate(0b10010) | platforms,
         };
 
         let mut wall_slide = false;
@@ -754,7 +783,7 @@ pub fn player_collisions(
                     // ignore enemies/check our ground collision
                     interaction = InteractionGroups {
                         memberships: PLAYER,
-                        filter: GROUND,
+                        filter: GROUND | platforms,
                     };
                     match first_hit.status {
                         // if we are not yet inside the enemy, collide, but not if we are falling
@@ -803,9 +832,17 @@ pub fn player_collisions(
                                     filter: Group::all(),
                                 },
                             );
-                            commands
-                                .entity(enemy)
-                                .insert(crate::game::enemy::Inside);
+                            commands.entity(enemy).insert((
+                                crate::game::enemy::Inside,
+                                // tells us when we stop being inside
+                                CollisionSensor::with_groups(
+                                    InteractionGroups {
+                                        memberships: ENEMY_INSIDE,
+                                        filter: PLAYER,
+                                    },
+                                )
+                                .with_touching(entity),
+                            ));
                         },
                         // maybe failed never happens?
                         TOIStatus::Failed => {
@@ -935,8 +972,9 @@ fn player_grounded(
     mut query: Query<
         (
             Entity,
-            &ShapeCaster,
+            &mut ShapeCaster,
             &ActionState<PlayerAction>,
+            &mut InputBuffer,
             &mut Transform,
             &mut TransitionQueue,
             Option<&mut CoyoteTime>,
@@ -949,6 +987,7 @@ fn player_grounded(
             Without<Dashing>,
         ),
     >,
+    mut commands: Commands,
     time: Res<GameTime>,
     config: Res<PlayerConfig>,
 ) {
@@ -956,8 +995,9 @@ fn player_grounded(
     let max_coyote_time = config.max_coyote_time;
     for (
         entity,
-        ray_cast_info,
+        mut ray_cast_info,
         action_state,
+        mut input_buffer,
         mut position,
         mut transitions,
         coyote_time,
@@ -973,6 +1013,24 @@ fn player_grounded(
         let is_falling = falling_toi.is_some();
         let time_of_impact = falling_toi.map_or(0.0, |x| x.1.toi);
 
+        // drop through a one-way platform we are standing on
+        let on_one_way = ray_cast.is_some_and(|(e, _)| {
+            spatial_query
+                .collision_groups(e)
+                .is_some_and(|groups| groups.memberships.contains(ONE_WAY))
+        });
+        if !is_falling
+            && on_one_way
+            && action_state.just_pressed(&PlayerAction::Fall)
+        {
+            ray_cast_info.interaction.filter.remove(ONE_WAY);
+            commands
+                .entity(entity)
+                .insert(DropThrough(config.drop_through_ticks));
+            transitions.push(Grounded::new_transition(Falling));
+            continue;
+        }
+
         // Adjust position if TOI is nonzero.
         if !is_falling && time_of_impact != 0.0 {
             position.translation.y =
@@ -995,22 +1053,45 @@ fn player_grounded(
             jump_count.0 = 0;
         }
 
-        if action_state.just_pressed(&PlayerAction::Jump) {
-            let allowed_jumps = if passives.contains(&Passive::RabbitsFoot) {
-                3
-            } else {
-                2
-            };
-            if jump_count.0 < allowed_jumps {
-                jump_count.0 += 1;
-                transitions.push(Grounded::new_transition(Jumping));
-            }
+        let allowed_jumps = if passives.contains(&Passive::RabbitsFoot) {
+            3
+        } else {
+            2
+        };
+        // a jump buffered just before landing is used here, on the first
+        // tick after the jump count has been restored
+        if jump_count.0 < allowed_jumps
+            && input_buffer.consume(PlayerAction::Jump)
+        {
+            jump_count.0 += 1;
+            transitions.push(Grounded::new_transition(Jumping));
         } else if is_falling && !in_c_time {
             transitions.push(Grounded::new_transition(Falling));
         }
     }
 }
 
+/// Collide with one-way platforms again, some time after dropping through
+fn player_drop_through(
+    mut query: Query<
+        (
+            Entity,
+            &mut DropThrough,
+            &mut ShapeCaster,
+        ),
+        With<Player>,
+    >,
+    mut commands: Commands,
+) {
+    for (entity, mut drop_through, mut shape_caster) in query.iter_mut() {
+        drop_through.0 = drop_through.0.saturating_sub(1);
+        if drop_through.0 == 0 {
+            shape_caster.interaction.filter.insert(ONE_WAY);
+            commands.entity(entity).remove::<DropThrough>();
+        }
+    }
+}
+
 fn player_falling(
     spatial_query: Res<PhysicsWorld>,
     mut query: Query<
@@ -1019,6 +1100,7 @@ fn player_falling(
             &mut Transform,
             &mut LinearVelocity,
             &ActionState<PlayerAction>,
+            &mut InputBuffer,
             &ShapeCaster,
             &mut TransitionQueue,
             &mut JumpCount,
@@ -1038,6 +1120,7 @@ fn player_falling(
         mut transform,
         mut velocity,
         action_state,
+        mut input_buffer,
         hits,
         mut transitions,
         mut jump_count,
@@ -1066,18 +1149,18 @@ fn player_falling(
             }
         }
         if falling {
-            if action_state.just_pressed(&PlayerAction::Jump) {
-                let allowed_jumps = if passives.contains(&Passive::RabbitsFoot)
-                {
-                    3
-                } else {
-                    2
-                };
-                if jump_count.0 < allowed_jumps {
-                    velocity.y = 0.0;
-                    jump_count.0 += 1;
-                    transitions.push(Falling::new_transition(Jumping));
-                }
+            let allowed_jumps = if passives.contains(&Passive::RabbitsFoot) {
+                3
+            } else {
+                2
+            };
+            // without jumps left, the press stays buffered for landing
+            if jump_count.0 < allowed_jumps
+                && input_buffer.consume(PlayerAction::Jump)
+            {
+                velocity.y = 0.0;
+                jump_count.0 += 1;
+                transitions.push(Falling::new_transition(Jumping));
             }
             if velocity.y > 0.0 {
                 velocity.y /= 1.2;
@@ -1097,6 +1180,7 @@ pub fn player_sliding(
         (
             Entity,
             &ActionState<PlayerAction>,
+            &mut InputBuffer,
             &mut TransitionQueue,
             &mut WallSlideTime,
             &mut LinearVelocity,
@@ -1109,6 +1193,7 @@ pub fn player_sliding(
     for (
         entity,
         action_state,
+        mut input_buffer,
         mut transitions,
         mut wall_slide_time,
         mut lin_vel,
@@ -1123,7 +1208,7 @@ pub fn player_sliding(
             jump_count.0 = 1;
         }
         if wall_slide_time.sliding(&config)
-            && action_state.just_pressed(&PlayerAction::Jump)
+            && input_buffer.consume(PlayerAction::Jump)
         {
             let jump_direction = direction
                 * if wall_slide_time.strict_sliding(&config) {
@@ -1157,6 +1242,8 @@ fn add_attack(
         (
             &mut TransitionQueue,
             &ActionState<PlayerAction>,
+            &mut InputBuffer,
+            &Facing,
             Option<&CanAttack>,
             Option<&WhirlAbility>,
             Has<Grounded>,
@@ -1171,25 +1258,42 @@ fn add_attack(
     >,
     player_config: Res<PlayerConfig>,
     time: Res<GameTime>,
+    weapon: CurrentWeapon,
 ) {
     for (
         mut transitions,
         action_state,
+        mut input_buffer,
+        facing,
         maybe_immediate,
         maybe_whirl_ability,
         is_grounded,
     ) in query.iter_mut()
     {
-        if action_state.just_pressed(&PlayerAction::Attack) {
-            transitions.push(CanAttack::new_transition(
-                Attacking::default(),
-            ));
-        } else if let Some(can_attack) = maybe_immediate {
-            if can_attack.immediate {
-                transitions.push(CanAttack::new_transition(
-                    Attacking::default(),
-                ));
-            }
+        // the direction held picks directional variants of the attack
+        let direction = if action_state.pressed(&PlayerAction::Fall) {
+            Some(AttackDirection::Down)
+        } else if action_state.value(&PlayerAction::Move) * facing.direction()
+            > 0.0
+        {
+            Some(AttackDirection::Forward)
+        } else {
+            None
+        };
+        let pressed = input_buffer.consume(PlayerAction::Attack);
+        let followup =
+            maybe_immediate.filter(|can_attack| can_attack.immediate);
+        if let Some(can_attack) = followup {
+            transitions.push(CanAttack::new_transition(Attacking {
+                combo: can_attack.combo,
+                direction,
+                ..Default::default()
+            }));
+        } else if pressed {
+            transitions.push(CanAttack::new_transition(Attacking {
+                direction,
+                ..Default::default()
+            }));
         }
         if let Some(whirl) = maybe_whirl_ability {
             if whirl.energy
@@ -1197,6 +1301,7 @@ fn add_attack(
                     / time.hz as f32)
                 > 0.0
                 && is_grounded
+                && weapon.melee().is_some_and(|melee| melee.whirl.is_some())
                 && action_state.pressed(&PlayerAction::Whirl)
             {
                 transitions.push(CanAttack::new_transition(
@@ -1215,20 +1320,20 @@ fn player_attack(
             &Facing,
             &Transform,
             Option<&WallSlideTime>,
-            &mut PlayerStats,
+            &mut Stats,
             &mut Attacking,
             &mut TransitionQueue,
             &ActionState<PlayerAction>,
+            &mut InputBuffer,
             &PlayerStatMod,
             Has<Stealthing>,
             Has<Grounded>,
         ),
         (With<Player>, Without<Whirling>),
     >,
+    mut gfx_query: Query<&mut ScriptPlayer<SpriteAnimation>, With<PlayerGfx>>,
     mut commands: Commands,
-    config: Res<PlayerConfig>,
-    combat_style: Res<PlayerCombatStyle>,
-    melee_weapon: Res<PlayerMeleeWeapon>,
+    weapon: CurrentWeapon,
     time: Res<GameTime>,
 ) {
     for (
@@ -1241,17 +1346,32 @@ fn player_attack(
         mut attacking,
         mut transitions,
         action_state,
+        mut input_buffer,
         stat_mod,
         stealthed,
         grounded,
     ) in query.iter_mut()
     {
-        if attacking.ticks == 0 {
-            let attack = match *combat_style {
-                PlayerCombatStyle::Ranged => {
+        let mut anim = gfx_query.get_mut(gent.e_gfx).ok();
+        if let Some(anim) = anim.as_mut() {
+            if attacking.ticks == 0 {
+                // the windows of the previous attack are carried over to the
+                // new animation, close them until it opens its own
+                anim.set_slot(CHAIN_WINDOW_SLOT, false);
+                anim.set_slot(CANCEL_WINDOW_SLOT, false);
+            }
+            // cooldown reduction makes attacks (and their windows) faster
+            if let Some(tracker) = anim.tracker_mut() {
+                tracker.extended.set_speed(stat_mod.cdr);
+            }
+        }
+        let step = weapon.attack(&attacking);
+        if let (0, Some(step)) = (attacking.ticks, step) {
+            let attack = match &step.projectile {
+                Some(projectile) => {
                     let mut animation: ScriptPlayer<SpriteAnimation> =
                         ScriptPlayer::default();
-                    animation.play_key("anim.player.BowBasicArrow");
+                    animation.play_key(&projectile.anim);
                     animation.set_slot("Start", true);
 
                     let is_player_pressed_against_wall = wall_slide_time
@@ -1262,9 +1382,9 @@ fn player_attack(
                         facing.direction()
                     };
                     // Used for the collider length and the starting position offset
-                    let arrow_length = 30.0;
+                    let arrow_length = projectile.length;
                     let vel = LinearVelocity(
-                        Vec2::X * arrow_direction * config.arrow_velocity,
+                        Vec2::X * arrow_direction * projectile.velocity,
                     );
                     let mut arrow_transform = *transform;
                     // Add an offset to avoid arrows immediately colliding with walls when fired
@@ -1274,10 +1394,10 @@ fn player_attack(
                     if !is_player_pressed_against_wall {
                         commands.entity(entity).insert(Knockback::new(
                             Vec2::new(
-                                -facing.direction() * config.bow_self_pushback,
+                                -facing.direction() * step.self_pushback,
                                 0.,
                             ),
-                            config.bow_self_pushback_ticks,
+                            step.self_pushback_ticks,
                         ));
                     }
 
@@ -1299,77 +1419,52 @@ fn player_attack(
                                     ENEMY_HURT | GROUND,
                                 ),
                             ),
-                            Attack::new(
-                                192,
-                                entity,
-                                config.bow_attack_damage * stat_mod.attack,
-                            )
-                            .with_max_targets(1),
+                            step.attack(entity, stat_mod.attack),
                             Pushback(Knockback::new(
                                 Vec2::new(
-                                    facing.direction() * config.bow_pushback,
+                                    facing.direction() * step.pushback,
                                     0.,
                                 ),
-                                config.bow_pushback_ticks,
+                                step.pushback_ticks,
                             )),
                             animation,
                             StateDespawnMarker,
+                            LevelDespawnMarker,
                         ))
                         .id()
                 },
-                PlayerCombatStyle::Melee => {
-                    let (
-                        damage,
-                        self_pushback,
-                        self_pushback_ticks,
-                        pushback,
-                        pushback_ticks,
-                    ) = match *melee_weapon {
-                        PlayerMeleeWeapon::Hammer => (
-                            config.hammer_attack_damage,
-                            config.hammer_self_pushback,
-                            config.hammer_self_pushback_ticks,
-                            config.hammer_pushback,
-                            config.hammer_pushback_ticks,
-                        ),
-                        PlayerMeleeWeapon::Sword => (
-                            config.sword_attack_damage,
-                            config.sword_self_pushback,
-                            config.sword_self_pushback_ticks,
-                            config.sword_pushback,
-                            config.sword_pushback_ticks,
-                        ),
-                    };
-
-                    // Slow the player down when they attack with the Hammer
-                    if let PlayerMeleeWeapon::Hammer = *melee_weapon {
-                        player_stats.set(
-                            StatType::MoveVelMax,
-                            config.hammer_max_move_vel,
-                        );
+                None => {
+                    // Slow the player down when they attack with heavy weapons
+                    if let Some(max_move_vel) =
+                        weapon.def().and_then(|def| def.max_move_vel)
+                    {
+                        player_stats.set(StatType::MoveVelMax, max_move_vel);
                     }
 
                     let is_attacking_downwards =
                         !grounded && action_state.pressed(&PlayerAction::Fall);
                     let self_knockback_strength = if is_attacking_downwards {
-                        Vec2::new(0.0, self_pushback * 2.0)
+                        Vec2::new(0.0, step.self_pushback * 2.0)
                     } else {
-                        Vec2::new(self_pushback * -facing.direction(), 0.)
+                        Vec2::new(
+                            step.self_pushback * -facing.direction(),
+                            0.,
+                        )
                     };
                     let mut attack_entity_commands = commands.spawn((
                         TransformBundle::from_transform(Transform::from_xyz(
                             0.0, 0.0, 0.0,
                         )),
-                        AnimationCollider(gent.e_gfx),
+                        AnimationCollider(gent.e_gfx, ColliderLayer::Hit),
                         // TODO: ? ColliderMeta
                         Collider::empty(InteractionGroups::new(
                             PLAYER_ATTACK,
                             ENEMY_HURT,
                         )),
-                        Attack::new(16, entity, damage * stat_mod.attack),
+                        step.attack(entity, stat_mod.attack),
                         SelfPushback(Knockback::new(
                             self_knockback_strength,
-                            self_pushback_ticks,
+                            step.self_pushback_ticks,
                         )),
                     ));
                     attack_entity_commands.set_parent(entity);
@@ -1377,8 +1472,11 @@ fn player_attack(
                     if !is_attacking_downwards {
                         attack_entity_commands.insert(Pushback(
                             Knockback::new(
-                                Vec2::new(facing.direction() * pushback, 0.),
-                                pushback_ticks,
+                                Vec2::new(
+                                    facing.direction() * step.pushback,
+                                    0.,
+                                ),
+                                step.pushback_ticks,
                             ),
                         ));
                     } else {
@@ -1395,23 +1493,28 @@ fn player_attack(
         }
 
         attacking.ticks += 1;
-        let maximum_ticks = (((Attacking::MAX * 8) as f32 / stat_mod.cdr)
-            as u32)
-            .clamp(Attacking::MIN * 8, Attacking::MAX * 8);
-
-        // if we are in the later half of attacking and another attack input was pressed,
-        // indicate an immediate follow up on animation end
-        if attacking.ticks >= maximum_ticks - 8
-            && action_state.just_pressed(&PlayerAction::Attack)
+        // the attack animation declares when the attack can be chained or
+        // cancelled, by enabling slots at the right frames
+        let window_open =
+            |slot: &str| anim.as_ref().is_some_and(|anim| anim.has_slot(slot));
+        let chain_window = window_open(CHAIN_WINDOW_SLOT);
+        let cancel_window = window_open(CANCEL_WINDOW_SLOT);
+
+        // if the chain window is open and another attack input was pressed
+        // (or buffered shortly before), indicate an immediate follow up
+        if (chain_window || cancel_window)
+            && input_buffer.consume(PlayerAction::Attack)
         {
             attacking.followup = true;
         }
 
         // leave attacking state
-        if attacking.ticks >= maximum_ticks {
+        if cancel_window || attacking.timed_out(stat_mod.cdr) {
             if attacking.followup {
+                // continue the combo chain
                 transitions.push(Attacking::new_transition(CanAttack {
                     immediate: true,
+                    combo: attacking.combo + 1,
                 }));
             } else {
                 transitions.push(Attacking::new_transition(
@@ -1424,7 +1527,7 @@ fn player_attack(
 
 /// Restores the player movement velocity after attacking.
 fn player_restore_velocity(
-    mut query: Query<&mut PlayerStats, (With<Player>, Added<CanAttack>)>,
+    mut query: Query<&mut Stats, (With<Player>, Added<CanAttack>)>,
 ) {
     for mut stats in query.iter_mut() {
         stats.reset_stat(StatType::MoveVelMax);
@@ -1451,7 +1554,7 @@ pub fn player_whirl(
             &mut TransitionQueue,
             &mut Whirling,
             &mut WhirlAbility,
-            &mut PlayerStats,
+            &mut Stats,
             &PlayerStatMod,
             Has<Stealthing>,
             &Gent,
@@ -1473,7 +1576,7 @@ pub fn player_whirl(
         ),
     >,
     mut commands: Commands,
-    melee_weapon: Res<PlayerMeleeWeapon>,
+    weapon: CurrentWeapon,
     config: Res<PlayerConfig>,
     time: Res<GameTime>,
 ) {
@@ -1495,12 +1598,10 @@ pub fn player_whirl(
         if action_state.pressed(&PlayerAction::Whirl)
             || whirling.ticks < Whirling::MIN_TICKS
         {
-            // Slow the player down when they attack with the Hammer
-            if let PlayerMeleeWeapon::Hammer = *melee_weapon {
-                player_stats.set(
-                    StatType::MoveVelMax,
-                    config.hammer_max_move_vel,
-                );
+            let melee = weapon.melee();
+            // Slow the player down when they attack with heavy weapons
+            if let Some(max_move_vel) = melee.and_then(|def| def.max_move_vel) {
+                player_stats.set(StatType::MoveVelMax, max_move_vel);
             }
 
             if let Some(attack_entity) = whirling.attack_entity {
@@ -1509,9 +1610,10 @@ pub fn player_whirl(
                     whirling.attack_entity = None;
                 }
             // if there is no attack, spawn a new one
-            } else {
-                let lifetime = melee_weapon.attack_lifetime(passives);
-                let damage = melee_weapon.base_damage() * stat_mod.attack;
+            } else if let Some(whirl) = melee.and_then(|def| def.whirl.as_ref())
+            {
+                let lifetime = whirl.attack_lifetime(passives);
+                let damage = whirl.damage * stat_mod.attack;
                 let new_attack = commands
                     .spawn((
                         AttackBundle {
@@ -1524,7 +1626,7 @@ pub fn player_whirl(
                         TransformBundle::from_transform(Transform::from_xyz(
                             0.0, 0.0, 0.0,
                         )),
-                        AnimationCollider(gent.e_gfx),
+                        AnimationCollider(gent.e_gfx, ColliderLayer::Hit),
                     ))
                     .set_parent(entity)
                     .id();
@@ -1593,7 +1695,7 @@ fn player_pickup_interact(
     mut query: Query<
         (
             &Transform,
-            &ActionState<PlayerAction>,
+            &mut ActionState<PlayerAction>,
             &mut Passives,
         ),
         With<Player>,
@@ -1604,9 +1706,16 @@ fn player_pickup_interact(
         With<PassiveDescriptionNode>,
     >,
     pickup_hint: Query<Entity, With<PickupHint>>,
+    mut collected_seeds: ResMut<CollectedSeeds>,
+    mut weapons: ResMut<PlayerWeapons>,
+    weapon_defs: Res<WeaponDefs>,
+    choice: Option<Res<PassiveChoice>>,
     mut commands: Commands,
 ) {
-    for (p_transform, action_state, mut passives) in query.iter_mut() {
+    if choice.is_some() {
+        return;
+    }
+    for (p_transform, mut action_state, mut passives) in query.iter_mut() {
         if action_state.just_pressed(&PlayerAction::Interact) {
             // Get Pickups in Range and pick up a single one
 
@@ -1616,27 +1725,42 @@ fn player_pickup_interact(
                 let dist =
                     p_pos.distance_squared(transform.translation.truncate());
 
-                println!("dist: {}", dist);
-
                 if dist <= PICKUP_RANGE_SQUARED {
                     match &pickup.p_type {
+                        // already got it some other way, like from a shop
+                        PickupType::PassiveDrop(passive)
+                            if passives.contains(passive) => {},
+                        PickupType::PassiveDrop(passive)
+                            if passives.is_full() =>
+                        {
+                            // Ask what to give up, the pickup stays until then
+                            action_state.consume(&PlayerAction::Interact);
+                            commands.insert_resource(PassiveChoice {
+                                pickup: entity,
+                                new: passive.clone(),
+                                selected: 0,
+                            });
+                            break;
+                        },
                         PickupType::PassiveDrop(passive) => {
-                            println!("passive pickup!!!");
                             passives.add_passive(passive.clone());
-                            // Despawn the passive description UI node for the picked up passive.
-                            if let Some((passive_description, _)) =
-                                passive_descriptions.iter().find(
-                                    |(_, passive_entity)| {
-                                        passive_entity.get() == entity
-                                    },
-                                )
-                            {
-                                commands
-                                    .entity(passive_description)
-                                    .despawn_recursive();
-                            }
                         },
-                        PickupType::Seed(_, (_, word)) => {
+                        PickupType::Weapon(weapon) => {
+                            let Some(def) = weapon_defs.get(weapon) else {
+                                continue;
+                            };
+                            weapons.add(def);
+                            commands
+                                .popup()
+                                .insert(PopupTimer::default())
+                                .with_children(|popup| {
+                                    popup.row().with_children(|row| {
+                                        row.text(&def.name);
+                                    });
+                                });
+                        },
+                        PickupType::Seed(category, (id, word)) => {
+                            collected_seeds.push((category.clone(), *id));
                             commands
                                 .popup()
                                 .insert(PopupTimer::default())
@@ -1647,15 +1771,100 @@ fn player_pickup_interact(
                                 });
                         },
                     }
-                    // Despawn the Pickup popup hint
-                    for entity in &pickup_hint {
-                        commands.entity(entity).despawn_recursive();
-                    }
-                    // Despawn the PickupDrop entity from the map
-                    commands.entity(entity).despawn_recursive();
+                    despawn_pickup(
+                        &mut commands,
+                        entity,
+                        &passive_descriptions,
+                        &pickup_hint,
+                    );
                     break;
                 }
             }
         }
     }
 }
+
+/// Replace, discard or leave a picked up passive when all slots are full
+fn player_passive_choice(
+    mut commands: Commands,
+    mut choice: ResMut<PassiveChoice>,
+    mut query: Query<
+        (
+            &mut ActionState<PlayerAction>,
+            &mut Passives,
+        ),
+        With<Player>,
+    >,
+    pickup_query: Query<(), With<PickupDrop>>,
+    passive_descriptions: Query<
+        (Entity, &PassiveEntity),
+        With<PassiveDescriptionNode>,
+    >,
+    pickup_hint: Query<Entity, With<PickupHint>>,
+) {
+    let Ok((mut action_state, mut passives)) = query.get_single_mut() else {
+        return;
+    };
+    if !pickup_query.contains(choice.pickup) {
+        commands.remove_resource::<PassiveChoice>();
+        return;
+    }
+    let options = PassiveChoice::options(&passives);
+    if action_state.just_pressed(&PlayerAction::Jump) {
+        choice.selected = (choice.selected + options.len() - 1) % options.len();
+    }
+    if action_state.just_pressed(&PlayerAction::Fall) {
+        choice.selected = (choice.selected + 1) % options.len();
+    }
+    for action in BLOCKED_ACTIONS {
+        action_state.consume(&action);
+    }
+    if !action_state.just_pressed(&PlayerAction::Interact) {
+        return;
+    }
+    action_state.consume(&PlayerAction::Interact);
+
+    match options.get(choice.selected) {
+        Some(PassiveChoiceOption::Replace(old)) => {
+            passives.replace(old, choice.new.clone());
+        },
+        Some(PassiveChoiceOption::Discard) => {
+            passives.discard(choice.new.clone());
+        },
+        Some(PassiveChoiceOption::Leave) | None => {
+            commands.remove_resource::<PassiveChoice>();
+            return;
+        },
+    }
+    despawn_pickup(
+        &mut commands,
+        choice.pickup,
+        &passive_descriptions,
+        &pickup_hint,
+    );
+    commands.remove_resource::<PassiveChoice>();
+}
+
+fn despawn_pickup(
+    commands: &mut Commands,
+    pickup: Entity,
+    passive_descriptions: &Query<
+        (Entity, &PassiveEntity),
+        With<PassiveDescriptionNode>,
+    >,
+    pickup_hint: &Query<Entity, With<PickupHint>>,
+) {
+    // Despawn the passive description UI node for the picked up passive.
+    if let Some((passive_description, _)) = passive_descriptions
+        .iter()
+        .find(|(_, passive_entity)| passive_entity.get() == pickup)
+    {
+        commands.entity(passive_description).despawn_recursive();
+    }
+    // Despawn the Pickup popup hint
+    for entity in pickup_hint {
+        commands.entity(entity).despawn_recursive();
+    }
+    // Despawn the PickupDrop entity from the map
+    commands.entity(pickup).despawn_recursive();
+}
@@ -0,0 +1,435 @@
+//! Passives, as defined in config assets
+//!
+//! Every passive is described in `player.passives.toml` (asset key
+//! `passives.player`): its name, description, icon, and a list of effects.
+//! Each effect has a trigger (`when`) and an action (`effect`):
+//!
+//! ```toml
+//! [[passive]]
+//! id = "GlowingShard"
+//! name = "Glowing Shard"
+//! description = "Defense scaling based on number of enemies nearby"
+//! icon = "items/passives/GlowingShard.png"
+//!
+//! [[passive.effects]]
+//! when = "per_nearby_enemy"
+//! effect = "stat_mod"
+//! defense = 2.0
+//! ```
+//!
+//! Stat multipliers apply continuously, for as long as their trigger holds
+//! (`always`, `airborne`, `grounded`, `moving`, `standing_still`,
+//! `per_nearby_enemy`). Heals and cooldown refunds happen once per event
+//! (`on_hit`, `on_crit`, `on_kill`, `on_xp_pickup`).
+//!
+//! Some passives have special behaviour that cannot (yet) be described in
+//! data. That is implemented in code, keyed by the passive's id (see the
+//! associated consts on [`Passive`]).
+
+use std::borrow::Cow;
+
+use bevy::reflect::TypePath;
+use bevy_common_assets::toml::TomlAssetPlugin;
+use theseeker_engine::assets::reload_on_modified;
+
+use crate::game::attack::{Attack, Crit, Health, Hit, Killed};
+use crate::game::xp_orbs::XpOrbPickup;
+use crate::prelude::*;
+
+use super::{CanDash, CanStealth, Passives, Player, WhirlAbility};
+
+pub(super) struct PassivesPlugin;
+
+impl Plugin for PassivesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(TomlAssetPlugin::<PassiveList>::new(&[
+            "passives.toml",
+        ]));
+        app.init_resource::<PassiveDefs>();
+        app.add_systems(
+            GameTickUpdate,
+            (
+                reload_on_modified::<PassiveList>("passives.player")
+                    .pipe(load_passive_defs),
+                update_passive_pool.run_if(resource_changed::<PassiveDefs>),
+            )
+                .chain()
+                .before(super::setup_player),
+        );
+    }
+}
+
+/// Identifies a passive (the `id` in the passives asset)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Passive(Cow<'static, str>);
+
+/// Passives that have some behaviour implemented in code
+#[allow(non_upper_case_globals)]
+impl Passive {
+    /// Heal when picking up xp orbs (the orbs turn red to show it)
+    pub const Bloodstone: Passive = Passive::from_static("Bloodstone");
+    /// Crit on every 2nd and 3rd hit when on low health
+    pub const FlamingHeart: Passive = Passive::from_static("FlamingHeart");
+    /// Deal double damage when backstabbing
+    pub const IceDagger: Passive = Passive::from_static("IceDagger");
+    /// Extra crits
+    pub const ObsidianNecklace: Passive =
+        Passive::from_static("ObsidianNecklace");
+    /// Halved max health
+    pub const SerpentRing: Passive = Passive::from_static("SerpentRing");
+    /// Sacrifice health but get increased cooldown reduction for every consecutive hit within 3 seconds
+    pub const FrenziedAttack: Passive = Passive::from_static("FrenziedAttack");
+    /// Deal more damage to packs of enemies
+    pub const PackKiller: Passive = Passive::from_static("PackKiller");
+    /// Extra crits
+    pub const DeadlyFeather: Passive = Passive::from_static("DeadlyFeather");
+    /// Scale damage based on distance between you and nearest enemy
+    pub const Sharpshooter: Passive = Passive::from_static("Sharpshooter");
+    /// Limits the damage taken from any attack to 1/3 of your maximum health.
+    pub const ProtectiveSpirit: Passive =
+        Passive::from_static("ProtectiveSpirit");
+    /// Gain 1 extra jump, and extra crits
+    pub const RabbitsFoot: Passive = Passive::from_static("RabbitsFoot");
+    /// Increases damage based on health percentage, at the cost of constant health degeneration.
+    pub const VitalityOverclock: Passive =
+        Passive::from_static("VitalityOverclock");
+}
+
+impl Passive {
+    const fn from_static(id: &'static str) -> Self {
+        Passive(Cow::Borrowed(id))
+    }
+}
+
+impl std::fmt::Display for Passive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Asset type for files with passive definitions
+#[derive(Asset, Debug, Clone)]
+#[derive(Serialize, Deserialize)]
+#[derive(TypePath)]
+pub struct PassiveList {
+    #[serde(default)]
+    pub passive: Vec<PassiveDef>,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct PassiveDef {
+    pub id: Passive,
+    pub name: String,
+    pub description: String,
+    /// Asset path of the icon image
+    pub icon: String,
+    #[serde(default)]
+    pub effects: Vec<PassiveEffect>,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct PassiveEffect {
+    #[serde(default)]
+    pub when: PassiveTrigger,
+    #[serde(flatten)]
+    pub action: PassiveAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PassiveTrigger {
+    #[default]
+    Always,
+    Airborne,
+    Grounded,
+    Moving,
+    StandingStill,
+    /// Multipliers are applied once per enemy nearby
+    PerNearbyEnemy,
+    OnHit,
+    OnCrit,
+    OnKill,
+    OnXpPickup,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "effect", rename_all = "snake_case")]
+pub enum PassiveAction {
+    /// Multiply the player's [`PlayerStatMod`](super::PlayerStatMod)
+    StatMod(StatMultipliers),
+    Heal {
+        amount: u32,
+    },
+    /// Reduce all ability cooldowns by this many seconds
+    RefundCooldowns {
+        seconds: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct StatMultipliers {
+    #[serde(default = "one")]
+    pub attack: f32,
+    #[serde(default = "one")]
+    pub defense: f32,
+    #[serde(default = "one")]
+    pub speed: f32,
+    #[serde(default = "one")]
+    pub cdr: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl Default for StatMultipliers {
+    fn default() -> Self {
+        Self {
+            attack: 1.0,
+            defense: 1.0,
+            speed: 1.0,
+            cdr: 1.0,
+        }
+    }
+}
+
+impl StatMultipliers {
+    /// Combine with another set of multipliers, `times` times over
+    ///
+    /// `times` can be fractional or zero.
+    pub fn stack(&mut self, other: &StatMultipliers, times: f32) {
+        self.attack *= 1.0 + (other.attack - 1.0) * times;
+        self.defense *= 1.0 + (other.defense - 1.0) * times;
+        self.speed *= 1.0 + (other.speed - 1.0) * times;
+        self.cdr *= 1.0 + (other.cdr - 1.0) * times;
+    }
+}
+
+/// What the player is currently doing, to evaluate continuous triggers
+pub struct PassiveContext {
+    pub airborne: bool,
+    pub moving: bool,
+    pub enemies_nearby: u32,
+}
+
+impl PassiveTrigger {
+    /// Does this trigger fire on discrete events, rather than continuously?
+    pub fn is_event(&self) -> bool {
+        matches!(
+            self,
+            PassiveTrigger::OnHit
+                | PassiveTrigger::OnCrit
+                | PassiveTrigger::OnKill
+                | PassiveTrigger::OnXpPickup
+        )
+    }
+
+    /// How many times a continuous trigger applies right now
+    fn strength(&self, ctx: &PassiveContext) -> f32 {
+        let active = match self {
+            PassiveTrigger::Always => true,
+            PassiveTrigger::Airborne => ctx.airborne,
+            PassiveTrigger::Grounded => !ctx.airborne,
+            PassiveTrigger::Moving => ctx.moving,
+            PassiveTrigger::StandingStill => !ctx.moving,
+            PassiveTrigger::PerNearbyEnemy => {
+                return ctx.enemies_nearby as f32;
+            },
+            _ => false,
+        };
+        if active {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl PassiveDef {
+    fn validate(&self) {
+        for effect in self.effects.iter() {
+            let is_stat_mod =
+                matches!(effect.action, PassiveAction::StatMod(_));
+            if is_stat_mod == effect.when.is_event() {
+                warn!(
+                    "Passive {:?}: effect {:?} cannot be triggered by {:?}",
+                    self.id, effect.action, effect.when
+                );
+            }
+        }
+    }
+}
+
+/// All known passives, from the passives asset
+#[derive(Resource, Default)]
+pub struct PassiveDefs {
+    defs: Vec<PassiveDef>,
+    icons: HashMap<Passive, Handle<Image>>,
+}
+
+impl PassiveDefs {
+    pub fn get(&self, id: &Passive) -> Option<&PassiveDef> {
+        self.defs.iter().find(|def| def.id == *id)
+    }
+
+    pub fn icon(&self, id: &Passive) -> Option<&Handle<Image>> {
+        self.icons.get(id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &Passive> {
+        self.defs.iter().map(|def| &def.id)
+    }
+
+    /// Combined stat multipliers of the given passives, in the given context
+    pub fn stat_multipliers<'a>(
+        &self,
+        passives: impl IntoIterator<Item = &'a Passive>,
+        ctx: &PassiveContext,
+    ) -> StatMultipliers {
+        let mut r = StatMultipliers::default();
+        for def in passives.into_iter().filter_map(|p| self.get(p)) {
+            for effect in def.effects.iter() {
+                if let PassiveAction::StatMod(m) = &effect.action {
+                    r.stack(m, effect.when.strength(ctx));
+                }
+            }
+        }
+        r
+    }
+
+    /// All event effects of the given passives, that fire on the given trigger
+    pub fn actions_on<'a>(
+        &'a self,
+        passives: impl IntoIterator<Item = &'a Passive> + 'a,
+        trigger: PassiveTrigger,
+    ) -> impl Iterator<Item = &'a PassiveAction> + 'a {
+        passives
+            .into_iter()
+            .filter_map(|p| self.get(p))
+            .flat_map(|def| def.effects.iter())
+            .filter(move |effect| effect.when == trigger)
+            .map(|effect| &effect.action)
+    }
+}
+
+fn load_passive_defs(
+    In(list): In<Option<PassiveList>>,
+    asset_server: Res<AssetServer>,
+    mut defs: ResMut<PassiveDefs>,
+) {
+    let Some(list) = list else {
+        return;
+    };
+    for def in list.passive.iter() {
+        def.validate();
+    }
+    *defs = PassiveDefs {
+        defs: list.passive.clone(),
+        icons: list
+            .passive
+            .iter()
+            .map(|def| {
+                (
+                    def.id.clone(),
+                    asset_server.load(&def.icon),
+                )
+            })
+            .collect(),
+    };
+}
+
+/// Keep the player's pool of passives in sync with the definitions,
+/// so passives added while the game is running can drop
+fn update_passive_pool(
+    defs: Res<PassiveDefs>,
+    mut q_player: Query<&mut Passives, With<Player>>,
+) {
+    for mut passives in q_player.iter_mut() {
+        passives.locked.retain(|p| defs.get(p).is_some());
+        for id in defs.ids() {
+            if !passives.contains(id) && !passives.locked.contains(id) {
+                passives.locked.push(id.clone());
+            }
+        }
+    }
+}
+
+/// Perform the effects of passives that are triggered by events
+pub fn on_passive_trigger(
+    defs: Res<PassiveDefs>,
+    attack_query: Query<(&Attack, Has<Crit>), Added<Hit>>,
+    mut player_query: Query<
+        (
+            Entity,
+            &Passives,
+            &mut Health,
+            Option<&mut CanDash>,
+            Option<&mut WhirlAbility>,
+            Option<&mut CanStealth>,
+        ),
+        With<Player>,
+    >,
+    mut killed_events: EventReader<Killed>,
+    mut xp_events: EventReader<XpOrbPickup>,
+) {
+    let Ok((
+        player_e,
+        passives,
+        mut health,
+        mut maybe_can_dash,
+        mut maybe_whirl_ability,
+        mut maybe_can_stealth,
+    )) = player_query.get_single_mut()
+    else {
+        killed_events.clear();
+        xp_events.clear();
+        return;
+    };
+
+    let mut triggers = vec![];
+    for (attack, is_crit) in attack_query.iter() {
+        if attack.attacker == player_e {
+            triggers.push(PassiveTrigger::OnHit);
+            if is_crit {
+                triggers.push(PassiveTrigger::OnCrit);
+            }
+        }
+    }
+    for killed in killed_events.read() {
+        if killed.killer == Some(player_e) {
+            triggers.push(PassiveTrigger::OnKill);
+        }
+    }
+    for _event in xp_events.read() {
+        triggers.push(PassiveTrigger::OnXpPickup);
+    }
+
+    for trigger in triggers {
+        for action in defs.actions_on(passives.iter(), trigger) {
+            match action {
+                PassiveAction::StatMod(_) => {},
+                PassiveAction::Heal { amount } => {
+                    health.current = (health.current + amount).min(health.max);
+                },
+                PassiveAction::RefundCooldowns { seconds } => {
+                    if let Some(ref mut can_dash) = maybe_can_dash {
+                        can_dash.remaining_cooldown -= seconds;
+                    }
+                    if let Some(ref mut whirl_ability) = maybe_whirl_ability {
+                        whirl_ability.energy += seconds;
+                    }
+                    if let Some(ref mut can_stealth) = maybe_can_stealth {
+                        can_stealth.remaining_cooldown -= seconds;
+                    }
+                },
+            }
+        }
+    }
+}
@@ -1,36 +1,129 @@
+//! Player weapons, as defined in config assets
+//!
+//! Every weapon is described in `player.weapons.toml` (asset key
+//! `weapons.player`): its name, which slot it goes in (`melee` or
+//! `ranged`), and the attacks of its combo chain. Attacking again once the
+//! attack's animation opens its chain window continues the chain, wrapping
+//! around after the last attack. An attack can have directional variants,
+//! played instead when their direction is held as the attack starts:
+//!
+//! ```toml
+//! [[weapon]]
+//! id = "Sword"
+//! name = "Sword"
+//! description = "Quick and reliable"
+//! style = "melee"
+//! icon = "ui/game/AttackSkillIcon.png"
+//! hit_slot = "SwordHit"
+//! starting = true
+//!
+//! [[weapon.combo]]
+//! anim = "anim.player.SwordBasic"
+//! damage = 37.0
+//! pushback = 60.0
+//! pushback_ticks = 12
+//!
+//! [[weapon.combo.directional]]
+//! direction = "forward"
+//! anim = "anim.player.SwordBasic"
+//! damage = 50.0
+//! ```
+//!
+//! Melee attacks hit with the `Hit` collider layer of their animation.
+//! Attacks with a `projectile` fire it instead.
+//!
+//! The animations declare when an attack can be chained or cancelled, by
+//! enabling the [`CHAIN_WINDOW_SLOT`] and [`CANCEL_WINDOW_SLOT`] slots at
+//! the right frames (see `player_attack`).
+//!
+//! The player starts with the `starting` weapons. The others have to be
+//! picked up, from `WeaponPickup` entities placed in the level.
+
+use std::borrow::Cow;
+
 use bevy::ecs::system::SystemParam;
-use bevy::prelude::{in_state, IntoSystemConfigs, Res};
+use bevy::reflect::TypePath;
+use bevy_common_assets::toml::TomlAssetPlugin;
 use leafwing_input_manager::prelude::ActionState;
-use strum_macros::Display;
-use theseeker_engine::prelude::{Commands, DetectChanges, OnEnter};
-use theseeker_engine::time::GameTickUpdate;
+use theseeker_engine::assets::config::color_hex;
+use theseeker_engine::assets::reload_on_modified;
+use theseeker_engine::input::InputManagerSystem;
 
+use crate::camera::CameraShake;
+use crate::game::attack::Attack;
 use crate::game::player::{Player, PlayerAction};
-use crate::prelude::{App, AppState, Plugin, Query, ResMut, Resource, With};
+use crate::game::status::{Stacking, StatType, StatusModifier};
+use crate::prelude::*;
 
-use super::{Passive, Passives, PlayerConfig, PlayerStateSet};
+use super::{Attacking, Passive, Passives, PlayerStateSet};
 
 pub(crate) struct PlayerWeaponPlugin;
 
 impl Plugin for PlayerWeaponPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<PlayerMeleeWeapon>();
-        app.init_resource::<PlayerRangedWeapon>();
+        app.add_plugins(TomlAssetPlugin::<WeaponList>::new(&[
+            "weapons.toml",
+        ]));
+        app.init_resource::<WeaponDefs>();
+        app.init_resource::<PlayerWeapons>();
         app.init_resource::<PlayerCombatStyle>();
         app.add_systems(
             OnEnter(AppState::InGame),
             initialize_resources,
         );
+        app.add_systems(
+            GameTickUpdate,
+            (
+                reload_on_modified::<WeaponList>("weapons.player")
+                    .pipe(load_weapon_defs),
+                update_player_weapons.run_if(resource_changed::<WeaponDefs>),
+            )
+                .chain()
+                .before(PlayerStateSet::Behavior),
+        );
         app.add_systems(
             GameTickUpdate,
             ((swap_combat_style, swap_melee_weapon)
                 .chain()
+                .after(InputManagerSystem::ManualControl)
                 .before(PlayerStateSet::Behavior))
             .run_if(in_state(AppState::InGame)),
         );
     }
 }
 
+/// Enabled by attack animations once attacking again continues the combo
+///
+/// Attack animations declare their combo windows by enabling this slot and
+/// [`CANCEL_WINDOW_SLOT`] at the right frames (usually with `chain` and
+/// `cancel` frame bookmarks). The attack system reads them: attacking again
+/// once the chain window is open continues the combo, and the attack ends
+/// (or goes on to the next one of the combo) once the cancel window opens.
+/// Cooldown reduction plays the animation faster, so both open sooner.
+pub const CHAIN_WINDOW_SLOT: &str = "ChainWindow";
+/// Enabled by attack animations once the attack can end, or be cancelled
+/// into the next attack of the combo
+pub const CANCEL_WINDOW_SLOT: &str = "CancelWindow";
+
+/// Identifies a weapon (the `id` in the weapons asset)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WeaponId(Cow<'static, str>);
+
+impl WeaponId {
+    pub fn new(id: impl Into<Cow<'static, str>>) -> Self {
+        WeaponId(id.into())
+    }
+}
+
+impl std::fmt::Display for WeaponId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Default)]
 pub struct PushbackValues {
     pub pushback: f32,
     pub pushback_ticks: u32,
@@ -38,161 +131,647 @@ pub struct PushbackValues {
     pub self_pushback_ticks: u32,
 }
 
-#[derive(Resource, Default, PartialEq, Eq, Display)]
+/// Which of the equipped weapons the player attacks with
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PlayerCombatStyle {
     Ranged,
     #[default]
     Melee,
 }
 
-#[derive(Resource, Default, PartialEq, Eq, Display)]
-pub enum PlayerMeleeWeapon {
-    Hammer,
-    #[default]
-    Sword,
+/// Held as an attack starts, to play one of its directional variants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttackDirection {
+    /// Moving the way the player is facing
+    Forward,
+    Down,
 }
 
-impl PlayerMeleeWeapon {
-    /// Returns the attack collider lifetime based on melee weapon and passives:
-    /// For Sword: default = 16, if (SerpentRing or FrenziedAttack) active then 12
-    /// For Hammer: default = 24, if (SerpentRing or FrenziedAttack) active then 18
-    pub fn attack_lifetime(&self, passives: &Passives) -> u32 {
-        let has_serpent_ring_or_frenzied_attack = passives
-            .contains(&Passive::SerpentRing)
-            || passives.contains(&Passive::FrenziedAttack);
-
-        match self {
-            Self::Hammer => {
-                if has_serpent_ring_or_frenzied_attack {
-                    18
-                } else {
-                    24
+/// Asset type for files with weapon definitions
+#[derive(Asset, Debug, Clone)]
+#[derive(Deserialize)]
+#[derive(TypePath)]
+pub struct WeaponList {
+    #[serde(default)]
+    pub weapon: Vec<WeaponDef>,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct WeaponDef {
+    pub id: WeaponId,
+    pub name: String,
+    pub description: String,
+    /// Which slot the weapon is equipped in
+    pub style: PlayerCombatStyle,
+    /// Asset path of the image shown for pickups
+    pub icon: String,
+    /// Slot enabled on the hit effect of enemies, to play the hit sound
+    pub hit_slot: String,
+    /// Held from the start, instead of having to be picked up
+    #[serde(default)]
+    pub starting: bool,
+    /// Limits the player's horizontal velocity while attacking
+    /// (in pixels/second)
+    pub max_move_vel: Option<f32>,
+    /// Camera shake on hit, instead of the default from the player config
+    pub screenshake: Option<ScreenShakeDef>,
+    /// The attacks played by attacking repeatedly, in order
+    pub combo: Vec<AttackStep>,
+    /// The whirl skill, for melee weapons
+    pub whirl: Option<WhirlDef>,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct AttackStep {
+    /// Animation key; `Idle`, `Run` or `Air` is appended, depending on what
+    /// the player is doing. Its `Hit` collider layer is the hit-box.
+    pub anim: String,
+    pub damage: f32,
+    /// How long the hit-box (or projectile) is active, in ticks
+    #[serde(default = "default_lifetime")]
+    pub lifetime: u32,
+    /// Maximum number of enemies hit at once
+    #[serde(default = "default_max_targets")]
+    pub max_targets: u32,
+    /// Knockback velocity applied to enemies hit
+    #[serde(default)]
+    pub pushback: f32,
+    #[serde(default)]
+    pub pushback_ticks: u32,
+    /// Velocity the player is pushed back with on hit
+    /// (or when firing a projectile)
+    #[serde(default)]
+    pub self_pushback: f32,
+    #[serde(default)]
+    pub self_pushback_ticks: u32,
+    /// Applied to enemies hit
+    pub status: Option<StatusDef>,
+    /// Fired instead of hitting with the animation's hit-box
+    pub projectile: Option<ProjectileDef>,
+    /// Slot enabled on the player's animation for this attack, so it can
+    /// play a different swing (like `HeavyAttack`)
+    pub slot: Option<String>,
+    /// For directional variants, the direction to hold
+    pub direction: Option<AttackDirection>,
+    /// Played instead of this attack when their direction is held
+    #[serde(default)]
+    pub directional: Vec<AttackStep>,
+}
+
+fn default_lifetime() -> u32 {
+    16
+}
+
+fn default_max_targets() -> u32 {
+    3
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct ProjectileDef {
+    pub anim: String,
+    /// (in pixels/second)
+    pub velocity: f32,
+    /// Length of the collider, the projectile is fired from this far ahead
+    /// of the player so it does not hit walls the player is standing at
+    pub length: f32,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct WhirlDef {
+    pub anim: String,
+    pub damage: f32,
+    /// How long each hit-box is active, in ticks
+    pub lifetime: u32,
+    /// Lifetime while the Serpent Ring or Frenzied Attack passive is held
+    pub frenzied_lifetime: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[derive(Deserialize)]
+pub struct ScreenShakeDef {
+    pub strength: f32,
+    pub duration_secs: f32,
+    pub frequency: f32,
+}
+
+/// A status modifier, refreshed when applied again
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct StatusDef {
+    pub id: String,
+    pub stats: Vec<StatType>,
+    /// One per stat, or a single one for all of them
+    #[serde(default)]
+    pub scalar: Vec<f32>,
+    /// One per stat, or a single one for all of them
+    #[serde(default)]
+    pub delta: Vec<f32>,
+    /// Color of the effect, written as a hex string
+    #[serde(with = "color_hex")]
+    pub color: Color,
+    /// In seconds
+    pub duration: f32,
+}
+
+impl WeaponDef {
+    /// The attack at `combo` attacks into the chain, or its variant for
+    /// the held direction
+    pub fn combo_step(
+        &self,
+        combo: usize,
+        direction: Option<AttackDirection>,
+    ) -> Option<&AttackStep> {
+        if self.combo.is_empty() {
+            return None;
+        }
+        let step = self.combo.get(combo % self.combo.len())?;
+        let variant = step.directional.iter().find(|variant| {
+            direction.is_some() && variant.direction == direction
+        });
+        Some(variant.unwrap_or(step))
+    }
+
+    /// Every attack of the combo chain, including directional variants
+    pub fn attacks(&self) -> impl Iterator<Item = &AttackStep> {
+        self.combo
+            .iter()
+            .flat_map(|step| std::iter::once(step).chain(&step.directional))
+    }
+
+    fn validate(&self) {
+        if self.combo.is_empty() {
+            warn!("Weapon {}: has no attacks", self.id);
+        }
+        let ranged = self.style == PlayerCombatStyle::Ranged;
+        if ranged && self.combo.iter().any(|step| step.projectile.is_none()) {
+            warn!(
+                "Weapon {}: ranged attacks need a projectile",
+                self.id
+            );
+        }
+        for step in self.combo.iter() {
+            if step.direction.is_some() {
+                warn!(
+                    "Weapon {}: `direction` is only used by directional attacks",
+                    self.id
+                );
+            }
+            for variant in step.directional.iter() {
+                if variant.direction.is_none() {
+                    warn!(
+                        "Weapon {}: directional attacks need a `direction`",
+                        self.id
+                    );
                 }
-            },
-            Self::Sword => {
-                if has_serpent_ring_or_frenzied_attack {
-                    12
-                } else {
-                    16
+                if !variant.directional.is_empty() {
+                    warn!(
+                        "Weapon {}: directional attacks can not have variants",
+                        self.id
+                    );
                 }
-            },
+            }
+        }
+        if ranged && self.whirl.is_some() {
+            warn!(
+                "Weapon {}: only melee weapons can whirl",
+                self.id
+            );
         }
     }
+}
 
-    pub fn base_damage(&self) -> f32 {
-        match self {
-            Self::Hammer => 51.0,
-            Self::Sword => 33.0,
+impl AttackStep {
+    pub fn attack(&self, attacker: Entity, attack_mod: f32) -> Attack {
+        let attack = Attack::new(
+            self.lifetime,
+            attacker,
+            self.damage * attack_mod,
+        )
+        .with_max_targets(self.max_targets);
+        match &self.status {
+            Some(status) => attack.set_stat_mod(status.modifier()),
+            None => attack,
         }
     }
 
-    pub fn pushback_values(&self, config: &PlayerConfig) -> PushbackValues {
-        let (pushback, pushback_ticks, self_pushback, self_pushback_ticks) =
-            match self {
-                Self::Hammer => (
-                    config.hammer_pushback,
-                    config.hammer_pushback_ticks,
-                    config.hammer_self_pushback,
-                    config.hammer_self_pushback_ticks,
-                ),
-                Self::Sword => (
-                    config.sword_pushback,
-                    config.sword_pushback_ticks,
-                    config.sword_self_pushback,
-                    config.sword_self_pushback_ticks,
-                ),
-            };
-
+    pub fn pushback_values(&self) -> PushbackValues {
         PushbackValues {
-            pushback,
-            pushback_ticks,
-            self_pushback,
-            self_pushback_ticks,
+            pushback: self.pushback,
+            pushback_ticks: self.pushback_ticks,
+            self_pushback: self.self_pushback,
+            self_pushback_ticks: self.self_pushback_ticks,
         }
     }
 }
 
-#[derive(Resource, Default, PartialEq, Eq, Display)]
-pub enum PlayerRangedWeapon {
-    #[default]
-    Bow,
+impl WhirlDef {
+    pub fn attack_lifetime(&self, passives: &Passives) -> u32 {
+        if passives.contains(&Passive::SerpentRing)
+            || passives.contains(&Passive::FrenziedAttack)
+        {
+            self.frenzied_lifetime
+        } else {
+            self.lifetime
+        }
+    }
+}
+
+impl ScreenShakeDef {
+    pub fn camera_shake(&self) -> CameraShake {
+        CameraShake::new(
+            self.strength,
+            self.duration_secs,
+            self.frequency,
+        )
+    }
+}
+
+impl StatusDef {
+    pub fn modifier(&self) -> StatusModifier {
+        StatusModifier::new(
+            self.id.clone(),
+            self.stats.clone(),
+            self.scalar.clone(),
+            self.delta.clone(),
+            Stacking::Refresh,
+            self.color,
+            self.duration,
+        )
+    }
+}
+
+/// All known weapons, from the weapons asset
+#[derive(Resource, Default)]
+pub struct WeaponDefs {
+    defs: Vec<WeaponDef>,
+    icons: HashMap<WeaponId, Handle<Image>>,
+}
+
+impl WeaponDefs {
+    pub fn get(&self, id: &WeaponId) -> Option<&WeaponDef> {
+        self.defs.iter().find(|def| def.id == *id)
+    }
+
+    pub fn icon(&self, id: &WeaponId) -> Option<&Handle<Image>> {
+        self.icons.get(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &WeaponDef> {
+        self.defs.iter()
+    }
+}
+
+/// The weapons the player holds, and which of them are equipped
+///
+/// Reset to the starting weapons when gameplay starts, and restored from
+/// the save file (after that reset) when loading.
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct PlayerWeapons {
+    /// In the order they were picked up
+    pub owned: Vec<WeaponId>,
+    pub melee: Option<WeaponId>,
+    pub ranged: Option<WeaponId>,
+}
+
+impl PlayerWeapons {
+    /// The weapons the player starts with, the first of each style equipped
+    pub fn starting(defs: &WeaponDefs) -> Self {
+        let mut weapons = PlayerWeapons::default();
+        for def in defs.iter().filter(|def| def.starting) {
+            weapons.owned.push(def.id.clone());
+            if weapons.equipped(def.style).is_none() {
+                *weapons.slot(def.style) = Some(def.id.clone());
+            }
+        }
+        weapons
+    }
+
+    pub fn equipped(&self, style: PlayerCombatStyle) -> Option<&WeaponId> {
+        match style {
+            PlayerCombatStyle::Melee => self.melee.as_ref(),
+            PlayerCombatStyle::Ranged => self.ranged.as_ref(),
+        }
+    }
+
+    fn slot(&mut self, style: PlayerCombatStyle) -> &mut Option<WeaponId> {
+        match style {
+            PlayerCombatStyle::Melee => &mut self.melee,
+            PlayerCombatStyle::Ranged => &mut self.ranged,
+        }
+    }
+
+    /// Pick up a weapon and equip it
+    pub fn add(&mut self, def: &WeaponDef) {
+        if !self.owned.contains(&def.id) {
+            self.owned.push(def.id.clone());
+        }
+        *self.slot(def.style) = Some(def.id.clone());
+    }
+
+    /// Equip the next owned weapon of the style
+    pub fn cycle(&mut self, style: PlayerCombatStyle, defs: &WeaponDefs) {
+        let of_style: Vec<_> = self
+            .owned
+            .iter()
+            .filter(|id| defs.get(id).is_some_and(|def| def.style == style))
+            .cloned()
+            .collect();
+        let current = self
+            .equipped(style)
+            .and_then(|id| of_style.iter().position(|other| other == id));
+        let next = match current {
+            Some(i) => of_style.get((i + 1) % of_style.len()),
+            None => of_style.first(),
+        };
+        if let Some(next) = next.cloned() {
+            *self.slot(style) = Some(next);
+        }
+    }
+
+    /// Forget weapons that are no longer defined, and fill empty slots
+    fn sync(&mut self, defs: &WeaponDefs) {
+        self.owned.retain(|id| defs.get(id).is_some());
+        if self.owned.is_empty() {
+            *self = PlayerWeapons::starting(defs);
+            return;
+        }
+        for style in [PlayerCombatStyle::Melee, PlayerCombatStyle::Ranged] {
+            let equipped = self.equipped(style).is_some_and(|id| {
+                self.owned.contains(id)
+                    && defs.get(id).is_some_and(|def| def.style == style)
+            });
+            if !equipped {
+                *self.slot(style) = None;
+                self.cycle(style, defs);
+            }
+        }
+    }
 }
 
 #[derive(SystemParam)]
 pub struct CurrentWeapon<'w> {
     combat_style: Res<'w, PlayerCombatStyle>,
-    melee_weapon: Res<'w, PlayerMeleeWeapon>,
-    ranged_weapon: Res<'w, PlayerRangedWeapon>,
+    weapons: Res<'w, PlayerWeapons>,
+    defs: Res<'w, WeaponDefs>,
 }
 
 impl CurrentWeapon<'_> {
     pub fn is_changed(&self) -> bool {
         self.combat_style.is_changed()
-            || self.melee_weapon.is_changed()
-            || self.ranged_weapon.is_changed()
+            || self.weapons.is_changed()
+            || self.defs.is_changed()
+    }
+
+    /// The equipped weapon of the current combat style
+    pub fn def(&self) -> Option<&WeaponDef> {
+        self.weapons
+            .equipped(*self.combat_style)
+            .and_then(|id| self.defs.get(id))
+    }
+
+    /// The equipped melee weapon, used for skills even while ranged
+    pub fn melee(&self) -> Option<&WeaponDef> {
+        self.weapons
+            .equipped(PlayerCombatStyle::Melee)
+            .and_then(|id| self.defs.get(id))
+    }
+
+    /// The current weapon's attack played by an `Attacking` player
+    pub fn attack(&self, attacking: &Attacking) -> Option<&AttackStep> {
+        self.def()?.combo_step(attacking.combo, attacking.direction)
     }
 
-    pub fn get_anim_key(&self, action: &str) -> String {
-        let weapon_str = self.to_string();
-        format!("anim.player.{weapon_str}{action}")
+    /// Animation key of an attack, `variant` is `Idle`, `Run` or `Air`
+    pub fn attack_anim_key(
+        &self,
+        attacking: &Attacking,
+        variant: &str,
+    ) -> Option<String> {
+        let step = self.attack(attacking)?;
+        Some(format!("{}{variant}", step.anim))
+    }
+
+    /// Animation slots of all the current weapon's attacks
+    pub fn attack_slots(&self) -> impl Iterator<Item = &str> {
+        self.def()
+            .into_iter()
+            .flat_map(|def| def.attacks())
+            .filter_map(|step| step.slot.as_deref())
+    }
+
+    /// Is the animation one of the current weapon's airborne attacks?
+    pub fn is_air_attack_anim(&self, key: &str) -> bool {
+        self.def().is_some_and(|def| {
+            def.attacks()
+                .any(|step| key.strip_prefix(&*step.anim) == Some("Air"))
+        })
     }
 
     /// Retrieves the Whirling skill animation key for the currently equipped melee weapon.
-    pub fn whirling_anim_key(&self) -> String {
-        let weapon_str = self.melee_weapon.to_string();
-        format!("anim.player.{weapon_str}Whirling")
+    pub fn whirling_anim_key(&self) -> Option<&str> {
+        let whirl = self.melee()?.whirl.as_ref()?;
+        Some(whirl.anim.as_str())
+    }
+
+    /// Slot for the hit sound of the current weapon
+    pub fn hit_slot(&self) -> Option<&str> {
+        Some(self.def()?.hit_slot.as_str())
     }
 
-    pub fn is_wielding_hammer(&self) -> bool {
-        *self.combat_style == PlayerCombatStyle::Melee
-            && *self.melee_weapon == PlayerMeleeWeapon::Hammer
+    pub fn on_hit_screenshake(&self) -> Option<CameraShake> {
+        let shake = self.def()?.screenshake?;
+        Some(shake.camera_shake())
     }
 }
 
-impl std::fmt::Display for CurrentWeapon<'_> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let weapon = match *self.combat_style {
-            PlayerCombatStyle::Ranged => self.ranged_weapon.to_string(),
-            PlayerCombatStyle::Melee => self.melee_weapon.to_string(),
-        };
-        write!(f, "{weapon}")
+fn load_weapon_defs(
+    In(list): In<Option<WeaponList>>,
+    asset_server: Res<AssetServer>,
+    mut defs: ResMut<WeaponDefs>,
+) {
+    let Some(list) = list else {
+        return;
+    };
+    for def in list.weapon.iter() {
+        def.validate();
     }
+    *defs = WeaponDefs {
+        defs: list.weapon.clone(),
+        icons: list
+            .weapon
+            .iter()
+            .map(|def| {
+                (
+                    def.id.clone(),
+                    asset_server.load(&def.icon),
+                )
+            })
+            .collect(),
+    };
 }
 
-fn initialize_resources(mut commands: Commands) {
-    commands.insert_resource(PlayerMeleeWeapon::default());
-    commands.insert_resource(PlayerRangedWeapon::default());
+/// Keep the player's weapons in sync with the definitions
+fn update_player_weapons(
+    defs: Res<WeaponDefs>,
+    mut weapons: ResMut<PlayerWeapons>,
+) {
+    weapons.sync(&defs);
+}
+
+pub(crate) fn initialize_resources(
+    mut commands: Commands,
+    defs: Res<WeaponDefs>,
+) {
+    commands.insert_resource(PlayerWeapons::starting(&defs));
     commands.insert_resource(PlayerCombatStyle::default());
 }
 
 fn swap_combat_style(
     mut combat_style: ResMut<PlayerCombatStyle>,
+    weapons: Res<PlayerWeapons>,
     query: Query<&ActionState<PlayerAction>, With<Player>>,
 ) {
     for action_state in &query {
         if action_state.just_pressed(&PlayerAction::SwapCombatStyle) {
-            *combat_style = match *combat_style {
+            let other = match *combat_style {
                 PlayerCombatStyle::Ranged => PlayerCombatStyle::Melee,
                 PlayerCombatStyle::Melee => PlayerCombatStyle::Ranged,
             };
+            if weapons.equipped(other).is_some() {
+                *combat_style = other;
+            }
         }
     }
 }
 
 fn swap_melee_weapon(
-    mut weapon: ResMut<PlayerMeleeWeapon>,
+    mut weapons: ResMut<PlayerWeapons>,
+    defs: Res<WeaponDefs>,
     query: Query<&ActionState<PlayerAction>, With<Player>>,
 ) {
     for action_state in &query {
         if action_state.just_pressed(&PlayerAction::SwapMeleeWeapon) {
-            *weapon = match *weapon {
-                PlayerMeleeWeapon::Sword => PlayerMeleeWeapon::Hammer,
-                PlayerMeleeWeapon::Hammer => PlayerMeleeWeapon::Sword,
-            };
+            weapons.cycle(PlayerCombatStyle::Melee, &defs);
         }
     }
 }
 
-fn is_current_weapon_changed(current_weapon: CurrentWeapon) -> bool {
-    current_weapon.is_changed()
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn defs() -> WeaponDefs {
+        let list: WeaponList = toml::from_str(include_str!(
+            "../../../../assets/player.weapons.toml"
+        ))
+        .unwrap();
+        WeaponDefs {
+            defs: list.weapon,
+            icons: HashMap::default(),
+        }
+    }
+
+    /// The shipped weapons are complete, and the player starts with a
+    /// weapon of each style
+    #[test]
+    fn shipped_weapons_are_complete() {
+        let defs = defs();
+        for def in defs.iter() {
+            assert!(
+                !def.combo.is_empty(),
+                "{} has no attacks",
+                def.id
+            );
+            if def.style == PlayerCombatStyle::Ranged {
+                assert!(def.combo.iter().all(|step| step.projectile.is_some()));
+            }
+        }
+        let weapons = PlayerWeapons::starting(&defs);
+        assert_eq!(
+            weapons.melee,
+            Some(WeaponId::new("Sword"))
+        );
+        assert_eq!(
+            weapons.ranged,
+            Some(WeaponId::new("Bow"))
+        );
+    }
+
+    #[test]
+    fn weapons_cycle_and_pick_up() {
+        let defs = defs();
+        let mut weapons = PlayerWeapons::starting(&defs);
+        let melee = weapons.melee.clone();
+
+        weapons.cycle(PlayerCombatStyle::Melee, &defs);
+        assert_ne!(weapons.melee, melee);
+        weapons.cycle(PlayerCombatStyle::Melee, &defs);
+        assert_eq!(weapons.melee, melee);
+        // the only ranged weapon stays equipped
+        let ranged = weapons.ranged.clone();
+        weapons.cycle(PlayerCombatStyle::Ranged, &defs);
+        assert_eq!(weapons.ranged, ranged);
+
+        let picked_up = defs
+            .iter()
+            .find(|def| !def.starting)
+            .expect("a weapon to pick up");
+        weapons.add(picked_up);
+        weapons.add(picked_up);
+        assert_eq!(
+            weapons.equipped(picked_up.style),
+            Some(&picked_up.id)
+        );
+        assert_eq!(
+            weapons
+                .owned
+                .iter()
+                .filter(|id| **id == picked_up.id)
+                .count(),
+            1
+        );
+
+        // combos wrap around
+        let first = &picked_up.combo[0];
+        let wrapped =
+            picked_up.combo_step(picked_up.combo.len(), None).unwrap();
+        assert_eq!(first.anim, wrapped.anim);
+    }
+
+    #[test]
+    fn combo_picks_directional_variants() {
+        let defs = defs();
+        let sword = defs.get(&WeaponId::new("Sword")).unwrap();
+        let (finisher, step) = sword
+            .combo
+            .iter()
+            .enumerate()
+            .find(|(_, step)| !step.directional.is_empty())
+            .expect("a combo with a directional finisher");
+        let variant = &step.directional[0];
+        let direction = variant.direction;
+        assert!(direction.is_some());
+
+        let played = sword.combo_step(finisher, direction).unwrap();
+        assert_eq!(played.damage, variant.damage);
+        // without a direction held, or for the other attacks, the
+        // regular attack is played
+        let played = sword.combo_step(finisher, None).unwrap();
+        assert_eq!(played.damage, step.damage);
+        let played = sword.combo_step(finisher + 1, direction).unwrap();
+        assert!(played.direction.is_none());
+        assert_eq!(
+            sword.attacks().count(),
+            sword.combo.len() + 1
+        );
+    }
 }
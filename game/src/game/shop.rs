@@ -0,0 +1,641 @@
+//! Spending xp at the merchant
+//!
+//! Xp orbs picked up during the run are kept as currency ([`Xp`]). A shop is
+//! opened from dialogue, with a `{ trigger = "shop" }` action. It sells
+//! passives from the player's locked pool, stat upgrades, and a reroll of
+//! its stock. When the player already holds [`Passives::MAX`] passives, the
+//! passives for sale become swaps: the player picks one of theirs to give
+//! up, which goes back into the pool.
+//!
+//! The stock is rolled when the shop is first opened, and rolled again on
+//! the next visit (after the player has walked away).
+//!
+//! Prices and upgrades are defined in `merchant.shop.toml` (asset key
+//! `shop.merchant`):
+//!
+//! ```toml
+//! passive_count = 2
+//! passive_price = 120
+//!
+//! [[upgrade]]
+//! id = "whetstone"
+//! name = "shop-upgrade-whetstone"
+//! price = 60
+//! attack = 1.1
+//! ```
+//!
+//! Upgrades use the same multipliers as passives (`attack`, `defense`,
+//! `speed`, `cdr`), and can also add `max_health`.
+
+use bevy::reflect::TypePath;
+use bevy_common_assets::toml::TomlAssetPlugin;
+use leafwing_input_manager::prelude::ActionState;
+use theseeker_engine::assets::reload_on_modified;
+use theseeker_engine::physics::CollisionSensor;
+use theseeker_engine::rng::GameRng;
+
+use super::attack::Health;
+use super::dialogue::{DialogueSet, DialogueTrigger, BLOCKED_ACTIONS};
+use super::player::{
+    Passive, PassiveDefs, Passives, Player, PlayerAction, PlayerStateSet,
+    StatMultipliers,
+};
+use super::xp_orbs::Xp;
+use crate::keybindings::{Control, Keybindings};
+use crate::locale::L10nKey;
+use crate::prelude::*;
+use crate::ui::popup::PopupUi;
+
+/// The [`DialogueTrigger`] that opens the shop
+pub const SHOP_TRIGGER: &str = "shop";
+
+pub struct ShopPlugin;
+
+impl Plugin for ShopPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(TomlAssetPlugin::<ShopConfig>::new(&[
+            "shop.toml",
+        ]));
+        app.init_resource::<ShopConfig>();
+        app.add_systems(
+            GameTickUpdate,
+            (
+                reload_on_modified::<ShopConfig>("shop.merchant")
+                    .pipe(load_shop_config),
+                restock.run_if(any_with_component::<ShopStock>),
+                shop_input
+                    .run_if(resource_exists::<OpenShop>)
+                    .before(DialogueSet),
+                open_shop
+                    .run_if(on_event::<DialogueTrigger>())
+                    .after(DialogueSet),
+            )
+                .before(PlayerStateSet::Behavior)
+                .run_if(
+                    in_state(GameState::Playing)
+                        .and_then(in_state(AppState::InGame)),
+                ),
+        );
+        app.add_systems(
+            Update,
+            update_shop_ui.run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+/// Asset type for the shop's prices and upgrades
+#[derive(Asset, Resource, Debug, Clone, Default)]
+#[derive(Deserialize)]
+#[derive(TypePath)]
+pub struct ShopConfig {
+    /// How many passives are for sale at once
+    pub passive_count: usize,
+    pub passive_price: u32,
+    pub swap_price: u32,
+    /// How many upgrades are for sale at once
+    pub upgrade_count: usize,
+    pub reroll_price: u32,
+    /// Every reroll during a visit costs this much more than the last
+    pub reroll_price_step: u32,
+    #[serde(default)]
+    pub upgrade: Vec<UpgradeDef>,
+}
+
+#[derive(Debug, Clone)]
+#[derive(Deserialize)]
+pub struct UpgradeDef {
+    pub id: String,
+    /// Fluent id of the name shown in the shop
+    pub name: String,
+    pub price: u32,
+    #[serde(default)]
+    pub max_health: u32,
+    #[serde(flatten)]
+    pub stats: StatMultipliers,
+}
+
+impl ShopConfig {
+    pub fn get_upgrade(&self, id: &str) -> Option<&UpgradeDef> {
+        self.upgrade.iter().find(|u| u.id == id)
+    }
+
+    pub fn price(&self, item: &ShopItem) -> u32 {
+        match item {
+            ShopItem::Passive(_) => self.passive_price,
+            ShopItem::Swap(_) => self.swap_price,
+            ShopItem::Upgrade(id) => {
+                self.get_upgrade(id).map(|u| u.price).unwrap_or(0)
+            },
+        }
+    }
+
+    pub fn reroll_price(&self, rerolls: u32) -> u32 {
+        self.reroll_price + self.reroll_price_step * rerolls
+    }
+
+    /// Pick what is for sale
+    pub fn roll_stock<R: Rng + ?Sized>(
+        &self,
+        passives: &Passives,
+        rng: &mut R,
+    ) -> Vec<ShopItem> {
+        let full = passives.is_full();
+        let for_sale = passives
+            .locked
+            .choose_multiple(rng, self.passive_count)
+            .map(|p| {
+                if full {
+                    ShopItem::Swap(p.clone())
+                } else {
+                    ShopItem::Passive(p.clone())
+                }
+            });
+        let upgrades = self
+            .upgrade
+            .choose_multiple(rng, self.upgrade_count)
+            .map(|u| ShopItem::Upgrade(u.id.clone()));
+        for_sale.chain(upgrades).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShopItem {
+    Passive(Passive),
+    /// A passive, in exchange for one of the player's
+    Swap(Passive),
+    /// Id of an [`UpgradeDef`]
+    Upgrade(String),
+}
+
+/// What a shop has for sale during this visit
+#[derive(Component, Debug, Default)]
+pub struct ShopStock {
+    /// The items, and whether they are sold
+    pub items: Vec<(ShopItem, bool)>,
+    pub rerolls: u32,
+}
+
+impl ShopStock {
+    fn new(items: Vec<ShopItem>) -> Self {
+        Self {
+            items: items.into_iter().map(|item| (item, false)).collect(),
+            rerolls: 0,
+        }
+    }
+}
+
+/// Upgrades bought by the player during the run
+#[derive(Component, Debug, Clone, Copy, Default)]
+#[derive(Serialize, Deserialize)]
+pub struct ShopUpgrades {
+    pub stats: StatMultipliers,
+    pub max_health: u32,
+}
+
+/// The shop the player is looking at
+#[derive(Resource, Debug)]
+pub struct OpenShop {
+    pub npc: Entity,
+    /// Index into the shop's [`ShopEntry`]s
+    pub selected: usize,
+    /// Index of the swap item the player is picking a passive for
+    pub swapping: Option<usize>,
+}
+
+/// A line of the shop that can be selected
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShopEntry {
+    /// Index into [`ShopStock::items`]
+    Item(usize),
+    Reroll,
+    Leave,
+    /// Give up this passive for the swap
+    GiveUp(Passive),
+    CancelSwap,
+}
+
+/// What can be selected, in order
+pub fn shop_entries(
+    stock: &ShopStock,
+    swapping: bool,
+    passives: &Passives,
+) -> Vec<ShopEntry> {
+    if swapping {
+        let mut held: Vec<_> = passives.iter().cloned().collect();
+        held.sort_by(|a, b| a.to_string().cmp(&b.to_string()));
+        held.into_iter()
+            .map(ShopEntry::GiveUp)
+            .chain([ShopEntry::CancelSwap])
+            .collect()
+    } else {
+        (0..stock.items.len())
+            .map(ShopEntry::Item)
+            .chain([ShopEntry::Reroll, ShopEntry::Leave])
+            .collect()
+    }
+}
+
+#[derive(Component)]
+struct ShopUi;
+
+fn load_shop_config(
+    In(loaded): In<Option<ShopConfig>>,
+    mut config: ResMut<ShopConfig>,
+) {
+    if let Some(loaded) = loaded {
+        *config = loaded;
+    }
+}
+
+/// The stock is rolled again once the player walks away
+fn restock(
+    mut commands: Commands,
+    q_stock: Query<(Entity, &CollisionSensor), With<ShopStock>>,
+    open: Option<Res<OpenShop>>,
+) {
+    for (entity, sensor) in &q_stock {
+        if !sensor.is_touching() {
+            commands.entity(entity).remove::<ShopStock>();
+            if open.as_ref().is_some_and(|open| open.npc == entity) {
+                commands.remove_resource::<OpenShop>();
+            }
+        }
+    }
+}
+
+fn open_shop(
+    mut commands: Commands,
+    mut triggers: EventReader<DialogueTrigger>,
+    q_stock: Query<(), With<ShopStock>>,
+    q_passives: Query<&Passives, With<Player>>,
+    config: Res<ShopConfig>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Ok(passives) = q_passives.get_single() else {
+        return;
+    };
+    for trigger in triggers.read() {
+        if trigger.id != SHOP_TRIGGER {
+            continue;
+        }
+        if !q_stock.contains(trigger.npc) {
+            let items = config.roll_stock(passives, &mut *rng);
+            commands.entity(trigger.npc).insert(ShopStock::new(items));
+        }
+        commands.insert_resource(OpenShop {
+            npc: trigger.npc,
+            selected: 0,
+            swapping: None,
+        });
+    }
+}
+
+fn shop_input(
+    mut commands: Commands,
+    mut open: ResMut<OpenShop>,
+    mut q_player: Query<
+        (
+            &mut ActionState<PlayerAction>,
+            &mut Passives,
+            &mut Health,
+            Option<&mut ShopUpgrades>,
+            Entity,
+        ),
+        With<Player>,
+    >,
+    mut q_stock: Query<&mut ShopStock>,
+    mut xp: ResMut<Xp>,
+    config: Res<ShopConfig>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Ok((mut action_state, mut passives, mut health, upgrades, e_player)) =
+        q_player.get_single_mut()
+    else {
+        return;
+    };
+    let Ok(mut stock) = q_stock.get_mut(open.npc) else {
+        commands.remove_resource::<OpenShop>();
+        return;
+    };
+    let entries = shop_entries(
+        &stock,
+        open.swapping.is_some(),
+        &passives,
+    );
+    if action_state.just_pressed(&PlayerAction::Jump) {
+        open.selected = (open.selected + entries.len() - 1) % entries.len();
+    }
+    if action_state.just_pressed(&PlayerAction::Fall) {
+        open.selected = (open.selected + 1) % entries.len();
+    }
+    for action in BLOCKED_ACTIONS {
+        action_state.consume(&action);
+    }
+    if !action_state.just_pressed(&PlayerAction::Interact) {
+        return;
+    }
+    action_state.consume(&PlayerAction::Interact);
+
+    match entries.get(open.selected).cloned() {
+        Some(ShopEntry::Item(i)) => {
+            let (item, sold) = &stock.items[i];
+            let price = config.price(item);
+            if *sold || xp.0 < price {
+                return;
+            }
+            match item.clone() {
+                ShopItem::Passive(passive) => {
+                    if passives.is_full() {
+                        return;
+                    }
+                    passives.add_passive(passive);
+                },
+                ShopItem::Swap(_) => {
+                    // paid for once the player picks what to give up
+                    open.swapping = Some(i);
+                    open.selected = 0;
+                    return;
+                },
+                ShopItem::Upgrade(id) => {
+                    let Some(def) = config.get_upgrade(&id) else {
+                        return;
+                    };
+                    let mut bought = upgrades.map(|u| *u).unwrap_or_default();
+                    bought.stats.stack(&def.stats, 1.0);
+                    bought.max_health += def.max_health;
+                    // the max goes up in `update_max_health`
+                    health.current += def.max_health;
+                    commands.entity(e_player).insert(bought);
+                },
+            }
+            xp.0 -= price;
+            stock.items[i].1 = true;
+        },
+        Some(ShopEntry::GiveUp(old)) => {
+            let Some(i) = open.swapping else {
+                return;
+            };
+            let (ShopItem::Swap(new), false) = stock.items[i].clone() else {
+                return;
+            };
+            let price = config.price(&stock.items[i].0);
+            if xp.0 < price || !passives.contains(&old) {
+                return;
+            }
+            xp.0 -= price;
+            passives.replace(&old, new);
+            stock.items[i].1 = true;
+            open.swapping = None;
+            open.selected = 0;
+        },
+        Some(ShopEntry::CancelSwap) => {
+            open.swapping = None;
+            open.selected = 0;
+        },
+        Some(ShopEntry::Reroll) => {
+            let price = config.reroll_price(stock.rerolls);
+            if xp.0 < price {
+                return;
+            }
+            xp.0 -= price;
+            let rerolls = stock.rerolls + 1;
+            *stock = ShopStock::new(config.roll_stock(&passives, &mut *rng));
+            stock.rerolls = rerolls;
+        },
+        Some(ShopEntry::Leave) | None => {
+            commands.remove_resource::<OpenShop>();
+        },
+    }
+}
+
+/// Rebuild the shop popup whenever something in it changes
+fn update_shop_ui(
+    mut commands: Commands,
+    open: Option<Res<OpenShop>>,
+    q_ui: Query<Entity, With<ShopUi>>,
+    q_stock: Query<Ref<ShopStock>>,
+    q_passives: Query<Ref<Passives>, With<Player>>,
+    xp: Res<Xp>,
+    config: Res<ShopConfig>,
+    defs: Res<PassiveDefs>,
+    keybindings: Res<Keybindings>,
+) {
+    let shown = open.as_ref().and_then(|open| {
+        Some((
+            open,
+            q_stock.get(open.npc).ok()?,
+            q_passives.get_single().ok()?,
+        ))
+    });
+    let Some((open, stock, passives)) = shown else {
+        for entity in &q_ui {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    let changed = open.is_changed()
+        || stock.is_changed()
+        || passives.is_changed()
+        || xp.is_changed();
+    if !changed && !q_ui.is_empty() {
+        return;
+    }
+    for entity in &q_ui {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let passive_name = |passive: &Passive| {
+        defs.get(passive)
+            .map(|def| def.name.clone())
+            .unwrap_or_else(|| passive.to_string())
+    };
+    let entries = shop_entries(
+        &stock,
+        open.swapping.is_some(),
+        &passives,
+    );
+    commands.popup().insert(ShopUi).with_children(|popup| {
+        popup.row().with_children(|row| {
+            let title = if open.swapping.is_some() {
+                "shop-swap-title"
+            } else {
+                "shop-title"
+            };
+            row.text("").insert(L10nKey(title.into()));
+        });
+        popup.row().with_children(|row| {
+            row.text("").insert(L10nKey("shop-xp".into()));
+            row.text(format!(" {}", xp.0));
+        });
+        popup.spacer();
+        for (i, entry) in entries.iter().enumerate() {
+            popup.row().with_children(|row| {
+                row.text(if i == open.selected { "> " } else { "  " });
+                let price = match entry {
+                    ShopEntry::Item(i) => {
+                        let (item, sold) = &stock.items[*i];
+                        match item {
+                            ShopItem::Passive(passive) => {
+                                row.text(passive_name(passive));
+                            },
+                            ShopItem::Swap(passive) => {
+                                row.text("")
+                                    .insert(L10nKey("shop-swap".into()));
+                                row.text(format!(" {}", passive_name(passive)));
+                            },
+                            ShopItem::Upgrade(id) => {
+                                let name = config
+                                    .get_upgrade(id)
+                                    .map(|u| u.name.clone())
+                                    .unwrap_or_else(|| id.clone());
+                                row.text("").insert(L10nKey(name));
+                            },
+                        }
+                        (!sold).then(|| config.price(item))
+                    },
+                    ShopEntry::Reroll => {
+                        row.text("").insert(L10nKey("shop-reroll".into()));
+                        Some(config.reroll_price(stock.rerolls))
+                    },
+                    ShopEntry::Leave => {
+                        row.text("").insert(L10nKey("shop-leave".into()));
+                        None
+                    },
+                    ShopEntry::GiveUp(passive) => {
+                        row.text(passive_name(passive));
+                        None
+                    },
+                    ShopEntry::CancelSwap => {
+                        row.text("").insert(L10nKey("shop-cancel".into()));
+                        None
+                    },
+                };
+                if let Some(price) = price {
+                    row.text(format!(" - {} ", price));
+                    row.text("").insert(L10nKey("shop-xp".into()));
+                }
+                if matches!(entry, ShopEntry::Item(i) if stock.items[*i].1) {
+                    row.text(" - ");
+                    row.text("").insert(L10nKey("shop-sold".into()));
+                }
+            });
+        }
+        popup.spacer();
+        popup.row().with_children(|row| {
+            row.control_icons(&keybindings, Control::Jump);
+            row.text(" / ");
+            row.control_icons(&keybindings, Control::Fall);
+            row.text("  ");
+            row.control_icons(&keybindings, Control::Interact);
+        });
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+        passive_count = 2
+        passive_price = 120
+        swap_price = 80
+        upgrade_count = 1
+        reroll_price = 20
+        reroll_price_step = 10
+
+        [[upgrade]]
+        id = "whetstone"
+        name = "shop-upgrade-whetstone"
+        price = 60
+        attack = 1.1
+
+        [[upgrade]]
+        id = "heart"
+        name = "shop-upgrade-heart"
+        price = 90
+        max_health = 20
+    "#;
+
+    fn passive(id: &'static str) -> Passive {
+        toml::Value::String(id.into()).try_into().unwrap()
+    }
+
+    #[test]
+    fn shop_config_parses() {
+        let config: ShopConfig = toml::from_str(CONFIG).unwrap();
+        let whetstone = config.get_upgrade("whetstone").unwrap();
+        assert_eq!(whetstone.stats.attack, 1.1);
+        assert_eq!(whetstone.stats.defense, 1.0);
+        assert_eq!(
+            config.get_upgrade("heart").unwrap().max_health,
+            20
+        );
+        assert_eq!(config.reroll_price(0), 20);
+        assert_eq!(config.reroll_price(2), 40);
+    }
+
+    /// The shipped config is valid and translated in every locale
+    #[test]
+    fn shipped_shop_is_complete() {
+        let config: ShopConfig = toml::from_str(include_str!(
+            "../../../assets/merchant.shop.toml"
+        ))
+        .unwrap();
+        assert!(config.upgrade.len() >= config.upgrade_count);
+        let locales = [
+            include_str!("../../../assets/locale/en-US/shop.ftl"),
+            include_str!("../../../assets/locale/ru-RU/shop.ftl"),
+            include_str!("../../../assets/locale/bg-BG/shop.ftl"),
+        ];
+        for upgrade in &config.upgrade {
+            for locale in locales {
+                assert!(
+                    locale
+                        .lines()
+                        .any(|line| line
+                            .starts_with(&format!("{} =", upgrade.name))),
+                    "{} is not translated",
+                    upgrade.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn stock_offers_swaps_when_passives_are_full() {
+        let config: ShopConfig = toml::from_str(CONFIG).unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut passives =
+            Passives::new(["A", "B", "C", "D", "E"].map(passive));
+
+        let stock = config.roll_stock(&passives, &mut rng);
+        assert_eq!(stock.len(), 3);
+        assert!(matches!(stock[0], ShopItem::Passive(_)));
+        assert!(matches!(stock[1], ShopItem::Passive(_)));
+        assert_ne!(stock[0], stock[1]);
+        assert!(matches!(stock[2], ShopItem::Upgrade(_)));
+
+        for id in ["A", "B", "C"] {
+            passives.add_passive(passive(id));
+        }
+        let stock = config.roll_stock(&passives, &mut rng);
+        assert!(matches!(
+            stock[..],
+            [ShopItem::Swap(_), ShopItem::Swap(_), ShopItem::Upgrade(_)]
+        ));
+
+        let entries = shop_entries(&ShopStock::new(stock), true, &passives);
+        assert_eq!(
+            entries,
+            vec![
+                ShopEntry::GiveUp(passive("A")),
+                ShopEntry::GiveUp(passive("B")),
+                ShopEntry::GiveUp(passive("C")),
+                ShopEntry::CancelSwap,
+            ]
+        );
+    }
+}
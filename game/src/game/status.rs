@@ -0,0 +1,428 @@
+//! Status modifiers (buffs/debuffs) on gameplay stats
+//!
+//! Any gent with [`Stats`] and [`StatusModifiers`] (the player and enemies)
+//! can be affected by any number of modifiers at once. Modifiers with the
+//! same id are the same effect, and their [`Stacking`] rule decides what
+//! happens when it is applied again.
+//!
+//! Effective stats are evaluated in a fixed order: the base value is
+//! multiplied by all scalars, then all deltas are added.
+
+use std::borrow::Cow;
+
+use theseeker_engine::gent::Gent;
+
+use crate::game::attack::DamageFlash;
+use crate::game::enemy::EnemyStateSet;
+use crate::game::player::PlayerStateSet;
+use crate::prelude::*;
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            GameTickUpdate,
+            update_status_modifiers
+                .before(PlayerStateSet::Behavior)
+                .before(EnemyStateSet::Behavior),
+        );
+    }
+}
+
+/// Extend with additional parameter Stats
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatType {
+    MoveVelMax,
+    MoveAccelInit,
+    MoveAccel,
+    WalkingSpeed,
+    ChasingSpeed,
+}
+
+/// What happens when a modifier is applied while one with the same id is
+/// already active
+// not all of these are used by the current modifiers
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stacking {
+    /// Restart the duration
+    Refresh,
+    /// Add a stack (the effect applies once per stack), and restart the duration
+    Stack { max_stacks: u32 },
+    /// Only keep the strongest of the two
+    StrongestWins,
+}
+
+/// A single Status Modifier can modify multiple Stats.
+/// scalar and delta will use the same coefficient for all Stats if there is only one.
+#[derive(Clone, Debug)]
+pub struct StatusModifier {
+    /// Modifiers with the same id are the same effect
+    pub id: Cow<'static, str>,
+    status_types: Vec<StatType>,
+
+    /// Multiplying Factor on Stat, e.g. 102.0 * 0.5 = 51.0
+    scalar: Vec<f32>,
+    /// Offsetting Value on Stat, e.g. 100.0 - 10.0 = 90.0
+    delta: Vec<f32>,
+
+    pub stacking: Stacking,
+
+    effect_col: Color,
+
+    /// In seconds
+    duration: f32,
+}
+
+// TODO: move to attack
+impl StatusModifier {
+    pub fn new(
+        id: impl Into<Cow<'static, str>>,
+        status_types: Vec<StatType>,
+        scalar: Vec<f32>,
+        delta: Vec<f32>,
+        stacking: Stacking,
+        effect_col: Color,
+        duration: f32,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            status_types,
+            scalar,
+            delta,
+            stacking,
+            effect_col,
+            duration,
+        }
+    }
+
+    pub fn basic_ice_spider() -> Self {
+        Self {
+            id: "ice_spider_slow".into(),
+            status_types: vec![
+                StatType::MoveVelMax,
+                StatType::MoveAccel,
+                StatType::MoveAccelInit,
+            ],
+            scalar: vec![0.5],
+            delta: vec![],
+            stacking: Stacking::Refresh,
+            //            effect_col: Color::hex("C2C9C9").unwrap(),
+            effect_col: Color::hex("7aa7ff").unwrap(), /* For More Visible Effect */
+            duration: 2.0,
+        }
+    }
+
+    fn scalar(&self, i: usize) -> f32 {
+        match self.scalar.len() {
+            0 => 1.0,
+            1 => self.scalar[0],
+            _ => self.scalar[i],
+        }
+    }
+
+    fn delta(&self, i: usize) -> f32 {
+        match self.delta.len() {
+            0 => 0.0,
+            1 => self.delta[0],
+            _ => self.delta[i],
+        }
+    }
+
+    /// How far this modifier moves stats away from their base values
+    fn strength(&self) -> f32 {
+        (0..self.status_types.len())
+            .map(|i| (self.scalar(i) - 1.0).abs() + self.delta(i).abs())
+            .sum()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ActiveModifier {
+    modifier: StatusModifier,
+    stacks: u32,
+    /// In seconds
+    time_remaining: f32,
+}
+
+/// All the status modifiers currently affecting a gent
+#[derive(Component, Clone, Debug, Default)]
+pub struct StatusModifiers {
+    /// In order of first application
+    active: Vec<ActiveModifier>,
+}
+
+impl StatusModifiers {
+    pub fn apply(&mut self, modifier: StatusModifier) {
+        let Some(active) = self
+            .active
+            .iter_mut()
+            .find(|a| a.modifier.id == modifier.id)
+        else {
+            self.active.push(ActiveModifier {
+                time_remaining: modifier.duration,
+                stacks: 1,
+                modifier,
+            });
+            return;
+        };
+        match modifier.stacking {
+            Stacking::Refresh => {},
+            Stacking::Stack { max_stacks } => {
+                active.stacks = (active.stacks + 1).min(max_stacks.max(1));
+            },
+            Stacking::StrongestWins => {
+                if modifier.strength() < active.modifier.strength() {
+                    return;
+                }
+            },
+        }
+        active.time_remaining = modifier.duration;
+        active.modifier = modifier;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.active.is_empty()
+    }
+
+    /// Count down the durations, and remove expired modifiers
+    ///
+    /// Returns true if anything was removed.
+    fn tick(&mut self, dt: f32) -> bool {
+        let len = self.active.len();
+        for active in self.active.iter_mut() {
+            active.time_remaining -= dt;
+        }
+        self.active.retain(|a| a.time_remaining > 0.);
+        self.active.len() != len
+    }
+
+    /// Combined (scalar, delta) of all modifiers, for each affected stat
+    fn evaluate(&self) -> HashMap<StatType, (f32, f32)> {
+        let mut r: HashMap<StatType, (f32, f32)> = HashMap::default();
+        for active in self.active.iter() {
+            let modifier = &active.modifier;
+            for (i, stat) in modifier.status_types.iter().enumerate() {
+                let (scalar, delta) = r.entry(*stat).or_insert((1.0, 0.0));
+                *scalar *= modifier.scalar(i).powi(active.stacks as i32);
+                *delta += modifier.delta(i) * active.stacks as f32;
+            }
+        }
+        r
+    }
+
+    /// Blend of the effect colors of all active modifiers
+    pub fn color(&self) -> Option<Color> {
+        if self.active.is_empty() {
+            return None;
+        }
+        let sum = self
+            .active
+            .iter()
+            .map(|a| Vec3::from_slice(&a.modifier.effect_col.as_rgba_f32()))
+            .sum::<Vec3>();
+        let avg = sum / self.active.len() as f32;
+        Some(Color::rgb(avg.x, avg.y, avg.z))
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Stats {
+    base_stats: HashMap<StatType, f32>,
+    effective_stats: HashMap<StatType, f32>,
+    /// Combined (scalar, delta) of the active status modifiers
+    modifiers: HashMap<StatType, (f32, f32)>,
+    /// Values set with [`Stats::set`], kept until [`Stats::reset_stat`]
+    overrides: HashMap<StatType, f32>,
+}
+
+impl Stats {
+    pub fn new(base_stats: impl IntoIterator<Item = (StatType, f32)>) -> Self {
+        let base_stats: HashMap<_, _> = base_stats.into_iter().collect();
+        Self {
+            effective_stats: base_stats.clone(),
+            base_stats,
+            modifiers: HashMap::default(),
+            overrides: HashMap::default(),
+        }
+    }
+
+    /// Replace the base values (keeps status modifiers and overrides in effect)
+    pub fn set_base_stats(
+        &mut self,
+        base_stats: impl IntoIterator<Item = (StatType, f32)>,
+    ) {
+        self.base_stats = base_stats.into_iter().collect();
+        self.refresh_stats();
+    }
+
+    pub fn get(&self, stat: StatType) -> f32 {
+        self.effective_stats[&stat]
+    }
+
+    /// Override the effective value of a stat, until it is reset
+    pub fn set(&mut self, stat: StatType, value: f32) {
+        if let Some(effective_stat) = self.effective_stats.get_mut(&stat) {
+            *effective_stat = value;
+            self.overrides.insert(stat, value);
+        }
+    }
+
+    /// Go back to the base value, with status modifiers applied
+    pub fn reset_stat(&mut self, stat: StatType) {
+        self.overrides.remove(&stat);
+        self.refresh_stat(stat);
+    }
+
+    /// Recompute the effective value of a stat, unless it is overridden
+    fn refresh_stat(&mut self, stat: StatType) {
+        if let Some(value) = self.overrides.get(&stat) {
+            self.effective_stats.insert(stat, *value);
+            return;
+        }
+        let Some(base) = self.base_stats.get(&stat) else {
+            return;
+        };
+        let (scalar, delta) =
+            self.modifiers.get(&stat).copied().unwrap_or((1.0, 0.0));
+        self.effective_stats.insert(stat, base * scalar + delta);
+    }
+
+    fn refresh_stats(&mut self) {
+        let stats: Vec<_> = self.base_stats.keys().copied().collect();
+        for stat in stats {
+            self.refresh_stat(stat);
+        }
+    }
+
+    pub fn update_stats(&mut self, modifiers: &StatusModifiers) {
+        self.modifiers = modifiers.evaluate();
+        self.refresh_stats();
+    }
+}
+
+fn update_status_modifiers(
+    mut query: Query<(
+        &mut StatusModifiers,
+        Option<&mut Stats>,
+        Option<&Gent>,
+    )>,
+    mut sprites: Query<(&mut Sprite, Option<&mut DamageFlash>)>,
+    time: Res<GameTime>,
+) {
+    for (mut modifiers, stats, gent) in query.iter_mut() {
+        // only counts as a change if something expired
+        if modifiers
+            .bypass_change_detection()
+            .tick(1.0 / time.hz as f32)
+        {
+            modifiers.set_changed();
+        }
+        let changed = modifiers.is_changed();
+
+        if changed {
+            if let Some(mut stats) = stats {
+                stats.update_stats(&modifiers);
+            }
+        }
+
+        // only touch the tint when it changes, so the damage flash is not
+        // overwritten (it restores the tint when it is over)
+        if !changed {
+            continue;
+        }
+        let Some(Ok((mut sprite, flash))) =
+            gent.map(|g| sprites.get_mut(g.e_gfx))
+        else {
+            continue;
+        };
+        let alpha = sprite.color.a();
+        let color = modifiers.color().unwrap_or(Color::WHITE).with_a(alpha);
+        match flash {
+            Some(mut flash) => flash.restore_color = color,
+            None => sprite.color = color,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn slow(scalar: f32, stacking: Stacking) -> StatusModifier {
+        StatusModifier {
+            id: "slow".into(),
+            status_types: vec![StatType::MoveVelMax],
+            scalar: vec![scalar],
+            delta: vec![],
+            stacking,
+            effect_col: Color::BLUE,
+            duration: 1.0,
+        }
+    }
+
+    fn effective(modifiers: &StatusModifiers) -> f32 {
+        let mut stats = Stats::new([(StatType::MoveVelMax, 100.0)]);
+        stats.update_stats(modifiers);
+        stats.get(StatType::MoveVelMax)
+    }
+
+    #[test]
+    fn stacking_rules() {
+        let mut modifiers = StatusModifiers::default();
+        modifiers.apply(slow(0.5, Stacking::Refresh));
+        modifiers.tick(0.5);
+        modifiers.apply(slow(0.5, Stacking::Refresh));
+        assert_eq!(effective(&modifiers), 50.0);
+        assert!(!modifiers.tick(0.75));
+
+        let mut modifiers = StatusModifiers::default();
+        for _ in 0..3 {
+            modifiers.apply(slow(
+                0.5,
+                Stacking::Stack { max_stacks: 2 },
+            ));
+        }
+        assert_eq!(effective(&modifiers), 25.0);
+
+        let mut modifiers = StatusModifiers::default();
+        modifiers.apply(slow(0.25, Stacking::StrongestWins));
+        modifiers.apply(slow(0.5, Stacking::StrongestWins));
+        assert_eq!(effective(&modifiers), 25.0);
+        assert!(modifiers.tick(1.0));
+        assert_eq!(effective(&modifiers), 100.0);
+    }
+
+    #[test]
+    fn different_sources_combine() {
+        let mut modifiers = StatusModifiers::default();
+        modifiers.apply(slow(0.5, Stacking::Refresh));
+        modifiers.apply(StatusModifier {
+            id: "haste".into(),
+            delta: vec![10.0],
+            scalar: vec![],
+            effect_col: Color::RED,
+            ..slow(1.0, Stacking::Refresh)
+        });
+        // scalars first, then deltas
+        assert_eq!(effective(&modifiers), 60.0);
+        assert!(modifiers.color().is_some());
+    }
+
+    #[test]
+    fn overrides_survive_modifier_changes() {
+        let mut stats = Stats::new([(StatType::MoveVelMax, 100.0)]);
+        stats.set(StatType::MoveVelMax, 20.0);
+
+        let mut modifiers = StatusModifiers::default();
+        modifiers.apply(slow(0.5, Stacking::Refresh));
+        stats.update_stats(&modifiers);
+        assert_eq!(stats.get(StatType::MoveVelMax), 20.0);
+
+        stats.reset_stat(StatType::MoveVelMax);
+        assert_eq!(stats.get(StatType::MoveVelMax), 50.0);
+    }
+}
@@ -2,13 +2,14 @@ use rapier2d::prelude::InteractionGroups;
 use theseeker_engine::animation::SpriteAnimationBundle;
 use theseeker_engine::assets::animation::SpriteAnimation;
 use theseeker_engine::gent::{Gent, TransformGfxFromGent};
-use theseeker_engine::physics::{Collider, PhysicsWorld, PLAYER, SENSOR};
+use theseeker_engine::physics::{
+    on_collision_changed, Collider, CollisionSensor, PLAYER, SENSOR,
+};
 use theseeker_engine::script::ScriptPlayer;
 
+use crate::level::LevelDespawnMarker;
 use crate::prelude::*;
 
-use super::player::{Idle, Player};
-
 pub struct SwitchesPlugin;
 
 impl Plugin for SwitchesPlugin {
@@ -18,10 +19,7 @@ impl Plugin for SwitchesPlugin {
             (
                 setup_switches.run_if(any_matching::<Added<Switch>>()),
                 setup_puzzles.run_if(any_matching::<Added<Switch>>()),
-                activate_switches.run_if(any_matching::<(
-                    With<Player>,
-                    Without<Idle>,
-                )>()),
+                activate_switches.run_if(on_collision_changed::<Switch>),
             )
                 .run_if(in_state(AppState::InGame)),
         );
@@ -103,6 +101,8 @@ fn setup_switches(
                         filter: PLAYER,
                     },
                 ),
+                CollisionSensor::default(),
+                LevelDespawnMarker,
             ))
             .remove_parent();
         commands.entity(e_gfx).insert((
@@ -119,6 +119,7 @@ fn setup_switches(
                 animation: SpriteAnimationBundle { player },
             },
             StateDespawnMarker,
+            LevelDespawnMarker,
         ));
     }
 }
@@ -149,41 +150,23 @@ fn setup_puzzles(
                 animation: SpriteAnimationBundle { player },
             },
             StateDespawnMarker,
+            LevelDespawnMarker,
         ));
     }
 }
 
 fn activate_switches(
-    query: Query<
-        (
-            Entity,
-            &Gent,
-            &GlobalTransform,
-            &Collider,
-        ),
-        With<Switch>,
-    >,
+    query: Query<(Entity, &Gent, &CollisionSensor), With<Switch>>,
     puzzle_id_query: Query<&PuzzleId>,
-    spatial_query: Res<PhysicsWorld>,
     mut puzzle_visibility_query: Query<(&PuzzleGfx, &mut Visibility)>,
     mut switch_animation_query: Query<
         &mut ScriptPlayer<SpriteAnimation>,
         With<SwitchGfx>,
     >,
 ) {
-    for (entity, gent, transform, collider) in query.iter() {
-        let intersections = spatial_query.intersect(
-            transform.translation().xy(),
-            collider.0.shape(),
-            InteractionGroups {
-                memberships: SENSOR,
-                filter: PLAYER,
-            },
-            Some(entity),
-        );
-
+    for (entity, gent, sensor) in query.iter() {
         if let Ok(mut animation) = switch_animation_query.get_mut(gent.e_gfx) {
-            let should_activate_switch = !intersections.is_empty();
+            let should_activate_switch = sensor.is_touching();
             animation.set_slot("Activated", should_activate_switch);
 
             if let Ok(switch_puzzle_id) = puzzle_id_query.get(entity) {
@@ -1,6 +1,6 @@
 use rapier2d::geometry::InteractionGroups;
 use rapier2d::prelude::Group;
-use theseeker_engine::physics::{Collider, GROUND};
+use theseeker_engine::physics::{Collider, StaticCollider, GROUND};
 
 use crate::prelude::*;
 
@@ -197,6 +197,7 @@ pub fn spawn_wall_collision(
                             10.,
                         ),
                         GlobalTransform::default(),
+                        StaticCollider,
                         StateDespawnMarker,
                     ));
                 }
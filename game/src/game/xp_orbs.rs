@@ -2,11 +2,13 @@ use bevy::prelude::*;
 use rand::Rng;
 use theseeker_engine::{
     physics::LinearVelocity,
+    rng::GameRng,
     time::{GameTickUpdate, GameTime, GameTimeAppExt},
 };
 
 use crate::{
     game::player::{Passive, Passives},
+    level::LevelDespawnMarker,
     prelude::StateDespawnMarker,
 };
 
@@ -16,6 +18,7 @@ pub struct XpPlugin;
 impl Plugin for XpPlugin {
     fn build(&self, app: &mut App) {
         app.add_gametick_event::<XpOrbPickup>();
+        app.init_resource::<Xp>();
         app.add_systems(
             GameTickUpdate,
             (
@@ -35,19 +38,22 @@ pub struct XpOrb {
 #[derive(Event)]
 pub struct XpOrbPickup;
 
+/// Xp collected during the run, that can be spent in shops
+#[derive(Resource, Debug, Default, Clone, Copy, Deref, DerefMut)]
+pub struct Xp(pub u32);
+
 fn spawn_orbs_on_death(
     enemy_q: Query<&GlobalTransform, (With<Enemy>, Added<Dead>)>,
     player_q: Query<&Passives, With<Player>>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>
+    asset_server: Res<AssetServer>,
+    mut rng: ResMut<GameRng>,
 ) {
     let size = Vec2::splat(2.0);
 
     for tr in enemy_q.iter() {
         let enemy_pos = tr.translation().truncate();
 
-        let mut rng = rand::thread_rng();
-
         let init_vel = Vec2::new(0.0, 2.0);
         const POS_RADIUS: f32 = 3.0;
         for _ in 0..12 {
@@ -84,6 +90,7 @@ fn spawn_orbs_on_death(
                     ..default()
                 },
                 StateDespawnMarker,
+                LevelDespawnMarker,
             ));
         }
     }
@@ -102,6 +109,7 @@ fn update_orbs_vel(
     )>,
     mut p_query: Query<(&GlobalTransform, &Passives), With<Player>>,
     mut xp_event: EventWriter<XpOrbPickup>,
+    mut xp: ResMut<Xp>,
 ) {
     let Ok((p, passives)) = p_query.get_single() else {
         return;
@@ -126,6 +134,7 @@ fn update_orbs_vel(
         if dist < DIST_THRESHOLD {
             commands.entity(entity).despawn();
             xp_event.send(XpOrbPickup);
+            xp.0 += 1;
         } else {
             const SPEEDUP_DIST: f32 = 150.0;
             //let scaled_dist = ((100.0 - dist).powi(2) / 100.).clamp(0.0, 2.);
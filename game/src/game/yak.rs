@@ -1,8 +1,12 @@
+use rapier2d::prelude::InteractionGroups;
 use theseeker_engine::animation::SpriteAnimationBundle;
 use theseeker_engine::assets::animation::SpriteAnimation;
 use theseeker_engine::gent::TransformGfxFromGent;
+use theseeker_engine::physics::{Collider, CollisionSensor, PLAYER, SENSOR};
 use theseeker_engine::script::ScriptPlayer;
 
+use super::dialogue::DialogueNpc;
+use crate::level::LevelDespawnMarker;
 use crate::prelude::*;
 
 pub struct YakPlugin;
@@ -18,10 +22,26 @@ impl Plugin for YakPlugin {
 }
 
 #[derive(Component, Default)]
-pub struct YakBlueprint;
+pub struct YakBlueprint {
+    /// Asset key of what it says, if it can be talked to
+    dialogue: Option<String>,
+}
+
+impl YakBlueprint {
+    fn from_entity_instance(entity: &EntityInstance) -> Self {
+        Self {
+            dialogue: entity
+                .get_maybe_string_field("dialogue")
+                .ok()
+                .cloned()
+                .flatten(),
+        }
+    }
+}
 
 #[derive(Bundle, LdtkEntity, Default)]
 pub struct YakBlueprintBundle {
+    #[with(YakBlueprint::from_entity_instance)]
     marker: YakBlueprint,
 }
 
@@ -39,10 +59,10 @@ pub struct YakGfxBundle {
 }
 
 pub fn setup_yak(
-    mut q: Query<(&mut Transform, Entity), Added<YakBlueprint>>,
+    mut q: Query<(&mut Transform, Entity, &YakBlueprint), Added<YakBlueprint>>,
     mut commands: Commands,
 ) {
-    for (mut xf_gent, e_gent) in q.iter_mut() {
+    for (mut xf_gent, e_gent, blueprint) in q.iter_mut() {
         println!("added yak");
         xf_gent.translation.z = 12.0 * 0.000001;
         xf_gent.translation.y += 5.0;
@@ -50,8 +70,22 @@ pub fn setup_yak(
         let e_gfx = commands.spawn(()).id();
         commands
             .entity(e_gent)
-            .insert(Name::new("Yak"))
+            .insert((Name::new("Yak"), LevelDespawnMarker))
             .remove_parent();
+        if let Some(dialogue) = &blueprint.dialogue {
+            commands.entity(e_gent).insert((
+                Collider::cuboid(
+                    40.0,
+                    40.0,
+                    InteractionGroups {
+                        memberships: SENSOR,
+                        filter: PLAYER,
+                    },
+                ),
+                CollisionSensor::default(),
+                DialogueNpc::new(dialogue),
+            ));
+        }
         let mut player = ScriptPlayer::<SpriteAnimation>::default();
         player.play_key("anim.yak.Idle");
         commands.entity(e_gfx).insert((
@@ -68,6 +102,7 @@ pub fn setup_yak(
                 animation: SpriteAnimationBundle { player },
             },
             StateDespawnMarker,
+            LevelDespawnMarker,
         ));
     }
 }
@@ -0,0 +1,140 @@
+use bevy::prelude::*;
+
+use super::enemy_hp::{DamageAnimation, Material};
+use crate::appstate::StateDespawnMarker;
+use crate::assets::UiAssets;
+use crate::game::attack::Health;
+use crate::game::boss::{Boss, BossFight};
+use crate::game::gentstate::Dead;
+use crate::prelude::Update;
+
+const BACKGROUND_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+
+/// Big health bar at the bottom of the screen, while fighting a boss
+///
+/// Uses the same material as the enemy health bars,
+/// which `EnemyHpBarPlugin` sets up.
+pub struct BossHpBarPlugin;
+
+impl Plugin for BossHpBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (instance, update_hp, despawn).chain(),
+        );
+    }
+}
+
+#[derive(Component)]
+struct Root {
+    boss: Entity,
+}
+
+#[derive(Component)]
+struct Bar {
+    boss: Entity,
+}
+
+fn instance(
+    mut commands: Commands,
+    boss_q: Query<(Entity, &Boss), Added<BossFight>>,
+    mut material: ResMut<Assets<Material>>,
+    uiassets: Res<UiAssets>,
+) {
+    for (entity, boss) in boss_q.iter() {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Percent(20.0),
+                        right: Val::Percent(20.0),
+                        bottom: Val::Px(24.0),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                },
+                Root { boss: entity },
+                StateDespawnMarker,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    boss.name.clone(),
+                    TextStyle {
+                        font: uiassets.font_regular.clone(),
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                    },
+                ));
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            height: Val::Px(14.0),
+                            padding: UiRect::horizontal(Val::Px(2.0)),
+                            ..default()
+                        },
+                        background_color: BACKGROUND_COLOR.into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn((
+                            MaterialNodeBundle {
+                                style: Style {
+                                    width: Val::Percent(100.0),
+                                    height: Val::Percent(200.0),
+                                    align_self: AlignSelf::Center,
+                                    ..default()
+                                },
+                                material: material.add(Material::full()),
+                                ..default()
+                            },
+                            Bar { boss: entity },
+                            DamageAnimation::default(),
+                        ));
+                    });
+            });
+    }
+}
+
+fn update_hp(
+    boss_q: Query<&Health, With<Boss>>,
+    mut hp_bar_q: Query<(
+        &Bar,
+        &Handle<Material>,
+        &mut DamageAnimation,
+    )>,
+    mut material: ResMut<Assets<Material>>,
+) {
+    for (hp_bar, handle, mut damage_animation) in &mut hp_bar_q {
+        let Ok(health) = boss_q.get(hp_bar.boss) else {
+            continue;
+        };
+        let Some(material) = material.get_mut(handle) else {
+            continue;
+        };
+        let health_factor = health.current as f32 / health.max as f32;
+        material.set_health(health_factor, &mut damage_animation);
+    }
+}
+
+fn despawn(
+    mut commands: Commands,
+    boss_q: Query<
+        (),
+        (
+            With<Boss>,
+            With<BossFight>,
+            Without<Dead>,
+        ),
+    >,
+    hp_root_q: Query<(Entity, &Root)>,
+) {
+    for (hp_entity, hp_root) in &hp_root_q {
+        if !boss_q.contains(hp_root.boss) {
+            commands.entity(hp_entity).despawn_recursive();
+        }
+    }
+}
@@ -53,6 +53,17 @@ pub struct DamageAnimation {
     delay: Timer,
 }
 
+impl Default for DamageAnimation {
+    fn default() -> Self {
+        Self {
+            delay: Timer::new(
+                Duration::from_millis(ANIMATION_DELAY_IN_MILLIS),
+                TimerMode::Once,
+            ),
+        }
+    }
+}
+
 #[derive(Asset, TypePath, AsBindGroup, Clone, Copy, Debug)]
 pub struct Material {
     /// A number between `0` and `1` indicating the health amount.
@@ -69,6 +80,35 @@ impl UiMaterial for Material {
     }
 }
 
+impl Material {
+    /// A bar at full health
+    pub fn full() -> Self {
+        Self {
+            health: 1.0,
+            damage: 1.0,
+        }
+    }
+
+    /// Show a new health amount (between `0` and `1`), with the damage
+    /// taken indicator catching up to it after a delay
+    pub fn set_health(
+        &mut self,
+        health_factor: f32,
+        damage_animation: &mut DamageAnimation,
+    ) {
+        // Reset the damage animation on taking damage
+        if self.health != health_factor {
+            damage_animation.delay.reset();
+        }
+
+        if damage_animation.delay.finished() {
+            self.damage = self.damage.lerp(health_factor, ANIMATION_SPEED);
+        }
+
+        self.health = health_factor;
+    }
+}
+
 fn instance(
     mut commands: Commands,
     enemy_q: Query<(Entity, Ref<Health>), (With<GlobalTransform>, With<Enemy>)>,
@@ -102,21 +142,11 @@ fn instance(
                                 align_self: AlignSelf::Center,
                                 ..default()
                             },
-                            material: material.add(Material {
-                                health: 1.0,
-                                damage: 1.0,
-                            }),
+                            material: material.add(Material::full()),
                             ..default()
                         },
                         Bar { parent: entity },
-                        DamageAnimation {
-                            delay: Timer::new(
-                                Duration::from_millis(
-                                    ANIMATION_DELAY_IN_MILLIS,
-                                ),
-                                TimerMode::Once,
-                            ),
-                        },
+                        DamageAnimation::default(),
                     ));
                 });
         }
@@ -185,18 +215,7 @@ fn update_hp(
         ))?;
 
         let health_factor = 1.0 * (health.current as f32 / health.max as f32);
-
-        // Reset the damage animation on taking damage
-        if material.health != health_factor {
-            damage_animation.delay.reset();
-        }
-
-        if damage_animation.delay.finished() {
-            material.damage =
-                material.damage.lerp(health_factor, ANIMATION_SPEED);
-        }
-
-        material.health = health_factor;
+        material.set_health(health_factor, &mut damage_animation);
     }
 
     Ok(())
@@ -1,4 +1,5 @@
 pub mod ability_cooldown;
+mod boss_hp;
 mod dmg_numbers;
 pub mod dof;
 pub mod enemy_hp;
@@ -12,6 +13,7 @@ use bevy_hanabi::HanabiPlugin;
 use post_processing::PostProcessingPlugin;
 
 use crate::graphics::ability_cooldown::AbilityCooldownPlugin;
+use crate::graphics::boss_hp::BossHpBarPlugin;
 use crate::graphics::dmg_numbers::DmgNumbersPlugin;
 use crate::graphics::dof::DepthOfFieldPlugin;
 use crate::graphics::enemy_hp::EnemyHpBarPlugin;
@@ -27,6 +29,7 @@ impl Plugin for GraphicsFxPlugin {
         app.add_plugins(DmgNumbersPlugin);
         app.add_plugins(PlayerHpBarPlugin);
         app.add_plugins(EnemyHpBarPlugin);
+        app.add_plugins(BossHpBarPlugin);
         app.add_plugins(AbilityCooldownPlugin);
         app.add_plugins(HanabiPlugin);
     }
@@ -0,0 +1,560 @@
+//! Gamepad rumble for combat events
+//!
+//! The rumble patterns are defined in `gamepad.haptics.toml` (asset key
+//! `haptics.gamepad`). Each pattern is a list of steps, played one after
+//! another. A step sets the strength of the strong (low frequency) and
+//! weak (high frequency) motors, from 0 to 1, for some milliseconds:
+//!
+//! ```toml
+//! hurt = [
+//!     { strong = 0.8, weak = 0.4, millis = 120 },
+//!     { millis = 60 },
+//!     { strong = 0.3, millis = 80 },
+//! ]
+//! ```
+//!
+//! A step with both motors at 0 is a pause.
+//!
+//! Rumble is not sent to the gamepads directly. It is sent as [`Rumble`]
+//! events, which [`rumble_gamepads`] passes on to all connected gamepads.
+//! Tests (and anything else that wants to know about it) can read the
+//! events instead, without needing a gamepad.
+//!
+//! The user can turn rumble off, or make it weaker, in the settings screen.
+//! This is saved in a config file in the user's config directory.
+
+use bevy::input::gamepad::{GamepadRumbleIntensity, GamepadRumbleRequest};
+use bevy::reflect::TypePath;
+use bevy_common_assets::toml::TomlAssetPlugin;
+use theseeker_engine::assets::reload_on_modified;
+
+use crate::game::attack::{DamageInfo, RespondToDamageInfoSet};
+use crate::game::gentstate::Dead;
+use crate::game::player::{Dashing, Player};
+use crate::prelude::*;
+use crate::user_config::UserConfig;
+
+pub struct HapticsPlugin;
+
+impl Plugin for HapticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(TomlAssetPlugin::<HapticPatterns>::new(
+            &["haptics.toml"],
+        ));
+        // headless games insert their own, so they don't depend on the user's config
+        if !app.world.contains_resource::<HapticsSettings>() {
+            app.insert_resource(HapticsSettings::load_or_default(
+                &HapticsSettings::default_path(),
+            ));
+        }
+        app.init_resource::<RumblePlayer>();
+        app.add_gametick_event::<Rumble>();
+        app.add_systems(
+            GameTickUpdate,
+            (
+                reload_on_modified::<HapticPatterns>("haptics.gamepad")
+                    .pipe(load_haptic_patterns),
+                (
+                    rumble_on_damage,
+                    rumble_on_dash,
+                    rumble_on_death,
+                )
+                    .after(RespondToDamageInfoSet)
+                    .run_if(in_state(AppState::InGame)),
+                play_rumble,
+                rumble_gamepads,
+            )
+                .chain(),
+        );
+        app.add_systems(
+            Update,
+            stop_rumble.run_if(resource_changed::<HapticsSettings>),
+        );
+        app.register_clicommand_noargs("haptics_toggle", cli_haptics_toggle);
+        app.register_clicommand_args(
+            "haptics_intensity",
+            cli_haptics_intensity,
+        );
+    }
+}
+
+/// Something that happened, which can have a rumble pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HapticEvent {
+    /// The player hit something
+    Hit,
+    /// The player landed a critical hit
+    Crit,
+    /// The player hit something while stealthed
+    Stealthed,
+    /// The player took damage
+    Hurt,
+    /// The player dashed
+    Dash,
+    /// The player died
+    Death,
+}
+
+/// One step of a rumble pattern
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Deserialize)]
+pub struct RumbleStep {
+    #[serde(default)]
+    pub strong: f32,
+    #[serde(default)]
+    pub weak: f32,
+    pub millis: u32,
+}
+
+impl RumbleStep {
+    /// How many game ticks the step lasts (at least one)
+    fn ticks(&self, hz: f64) -> u32 {
+        ((self.millis as f64 / 1000.0 * hz).round() as u32).max(1)
+    }
+
+    fn is_pause(&self) -> bool {
+        self.strong <= 0.0 && self.weak <= 0.0
+    }
+}
+
+/// Steps that are played one after another
+#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Deserialize)]
+#[serde(transparent)]
+pub struct RumblePattern(pub Vec<RumbleStep>);
+
+impl RumblePattern {
+    /// The step that starts on the given tick of playback, if any
+    fn step_starting_at(&self, tick: u32, hz: f64) -> Option<&RumbleStep> {
+        let mut start = 0;
+        for step in &self.0 {
+            if start == tick {
+                return Some(step);
+            }
+            start += step.ticks(hz);
+        }
+        None
+    }
+
+    /// How many game ticks the whole pattern lasts
+    fn ticks(&self, hz: f64) -> u32 {
+        self.0.iter().map(|step| step.ticks(hz)).sum()
+    }
+}
+
+/// Asset type for the file with the rumble patterns
+#[derive(Asset, Debug, Clone, Default)]
+#[derive(Deserialize)]
+#[derive(TypePath)]
+pub struct HapticPatterns {
+    #[serde(default)]
+    pub hit: RumblePattern,
+    #[serde(default)]
+    pub crit: RumblePattern,
+    #[serde(default)]
+    pub stealthed: RumblePattern,
+    #[serde(default)]
+    pub hurt: RumblePattern,
+    #[serde(default)]
+    pub dash: RumblePattern,
+    #[serde(default)]
+    pub death: RumblePattern,
+}
+
+impl HapticPatterns {
+    pub fn get(&self, event: HapticEvent) -> &RumblePattern {
+        match event {
+            HapticEvent::Hit => &self.hit,
+            HapticEvent::Crit => &self.crit,
+            HapticEvent::Stealthed => &self.stealthed,
+            HapticEvent::Hurt => &self.hurt,
+            HapticEvent::Dash => &self.dash,
+            HapticEvent::Death => &self.death,
+        }
+    }
+}
+
+/// The user's rumble settings
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct HapticsSettings {
+    pub enabled: bool,
+    /// Multiplies the strength of all rumble (0 to 1)
+    pub intensity: f32,
+}
+
+impl Default for HapticsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl UserConfig for HapticsSettings {
+    const FILE_NAME: &'static str = "haptics.toml";
+    const DESCRIPTION: &'static str = "haptics settings";
+
+    fn after_load(&mut self) {
+        self.intensity = self.intensity.clamp(0.0, 1.0);
+    }
+}
+
+impl HapticsSettings {
+    /// Change the intensity, keeping it between 0 and 1
+    pub fn set_intensity(&mut self, intensity: f32) {
+        // avoid float noise from repeated steps, like 0.70000005
+        self.intensity = (intensity.clamp(0.0, 1.0) * 100.0).round() / 100.0;
+    }
+}
+
+/// Event: rumble the gamepads
+///
+/// The intensity already has the user's settings applied.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct Rumble {
+    pub intensity: GamepadRumbleIntensity,
+    pub duration: Duration,
+}
+
+/// The rumble patterns that are currently playing
+#[derive(Resource, Default)]
+pub struct RumblePlayer {
+    patterns: HapticPatterns,
+    /// Which patterns are playing, and for how many ticks so far
+    playing: Vec<(HapticEvent, u32)>,
+}
+
+impl RumblePlayer {
+    /// Start playing the pattern for the event (from the beginning)
+    pub fn start(&mut self, event: HapticEvent) {
+        self.playing.retain(|(playing, _)| *playing != event);
+        if !self.patterns.get(event).0.is_empty() {
+            self.playing.push((event, 0));
+        }
+    }
+
+    /// Advance playback by one tick, returning the steps that start now
+    fn tick(&mut self, hz: f64) -> Vec<RumbleStep> {
+        let mut steps = vec![];
+        let patterns = &self.patterns;
+        self.playing.retain_mut(|(event, tick)| {
+            let pattern = patterns.get(*event);
+            if let Some(step) = pattern.step_starting_at(*tick, hz) {
+                if !step.is_pause() {
+                    steps.push(*step);
+                }
+            }
+            *tick += 1;
+            *tick < pattern.ticks(hz)
+        });
+        steps
+    }
+}
+
+fn load_haptic_patterns(
+    In(patterns): In<Option<HapticPatterns>>,
+    mut player: ResMut<RumblePlayer>,
+) {
+    if let Some(patterns) = patterns {
+        player.patterns = patterns;
+    }
+}
+
+/// Which rumble pattern (if any) a damage event should play
+fn damage_haptic_event(
+    info: &DamageInfo,
+    player: Entity,
+) -> Option<HapticEvent> {
+    if info.target == player {
+        Some(HapticEvent::Hurt)
+    } else if info.attacker != player {
+        None
+    } else if info.stealthed {
+        Some(HapticEvent::Stealthed)
+    } else if info.crit {
+        Some(HapticEvent::Crit)
+    } else {
+        Some(HapticEvent::Hit)
+    }
+}
+
+fn rumble_on_damage(
+    mut ev_damage: EventReader<DamageInfo>,
+    q_player: Query<Entity, With<Player>>,
+    settings: Res<HapticsSettings>,
+    mut player: ResMut<RumblePlayer>,
+) {
+    let Ok(e_player) = q_player.get_single() else {
+        return;
+    };
+    // one attack can hit many things at once, but should only rumble once
+    let events: HashSet<HapticEvent> = ev_damage
+        .read()
+        .filter_map(|info| damage_haptic_event(info, e_player))
+        .collect();
+    if !settings.enabled {
+        return;
+    }
+    for event in events {
+        player.start(event);
+    }
+}
+
+fn rumble_on_dash(
+    q_player: Query<(), (With<Player>, Added<Dashing>)>,
+    settings: Res<HapticsSettings>,
+    mut player: ResMut<RumblePlayer>,
+) {
+    if settings.enabled && !q_player.is_empty() {
+        player.start(HapticEvent::Dash);
+    }
+}
+
+fn rumble_on_death(
+    q_player: Query<(), (With<Player>, Added<Dead>)>,
+    settings: Res<HapticsSettings>,
+    mut player: ResMut<RumblePlayer>,
+) {
+    if settings.enabled && !q_player.is_empty() {
+        player.start(HapticEvent::Death);
+    }
+}
+
+fn play_rumble(
+    mut player: ResMut<RumblePlayer>,
+    settings: Res<HapticsSettings>,
+    time: Res<GameTime>,
+    mut ev_rumble: EventWriter<Rumble>,
+) {
+    if player.playing.is_empty() {
+        return;
+    }
+    for step in player.tick(time.hz) {
+        ev_rumble.send(Rumble {
+            intensity: GamepadRumbleIntensity {
+                strong_motor: step.strong * settings.intensity,
+                weak_motor: step.weak * settings.intensity,
+            },
+            duration: Duration::from_millis(step.millis as u64),
+        });
+    }
+}
+
+/// Send the rumble to all connected gamepads
+pub fn rumble_gamepads(
+    mut ev_rumble: EventReader<Rumble>,
+    gamepads: Res<Gamepads>,
+    mut ev_request: EventWriter<GamepadRumbleRequest>,
+) {
+    for rumble in ev_rumble.read() {
+        for gamepad in gamepads.iter() {
+            ev_request.send(GamepadRumbleRequest::Add {
+                gamepad,
+                intensity: rumble.intensity,
+                duration: rumble.duration,
+            });
+        }
+    }
+}
+
+/// Stop all rumble right away when it gets disabled
+fn stop_rumble(
+    settings: Res<HapticsSettings>,
+    gamepads: Res<Gamepads>,
+    mut player: ResMut<RumblePlayer>,
+    mut ev_request: EventWriter<GamepadRumbleRequest>,
+) {
+    if settings.enabled {
+        return;
+    }
+    player.playing.clear();
+    for gamepad in gamepads.iter() {
+        ev_request.send(GamepadRumbleRequest::Stop { gamepad });
+    }
+}
+
+fn cli_haptics_toggle(mut settings: ResMut<HapticsSettings>) {
+    settings.enabled = !settings.enabled;
+    settings.save_user_config();
+}
+
+fn cli_haptics_intensity(
+    In(args): In<Vec<String>>,
+    mut settings: ResMut<HapticsSettings>,
+) {
+    // a leading + or - changes the current intensity
+    let parsed = match args.as_slice() {
+        [arg] if arg.starts_with(['+', '-']) => {
+            arg.parse::<f32>().map(|delta| settings.intensity + delta)
+        },
+        [arg] => arg.parse::<f32>(),
+        _ => {
+            error!("\"haptics_intensity <0..1>\" or \"haptics_intensity <+/-change>\"");
+            return;
+        },
+    };
+    match parsed {
+        Ok(intensity) => {
+            settings.set_intensity(intensity);
+            settings.save_user_config();
+        },
+        Err(e) => {
+            error!("\"haptics_intensity\": {}", e);
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::headless::HeadlessGame;
+
+    fn patterns() -> HapticPatterns {
+        toml::from_str(
+            r#"
+            hit = [{ weak = 0.5, millis = 20 }]
+            hurt = [
+                { strong = 0.8, weak = 0.4, millis = 30 },
+                { millis = 20 },
+                { strong = 0.3, millis = 10 },
+            ]
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rumble_pattern_playback() {
+        // 100 ticks per second, so ticks are 10 millis
+        let hz = 100.0;
+        let mut player = RumblePlayer {
+            patterns: patterns(),
+            playing: vec![],
+        };
+        assert_eq!(player.patterns.hurt.ticks(hz), 6);
+
+        player.start(HapticEvent::Hurt);
+        // no pattern for it, so nothing plays
+        player.start(HapticEvent::Dash);
+        let steps: Vec<Vec<RumbleStep>> =
+            (0..8).map(|_| player.tick(hz)).collect();
+        let hurt = &player.patterns.hurt.0;
+        assert_eq!(
+            steps,
+            vec![
+                vec![hurt[0]],
+                vec![],
+                vec![],
+                // the pause makes no rumble
+                vec![],
+                vec![],
+                vec![hurt[2]],
+                vec![],
+                vec![],
+            ]
+        );
+        assert!(player.playing.is_empty());
+
+        // restarting a pattern plays it from the beginning
+        player.start(HapticEvent::Hit);
+        player.tick(hz);
+        player.start(HapticEvent::Hit);
+        assert_eq!(player.tick(hz).len(), 1);
+    }
+
+    #[test]
+    fn damage_maps_to_haptic_events() {
+        let player = Entity::from_raw(1);
+        let enemy = Entity::from_raw(2);
+        let info = DamageInfo {
+            attacker: player,
+            source: player,
+            target: enemy,
+            amount: 10.0,
+            crit: false,
+            stealthed: false,
+        };
+        let event = |info| damage_haptic_event(&info, player);
+        assert_eq!(event(info), Some(HapticEvent::Hit));
+        assert_eq!(
+            event(DamageInfo { crit: true, ..info }),
+            Some(HapticEvent::Crit)
+        );
+        assert_eq!(
+            event(DamageInfo {
+                crit: true,
+                stealthed: true,
+                ..info
+            }),
+            Some(HapticEvent::Stealthed)
+        );
+        assert_eq!(
+            event(DamageInfo {
+                attacker: enemy,
+                source: enemy,
+                target: player,
+                ..info
+            }),
+            Some(HapticEvent::Hurt)
+        );
+        assert_eq!(
+            event(DamageInfo {
+                attacker: enemy,
+                ..info
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn haptics_intensity_is_clamped() {
+        let mut settings = HapticsSettings::default();
+        settings.set_intensity(settings.intensity - 0.3);
+        assert_eq!(settings.intensity, 0.7);
+        settings.set_intensity(1.5);
+        assert_eq!(settings.intensity, 1.0);
+        settings.set_intensity(-0.5);
+        assert_eq!(settings.intensity, 0.0);
+    }
+
+    #[test]
+    fn player_damage_sends_rumble() {
+        let mut game = HeadlessGame::new();
+        game.load_assets().enter_level("level.dev");
+        let player = game.spawn_player(Vec2::new(64.0, 64.0));
+        game.world_mut().resource_mut::<HapticsSettings>().intensity = 0.5;
+        game.step_ticks(1);
+        let take_rumble = |game: &mut HeadlessGame| -> Vec<Rumble> {
+            game.world_mut()
+                .resource_mut::<Events<Rumble>>()
+                .drain()
+                .collect()
+        };
+        assert_eq!(take_rumble(&mut game), vec![]);
+
+        let enemy = game.world_mut().spawn_empty().id();
+        game.world_mut().send_event(DamageInfo {
+            attacker: enemy,
+            source: enemy,
+            target: player,
+            amount: 10.0,
+            crit: false,
+            stealthed: false,
+        });
+        game.step_ticks(1);
+        let step = game.world().resource::<RumblePlayer>().patterns.hurt.0[0];
+        assert_eq!(
+            take_rumble(&mut game),
+            vec![Rumble {
+                intensity: GamepadRumbleIntensity {
+                    strong_motor: step.strong * 0.5,
+                    weak_motor: step.weak * 0.5,
+                },
+                duration: Duration::from_millis(step.millis as u64),
+            }]
+        );
+    }
+}
@@ -0,0 +1,273 @@
+//! Headless App
+//!
+//! Runs the full game (same plugins as the real app), but without a window
+//! or a GPU, and with `GameTime` in manual mode: nothing is simulated unless
+//! explicitly asked for. This is what lets us test gameplay systems
+//! deterministically, by stepping an exact number of game ticks.
+//!
+//! Use like:
+//!
+//! ```ignore
+//! let mut game = HeadlessGame::new();
+//! game.load_assets().enter_level("level.dev");
+//! let player = game.spawn_player(Vec2::new(64.0, 64.0));
+//! game.step_ticks(96);
+//! let health = game.world().get::<Health>(player).unwrap();
+//! ```
+
+use bevy::render::settings::WgpuSettings;
+use bevy::render::RenderPlugin;
+use bevy::window::ExitCondition;
+use theseeker_engine::physics::Collider;
+use theseeker_engine::replay::{InputReplay, InputReplayer};
+
+use crate::game::player::{PlayerAction, PlayerBlueprint};
+use crate::haptics::HapticsSettings;
+use crate::keybindings::Keybindings;
+use crate::level::StartingLevel;
+use crate::prelude::*;
+
+/// Wrapper around a headless [`App`], with helpers for driving it
+pub struct HeadlessGame {
+    pub app: App,
+}
+
+impl HeadlessGame {
+    /// Give up waiting for something (such as assets) after this many frames
+    const MAX_WAIT_FRAMES: u32 = 10_000;
+    /// Fixed RNG seed, so that every headless run is the same
+    pub const SEED: u64 = 0x5EE4E4;
+
+    pub fn new() -> Self {
+        let mut app = App::new();
+
+        let bevy_plugins = DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(RenderPlugin {
+                // no backends means no GPU; the render sub-app is never created
+                render_creation: WgpuSettings {
+                    backends: None,
+                    ..Default::default()
+                }
+                .into(),
+                synchronous_pipeline_compilation: false,
+            })
+            .set(ImagePlugin::default_nearest())
+            .disable::<bevy::winit::WinitPlugin>()
+            // the global logger can only be set once per process,
+            // and tests run many apps in the same process
+            .disable::<bevy::log::LogPlugin>();
+
+        // must be inserted before the engine plugins, so they don't add the default one
+        app.insert_resource(GameTime::new_manual(96.0));
+        app.insert_resource(GameRng::from_seed(Self::SEED));
+        // don't depend on the user's keybindings config file
+        app.insert_resource(Keybindings::default());
+        app.insert_resource(HapticsSettings::default());
+        app.add_plugins(bevy_plugins);
+        crate::add_game_plugins(&mut app);
+
+        Self { app }
+    }
+
+    pub fn world(&self) -> &World {
+        &self.app.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    pub fn app_state(&self) -> AppState {
+        *self.app.world.resource::<State<AppState>>().get()
+    }
+
+    /// Run Bevy frame updates (without any game ticks) until `done` returns true
+    ///
+    /// Panics if it takes too long. `what` is only used for the panic message.
+    pub fn update_until(
+        &mut self,
+        what: &str,
+        mut done: impl FnMut(&mut World) -> bool,
+    ) -> &mut Self {
+        for _ in 0..Self::MAX_WAIT_FRAMES {
+            if done(&mut self.app.world) {
+                return self;
+            }
+            self.app.update();
+            // asset loading happens on other threads, give it a chance
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("Headless game timed out waiting for {}", what);
+    }
+
+    /// Wait for the loading screen to finish (we end up in the main menu)
+    pub fn load_assets(&mut self) -> &mut Self {
+        self.update_until("assets to load", |world| {
+            *world.resource::<State<AppState>>().get() == AppState::MainMenu
+        })
+    }
+
+    /// Enter gameplay, using the level with the given asset key,
+    /// and wait until its collision geometry has been spawned
+    pub fn enter_level(&mut self, key: &str) -> &mut Self {
+        self.app.world.insert_resource(StartingLevel(key.into()));
+        self.app
+            .world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::InGame);
+        self.update_until("level to spawn", |world| {
+            let mut q = world.query_filtered::<(), With<Collider>>();
+            q.iter(world).next().is_some()
+        })
+    }
+
+    /// Spawn a player at the given position, and run one tick to set it up
+    pub fn spawn_player(&mut self, pos: Vec2) -> Entity {
+        let e = self
+            .app
+            .world
+            .spawn((
+                PlayerBlueprint,
+                SpatialBundle::from_transform(Transform::from_translation(
+                    pos.extend(0.0),
+                )),
+            ))
+            .id();
+        self.step_ticks(1);
+        e
+    }
+
+    /// Feed a recording of player input into the simulation
+    ///
+    /// Call before spawning the player; the replay starts on the first tick
+    /// where the player exists.
+    pub fn play_replay(
+        &mut self,
+        replay: InputReplay<PlayerAction>,
+    ) -> &mut Self {
+        let world = &mut self.app.world;
+        world.resource_mut::<GameRng>().reseed(replay.seed);
+        world
+            .resource_mut::<InputReplayer<PlayerAction>>()
+            .start_playback(replay);
+        self
+    }
+
+    /// Simulate exactly `ticks` game ticks (in one Bevy frame update)
+    pub fn step_ticks(&mut self, ticks: u64) -> &mut Self {
+        self.app.step_gameticks(ticks);
+        self
+    }
+
+    /// The number of game ticks simulated so far
+    pub fn tick(&self) -> u64 {
+        self.app.world.resource::<GameTime>().tick()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use theseeker_engine::replay::{
+        InputReplay, RecordedAction, RecordedButton, ReplayChange, ReplayFrame,
+    };
+
+    use super::HeadlessGame;
+    use crate::game::attack::{Health, KillCount};
+    use crate::game::player::PlayerAction;
+    use crate::prelude::*;
+
+    #[test]
+    fn step_exact_ticks() {
+        let mut game = HeadlessGame::new();
+        game.load_assets();
+        let start = game.tick();
+        game.step_ticks(10);
+        assert_eq!(game.tick(), start + 10);
+        // no wall-clock time leaks in when updating without stepping
+        game.app.update();
+        assert_eq!(game.tick(), start + 10);
+    }
+
+    #[test]
+    fn player_idles_in_dev_level() {
+        let mut game = HeadlessGame::new();
+        game.load_assets().enter_level("level.dev");
+        let start = Vec2::new(64.0, 64.0);
+        let player = game.spawn_player(start);
+        game.step_ticks(96 * 2);
+
+        let health = game.world().get::<Health>(player).unwrap();
+        assert_eq!(health.current, health.max);
+        let xf = game.world().get::<Transform>(player).unwrap();
+        // no input: we should not have moved sideways, only fallen
+        assert_eq!(xf.translation.x, start.x);
+        assert!(xf.translation.y <= start.y);
+        assert_eq!(game.world().resource::<KillCount>().0, 0);
+    }
+
+    fn run_right_and_jump() -> InputReplay<PlayerAction> {
+        let change = |action, state, value| ReplayChange {
+            action,
+            recorded: RecordedAction { state, value },
+        };
+        let mut replay = InputReplay::new(HeadlessGame::SEED, "level.dev");
+        replay.len = 96;
+        replay.frames = vec![
+            ReplayFrame {
+                tick: 0,
+                changes: vec![change(
+                    PlayerAction::Move,
+                    RecordedButton::JustPressed,
+                    1.0,
+                )],
+            },
+            ReplayFrame {
+                tick: 1,
+                changes: vec![change(
+                    PlayerAction::Move,
+                    RecordedButton::Pressed,
+                    1.0,
+                )],
+            },
+            ReplayFrame {
+                tick: 10,
+                changes: vec![change(
+                    PlayerAction::Jump,
+                    RecordedButton::JustPressed,
+                    1.0,
+                )],
+            },
+            ReplayFrame {
+                tick: 11,
+                changes: vec![change(
+                    PlayerAction::Jump,
+                    RecordedButton::Pressed,
+                    1.0,
+                )],
+            },
+        ];
+        replay
+    }
+
+    #[test]
+    fn replay_is_deterministic() {
+        let mut positions = vec![];
+        for _ in 0..2 {
+            let mut game = HeadlessGame::new();
+            game.load_assets().enter_level("level.dev");
+            game.play_replay(run_right_and_jump());
+            let start = Vec2::new(64.0, 64.0);
+            let player = game.spawn_player(start);
+            game.step_ticks(96);
+            let xf = game.world().get::<Transform>(player).unwrap();
+            assert!(xf.translation.x > start.x);
+            positions.push(xf.translation);
+        }
+        assert_eq!(positions[0], positions[1]);
+    }
+}
@@ -0,0 +1,473 @@
+//! User-configurable keybindings
+//!
+//! The keyboard keys and gamepad buttons for every [`PlayerAction`] can be
+//! changed by the user (from the settings screen). They are stored in a
+//! config file in the user's config directory, which is loaded on startup.
+//!
+//! Movement is split into two controls (left and right). Their bindings are
+//! paired up in order: the first key for left goes with the first key for
+//! right, and so on. The analog sticks cannot be rebound.
+
+use std::collections::BTreeMap;
+
+use leafwing_input_manager::axislike::{AxisType, SingleAxis, VirtualAxis};
+use leafwing_input_manager::input_map::InputMap;
+use leafwing_input_manager::prelude::WithAxisProcessingPipelineExt;
+use leafwing_input_manager::user_input::InputKind;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::game::player::PlayerAction;
+use crate::prelude::*;
+use crate::user_config::UserConfig;
+
+pub struct KeybindingsPlugin;
+
+impl Plugin for KeybindingsPlugin {
+    fn build(&self, app: &mut App) {
+        // headless games insert their own, so they don't depend on the user's config
+        if !app.world.contains_resource::<Keybindings>() {
+            app.insert_resource(Keybindings::load_or_default(
+                &Keybindings::default_path(),
+            ));
+        }
+        app.add_systems(
+            Update,
+            apply_keybindings.run_if(resource_changed::<Keybindings>),
+        );
+        app.register_clicommand_noargs(
+            "keybindings_reset",
+            cli_keybindings_reset,
+        );
+    }
+}
+
+/// Something that the user can bind keys and buttons to
+///
+/// The same as [`PlayerAction`], except that movement is split
+/// into its two directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Serialize, Deserialize, EnumIter)]
+pub enum Control {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Fall,
+    Attack,
+    Dash,
+    Whirl,
+    Stealth,
+    SwapCombatStyle,
+    SwapMeleeWeapon,
+    Interact,
+    ToggleControlOverlay,
+}
+
+impl Control {
+    pub fn action(self) -> PlayerAction {
+        match self {
+            Control::MoveLeft | Control::MoveRight => PlayerAction::Move,
+            Control::Jump => PlayerAction::Jump,
+            Control::Fall => PlayerAction::Fall,
+            Control::Attack => PlayerAction::Attack,
+            Control::Dash => PlayerAction::Dash,
+            Control::Whirl => PlayerAction::Whirl,
+            Control::Stealth => PlayerAction::Stealth,
+            Control::SwapCombatStyle => PlayerAction::SwapCombatStyle,
+            Control::SwapMeleeWeapon => PlayerAction::SwapMeleeWeapon,
+            Control::Interact => PlayerAction::Interact,
+            Control::ToggleControlOverlay => PlayerAction::ToggleControlOverlay,
+        }
+    }
+
+    /// Name to display in the UI
+    pub fn name(self) -> &'static str {
+        match self {
+            Control::MoveLeft => "Move Left",
+            Control::MoveRight => "Move Right",
+            Control::Jump => "Jump",
+            Control::Fall => "Fall",
+            Control::Attack => "Attack",
+            Control::Dash => "Dash",
+            Control::Whirl => "Whirl",
+            Control::Stealth => "Stealth",
+            Control::SwapCombatStyle => "Swap Melee/Ranged",
+            Control::SwapMeleeWeapon => "Swap Melee Weapon",
+            Control::Interact => "Interact",
+            Control::ToggleControlOverlay => "Show/Hide Controls",
+        }
+    }
+}
+
+/// A key or gamepad button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+}
+
+impl Binding {
+    /// Text for the control icon of this binding
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Key(key) => match key {
+                KeyCode::Space => "SPACE".into(),
+                KeyCode::ArrowUp => "[^]".into(),
+                KeyCode::ArrowDown => "[v]".into(),
+                KeyCode::ArrowLeft => "[<]".into(),
+                KeyCode::ArrowRight => "[>]".into(),
+                KeyCode::Backquote => "`".into(),
+                KeyCode::Semicolon => ";".into(),
+                KeyCode::Quote => "'".into(),
+                KeyCode::Comma => ",".into(),
+                KeyCode::Period => ".".into(),
+                KeyCode::Slash => "/".into(),
+                KeyCode::Backslash => "\\".into(),
+                KeyCode::BracketLeft => "[".into(),
+                KeyCode::BracketRight => "]".into(),
+                KeyCode::Minus => "-".into(),
+                KeyCode::Equal => "=".into(),
+                KeyCode::ShiftLeft => "L Shift".into(),
+                KeyCode::ShiftRight => "R Shift".into(),
+                KeyCode::ControlLeft => "L Ctrl".into(),
+                KeyCode::ControlRight => "R Ctrl".into(),
+                KeyCode::AltLeft => "L Alt".into(),
+                KeyCode::AltRight => "R Alt".into(),
+                other => {
+                    let name = format!("{:?}", other);
+                    name.strip_prefix("Key")
+                        .or_else(|| name.strip_prefix("Digit"))
+                        .unwrap_or(&name)
+                        .to_owned()
+                },
+            },
+            Binding::Button(button) => match button {
+                GamepadButtonType::South => "A / Cross".into(),
+                GamepadButtonType::East => "B / Circle".into(),
+                GamepadButtonType::West => "X / Square".into(),
+                GamepadButtonType::North => "Y / Triangle".into(),
+                GamepadButtonType::LeftTrigger => "L1".into(),
+                GamepadButtonType::RightTrigger => "R1".into(),
+                GamepadButtonType::LeftTrigger2 => "L2".into(),
+                GamepadButtonType::RightTrigger2 => "R2".into(),
+                GamepadButtonType::LeftThumb => "L3".into(),
+                GamepadButtonType::RightThumb => "R3".into(),
+                GamepadButtonType::DPadUp => "D-Pad Up".into(),
+                GamepadButtonType::DPadDown => "D-Pad Down".into(),
+                GamepadButtonType::DPadLeft => "D-Pad Left".into(),
+                GamepadButtonType::DPadRight => "D-Pad Right".into(),
+                other => format!("{:?}", other),
+            },
+        }
+    }
+}
+
+/// The user's bindings for every [`Control`]
+///
+/// Controls that are missing from the config file get their default
+/// bindings.
+#[derive(Resource, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Keybindings {
+    pub controls: BTreeMap<Control, Vec<Binding>>,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        use Binding::{Button, Key};
+        use GamepadButtonType as G;
+        let controls = Control::iter()
+            .map(|control| {
+                let bindings = match control {
+                    Control::MoveLeft => vec![
+                        Key(KeyCode::KeyA),
+                        Key(KeyCode::ArrowLeft),
+                        Button(G::DPadLeft),
+                    ],
+                    Control::MoveRight => vec![
+                        Key(KeyCode::KeyD),
+                        Key(KeyCode::ArrowRight),
+                        Button(G::DPadRight),
+                    ],
+                    Control::Jump => vec![
+                        Key(KeyCode::Space),
+                        Key(KeyCode::KeyW),
+                        Key(KeyCode::ArrowUp),
+                        Button(G::LeftTrigger2),
+                    ],
+                    Control::Fall => vec![
+                        Key(KeyCode::ArrowDown),
+                        Key(KeyCode::KeyS),
+                        Button(G::DPadDown),
+                    ],
+                    Control::Attack => vec![
+                        Key(KeyCode::KeyJ),
+                        Key(KeyCode::Digit1),
+                        Button(G::West),
+                    ],
+                    Control::Dash => vec![
+                        Key(KeyCode::KeyK),
+                        Key(KeyCode::Digit2),
+                        Button(G::RightTrigger2),
+                    ],
+                    Control::Whirl => vec![
+                        Key(KeyCode::KeyL),
+                        Key(KeyCode::Digit3),
+                        Button(G::South),
+                    ],
+                    Control::Stealth => vec![
+                        Key(KeyCode::Semicolon),
+                        Key(KeyCode::Digit4),
+                        Button(G::East),
+                    ],
+                    Control::SwapCombatStyle => vec![
+                        Key(KeyCode::KeyH),
+                        Key(KeyCode::Backquote),
+                        Button(G::LeftTrigger),
+                    ],
+                    Control::SwapMeleeWeapon => {
+                        vec![Key(KeyCode::KeyG), Button(G::RightTrigger)]
+                    },
+                    Control::Interact => {
+                        vec![Key(KeyCode::KeyF), Button(G::North)]
+                    },
+                    Control::ToggleControlOverlay => {
+                        vec![Key(KeyCode::KeyC), Button(G::Start)]
+                    },
+                };
+                (control, bindings)
+            })
+            .collect();
+        Self { controls }
+    }
+}
+
+impl UserConfig for Keybindings {
+    const FILE_NAME: &'static str = "keybindings.toml";
+    const DESCRIPTION: &'static str = "keybindings";
+
+    fn after_load(&mut self) {
+        for (control, bindings) in Self::default().controls {
+            self.controls.entry(control).or_insert(bindings);
+        }
+    }
+}
+
+impl Keybindings {
+    pub fn bindings(&self, control: Control) -> &[Binding] {
+        self.controls
+            .get(&control)
+            .map(|b| b.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Replace the binding at `index`, or add a new one if `index` is `None`
+    pub fn set(
+        &mut self,
+        control: Control,
+        index: Option<usize>,
+        binding: Binding,
+    ) {
+        let bindings = self.controls.entry(control).or_default();
+        match index.and_then(|i| bindings.get_mut(i)) {
+            Some(old) => *old = binding,
+            None => {
+                if !bindings.contains(&binding) {
+                    bindings.push(binding);
+                }
+            },
+        }
+    }
+
+    pub fn remove(&mut self, control: Control, index: usize) {
+        if let Some(bindings) = self.controls.get_mut(&control) {
+            if index < bindings.len() {
+                bindings.remove(index);
+            }
+        }
+    }
+
+    /// Bindings that are used for more than one control, and which controls
+    pub fn conflicts(&self) -> HashMap<Binding, Vec<Control>> {
+        let mut users: HashMap<Binding, Vec<Control>> = HashMap::default();
+        for (control, bindings) in &self.controls {
+            for binding in bindings {
+                let controls = users.entry(*binding).or_default();
+                if !controls.contains(control) {
+                    controls.push(*control);
+                }
+            }
+        }
+        users.retain(|_, controls| controls.len() > 1);
+        users
+    }
+
+    /// Describe everything that is wrong with the bindings, for the user
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = self
+            .conflicts()
+            .into_iter()
+            .map(|(binding, controls)| {
+                let names: Vec<&str> =
+                    controls.iter().map(|c| c.name()).collect();
+                format!(
+                    "{} is bound to: {}",
+                    binding.label(),
+                    names.join(", ")
+                )
+            })
+            .collect();
+        problems.sort();
+        let left = self.bindings(Control::MoveLeft);
+        let right = self.bindings(Control::MoveRight);
+        let n_keys = |b: &[Binding]| {
+            b.iter().filter(|b| matches!(b, Binding::Key(_))).count()
+        };
+        if n_keys(left) != n_keys(right) {
+            problems.push(format!(
+                "Move Left has {} key(s), but Move Right has {}",
+                n_keys(left),
+                n_keys(right)
+            ));
+        }
+        if left.len() - n_keys(left) != right.len() - n_keys(right) {
+            problems.push(format!(
+                "Move Left has {} button(s), but Move Right has {}",
+                left.len() - n_keys(left),
+                right.len() - n_keys(right)
+            ));
+        }
+        problems
+    }
+
+    pub fn input_map(&self) -> InputMap<PlayerAction> {
+        let mut map = InputMap::default();
+        for (control, bindings) in &self.controls {
+            if matches!(
+                control,
+                Control::MoveLeft | Control::MoveRight
+            ) {
+                continue;
+            }
+            for binding in bindings {
+                match *binding {
+                    Binding::Key(key) => map.insert(control.action(), key),
+                    Binding::Button(button) => {
+                        map.insert(control.action(), button)
+                    },
+                };
+            }
+        }
+
+        // pair up the left and right movement bindings into axes
+        let keys = |control| {
+            self.bindings(control).iter().filter_map(|b| match b {
+                Binding::Key(key) => Some(*key),
+                Binding::Button(_) => None,
+            })
+        };
+        let buttons = |control| {
+            self.bindings(control).iter().filter_map(|b| match b {
+                Binding::Key(_) => None,
+                Binding::Button(button) => Some(*button),
+            })
+        };
+        for (left, right) in
+            keys(Control::MoveLeft).zip(keys(Control::MoveRight))
+        {
+            map.insert(
+                PlayerAction::Move,
+                VirtualAxis::from_keys(left, right),
+            );
+        }
+        for (left, right) in
+            buttons(Control::MoveLeft).zip(buttons(Control::MoveRight))
+        {
+            let mut axis = VirtualAxis::horizontal_dpad();
+            axis.negative = InputKind::GamepadButton(left);
+            axis.positive = InputKind::GamepadButton(right);
+            map.insert(PlayerAction::Move, axis);
+        }
+
+        // analog sticks are not configurable
+        map.insert(
+            PlayerAction::Move,
+            SingleAxis::new(AxisType::Gamepad(
+                GamepadAxisType::LeftStickX,
+            )),
+        );
+        map.insert(
+            PlayerAction::Fall,
+            SingleAxis::new(AxisType::Gamepad(
+                GamepadAxisType::LeftStickY,
+            ))
+            .with_bounds(-1.0, 0.0),
+        );
+        map
+    }
+}
+
+/// Update the input maps of existing players when the bindings change
+fn apply_keybindings(
+    keybindings: Res<Keybindings>,
+    mut q_map: Query<&mut InputMap<PlayerAction>>,
+) {
+    for mut map in &mut q_map {
+        *map = keybindings.input_map();
+    }
+}
+
+fn cli_keybindings_reset(mut keybindings: ResMut<Keybindings>) {
+    *keybindings = Keybindings::default();
+    keybindings.save_user_config();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_keybindings_have_no_problems() {
+        let keybindings = Keybindings::default();
+        assert_eq!(
+            keybindings.problems(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            keybindings.controls.len(),
+            Control::iter().count()
+        );
+    }
+
+    #[test]
+    fn keybindings_toml_roundtrip() {
+        let mut keybindings = Keybindings::default();
+        keybindings.set(
+            Control::Jump,
+            Some(0),
+            Binding::Key(KeyCode::KeyX),
+        );
+        let text = toml::to_string(&keybindings).unwrap();
+        let loaded: Keybindings = toml::from_str(&text).unwrap();
+        assert_eq!(loaded, keybindings);
+    }
+
+    #[test]
+    fn detects_conflicts() {
+        let mut keybindings = Keybindings::default();
+        keybindings.set(
+            Control::Dash,
+            None,
+            Binding::Key(KeyCode::KeyJ),
+        );
+        let conflicts = keybindings.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[&Binding::Key(KeyCode::KeyJ)],
+            vec![Control::Attack, Control::Dash]
+        );
+        keybindings.remove(Control::MoveRight, 0);
+        assert_eq!(keybindings.problems().len(), 2);
+    }
+}
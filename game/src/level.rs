@@ -14,7 +14,12 @@
 //! play the game, doesn't belong here. Put that stuff under [`crate::game`].
 
 use seek_ecs_tilemap::tiles::TilePos;
+use theseeker_engine::assets::manifest::StreamedAssets;
+use theseeker_engine::gent::InterpolateTransform;
+use theseeker_engine::physics::LinearVelocity;
 
+use crate::camera::CameraRig;
+use crate::game::player::Player;
 use crate::parallax::{Parallax, ParallaxOffset};
 use crate::prelude::*;
 
@@ -23,12 +28,31 @@ pub struct LevelManagerPlugin;
 impl Plugin for LevelManagerPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(LevelSelection::Identifier(
-            "Level_0".into(),
+            FIRST_LEVEL.into(),
         ));
+        app.init_resource::<StartingLevel>();
         app.add_systems(
             OnEnter(AppState::InGame),
             game_level_init,
         );
+        app.add_systems(
+            GameTickUpdate,
+            (use_level_doors, stream_level_assets)
+                .chain()
+                .run_if(in_state(AppState::InGame)),
+        );
+        app.add_systems(
+            OnEnter(AppState::AssetsLoading),
+            request_first_level_assets,
+        );
+        app.add_systems(
+            OnExit(AppState::InGame),
+            release_level_assets,
+        );
+        app.add_systems(
+            Update,
+            arrive_through_door.run_if(resource_exists::<LevelTransition>),
+        );
         app.add_systems(Update, attach_parallax);
         app.add_systems(Update, hide_level_0);
         app.add_systems(
@@ -38,16 +62,137 @@ impl Plugin for LevelManagerPlugin {
     }
 }
 
+/// Asset key of the LDtk project to spawn when entering the gameplay state
+#[derive(Resource, Debug, Clone)]
+pub struct StartingLevel(pub String);
+
+impl Default for StartingLevel {
+    fn default() -> Self {
+        Self("level.01".into())
+    }
+}
+
+/// Identifier of the LDtk level (within the project) to start in
+const FIRST_LEVEL: &str = "Level_0";
+
+/// Identifier of the LDtk level to start in instead of [`FIRST_LEVEL`]
+///
+/// Set when loading a save file, and used up when entering the gameplay state.
+#[derive(Resource, Debug, Clone)]
+pub struct ResumeLevel(pub String);
+
+/// How close the player has to be to a door, for the assets of the level
+/// behind it to start loading
+const DOOR_STREAMING_DISTANCE: f32 = 160.0;
+
+/// Name of the asset manifest group with the assets of a level
+/// (see `levels.manifest.toml`)
+fn level_assets_group(project: &str, level: &str) -> String {
+    format!("{}/{}", project, level)
+}
+
+/// Marker for entities that belong to the current level
+///
+/// They are despawned when the player goes to another level. Entities spawned
+/// by LDtk as children of the level are despawned along with it, so this is
+/// only needed for those that are unparented or spawned separately.
+#[derive(Component)]
+pub struct LevelDespawnMarker;
+
+/// A door/exit to another level, as an LDtk entity
+///
+/// Walking into it loads the level `to_level`, and places the player at the
+/// door called `to_door` there. The LDtk entity's size is the trigger area.
+#[derive(Component, Debug, Default, Clone)]
+pub struct LevelDoor {
+    /// Name of this door, for doors in other levels to lead to
+    pub name: String,
+    /// LDtk identifier of the level to go to
+    pub to_level: String,
+    /// Name of the door to arrive at, in `to_level`
+    pub to_door: String,
+    half_size: Vec2,
+}
+
+impl LevelDoor {
+    fn from_entity_instance(entity: &EntityInstance) -> Self {
+        let field = |name: &str| {
+            entity.get_string_field(name).cloned().unwrap_or_else(|_| {
+                error!(
+                    "Door {} has no string field {:?}",
+                    entity.iid, name
+                );
+                String::new()
+            })
+        };
+        Self {
+            name: field("name"),
+            to_level: field("to_level"),
+            to_door: field("to_door"),
+            half_size: Vec2::new(
+                entity.width as f32,
+                entity.height as f32,
+            ) / 2.0,
+        }
+    }
+
+    fn contains(&self, door_pos: Vec2, pos: Vec2) -> bool {
+        Rect::from_center_half_size(door_pos, self.half_size).contains(pos)
+    }
+}
+
+/// Marker for doors that cannot be used at the moment
+/// (such as during a boss fight)
+#[derive(Component)]
+pub struct LockedDoor;
+
+#[derive(Bundle, LdtkEntity, Default)]
+pub struct LevelDoorBundle {
+    #[with(LevelDoor::from_entity_instance)]
+    door: LevelDoor,
+}
+
+/// Present while switching levels: the player is going to `to_door`
+#[derive(Resource, Debug)]
+struct LevelTransition {
+    to_door: String,
+}
+
+/// The door the player arrived through
+///
+/// It does not work until the player has stepped out of it, so that
+/// they don't immediately go back.
+#[derive(Resource, Debug)]
+struct ArrivalDoor(Entity);
+
 /// System to perform initial setup when entering the gameplay state, load the starting level.
-fn game_level_init(mut commands: Commands, preloaded: Res<PreloadedAssets>) {
-    // TODO: per-level asset management instead of preloaded assets
-    // TODO: when we have save files, use that to choose the level to init at
+fn game_level_init(
+    mut commands: Commands,
+    preloaded: Res<PreloadedAssets>,
+    starting_level: Res<StartingLevel>,
+    resume_level: Option<Res<ResumeLevel>>,
+) {
+    let level = resume_level
+        .map(|resume| resume.0.clone())
+        .unwrap_or_else(|| FIRST_LEVEL.into());
+    commands.insert_resource(LevelSelection::Identifier(level));
+    commands.remove_resource::<ResumeLevel>();
+    commands.remove_resource::<LevelTransition>();
+    commands.remove_resource::<ArrivalDoor>();
+
+    // (loading a save file sets `StartingLevel` and `ResumeLevel`
+    // before we get here)
+    // level-specific assets are loaded by `stream_level_assets`
+
+    let Some(ldtk_handle) = preloaded.get_single_asset(&starting_level.0)
+    else {
+        error!("Expected level asset key {:?}", starting_level.0);
+        return;
+    };
 
     commands.spawn((
         LdtkWorldBundle {
-            ldtk_handle: preloaded
-                .get_single_asset("level.01")
-                .expect("Expected asset key 'level.01'"),
+            ldtk_handle,
             ..Default::default()
         },
         StateDespawnMarker,
@@ -66,6 +211,157 @@ fn game_level_init(mut commands: Commands, preloaded: Res<PreloadedAssets>) {
     // ));
 }
 
+/// Start a level transition when the player walks into a door
+fn use_level_doors(
+    mut commands: Commands,
+    q_player: Query<&Transform, With<Player>>,
+    q_doors: Query<
+        (Entity, &LevelDoor, &GlobalTransform),
+        Without<LockedDoor>,
+    >,
+    q_level_entities: Query<Entity, With<LevelDespawnMarker>>,
+    transition: Option<Res<LevelTransition>>,
+    arrival: Option<Res<ArrivalDoor>>,
+    mut level_selection: ResMut<LevelSelection>,
+) {
+    if transition.is_some() {
+        return;
+    }
+    let Ok(xf_player) = q_player.get_single() else {
+        return;
+    };
+    let pos = xf_player.translation.xy();
+    for (e_door, door, xf_door) in &q_doors {
+        if !door.contains(xf_door.translation().xy(), pos) {
+            if arrival.as_ref().is_some_and(|a| a.0 == e_door) {
+                commands.remove_resource::<ArrivalDoor>();
+            }
+            continue;
+        }
+        if arrival.as_ref().is_some_and(|a| a.0 == e_door) {
+            continue;
+        }
+        info!(
+            "Going to level {:?}, door {:?}",
+            door.to_level, door.to_door
+        );
+        *level_selection = LevelSelection::Identifier(door.to_level.clone());
+        commands.insert_resource(LevelTransition {
+            to_door: door.to_door.clone(),
+        });
+        commands.remove_resource::<ArrivalDoor>();
+        for e in &q_level_entities {
+            commands.entity(e).despawn_recursive();
+        }
+        break;
+    }
+}
+
+/// Keep the assets of the current level, and of the levels behind nearby
+/// doors, loaded. Release those of all other levels.
+fn stream_level_assets(
+    mut streamed: ResMut<StreamedAssets>,
+    starting_level: Res<StartingLevel>,
+    level_selection: Res<LevelSelection>,
+    q_player: Query<&Transform, With<Player>>,
+    q_doors: Query<(&LevelDoor, &GlobalTransform)>,
+) {
+    let mut wanted = HashSet::default();
+    if let LevelSelection::Identifier(level) = &*level_selection {
+        wanted.insert(level_assets_group(
+            &starting_level.0,
+            level,
+        ));
+    }
+    if let Ok(xf_player) = q_player.get_single() {
+        let pos = xf_player.translation.xy();
+        for (door, xf_door) in &q_doors {
+            let distance = xf_door.translation().xy().distance(pos);
+            if distance < DOOR_STREAMING_DISTANCE {
+                wanted.insert(level_assets_group(
+                    &starting_level.0,
+                    &door.to_level,
+                ));
+            }
+        }
+    }
+    let released: Vec<String> = streamed
+        .iter_requested()
+        .filter(|group| !wanted.contains(*group))
+        .map(String::from)
+        .collect();
+    for group in released {
+        streamed.release(&group);
+    }
+    for group in &wanted {
+        if !streamed.is_requested(group) {
+            streamed.request(group);
+        }
+    }
+}
+
+/// Load the first level's assets during the loading screen, like everything else
+fn request_first_level_assets(
+    mut streamed: ResMut<StreamedAssets>,
+    starting_level: Res<StartingLevel>,
+) {
+    streamed.request(&level_assets_group(
+        &starting_level.0,
+        FIRST_LEVEL,
+    ));
+}
+
+fn release_level_assets(mut streamed: ResMut<StreamedAssets>) {
+    streamed.release_all();
+}
+
+/// Once the new level has spawned, move the player to the door they came through
+fn arrive_through_door(
+    mut commands: Commands,
+    mut level_events: EventReader<LevelEvent>,
+    transition: Res<LevelTransition>,
+    q_doors: Query<(Entity, &LevelDoor, &GlobalTransform)>,
+    mut q_player: Query<
+        (
+            &mut Transform,
+            &mut LinearVelocity,
+            &mut InterpolateTransform,
+        ),
+        With<Player>,
+    >,
+    mut rig: ResMut<CameraRig>,
+) {
+    // by now, the new level's entities have their final transforms
+    if !level_events
+        .read()
+        .any(|ev| matches!(ev, LevelEvent::Transformed(_)))
+    {
+        return;
+    }
+    commands.remove_resource::<LevelTransition>();
+    let Some((e_door, _, xf_door)) = q_doors
+        .iter()
+        .find(|(_, door, _)| door.name == transition.to_door)
+    else {
+        error!(
+            "Level has no door named {:?}",
+            transition.to_door
+        );
+        return;
+    };
+    let Ok((mut xf_player, mut velocity, mut interpolation)) =
+        q_player.get_single_mut()
+    else {
+        return;
+    };
+    let pos = xf_door.translation().xy();
+    xf_player.translation = pos.extend(xf_player.translation.z);
+    velocity.0 = Vec2::ZERO;
+    interpolation.teleport();
+    rig.jump_to(pos);
+    commands.insert_resource(ArrivalDoor(e_door));
+}
+
 /// when a specific entity is spawned on level load
 fn add_despawn_marker_to_entity<T: Component>(
     mut commands: Commands,
@@ -0,0 +1,107 @@
+//! `theseeker_game lint-assets`: check script and animation assets offline
+//!
+//! Runs without opening a window or starting Bevy, so it can be used in CI.
+//! Prints one line per problem and exits with a non-zero status if there are
+//! errors (or warnings, with `--deny-warnings`).
+//!
+//! Options:
+//!  - `--assets <dir>`: the assets folder (default: `assets`)
+//!  - `--deny-warnings`: fail on warnings, too
+//!  - `--github`: print GitHub Actions annotations instead of plain lines
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use theseeker_engine::assets::lint::{lint_assets, Severity};
+
+use crate::assets::DYNAMIC_ASSET_FILES;
+
+/// Slots that are set from Rust code, using `ScriptPlayer::set_slot`
+///
+/// Keep in sync with the code. `*` at the start or end is a wildcard.
+const CODE_SLOTS: &[&str] = &[
+    "Activated",
+    "AttackHit",
+    "AttackTransition",
+    "Damaged",
+    "DecayRate",
+    "DirectionChanged",
+    "DirectionLeft",
+    "DirectionRight",
+    "DownwardAttack",
+    "FrenziedAttack",
+    // `slot` of attacks in `player.weapons.toml`
+    "HeavyAttack",
+    "MovingDown",
+    "MovingHorizontally",
+    "MovingSideways",
+    "MovingUp",
+    "MovingVertically",
+    "PlayerNearby",
+    "SerpentRing",
+    "Start",
+    "XpOrb",
+    "fall",
+    "jump",
+    // random hit spark
+    "Spark*",
+    // `hit_slot` of the current weapon, for its hit sound
+    "*Hit",
+];
+
+pub fn run(mut args: impl Iterator<Item = String>) -> ExitCode {
+    let mut assets = PathBuf::from("assets");
+    let mut deny_warnings = false;
+    let mut github = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--assets" => {
+                let Some(dir) = args.next() else {
+                    eprintln!("--assets needs a directory");
+                    return ExitCode::FAILURE;
+                };
+                assets = dir.into();
+            },
+            "--deny-warnings" => deny_warnings = true,
+            "--github" => github = true,
+            other => {
+                eprintln!("Unknown option: {}", other);
+                return ExitCode::FAILURE;
+            },
+        }
+    }
+
+    let diagnostics = lint_assets(
+        assets.as_path(),
+        DYNAMIC_ASSET_FILES,
+        CODE_SLOTS,
+    );
+    let mut failed = false;
+    for d in &diagnostics {
+        failed |= d.severity == Severity::Error || deny_warnings;
+        if github {
+            println!(
+                "::{} file={}::{}",
+                d.severity,
+                assets.join(&d.file).display(),
+                d.message
+            );
+        } else {
+            println!("{}", d);
+        }
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    eprintln!(
+        "lint-assets: {} error(s), {} warning(s)",
+        errors,
+        diagnostics.len() - errors
+    );
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
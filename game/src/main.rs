@@ -10,6 +10,8 @@ mod prelude {
     pub use crate::gamestate::GameState;
 }
 
+use std::process::ExitCode;
+
 use bevy::core::TaskPoolThreadAssignmentPolicy;
 use bevy::ecs::schedule::ExecutorKind;
 use bevy::render::settings::{WgpuFeatures, WgpuSettings};
@@ -25,9 +27,17 @@ mod camera;
 mod cli;
 mod game;
 mod gamestate;
+mod haptics;
+#[cfg_attr(not(test), allow(dead_code))]
+mod headless;
+mod keybindings;
 mod level;
+mod lint;
 mod locale;
+mod replay;
+mod save;
 mod stepping_egui;
+mod user_config;
 
 mod screens {
     pub mod loading;
@@ -40,7 +50,13 @@ mod dev;
 pub mod graphics;
 mod parallax;
 
-fn main() {
+fn main() -> ExitCode {
+    // offline tools, that don't start the game
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("lint-assets") {
+        return crate::lint::run(args);
+    }
+
     let mut app = App::new();
     app.insert_resource(ClearColor(Color::BLACK));
 
@@ -108,6 +124,21 @@ fn main() {
     app.insert_resource(Msaa::Off);
     app.add_plugins(bevy_plugins);
 
+    add_game_plugins(&mut app);
+
+    #[cfg(feature = "dev")]
+    app.add_plugins(crate::dev::DevPlugin);
+
+    app.run();
+
+    ExitCode::SUCCESS
+}
+
+/// Everything that makes up our game, on top of the Bevy plugins
+///
+/// Shared between the real app and the [`headless`] one, so that they
+/// always run the same simulation.
+fn add_game_plugins(app: &mut App) {
     // configure our app states
     app.add_plugins(crate::appstate::AppStatesPlugin);
 
@@ -143,18 +174,17 @@ fn main() {
         crate::ui::UiPlugin,
         crate::camera::CameraPlugin,
         crate::level::LevelManagerPlugin,
+        crate::replay::ReplayPlugin,
+        crate::save::SavePlugin,
+        crate::keybindings::KeybindingsPlugin,
         crate::parallax::ParallaxPlugin,
         crate::game::GameplayPlugin,
         crate::gamestate::GameStatePlugin,
         crate::graphics::GraphicsFxPlugin,
     ));
-
-    #[cfg(feature = "dev")]
-    app.add_plugins(crate::dev::DevPlugin);
+    app.add_plugins(crate::haptics::HapticsPlugin);
 
     app.edit_schedule(Update, |s| {
         s.set_executor_kind(ExecutorKind::SingleThreaded);
     });
-
-    app.run();
 }
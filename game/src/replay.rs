@@ -0,0 +1,194 @@
+//! Recording/playback of gameplay, for reproducing bugs
+//!
+//! `replay_record [file]` restarts the game with a fresh RNG seed and records
+//! all player input until `replay_stop` (or until leaving gameplay).
+//! `replay_play <file>` restarts the game in the recorded level, with the
+//! recorded seed, and feeds the recorded input back in, tick by tick. When
+//! the replay ends, the level the game was going to start in is restored.
+
+use std::path::PathBuf;
+
+use theseeker_engine::replay::{InputReplay, InputReplayer, ReplayMode};
+
+use crate::game::attack::KillCount;
+use crate::game::boss::DefeatedBosses;
+use crate::game::dialogue::DialogueFlags;
+use crate::game::pickups::DropTracker;
+use crate::game::player::PlayerAction;
+use crate::level::StartingLevel;
+use crate::prelude::*;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            start_pending_replay.run_if(resource_exists::<PendingReplay>),
+        );
+        // leaving gameplay invalidates the recording, so finish it here
+        app.add_systems(
+            OnExit(AppState::InGame),
+            cli_replay_stop,
+        );
+        app.register_clicommand_args("replay_record", cli_replay_record_args);
+        app.register_clicommand_noargs("replay_record", cli_replay_record);
+        app.register_clicommand_noargs("replay_stop", cli_replay_stop);
+        app.register_clicommand_args("replay_play", cli_replay_play);
+        app.add_systems(
+            Update,
+            restore_starting_level.run_if(
+                resource_exists::<ReplayedLevel>
+                    .and_then(not(resource_exists::<PendingReplay>)),
+            ),
+        );
+    }
+}
+
+/// Default file name for recordings, if none is given
+const DEFAULT_REPLAY_FILE: &str = "replay.toml";
+
+/// What to do when gameplay (re)starts
+#[derive(Resource)]
+enum PendingReplay {
+    Record { seed: u64, path: PathBuf },
+    Play(InputReplay<PlayerAction>),
+}
+
+/// Where the recording in progress should be saved
+#[derive(Resource)]
+struct RecordingFile(PathBuf);
+
+/// The [`StartingLevel`] from before a replay replaced it
+#[derive(Resource)]
+struct ReplayedLevel {
+    previous: StartingLevel,
+}
+
+fn cli_replay_record(
+    mut commands: Commands,
+    mut next: ResMut<NextState<AppState>>,
+) {
+    commands.insert_resource(PendingReplay::Record {
+        seed: thread_rng().gen(),
+        path: DEFAULT_REPLAY_FILE.into(),
+    });
+    next.set(AppState::Restart);
+}
+
+fn cli_replay_record_args(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    mut next: ResMut<NextState<AppState>>,
+) {
+    if args.len() != 1 {
+        error!("\"replay_record [file]\"");
+        return;
+    }
+    commands.insert_resource(PendingReplay::Record {
+        seed: thread_rng().gen(),
+        path: (&args[0]).into(),
+    });
+    next.set(AppState::Restart);
+}
+
+fn cli_replay_play(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    mut next: ResMut<NextState<AppState>>,
+    level: Res<StartingLevel>,
+    replayed: Option<Res<ReplayedLevel>>,
+) {
+    if args.len() != 1 {
+        error!("\"replay_play <file>\"");
+        return;
+    }
+    let replay = match InputReplay::<PlayerAction>::load(args[0].as_ref()) {
+        Ok(replay) => replay,
+        Err(e) => {
+            error!("{:#}", e);
+            return;
+        },
+    };
+    info!(
+        "Playing replay {:?}: level {:?}, {} ticks.",
+        args[0], replay.level, replay.len
+    );
+    // a replay that is still playing has replaced it already
+    if replayed.is_none() {
+        commands.insert_resource(ReplayedLevel {
+            previous: StartingLevel(level.0.clone()),
+        });
+    }
+    commands.insert_resource(StartingLevel(replay.level.clone()));
+    commands.insert_resource(PendingReplay::Play(replay));
+    next.set(AppState::Restart);
+}
+
+/// Put the simulation into a known state and start recording/playback
+fn start_pending_replay(
+    mut commands: Commands,
+    mut pending: ResMut<PendingReplay>,
+    mut rng: ResMut<GameRng>,
+    mut kill_count: ResMut<KillCount>,
+    mut replayer: ResMut<InputReplayer<PlayerAction>>,
+    level: Res<StartingLevel>,
+) {
+    commands.remove_resource::<PendingReplay>();
+    // will be recreated from the new seed when the player spawns
+    commands.remove_resource::<DropTracker>();
+    commands.insert_resource(DefeatedBosses::default());
+    commands.insert_resource(DialogueFlags::default());
+    kill_count.0 = 0;
+
+    match &mut *pending {
+        PendingReplay::Record { seed, path } => {
+            info!("Recording replay to {:?}.", path);
+            rng.reseed(*seed);
+            replayer.start_recording(*seed, &level.0);
+            commands.insert_resource(RecordingFile(std::mem::take(path)));
+        },
+        PendingReplay::Play(replay) => {
+            rng.reseed(replay.seed);
+            replayer.start_playback(replay.clone());
+        },
+    }
+}
+
+fn cli_replay_stop(
+    mut commands: Commands,
+    mut replayer: ResMut<InputReplayer<PlayerAction>>,
+    file: Option<Res<RecordingFile>>,
+) {
+    if replayer.mode() == ReplayMode::Off {
+        return;
+    }
+    let ticks = replayer.ticks();
+    let Some(replay) = replayer.stop() else {
+        info!("Stopped replay after {} ticks.", ticks);
+        return;
+    };
+    let path = file
+        .map(|f| f.0.clone())
+        .unwrap_or_else(|| DEFAULT_REPLAY_FILE.into());
+    commands.remove_resource::<RecordingFile>();
+    match replay.save(&path) {
+        Ok(()) => info!(
+            "Saved replay ({} ticks) to {:?}.",
+            ticks, path
+        ),
+        Err(e) => error!("{:#}", e),
+    }
+}
+
+/// Once the replay is over, go back to starting in the usual level
+fn restore_starting_level(
+    mut commands: Commands,
+    replayer: Res<InputReplayer<PlayerAction>>,
+    replayed: Res<ReplayedLevel>,
+) {
+    if replayer.mode() != ReplayMode::Playing {
+        commands.insert_resource(replayed.previous.clone());
+        commands.remove_resource::<ReplayedLevel>();
+    }
+}
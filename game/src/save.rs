@@ -0,0 +1,523 @@
+//! Save files
+//!
+//! A save file captures the progress of the current run: which level we are
+//! in, the player's passives, weapons, upgrades and health, unspent xp, drop
+//! progress and collected seeds, how far along each enemy spawner is, which
+//! bosses are defeated, and the flags set by dialogue.
+//! It does not capture exact positions of anything; loading restarts the
+//! level and then applies the saved progress on top.
+//!
+//! The game autosaves when leaving gameplay (if the player is still alive),
+//! and the save is deleted on game over (death ends the run).
+
+use std::path::{Path, PathBuf};
+
+use bevy::ecs::system::SystemParam;
+
+use crate::game::attack::{Health, KillCount};
+use crate::game::boss::DefeatedBosses;
+use crate::game::dialogue::DialogueFlags;
+use crate::game::enemy::{
+    spawn_enemies, EnemySpawner, SpawnSlot, SpawnerState, Tier,
+};
+use crate::game::pickups::{CollectedSeeds, DropTracker, PlanetarySeed};
+use crate::game::player::player_weapon::{
+    initialize_resources, PlayerWeapons, WeaponId,
+};
+use crate::game::player::{Passive, Passives, Player, PlayerStateSet};
+use crate::game::shop::ShopUpgrades;
+use crate::game::xp_orbs::Xp;
+use crate::level::{ResumeLevel, StartingLevel};
+use crate::prelude::*;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::InGame),
+            start_pending_load
+                .run_if(resource_exists::<PendingLoad>)
+                .after(initialize_resources),
+        );
+        app.add_systems(
+            OnExit(AppState::InGame),
+            autosave
+                .run_if(not(resource_exists::<PendingLoad>))
+                .before(despawn_all_recursive::<With<StateDespawnMarker>>),
+        );
+        app.add_systems(
+            GameTickUpdate,
+            (
+                apply_loaded_player
+                    .run_if(resource_exists::<PendingPlayerLoad>)
+                    .before(PlayerStateSet::Behavior),
+                apply_loaded_spawners
+                    .run_if(resource_exists::<PendingSpawnersLoad>)
+                    .before(spawn_enemies),
+            ),
+        );
+        app.register_clicommand_noargs("save", cli_save);
+        app.register_clicommand_args("save", cli_save_args);
+        app.register_clicommand_noargs("load", cli_load);
+        app.register_clicommand_args("load", cli_load_args);
+    }
+}
+
+/// Bump this whenever the format of [`SaveData`] changes
+pub const SAVE_FORMAT_VERSION: u32 = 7;
+
+/// Everything we store in a save file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SaveData {
+    pub version: u32,
+    /// Asset key of the level the player was in
+    pub level: String,
+    /// Identifier of the LDtk level (within `level`) the player was in
+    pub level_id: String,
+    pub kill_count: u32,
+    /// Unspent xp
+    pub xp: u32,
+    pub player: PlayerSave,
+    pub drops: DropsSave,
+    pub spawners: Vec<SpawnerSave>,
+    /// LDtk entity iids of the defeated bosses
+    pub defeated_bosses: Vec<String>,
+    /// See [`DialogueFlags`]
+    pub dialogue_flags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerSave {
+    pub health: u32,
+    pub passives: Vec<Passive>,
+    pub locked_passives: Vec<Passive>,
+    /// Bought in shops (if any)
+    pub upgrades: Option<ShopUpgrades>,
+    pub weapons: PlayerWeapons,
+}
+
+/// State of the [`DropTracker`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DropsSave {
+    pub progress: usize,
+    pub passive_rolls: Vec<u32>,
+    /// The seeds that have not dropped yet
+    pub seeds: Vec<(PlanetarySeed, Vec<(u32, String)>)>,
+    /// The seeds the player has picked up (see [`CollectedSeeds`])
+    pub collected_seeds: Vec<(PlanetarySeed, u32)>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpawnerSave {
+    /// The LDtk entity iid of the spawner
+    pub iid: String,
+    pub clears: u32,
+    pub threshold_next: u32,
+    pub next_buff_index: usize,
+    /// The tier of each slot
+    pub slots: Vec<Tier>,
+}
+
+impl SaveData {
+    pub fn load(path: &Path) -> AnyResult<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read save file {:?}", path))?;
+        let data: Self = toml::from_str(&text)
+            .with_context(|| format!("Invalid save file {:?}", path))?;
+        ensure!(
+            data.version == SAVE_FORMAT_VERSION,
+            "Save file {:?} has format version {}, expected {}",
+            path,
+            data.version,
+            SAVE_FORMAT_VERSION
+        );
+        Ok(data)
+    }
+
+    pub fn save(&self, path: &Path) -> AnyResult<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| {
+                format!("Cannot create save directory {:?}", dir)
+            })?;
+        }
+        let text = toml::to_string(self)?;
+        std::fs::write(path, text)
+            .with_context(|| format!("Cannot write save file {:?}", path))?;
+        Ok(())
+    }
+}
+
+/// Where the save file goes, if no other file is specified
+pub fn default_save_path() -> PathBuf {
+    directories::ProjectDirs::from("", "TheSeekerGame", "TheSeeker")
+        .map(|dirs| dirs.data_dir().join("save.toml"))
+        .unwrap_or_else(|| "save.toml".into())
+}
+
+/// Is there something for the "Continue" button to load?
+pub fn has_save() -> bool {
+    default_save_path().is_file()
+}
+
+/// Delete the default save file (the run is over)
+pub fn wipe_save() {
+    let path = default_save_path();
+    if path.is_file() {
+        if let Err(e) = std::fs::remove_file(&path) {
+            error!(
+                "Cannot delete save file {:?}: {}",
+                path, e
+            );
+        }
+    }
+}
+
+/// Everything we need to look at to create a [`SaveData`]
+#[derive(SystemParam)]
+struct RunProgress<'w, 's> {
+    level: Res<'w, StartingLevel>,
+    level_selection: Res<'w, LevelSelection>,
+    kill_count: Res<'w, KillCount>,
+    xp: Res<'w, Xp>,
+    defeated_bosses: Res<'w, DefeatedBosses>,
+    dialogue_flags: Res<'w, DialogueFlags>,
+    collected_seeds: Res<'w, CollectedSeeds>,
+    weapons: Res<'w, PlayerWeapons>,
+    drop_tracker: Option<Res<'w, DropTracker>>,
+    q_player: Query<
+        'w,
+        's,
+        (
+            &'static Health,
+            &'static Passives,
+            Option<&'static ShopUpgrades>,
+        ),
+        With<Player>,
+    >,
+    q_spawner: Query<
+        'w,
+        's,
+        (
+            &'static EnemySpawner,
+            &'static EntityIid,
+        ),
+    >,
+}
+
+impl RunProgress<'_, '_> {
+    fn to_save_data(&self) -> Option<SaveData> {
+        let (health, passives, upgrades) = self.q_player.get_single().ok()?;
+        let drop_tracker = self.drop_tracker.as_ref()?;
+        let LevelSelection::Identifier(level_id) = &*self.level_selection
+        else {
+            return None;
+        };
+        Some(SaveData {
+            version: SAVE_FORMAT_VERSION,
+            level: self.level.0.clone(),
+            level_id: level_id.clone(),
+            kill_count: self.kill_count.0,
+            xp: self.xp.0,
+            player: PlayerSave {
+                health: health.current,
+                passives: passives.current.iter().cloned().collect(),
+                locked_passives: passives.locked.clone(),
+                upgrades: upgrades.copied(),
+                weapons: self.weapons.clone(),
+            },
+            drops: DropsSave {
+                progress: drop_tracker.progress,
+                passive_rolls: drop_tracker.passive_rolls.clone(),
+                seeds: drop_tracker
+                    .seeds
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+                collected_seeds: self.collected_seeds.0.clone(),
+            },
+            spawners: self
+                .q_spawner
+                .iter()
+                .map(|(spawner, iid)| SpawnerSave {
+                    iid: iid.as_str().into(),
+                    clears: spawner.clears,
+                    threshold_next: spawner.threshold_next,
+                    next_buff_index: spawner.next_buff_index,
+                    slots: spawner.slots.iter().map(|s| s.tier).collect(),
+                })
+                .collect(),
+            defeated_bosses: self.defeated_bosses.0.iter().cloned().collect(),
+            dialogue_flags: {
+                let mut flags: Vec<String> =
+                    self.dialogue_flags.iter().cloned().collect();
+                // so the file does not change for no reason
+                flags.sort();
+                flags
+            },
+        })
+    }
+
+    fn save(&self, path: &Path) {
+        let Some(data) = self.to_save_data() else {
+            error!("Cannot save: no run in progress!");
+            return;
+        };
+        match data.save(path) {
+            Ok(()) => info!("Saved game to {:?}.", path),
+            Err(e) => error!("{:#}", e),
+        }
+    }
+}
+
+fn cli_save(run: RunProgress) {
+    run.save(&default_save_path());
+}
+
+fn cli_save_args(In(args): In<Vec<String>>, run: RunProgress) {
+    if args.len() != 1 {
+        error!("\"save [file]\"");
+        return;
+    }
+    run.save(args[0].as_ref());
+}
+
+fn autosave(run: RunProgress) {
+    // the player is gone if they died; nothing to save then
+    if run.q_player.get_single().is_ok() {
+        run.save(&default_save_path());
+    }
+}
+
+/// Waiting for gameplay to (re)start, to apply a loaded save
+#[derive(Resource)]
+struct PendingLoad(SaveData);
+
+/// Waiting for the player to be spawned
+#[derive(Resource)]
+struct PendingPlayerLoad(PlayerSave);
+
+/// Waiting for the level's spawners to be spawned
+#[derive(Resource)]
+struct PendingSpawnersLoad(Vec<SpawnerSave>);
+
+fn cli_load(commands: Commands, next: ResMut<NextState<AppState>>) {
+    load(&default_save_path(), commands, next);
+}
+
+fn cli_load_args(
+    In(args): In<Vec<String>>,
+    commands: Commands,
+    next: ResMut<NextState<AppState>>,
+) {
+    if args.len() != 1 {
+        error!("\"load [file]\"");
+        return;
+    }
+    load(args[0].as_ref(), commands, next);
+}
+
+fn load(
+    path: &Path,
+    mut commands: Commands,
+    mut next: ResMut<NextState<AppState>>,
+) {
+    let data = match SaveData::load(path) {
+        Ok(data) => data,
+        Err(e) => {
+            error!("{:#}", e);
+            return;
+        },
+    };
+    info!("Loading game from {:?}.", path);
+    commands.insert_resource(StartingLevel(data.level.clone()));
+    commands.insert_resource(ResumeLevel(data.level_id.clone()));
+    commands.insert_resource(PendingLoad(data));
+    next.set(AppState::Restart);
+}
+
+fn start_pending_load(mut commands: Commands, pending: Res<PendingLoad>) {
+    let data = &pending.0;
+    commands.remove_resource::<PendingLoad>();
+    commands.insert_resource(KillCount(data.kill_count));
+    commands.insert_resource(Xp(data.xp));
+    commands.insert_resource(data.player.weapons.clone());
+    commands.insert_resource(DefeatedBosses(
+        data.defeated_bosses.iter().cloned().collect(),
+    ));
+    commands.insert_resource(DialogueFlags(
+        data.dialogue_flags.iter().cloned().collect(),
+    ));
+    commands.insert_resource(DropTracker {
+        progress: data.drops.progress,
+        passive_rolls: data.drops.passive_rolls.clone(),
+        seeds: data.drops.seeds.iter().cloned().collect(),
+    });
+    commands.insert_resource(CollectedSeeds(
+        data.drops.collected_seeds.clone(),
+    ));
+    commands.insert_resource(PendingPlayerLoad(data.player.clone()));
+    commands.insert_resource(PendingSpawnersLoad(
+        data.spawners.clone(),
+    ));
+}
+
+fn apply_loaded_player(
+    mut commands: Commands,
+    pending: Res<PendingPlayerLoad>,
+    mut q_player: Query<(Entity, &mut Health, &mut Passives), With<Player>>,
+) {
+    let Ok((e_player, mut health, mut passives)) = q_player.get_single_mut()
+    else {
+        return;
+    };
+    let save = &pending.0;
+    if let Some(upgrades) = save.upgrades {
+        commands.entity(e_player).insert(upgrades);
+    }
+    // `update_max_health` sets the max (with the upgrades) and clamps this
+    health.current = save.health;
+    passives.current = save.passives.iter().cloned().collect();
+    passives.locked = save.locked_passives.clone();
+    commands.remove_resource::<PendingPlayerLoad>();
+}
+
+fn apply_loaded_spawners(
+    mut commands: Commands,
+    pending: Res<PendingSpawnersLoad>,
+    mut q_spawner: Query<(&mut EnemySpawner, &EntityIid)>,
+) {
+    // wait for the level to spawn
+    if q_spawner.is_empty() {
+        return;
+    }
+    for (mut spawner, iid) in q_spawner.iter_mut() {
+        let Some(save) = pending.0.iter().find(|s| s.iid == iid.as_str())
+        else {
+            continue;
+        };
+        spawner.clears = save.clears;
+        spawner.threshold_next = save.threshold_next;
+        spawner.next_buff_index = save.next_buff_index;
+        spawner.slots = save
+            .slots
+            .iter()
+            .map(|&tier| SpawnSlot { enemy: None, tier })
+            .collect();
+        // the enemies are not saved; spawn a fresh batch
+        if !spawner.slots.is_empty() {
+            spawner.spawn_state = SpawnerState::Ready;
+        }
+    }
+    commands.remove_resource::<PendingSpawnersLoad>();
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::headless::HeadlessGame;
+
+    #[test]
+    fn save_data_toml_roundtrip() {
+        let data = SaveData {
+            version: SAVE_FORMAT_VERSION,
+            level: "level.01".into(),
+            level_id: "Level_2".into(),
+            kill_count: 42,
+            xp: 130,
+            player: PlayerSave {
+                health: 77,
+                passives: vec![Passive::Bloodstone],
+                locked_passives: vec![Passive::SerpentRing],
+                upgrades: Some(ShopUpgrades {
+                    max_health: 20,
+                    ..Default::default()
+                }),
+                weapons: PlayerWeapons {
+                    owned: vec![
+                        WeaponId::new("Sword"),
+                        WeaponId::new("Bow"),
+                        WeaponId::new("Hammer"),
+                    ],
+                    melee: Some(WeaponId::new("Hammer")),
+                    ranged: Some(WeaponId::new("Bow")),
+                },
+            },
+            drops: DropsSave {
+                progress: 2,
+                passive_rolls: vec![3, 7, 12],
+                seeds: vec![(
+                    PlanetarySeed::CategoryA,
+                    vec![(1, "PLANETARY_SEED_A1".into())],
+                )],
+                collected_seeds: vec![(PlanetarySeed::CategoryB, 4)],
+            },
+            spawners: vec![SpawnerSave {
+                iid: "a3a1b2c0-66b0-11ee-8fa3-2d3d2b6b9c71".into(),
+                clears: 1,
+                threshold_next: 1,
+                next_buff_index: 4,
+                slots: vec![Tier::Three, Tier::Two, Tier::Two],
+            }],
+            defeated_bosses: vec!["b7e2c1a0-66b0-11ee-8fa3-4f1d0c2e8a55".into()],
+            dialogue_flags: vec!["merchant.first".into()],
+        };
+        let text = toml::to_string(&data).unwrap();
+        let loaded: SaveData = toml::from_str(&text).unwrap();
+        assert_eq!(loaded.level_id, "Level_2");
+        assert_eq!(loaded.kill_count, 42);
+        assert_eq!(loaded.xp, 130);
+        assert_eq!(
+            loaded.player.upgrades.map(|u| u.max_health),
+            Some(20)
+        );
+        assert_eq!(loaded.player.passives, data.player.passives);
+        assert_eq!(loaded.player.weapons, data.player.weapons);
+        assert_eq!(loaded.drops.seeds, data.drops.seeds);
+        assert_eq!(loaded.drops.collected_seeds, data.drops.collected_seeds);
+        assert_eq!(loaded.defeated_bosses, data.defeated_bosses);
+        assert_eq!(loaded.dialogue_flags, data.dialogue_flags);
+        assert!(matches!(
+            loaded.spawners[0].slots[..],
+            [Tier::Three, Tier::Two, Tier::Two]
+        ));
+    }
+
+    #[test]
+    fn level_selection_roundtrip() {
+        let mut game = HeadlessGame::new();
+        game.load_assets().enter_level("level.dev");
+        game.spawn_player(Vec2::new(64.0, 64.0));
+        game.world_mut().insert_resource(LevelSelection::Identifier(
+            "Level_1".into(),
+        ));
+        let mut data = game
+            .world_mut()
+            .run_system_once(|run: RunProgress| run.to_save_data())
+            .unwrap();
+        assert_eq!(data.level_id, "Level_1");
+        data.player.weapons.melee = Some(WeaponId::new("Hammer"));
+        data.player.weapons.owned.push(WeaponId::new("Hammer"));
+        let weapons = data.player.weapons.clone();
+
+        let path = std::env::temp_dir().join("theseeker_level_roundtrip.toml");
+        data.save(&path).unwrap();
+        game.world_mut().run_system_once(
+            move |commands: Commands, next: ResMut<NextState<AppState>>| {
+                load(&path, commands, next)
+            },
+        );
+        game.update_until("save to load", |world| {
+            *world.resource::<State<AppState>>().get() == AppState::InGame
+                && !world.contains_resource::<PendingLoad>()
+        });
+        assert!(matches!(
+            game.world().resource::<LevelSelection>(),
+            LevelSelection::Identifier(level) if level == "Level_1"
+        ));
+        // not reset to the starting weapons
+        assert_eq!(*game.world().resource::<PlayerWeapons>(), weapons);
+    }
+}
@@ -14,6 +14,7 @@ mod kill_counter;
 mod mainmenu;
 mod passives;
 pub mod popup;
+mod settings;
 mod skill_toolbar;
 
 #[cfg(not(feature = "release"))]
@@ -29,6 +30,7 @@ impl Plugin for UiPlugin {
             popup::plugin,
             passives::plugin,
             self::mainmenu::MainMenuPlugin,
+            self::settings::SettingsPlugin,
             SkillToolbarPlugin,
             KillCounterPlugin,
         ));
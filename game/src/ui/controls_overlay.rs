@@ -2,11 +2,13 @@ use anyhow::Result;
 use bevy::prelude::*;
 use bevy::utils;
 use leafwing_input_manager::prelude::ActionState;
+use strum::IntoEnumIterator;
 
 use super::popup::PopupTimer;
 use super::popup::PopupUi;
 use super::AppState;
 use crate::game::player::PlayerAction;
+use crate::keybindings::{Control, Keybindings};
 
 #[derive(Component)]
 struct ControlsOverlay;
@@ -35,114 +37,57 @@ pub(super) fn plugin(app: &mut App) {
     );
 }
 
-fn spawn_control_hint(mut commands: Commands) {
+fn spawn_control_hint(mut commands: Commands, keybindings: Res<Keybindings>) {
     commands
         .popup()
         .insert((ControlsHint, PopupTimer::default()))
         .with_children(|popup| {
             popup.row().with_children(|row| {
                 row.text("Press ");
-                row.control_icon("C");
-                row.text(" or ");
-                row.control_icon("Start");
+                row.control_icons(
+                    &keybindings,
+                    Control::ToggleControlOverlay,
+                );
                 row.text(" to show Controls");
             });
         });
 }
 
-fn spawn_control_overlay(mut commands: Commands) {
+fn spawn_control_overlay(
+    mut commands: Commands,
+    keybindings: Res<Keybindings>,
+) {
     commands
         .root()
         .insert(ControlsOverlay)
         .with_children(|root| {
             root.container().with_children(|container| {
-                container.row().with_children(|row| {
-                    row.text("Move Left: ");
-                    row.control_icon("A");
-                    row.text(" or ");
-                    row.control_icon("[<]");
-                    row.text(" or ");
-                    row.control_icon("L Stick Left");
-                });
-                container.row().with_children(|row| {
-                    row.text("Move Right: ");
-                    row.control_icon("D");
-                    row.text(" or ");
-                    row.control_icon("[>]");
-                    row.text(" or ");
-                    row.control_icon("L Stick Right");
-                });
-                container.row().with_children(|row| {
-                    row.text("Jump: ");
-                    row.control_icon("W");
-                    row.text(" or ");
-                    row.control_icon("SPACE");
-                    row.text(" or ");
-                    row.control_icon("[^]");
-                    row.text(" or ");
-                    row.control_icon("L2");
-                });
-                container.spacer();
-                container.row().with_children(|row| {
-                    row.text("Attack: ");
-                    row.control_icon("J");
-                    row.text(" or ");
-                    row.control_icon("1");
-                    row.text(" or ");
-                    row.control_icon("X / Square");
-                });
-                container.row().with_children(|row| {
-                    row.text("Dash: ");
-                    row.control_icon("K");
-                    row.text(" or ");
-                    row.control_icon("2");
-                    row.text(" or ");
-                    row.control_icon("R2");
-                });
-                container.row().with_children(|row| {
-                    row.text("Whirl: ");
-                    row.control_icon("L");
-                    row.text(" or ");
-                    row.control_icon("3");
-                    row.text(" or ");
-                    row.control_icon("A / Cross");
-                });
-                container.row().with_children(|row| {
-                    row.text("Stealth: ");
-                    row.control_icon(";");
-                    row.text(" or ");
-                    row.control_icon("4");
-                    row.text(" or ");
-                    row.control_icon("B / Circle");
-                });
-                container.row().with_children(|row| {
-                    row.text("Swap Melee/Ranged: ");
-                    row.control_icon("H");
-                    row.text(" or ");
-                    row.control_icon("`");
-                    row.text(" or ");
-                    row.control_icon("L1");
-                });
-                container.row().with_children(|row| {
-                    row.text("Swap Melee Weapon: ");
-                    row.control_icon("G");
-                    row.text(" or ");
-                    row.control_icon("R1");
-                });
-                container.spacer();
-                container.row().with_children(|row| {
-                    row.text("Interact: ");
-                    row.control_icon("F");
-                    row.text(" or ");
-                    row.control_icon("Y / Triangle");
-                });
-                container.spacer();
-                container.row().with_children(|row| {
-                    row.text("Show/Hide Controls: ");
-                    row.control_icon("C");
-                    row.text(" or ");
-                    row.control_icon("Start");
-                });
+                for control in Control::iter() {
+                    container.row().with_children(|row| {
+                        row.text(format!("{}: ", control.name()));
+                        row.control_icons(&keybindings, control);
+                        // the analog stick is not configurable
+                        match control {
+                            Control::MoveLeft => {
+                                row.text(" or ");
+                                row.control_icon("L Stick Left");
+                            },
+                            Control::MoveRight => {
+                                row.text(" or ");
+                                row.control_icon("L Stick Right");
+                            },
+                            _ => {},
+                        }
+                    });
+                    if matches!(
+                        control,
+                        Control::Fall
+                            | Control::SwapMeleeWeapon
+                            | Control::Interact
+                    ) {
+                        container.spacer();
+                    }
+                }
             });
         });
 }
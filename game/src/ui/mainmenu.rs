@@ -67,12 +67,26 @@ fn spawn_mainmenu(
         },))
         .id();
 
+    let e_butt_continue = crate::save::has_save().then(|| {
+        spawn_menuentry(
+            &mut commands,
+            &uiassets,
+            OnClick::new().cli("load"),
+            "mainmenu-entry-continue",
+        )
+    });
     let e_butt_play = spawn_menuentry(
         &mut commands,
         &uiassets,
         OnClick::new().cli("AppState InGame"),
         "mainmenu-entry-play",
     );
+    let e_butt_settings = spawn_menuentry(
+        &mut commands,
+        &uiassets,
+        OnClick::new().cli("AppState Settings"),
+        "mainmenu-entry-settings",
+    );
     let e_butt_exit = spawn_menuentry(
         &mut commands,
         &uiassets,
@@ -83,8 +97,12 @@ fn spawn_mainmenu(
     commands
         .entity(e_menu_root)
         .push_children(&[e_logo_image, e_menu_wrapper]);
+    if let Some(e_butt_continue) = e_butt_continue {
+        commands.entity(e_menu_wrapper).add_child(e_butt_continue);
+    }
     commands.entity(e_menu_wrapper).push_children(&[
         e_butt_play,
+        e_butt_settings,
         e_butt_exit,
     ]);
 }
@@ -1,8 +1,11 @@
-use crate::game::player::{Passives, Player};
+use super::popup::PopupUi;
+use crate::game::player::{
+    Passive, PassiveChoice, PassiveChoiceOption, PassiveDefs, Passives, Player,
+};
+use crate::keybindings::{Control, Keybindings};
+use crate::locale::L10nKey;
 use crate::prelude::*;
 
-use crate::game::pickups::PickupAssetHandles;
-
 pub(super) fn plugin(app: &mut App) {
     app.add_systems(
         OnEnter(AppState::InGame),
@@ -14,6 +17,10 @@ pub(super) fn plugin(app: &mut App) {
             any_matching::<(Changed<Passives>, With<Player>)>(),
         )),
     );
+    app.add_systems(
+        Update,
+        display_passive_choice.run_if(in_state(AppState::InGame)),
+    );
 }
 
 #[derive(Component)]
@@ -38,7 +45,7 @@ fn spawn_passive_ui_container(mut commands: Commands) {
 // TODO: Improve this system by making it event driven
 fn display_passives(
     mut commands: Commands,
-    pickup_assets: Res<PickupAssetHandles>,
+    passive_defs: Res<PassiveDefs>,
     passives: Query<&Passives, With<Player>>,
     passives_ui_node: Query<Entity, With<PassivesUiNode>>,
 ) {
@@ -50,9 +57,7 @@ fn display_passives(
         commands.entity(entity).despawn_descendants().with_children(
             |builder| {
                 passives.iter().for_each(|passive| {
-                    if let Some(handle) =
-                        pickup_assets.get_passive_handle(passive)
-                    {
+                    if let Some(handle) = passive_defs.icon(passive) {
                         builder.spawn(ImageBundle {
                             image: UiImage::new(handle.clone()),
                             style: Style {
@@ -68,3 +73,117 @@ fn display_passives(
         );
     }
 }
+
+#[derive(Component)]
+struct PassiveChoiceUi;
+
+/// Compare the held passives to the one being picked up
+fn display_passive_choice(
+    mut commands: Commands,
+    choice: Option<Res<PassiveChoice>>,
+    passives: Query<Ref<Passives>, With<Player>>,
+    q_ui: Query<Entity, With<PassiveChoiceUi>>,
+    passive_defs: Res<PassiveDefs>,
+    keybindings: Res<Keybindings>,
+) {
+    let shown = choice
+        .as_ref()
+        .and_then(|choice| Some((choice, passives.get_single().ok()?)));
+    let Some((choice, passives)) = shown else {
+        for entity in &q_ui {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    if !choice.is_changed() && !passives.is_changed() && !q_ui.is_empty() {
+        return;
+    }
+    for entity in &q_ui {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    commands
+        .popup()
+        .insert(PassiveChoiceUi)
+        .with_children(|popup| {
+            popup.row().with_children(|row| {
+                row.text("").insert(L10nKey("passive-choice-title".into()));
+            });
+            popup.spacer();
+            popup.row().with_children(|row| {
+                row.text("  ");
+                row.text("").insert(L10nKey("passive-choice-new".into()));
+                passive_summary(row, &passive_defs, &choice.new);
+            });
+            popup.spacer();
+            let options = PassiveChoice::options(&passives);
+            for (i, option) in options.iter().enumerate() {
+                popup.row().with_children(|row| {
+                    row.text(if i == choice.selected { "> " } else { "  " });
+                    match option {
+                        PassiveChoiceOption::Replace(old) => {
+                            row.text("").insert(L10nKey(
+                                "passive-choice-replace".into(),
+                            ));
+                            passive_summary(row, &passive_defs, old);
+                        },
+                        PassiveChoiceOption::Discard => {
+                            row.text("").insert(L10nKey(
+                                "passive-choice-discard".into(),
+                            ));
+                            row.text(format!(
+                                " {}",
+                                passive_name(&passive_defs, &choice.new)
+                            ));
+                        },
+                        PassiveChoiceOption::Leave => {
+                            row.text("")
+                                .insert(L10nKey("passive-choice-leave".into()));
+                        },
+                    }
+                });
+            }
+            popup.spacer();
+            popup.row().with_children(|row| {
+                row.control_icons(&keybindings, Control::Jump);
+                row.text(" / ");
+                row.control_icons(&keybindings, Control::Fall);
+                row.text("  ");
+                row.control_icons(&keybindings, Control::Interact);
+            });
+        });
+}
+
+fn passive_name(passive_defs: &PassiveDefs, passive: &Passive) -> String {
+    passive_defs
+        .get(passive)
+        .map(|def| def.name.clone())
+        .unwrap_or_else(|| passive.to_string())
+}
+
+/// Icon, name and description of a passive, on one row
+fn passive_summary(
+    row: &mut ChildBuilder,
+    passive_defs: &PassiveDefs,
+    passive: &Passive,
+) {
+    row.text(" ");
+    if let Some(handle) = passive_defs.icon(passive) {
+        row.spawn(ImageBundle {
+            image: UiImage::new(handle.clone()),
+            style: Style {
+                width: Val::Px(32.0),
+                height: Val::Px(32.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+    }
+    row.text(format!(
+        " {}",
+        passive_name(passive_defs, passive)
+    ));
+    if let Some(def) = passive_defs.get(passive) {
+        row.text(format!(" - {}", def.description));
+    }
+}
@@ -2,6 +2,7 @@ use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
 
 use super::{Spawn, StateDespawnMarker};
+use crate::keybindings::{Control, Keybindings};
 
 const OVERLAY_COLOR: Color = Color::rgba(0.08, 0.10, 0.06, 0.65);
 const BACKGROUND_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.8);
@@ -44,6 +45,8 @@ pub trait PopupUi {
     fn row(&mut self) -> EntityCommands;
     fn text(&mut self, string: impl Into<String>) -> EntityCommands;
     fn control_icon(&mut self, string: impl Into<String>) -> EntityCommands;
+    /// Icons for all the keys/buttons bound to the control, separated by "or"
+    fn control_icons(&mut self, keybindings: &Keybindings, control: Control);
     fn spacer(&mut self) -> EntityCommands;
     fn popup(&mut self) -> EntityCommands;
 }
@@ -155,6 +158,19 @@ impl<T: Spawn> PopupUi for T {
         entity
     }
 
+    fn control_icons(&mut self, keybindings: &Keybindings, control: Control) {
+        let bindings = keybindings.bindings(control);
+        if bindings.is_empty() {
+            self.text("(unbound)");
+        }
+        for (i, binding) in bindings.iter().enumerate() {
+            if i > 0 {
+                self.text(" or ");
+            }
+            self.control_icon(binding.label());
+        }
+    }
+
     fn spacer(&mut self) -> EntityCommands {
         self.spawn((
             Name::new("popup_spacer"),
@@ -0,0 +1,510 @@
+//! Settings screen, for changing the keybindings and rumble
+
+use bevy::ui::RelativeCursorPosition;
+use strum::IntoEnumIterator;
+
+use super::spawn_menuentry;
+use crate::assets::UiAssets;
+use crate::haptics::HapticsSettings;
+use crate::keybindings::{Binding, Control, Keybindings};
+use crate::locale::L10nKey;
+use crate::prelude::*;
+use crate::user_config::UserConfig;
+
+const ICON_COLOR: Color = Color::rgb(0.32, 0.37, 0.28);
+const CONFLICT_COLOR: Color = Color::rgb(0.6, 0.15, 0.1);
+const WAITING_COLOR: Color = Color::rgb(0.55, 0.5, 0.2);
+const PROBLEM_TEXT_COLOR: Color = Color::rgb(1.0, 0.45, 0.35);
+const DISABLED_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
+const SLIDER_TRACK_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::Settings),
+            spawn_settings,
+        );
+        app.add_systems(
+            OnExit(AppState::Settings),
+            stop_rebinding,
+        );
+        app.add_systems(
+            Update,
+            (
+                start_rebinding,
+                capture_rebinding.run_if(resource_exists::<Rebinding>),
+                refresh_bindings_list,
+                drag_haptics_slider,
+                refresh_haptics,
+            )
+                .chain()
+                .run_if(in_state(AppState::Settings)),
+        );
+    }
+}
+
+/// Present while waiting for the user to press the new key/button
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+struct Rebinding {
+    control: Control,
+    /// Which binding to replace, or `None` to add a new one
+    index: Option<usize>,
+}
+
+/// The node that the rows of bindings go into
+#[derive(Component)]
+struct BindingsList;
+
+/// Text listing conflicts and other problems with the bindings
+#[derive(Component)]
+struct ProblemsText;
+
+/// Clicking on it starts rebinding
+#[derive(Component)]
+struct BindingButton(Rebinding);
+
+/// Turns rumble on/off
+#[derive(Component)]
+struct HapticsToggle;
+
+/// Clicking/dragging on it sets the rumble intensity
+#[derive(Component)]
+struct HapticsSlider;
+
+/// The part of the slider that shows the intensity
+#[derive(Component)]
+struct HapticsSliderFill;
+
+#[derive(Component)]
+struct HapticsIntensityText;
+
+fn spawn_settings(mut commands: Commands, uiassets: Res<UiAssets>) {
+    commands.spawn((
+        Camera2dBundle::default(),
+        StateDespawnMarker,
+    ));
+
+    let text_style = |font_size: f32| TextStyle {
+        font: uiassets.font_regular.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+
+    let e_root = commands
+        .spawn((
+            StateDespawnMarker,
+            NodeBundle {
+                background_color: BackgroundColor(Color::rgb(0.0, 0.0, 0.0)),
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(0.),
+                    right: Val::Px(0.),
+                    top: Val::Px(0.),
+                    bottom: Val::Px(0.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::SpaceEvenly,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ))
+        .id();
+    let e_title = commands
+        .spawn((
+            L10nKey("settings-title".to_owned()),
+            TextBundle::from_section("settings-title", text_style(32.0)),
+        ))
+        .id();
+    let e_hint = commands
+        .spawn((
+            L10nKey("settings-rebind-hint".to_owned()),
+            TextBundle::from_section("settings-rebind-hint", text_style(16.0)),
+        ))
+        .id();
+    let e_list = commands
+        .spawn((
+            BindingsList,
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        ))
+        .id();
+    let e_haptics = spawn_haptics_row(&mut commands, &uiassets);
+    let e_problems = commands
+        .spawn((
+            ProblemsText,
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    color: PROBLEM_TEXT_COLOR,
+                    ..text_style(16.0)
+                },
+            ),
+        ))
+        .id();
+    let e_buttons = commands
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .id();
+    let e_butt_reset = spawn_menuentry(
+        &mut commands,
+        &uiassets,
+        OnClick::new().cli("keybindings_reset"),
+        "settings-entry-reset",
+    );
+    let e_butt_back = spawn_menuentry(
+        &mut commands,
+        &uiassets,
+        OnClick::new().cli("AppState MainMenu"),
+        "settings-entry-back",
+    );
+
+    commands
+        .entity(e_buttons)
+        .push_children(&[e_butt_reset, e_butt_back]);
+    commands.entity(e_root).push_children(&[
+        e_title, e_hint, e_list, e_haptics, e_problems, e_buttons,
+    ]);
+}
+
+/// The rumble toggle and intensity slider
+///
+/// Their state is filled in by [`refresh_haptics`].
+fn spawn_haptics_row(commands: &mut Commands, uiassets: &UiAssets) -> Entity {
+    let text_style = TextStyle {
+        font: uiassets.font_regular.clone(),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .with_children(|row| {
+            row.spawn((
+                L10nKey("settings-rumble".to_owned()),
+                TextBundle {
+                    text: Text::from_section(
+                        "settings-rumble",
+                        text_style.clone(),
+                    ),
+                    style: Style {
+                        width: Val::Px(240.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ));
+            row.spawn((
+                HapticsToggle,
+                OnClick::new().cli("haptics_toggle"),
+                ButtonBundle {
+                    style: Style {
+                        padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+                        margin: UiRect::horizontal(Val::Px(4.0)),
+                        min_width: Val::Px(56.0),
+                        justify_content: JustifyContent::Center,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ))
+            .with_children(|button| {
+                button.spawn((
+                    L10nKey("settings-rumble-on".to_owned()),
+                    TextBundle::from_section("", text_style.clone()),
+                ));
+            });
+            row.spawn((
+                HapticsSlider,
+                RelativeCursorPosition::default(),
+                ButtonBundle {
+                    background_color: BackgroundColor(SLIDER_TRACK_COLOR),
+                    style: Style {
+                        width: Val::Px(160.0),
+                        height: Val::Px(14.0),
+                        margin: UiRect::horizontal(Val::Px(8.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ))
+            .with_children(|slider| {
+                slider.spawn((
+                    HapticsSliderFill,
+                    NodeBundle {
+                        style: Style {
+                            height: Val::Percent(100.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                ));
+            });
+            row.spawn((
+                HapticsIntensityText,
+                TextBundle::from_section("", text_style),
+            ));
+        })
+        .id()
+}
+
+fn stop_rebinding(mut commands: Commands) {
+    commands.remove_resource::<Rebinding>();
+}
+
+fn start_rebinding(
+    mut commands: Commands,
+    q_button: Query<(&Interaction, &BindingButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in &q_button {
+        if *interaction == Interaction::Pressed {
+            commands.insert_resource(button.0);
+        }
+    }
+}
+
+/// Wait for the user to press a key or gamepad button, and bind it
+///
+/// Escape cancels, Delete or Backspace removes the binding.
+fn capture_rebinding(
+    mut commands: Commands,
+    rebinding: Res<Rebinding>,
+    mut keybindings: ResMut<Keybindings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+) {
+    let binding = if keys.just_pressed(KeyCode::Escape) {
+        None
+    } else if keys.any_just_pressed([KeyCode::Delete, KeyCode::Backspace]) {
+        if let Some(index) = rebinding.index {
+            keybindings.remove(rebinding.control, index);
+            keybindings.save_user_config();
+        }
+        None
+    } else if let Some(key) = keys.get_just_pressed().next() {
+        Some(Binding::Key(*key))
+    } else if let Some(button) = gamepad_buttons.get_just_pressed().next() {
+        Some(Binding::Button(button.button_type))
+    } else {
+        // keep waiting
+        return;
+    };
+    if let Some(binding) = binding {
+        keybindings.set(
+            rebinding.control,
+            rebinding.index,
+            binding,
+        );
+        keybindings.save_user_config();
+    }
+    commands.remove_resource::<Rebinding>();
+}
+
+/// Respawn the rows of bindings whenever something about them changes
+fn refresh_bindings_list(
+    mut commands: Commands,
+    uiassets: Res<UiAssets>,
+    keybindings: Res<Keybindings>,
+    rebinding: Option<Res<Rebinding>>,
+    q_list: Query<Entity, With<BindingsList>>,
+    mut q_problems: Query<&mut Text, With<ProblemsText>>,
+    mut last: Local<Option<(Entity, Keybindings, Option<Rebinding>)>>,
+) {
+    let Ok(e_list) = q_list.get_single() else {
+        return;
+    };
+    let rebinding = rebinding.map(|r| *r);
+    let current = (e_list, keybindings.clone(), rebinding);
+    if last.as_ref() == Some(&current) {
+        return;
+    }
+    *last = Some(current);
+
+    let problems = keybindings.problems();
+    for mut text in &mut q_problems {
+        text.sections[0].value = problems.join("\n");
+    }
+
+    let conflicts = keybindings.conflicts();
+    let text_style = TextStyle {
+        font: uiassets.font_regular.clone(),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+    commands.entity(e_list).despawn_descendants();
+    commands.entity(e_list).with_children(|list| {
+        for control in Control::iter() {
+            list.spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    margin: UiRect::vertical(Val::Px(2.0)),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with_children(|row| {
+                row.spawn(TextBundle {
+                    text: Text::from_section(
+                        control.name(),
+                        text_style.clone(),
+                    ),
+                    style: Style {
+                        width: Val::Px(240.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+                let bindings = keybindings.bindings(control);
+                let slots = bindings
+                    .iter()
+                    .enumerate()
+                    .map(|(i, binding)| (Some(i), Some(binding)))
+                    .chain([(None, None)]);
+                for (index, binding) in slots {
+                    let this = Rebinding { control, index };
+                    let (label, color) = if rebinding == Some(this) {
+                        ("...".to_owned(), WAITING_COLOR)
+                    } else if let Some(binding) = binding {
+                        let color = if conflicts.contains_key(binding) {
+                            CONFLICT_COLOR
+                        } else {
+                            ICON_COLOR
+                        };
+                        (binding.label(), color)
+                    } else {
+                        ("+".to_owned(), ICON_COLOR)
+                    };
+                    row.spawn((
+                        BindingButton(this),
+                        ButtonBundle {
+                            background_color: BackgroundColor(color),
+                            style: Style {
+                                padding: UiRect::axes(
+                                    Val::Px(6.0),
+                                    Val::Px(2.0),
+                                ),
+                                margin: UiRect::horizontal(Val::Px(4.0)),
+                                min_width: Val::Px(28.0),
+                                justify_content: JustifyContent::Center,
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            label,
+                            text_style.clone(),
+                        ));
+                    });
+                }
+            });
+        }
+    });
+}
+
+/// Set the rumble intensity from where the slider is clicked/dragged
+///
+/// Only saves once the mouse button is released.
+fn drag_haptics_slider(
+    q_slider: Query<
+        (&Interaction, &RelativeCursorPosition),
+        With<HapticsSlider>,
+    >,
+    mut haptics: ResMut<HapticsSettings>,
+    mut dragging: Local<bool>,
+) {
+    for (interaction, cursor) in &q_slider {
+        if *interaction == Interaction::Pressed {
+            *dragging = true;
+            let Some(pos) = cursor.normalized else {
+                continue;
+            };
+            let mut new = *haptics;
+            new.set_intensity(pos.x);
+            // avoid triggering change detection every frame
+            if new != *haptics {
+                *haptics = new;
+            }
+        } else if *dragging {
+            *dragging = false;
+            haptics.save_user_config();
+        }
+    }
+}
+
+fn refresh_haptics(
+    haptics: Res<HapticsSettings>,
+    q_toggle: Query<&Children, With<HapticsToggle>>,
+    mut q_toggle_color: Query<&mut BackgroundColor, With<HapticsToggle>>,
+    mut q_key: Query<&mut L10nKey>,
+    mut q_fill: Query<
+        (&mut Style, &mut BackgroundColor),
+        (
+            With<HapticsSliderFill>,
+            Without<HapticsToggle>,
+        ),
+    >,
+    mut q_text: Query<&mut Text, With<HapticsIntensityText>>,
+) {
+    // runs every frame (the screen might have just been spawned),
+    // so only touch what differs, to not trigger change detection
+    let color = if haptics.enabled {
+        ICON_COLOR
+    } else {
+        DISABLED_COLOR
+    };
+    let key = if haptics.enabled {
+        "settings-rumble-on"
+    } else {
+        "settings-rumble-off"
+    };
+    for children in &q_toggle {
+        for child in children.iter() {
+            if let Ok(mut l10n_key) = q_key.get_mut(*child) {
+                if l10n_key.0 != key {
+                    l10n_key.0 = key.to_owned();
+                }
+            }
+        }
+    }
+    for mut background in &mut q_toggle_color {
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+    for (mut style, mut background) in &mut q_fill {
+        let width = Val::Percent(haptics.intensity * 100.0);
+        if style.width != width {
+            style.width = width;
+        }
+        if background.0 != color {
+            background.0 = color;
+        }
+    }
+    let percent = format!("{:.0}%", haptics.intensity * 100.0);
+    for mut text in &mut q_text {
+        if text.sections[0].value != percent {
+            text.sections[0].value = percent.clone();
+        }
+    }
+}
@@ -221,11 +221,9 @@ fn update_attack_ability_ui(
         return;
     };
     for entity in ui.iter() {
-        let factor = if let Some(attack) = attack {
-            1.0 - attack.ticks as f32 / (Attacking::MAX * 8) as f32
-        } else {
-            0.0
-        };
+        // how long an attack lasts is up to its animation (until it opens
+        // the cancel window), so there is no progress to show
+        let factor = if attack.is_some() { 1.0 } else { 0.0 };
         commands.entity(entity).factor(factor);
     }
 }
@@ -0,0 +1,140 @@
+//! Settings files in the user's config directory
+//!
+//! Settings that the user changes from the settings screen (like the
+//! keybindings) are saved as TOML files, one per kind of setting. They
+//! are loaded on startup; if a file is missing or broken, the defaults
+//! are used instead.
+
+use std::path::{Path, PathBuf};
+
+use crate::prelude::*;
+
+/// Settings that are stored in a file in the user's config directory
+pub trait UserConfig: Serialize + DeserializeOwned + Default {
+    /// Name of the file in the config directory
+    const FILE_NAME: &'static str;
+    /// What the file is called in error messages
+    const DESCRIPTION: &'static str;
+
+    /// Fix up the settings after loading them (like filling in
+    /// missing values)
+    fn after_load(&mut self) {}
+
+    /// Where the file goes
+    fn default_path() -> PathBuf {
+        directories::ProjectDirs::from("", "TheSeekerGame", "TheSeeker")
+            .map(|dirs| dirs.config_dir().join(Self::FILE_NAME))
+            .unwrap_or_else(|| Self::FILE_NAME.into())
+    }
+
+    /// Load the settings from a file, or use the defaults if that fails
+    fn load_or_default(path: &Path) -> Self {
+        if !path.is_file() {
+            return Self::default();
+        }
+        match Self::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("{:#}", e);
+                Self::default()
+            },
+        }
+    }
+
+    fn load(path: &Path) -> AnyResult<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| {
+            format!(
+                "Cannot read {} file {:?}",
+                Self::DESCRIPTION,
+                path
+            )
+        })?;
+        let mut config: Self = toml::from_str(&text).with_context(|| {
+            format!(
+                "Invalid {} file {:?}",
+                Self::DESCRIPTION,
+                path
+            )
+        })?;
+        config.after_load();
+        Ok(config)
+    }
+
+    fn save(&self, path: &Path) -> AnyResult<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).with_context(|| {
+                format!(
+                    "Cannot create config directory {:?}",
+                    dir
+                )
+            })?;
+        }
+        let text = toml::to_string(self)?;
+        std::fs::write(path, text).with_context(|| {
+            format!(
+                "Cannot write {} file {:?}",
+                Self::DESCRIPTION,
+                path
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Save to the default location, logging any errors
+    fn save_user_config(&self) {
+        if let Err(e) = self.save(&Self::default_path()) {
+            error!("{:#}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Default, PartialEq)]
+    #[derive(Serialize, Deserialize)]
+    #[serde(default)]
+    struct TestConfig {
+        volume: f32,
+        names: Vec<String>,
+    }
+
+    impl UserConfig for TestConfig {
+        const FILE_NAME: &'static str = "test.toml";
+        const DESCRIPTION: &'static str = "test settings";
+
+        fn after_load(&mut self) {
+            self.volume = self.volume.min(1.0);
+        }
+    }
+
+    #[test]
+    fn user_config_file_roundtrip() {
+        let dir = std::env::temp_dir().join("theseeker_user_config");
+        let path = dir.join(TestConfig::FILE_NAME);
+        let config = TestConfig {
+            volume: 2.0,
+            names: vec!["a".into()],
+        };
+        config.save(&path).unwrap();
+        assert_eq!(
+            TestConfig::load(&path).unwrap(),
+            TestConfig {
+                volume: 1.0,
+                ..config
+            }
+        );
+
+        std::fs::write(&path, "volume = \"loud\"").unwrap();
+        assert!(TestConfig::load(&path).is_err());
+        assert_eq!(
+            TestConfig::load_or_default(&path),
+            TestConfig::default()
+        );
+        assert_eq!(
+            TestConfig::load_or_default(&dir.join("missing.toml")),
+            TestConfig::default()
+        );
+    }
+}